@@ -1,11 +1,7 @@
-use std::collections::HashMap;
-
-use serde_json;
-
 use pandoc_types::definition::*;
 
 fn main() {
-    let mut meta = HashMap::default();
+    let mut meta = Meta::default();
     meta.insert(
         "title".to_owned(),
         MetaValue::MetaInlines(vec![Inline::Str("a".to_owned())]),
@@ -0,0 +1,421 @@
+//! This module contains a port of [Text.Pandoc.Arbitrary] to Rust, as
+//! [proptest] strategies. It is only available with the `proptest` feature.
+//!
+//! [`Pandoc`], [`Block`], [`Inline`], [`MetaValue`], [`Table`] and [`Attr`]
+//! implement [`Arbitrary`], so their strategies can be obtained with
+//! [`any`](proptest::arbitrary::any), or with
+//! [`any_with`](proptest::arbitrary::any_with) to control the size of the
+//! generated values with a [`Config`]. Generated tables are normalized, so
+//! their cells always fill a valid grid.
+//!
+//! ```
+//! use pandoc_types::arbitrary::Config;
+//! use pandoc_types::definition::Pandoc;
+//! use proptest::prelude::*;
+//!
+//! proptest! {
+//!     fn clone_is_equal(doc in any_with::<Pandoc>(Config { depth: 2, size: 3 })) {
+//!         prop_assert_eq!(doc.clone(), doc);
+//!     }
+//! }
+//! # clone_is_equal();
+//! ```
+//!
+//! [Text.Pandoc.Arbitrary]: https://hackage.haskell.org/package/pandoc-types/docs/Text-Pandoc-Arbitrary.html
+//! [proptest]: https://docs.rs/proptest
+use proptest::arbitrary::Arbitrary;
+use proptest::collection::vec;
+use proptest::option;
+use proptest::prelude::*;
+use proptest::strategy::BoxedStrategy;
+
+use crate::definition::{
+    Alignment, Attr, Block, Caption, Cell, Citation, CitationMode, ColSpec, ColWidth, Format,
+    Inline, ListAttributes, ListNumberDelim, ListNumberStyle, MathType, Meta, MetaValue, Pandoc,
+    QuoteType, Row, Table, TableBody, TableFoot, TableHead, Target,
+};
+
+/// Controls the size of generated values.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Config {
+    /// The maximum nesting depth of elements that contain other elements,
+    /// like emphasis, block quotes, lists, metadata maps or table cells.
+    pub depth: u32,
+    /// The maximum length of generated lists, like the inlines of a
+    /// paragraph, the items of a list or the rows of a table.
+    pub size: usize,
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Config { depth: 2, size: 3 }
+    }
+}
+
+impl Config {
+    fn nested(self) -> Self {
+        Config {
+            depth: self.depth.saturating_sub(1),
+            ..self
+        }
+    }
+}
+
+const WORD_CHARS: &[char] = &[
+    'a', 'b', 'c', 'x', 'y', 'z', 'A', 'B', 'Z', '0', '1', '9', 'é', 'λ',
+];
+
+const TEXT_CHARS: &[char] = &[
+    'a', 'b', 'z', 'A', 'Z', '0', '9', 'é', ' ', '\n', '.', ',', '*', '_', '<', '>', '&', '\\', '-',
+];
+
+fn chars(chars: &'static [char], len: std::ops::RangeInclusive<usize>) -> BoxedStrategy<String> {
+    vec(prop::sample::select(chars), len)
+        .prop_map(String::from_iter)
+        .boxed()
+}
+
+fn word() -> BoxedStrategy<String> {
+    chars(WORD_CHARS, 1..=8)
+}
+
+fn text() -> BoxedStrategy<String> {
+    chars(TEXT_CHARS, 0..=16)
+}
+
+fn format() -> BoxedStrategy<Format> {
+    prop::sample::select(&["html", "latex", "markdown", "tex", "openxml"][..])
+        .prop_map(|f| Format(f.into()))
+        .boxed()
+}
+
+fn target() -> BoxedStrategy<Target> {
+    (text(), text())
+        .prop_map(|(url, title)| Target { url, title })
+        .boxed()
+}
+
+fn attr() -> BoxedStrategy<Attr> {
+    (
+        prop_oneof![Just(String::new()), word()],
+        vec(word(), 0..3),
+        vec((word(), text()), 0..3),
+    )
+        .prop_map(|(identifier, classes, attributes)| Attr {
+            identifier,
+            classes,
+            attributes,
+        })
+        .boxed()
+}
+
+fn leaf_inline() -> BoxedStrategy<Inline> {
+    prop_oneof![
+        4 => word().prop_map(Inline::Str),
+        4 => Just(Inline::Space),
+        1 => Just(Inline::SoftBreak),
+        1 => Just(Inline::LineBreak),
+        1 => (attr(), text()).prop_map(|(attr, code)| Inline::Code(attr, code)),
+        1 => (
+            prop_oneof![Just(MathType::DisplayMath), Just(MathType::InlineMath)],
+            text()
+        )
+            .prop_map(|(math_type, math)| Inline::Math(math_type, math)),
+        1 => (format(), text()).prop_map(|(format, raw)| Inline::RawInline(format, raw)),
+    ]
+    .boxed()
+}
+
+fn inline(config: Config) -> BoxedStrategy<Inline> {
+    if config.depth == 0 {
+        return leaf_inline();
+    }
+    let inner = inlines(config.nested());
+    prop_oneof![
+        5 => leaf_inline(),
+        1 => inner.clone().prop_map(Inline::Emph),
+        1 => inner.clone().prop_map(Inline::Underline),
+        1 => inner.clone().prop_map(Inline::Strong),
+        1 => inner.clone().prop_map(Inline::Strikeout),
+        1 => inner.clone().prop_map(Inline::Superscript),
+        1 => inner.clone().prop_map(Inline::Subscript),
+        1 => inner.clone().prop_map(Inline::SmallCaps),
+        1 => (
+            prop_oneof![Just(QuoteType::SingleQuote), Just(QuoteType::DoubleQuote)],
+            inner.clone()
+        )
+            .prop_map(|(quote_type, inlines)| Inline::Quoted(quote_type, inlines)),
+        1 => (vec(citation(config.nested()), 1..=config.size.max(1)), inner.clone())
+            .prop_map(|(citations, inlines)| Inline::Cite(citations, inlines)),
+        1 => (attr(), inner.clone(), target())
+            .prop_map(|(attr, inlines, target)| Inline::Link(attr, inlines, target)),
+        1 => (attr(), inner.clone(), target())
+            .prop_map(|(attr, inlines, target)| Inline::Image(attr, inlines, target)),
+        1 => blocks(config.nested()).prop_map(Inline::Note),
+        1 => (attr(), inner).prop_map(|(attr, inlines)| Inline::Span(attr, inlines)),
+    ]
+    .boxed()
+}
+
+fn inlines(config: Config) -> BoxedStrategy<Vec<Inline>> {
+    vec(inline(config), 0..=config.size).boxed()
+}
+
+fn citation(config: Config) -> BoxedStrategy<Citation> {
+    (
+        word(),
+        inlines(config),
+        inlines(config),
+        prop_oneof![
+            Just(CitationMode::AuthorInText),
+            Just(CitationMode::SuppressAuthor),
+            Just(CitationMode::NormalCitation),
+        ],
+        0..10i32,
+        0..10i32,
+    )
+        .prop_map(
+            |(citation_id, citation_prefix, citation_suffix, citation_mode, note_num, hash)| {
+                Citation {
+                    citation_id,
+                    citation_prefix,
+                    citation_suffix,
+                    citation_mode,
+                    citation_note_num: note_num,
+                    citation_hash: hash,
+                }
+            },
+        )
+        .boxed()
+}
+
+fn leaf_block(config: Config) -> BoxedStrategy<Block> {
+    let inner = inlines(config.nested());
+    prop_oneof![
+        4 => inner.clone().prop_map(Block::Plain),
+        4 => inner.clone().prop_map(Block::Para),
+        1 => vec(inner.clone(), 1..=config.size.max(1)).prop_map(Block::LineBlock),
+        1 => (attr(), text()).prop_map(|(attr, code)| Block::CodeBlock(attr, code)),
+        1 => (format(), text()).prop_map(|(format, raw)| Block::RawBlock(format, raw)),
+        1 => (1..=6i32, attr(), inner)
+            .prop_map(|(level, attr, inlines)| Block::Header(level, attr, inlines)),
+        1 => Just(Block::HorizontalRule),
+    ]
+    .boxed()
+}
+
+fn list_attributes() -> BoxedStrategy<ListAttributes> {
+    (
+        1..10i32,
+        prop_oneof![
+            Just(ListNumberStyle::DefaultStyle),
+            Just(ListNumberStyle::Example),
+            Just(ListNumberStyle::Decimal),
+            Just(ListNumberStyle::LowerRoman),
+            Just(ListNumberStyle::UpperRoman),
+            Just(ListNumberStyle::LowerAlpha),
+            Just(ListNumberStyle::UpperAlpha),
+        ],
+        prop_oneof![
+            Just(ListNumberDelim::DefaultDelim),
+            Just(ListNumberDelim::Period),
+            Just(ListNumberDelim::OneParen),
+            Just(ListNumberDelim::TwoParens),
+        ],
+    )
+        .prop_map(|(start_number, style, delim)| ListAttributes {
+            start_number,
+            style,
+            delim,
+        })
+        .boxed()
+}
+
+fn block(config: Config) -> BoxedStrategy<Block> {
+    if config.depth == 0 {
+        return leaf_block(config);
+    }
+    let inner = blocks(config.nested());
+    let items = vec(inner.clone(), 1..=config.size.max(1));
+    prop_oneof![
+        5 => leaf_block(config),
+        1 => inner.clone().prop_map(Block::BlockQuote),
+        1 => (list_attributes(), items.clone())
+            .prop_map(|(attributes, items)| Block::OrderedList(attributes, items)),
+        1 => items.clone().prop_map(Block::BulletList),
+        1 => vec((inlines(config.nested()), items), 1..=config.size.max(1))
+            .prop_map(Block::DefinitionList),
+        1 => table(config).prop_map(Block::Table),
+        1 => (attr(), caption(config.nested()), inner.clone())
+            .prop_map(|(attr, caption, blocks)| Block::Figure(attr, caption, blocks)),
+        1 => (attr(), inner).prop_map(|(attr, blocks)| Block::Div(attr, blocks)),
+    ]
+    .boxed()
+}
+
+fn blocks(config: Config) -> BoxedStrategy<Vec<Block>> {
+    vec(block(config), 0..=config.size).boxed()
+}
+
+fn caption(config: Config) -> BoxedStrategy<Caption> {
+    (option::of(inlines(config)), blocks(config))
+        .prop_map(|(short, long)| Caption { short, long })
+        .boxed()
+}
+
+fn alignment() -> BoxedStrategy<Alignment> {
+    prop_oneof![
+        Just(Alignment::AlignLeft),
+        Just(Alignment::AlignRight),
+        Just(Alignment::AlignCenter),
+        Just(Alignment::AlignDefault),
+    ]
+    .boxed()
+}
+
+fn col_spec() -> BoxedStrategy<ColSpec> {
+    let width = prop_oneof![
+        Just(ColWidth::ColWidthDefault),
+        (1..=100u32).prop_map(|n| ColWidth::ColWidth(n as f64 / 100.0)),
+    ];
+    (alignment(), width)
+        .prop_map(|(align, width)| ColSpec(align, width))
+        .boxed()
+}
+
+fn cell(config: Config) -> BoxedStrategy<Cell> {
+    (
+        attr(),
+        alignment(),
+        prop_oneof![3 => Just(1i32), 1 => 1..=3i32],
+        prop_oneof![3 => Just(1i32), 1 => 1..=3i32],
+        blocks(config),
+    )
+        .prop_map(|(attr, align, row_span, col_span, content)| Cell {
+            attr,
+            align,
+            row_span,
+            col_span,
+            content,
+        })
+        .boxed()
+}
+
+fn rows(config: Config) -> BoxedStrategy<Vec<Row>> {
+    let row =
+        (attr(), vec(cell(config), 0..=config.size)).prop_map(|(attr, cells)| Row { attr, cells });
+    vec(row, 0..=config.size).boxed()
+}
+
+fn table(config: Config) -> BoxedStrategy<Table> {
+    let inner = config.nested();
+    let body = (attr(), 0..3i32, rows(inner), rows(inner)).prop_map(
+        |(attr, row_head_columns, head, body)| TableBody {
+            attr,
+            row_head_columns,
+            head,
+            body,
+        },
+    );
+    (
+        attr(),
+        caption(inner),
+        vec(col_spec(), 1..=config.size.max(1)),
+        (attr(), rows(inner)).prop_map(|(attr, rows)| TableHead { attr, rows }),
+        vec(body, 0..=config.size),
+        (attr(), rows(inner)).prop_map(|(attr, rows)| TableFoot { attr, rows }),
+    )
+        .prop_map(|(attr, caption, colspecs, head, bodies, foot)| {
+            let mut table = Table {
+                attr,
+                caption,
+                colspecs,
+                head,
+                bodies,
+                foot,
+            };
+            table.normalize();
+            table
+        })
+        .boxed()
+}
+
+fn meta_value(config: Config) -> BoxedStrategy<MetaValue> {
+    let leaf = prop_oneof![
+        any::<bool>().prop_map(MetaValue::MetaBool),
+        text().prop_map(MetaValue::MetaString),
+        inlines(config.nested()).prop_map(MetaValue::MetaInlines),
+        blocks(config.nested()).prop_map(MetaValue::MetaBlocks),
+    ];
+    if config.depth == 0 {
+        return leaf.boxed();
+    }
+    let inner = meta_value(config.nested());
+    prop_oneof![
+        4 => leaf,
+        1 => vec(inner.clone(), 0..=config.size).prop_map(MetaValue::MetaList),
+        1 => vec((word(), inner), 0..=config.size)
+            .prop_map(|fields| MetaValue::MetaMap(fields.into_iter().collect())),
+    ]
+    .boxed()
+}
+
+fn meta(config: Config) -> BoxedStrategy<Meta> {
+    vec((word(), meta_value(config.nested())), 0..=config.size)
+        .prop_map(|fields| fields.into_iter().collect())
+        .boxed()
+}
+
+macro_rules! arbitrary {
+    ($($t:ty => $strategy:expr),* $(,)?) => {
+        $(
+            impl Arbitrary for $t {
+                type Parameters = Config;
+                type Strategy = BoxedStrategy<$t>;
+
+                fn arbitrary_with(config: Config) -> Self::Strategy {
+                    #[allow(clippy::redundant_closure_call)]
+                    ($strategy)(config)
+                }
+            }
+        )*
+    };
+}
+
+arbitrary! {
+    Pandoc => |config| {
+        (meta(config), blocks(config))
+            .prop_map(|(meta, blocks)| Pandoc { blocks, meta })
+            .boxed()
+    },
+    Block => block,
+    Inline => inline,
+    MetaValue => meta_value,
+    Table => table,
+    Attr => |_| attr(),
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    proptest! {
+        #![proptest_config(ProptestConfig::with_cases(64))]
+
+        #[test]
+        fn serde_round_trip(doc in any::<Pandoc>()) {
+            let json = serde_json::to_string(&doc).unwrap();
+            prop_assert_eq!(serde_json::from_str::<Pandoc>(&json).unwrap(), doc);
+        }
+
+        #[test]
+        fn valid_tables(table in any_with::<Table>(Config { depth: 1, size: 5 })) {
+            prop_assert!(table.grid().is_valid());
+        }
+
+        #[test]
+        fn depth(inline in any_with::<Inline>(Config { depth: 0, size: 4 })) {
+            prop_assert!(!matches!(inline, Inline::Emph(_) | Inline::Note(_) | Inline::Span(..)));
+        }
+    }
+}
@@ -0,0 +1,1008 @@
+//! This module contains a port of [Text.Pandoc.Builder] to Rust.
+//!
+//! The builder provides the [`Inlines`] and [`Blocks`] sequence types along
+//! with functions to construct each kind of element. Sequences can be
+//! concatenated with `+` (the equivalent of Haskell's `<>`), which for
+//! [`Inlines`] merges adjacent [`Inline::Str`] elements and collapses
+//! redundant spaces the same way the Haskell builder does.
+//!
+//! ```
+//! use pandoc_types::builder::*;
+//! use pandoc_types::definition::{Block, Inline};
+//!
+//! let blocks = header(1, text("Hello world"))
+//!     + para(text("This is ") + emph("important") + text("."));
+//!
+//! assert_eq!(
+//!     blocks.into_vec(),
+//!     vec![
+//!         Block::Header(
+//!             1,
+//!             Default::default(),
+//!             vec![
+//!                 Inline::Str("Hello".into()),
+//!                 Inline::Space,
+//!                 Inline::Str("world".into()),
+//!             ],
+//!         ),
+//!         Block::Para(vec![
+//!             Inline::Str("This".into()),
+//!             Inline::Space,
+//!             Inline::Str("is".into()),
+//!             Inline::Space,
+//!             Inline::Emph(vec![Inline::Str("important".into())]),
+//!             Inline::Str(".".into()),
+//!         ]),
+//!     ]
+//! );
+//! ```
+//!
+//! [Text.Pandoc.Builder]: https://hackage.haskell.org/package/pandoc-types/docs/Text-Pandoc-Builder.html
+use std::iter::Sum;
+use std::ops::{Add, AddAssign, Deref};
+
+use crate::definition::{
+    Alignment, Attr, Block, Caption, Cell, Citation, ColSpec, ColWidth, Format, Inline,
+    ListAttributes, MathType, Pandoc, QuoteType, Row, Table, TableBody, TableFoot, TableHead,
+    Target,
+};
+
+/// A sequence of elements, the equivalent of Haskell's `Many`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Many<T>(Vec<T>);
+
+/// A sequence of inlines.
+pub type Inlines = Many<Inline>;
+
+/// A sequence of blocks.
+pub type Blocks = Many<Block>;
+
+impl<T> Many<T> {
+    /// Returns an empty sequence.
+    pub fn new() -> Self {
+        Many(Vec::new())
+    }
+
+    /// Returns a sequence containing a single element.
+    pub fn singleton(x: T) -> Self {
+        Many(vec![x])
+    }
+
+    /// Returns the elements of the sequence.
+    pub fn into_vec(self) -> Vec<T> {
+        self.0
+    }
+}
+
+impl<T> Default for Many<T> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<T> Deref for Many<T> {
+    type Target = [T];
+
+    fn deref(&self) -> &[T] {
+        &self.0
+    }
+}
+
+/// Wraps the elements without any normalization, like Haskell's `fromList`.
+impl<T> From<Vec<T>> for Many<T> {
+    fn from(xs: Vec<T>) -> Self {
+        Many(xs)
+    }
+}
+
+impl<T> From<Many<T>> for Vec<T> {
+    fn from(xs: Many<T>) -> Self {
+        xs.0
+    }
+}
+
+impl<T> IntoIterator for Many<T> {
+    type Item = T;
+    type IntoIter = std::vec::IntoIter<T>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.0.into_iter()
+    }
+}
+
+impl<'a, T> IntoIterator for &'a Many<T> {
+    type Item = &'a T;
+    type IntoIter = std::slice::Iter<'a, T>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.0.iter()
+    }
+}
+
+impl Inlines {
+    /// Appends an inline, merging it with the last inline where possible.
+    pub fn push(&mut self, y: Inline) {
+        let x = match self.0.pop() {
+            Some(x) => x,
+            None => {
+                self.0.push(y);
+                return;
+            }
+        };
+        let melded = match (x, y) {
+            (Inline::Space, Inline::Space) => Inline::Space,
+            (Inline::Space, Inline::SoftBreak) => Inline::SoftBreak,
+            (Inline::SoftBreak, Inline::Space) => Inline::SoftBreak,
+            (Inline::SoftBreak, Inline::SoftBreak) => Inline::SoftBreak,
+            (Inline::Space, Inline::LineBreak) => Inline::LineBreak,
+            (Inline::LineBreak, Inline::Space) => Inline::LineBreak,
+            (Inline::SoftBreak, Inline::LineBreak) => Inline::LineBreak,
+            (Inline::LineBreak, Inline::SoftBreak) => Inline::LineBreak,
+            (Inline::Str(mut x), Inline::Str(y)) => {
+                x.push_str(&y);
+                Inline::Str(x)
+            }
+            (Inline::Emph(x), Inline::Emph(y)) => Inline::Emph(meld(x, y)),
+            (Inline::Underline(x), Inline::Underline(y)) => Inline::Underline(meld(x, y)),
+            (Inline::Strong(x), Inline::Strong(y)) => Inline::Strong(meld(x, y)),
+            (Inline::Subscript(x), Inline::Subscript(y)) => Inline::Subscript(meld(x, y)),
+            (Inline::Superscript(x), Inline::Superscript(y)) => Inline::Superscript(meld(x, y)),
+            (Inline::Strikeout(x), Inline::Strikeout(y)) => Inline::Strikeout(meld(x, y)),
+            (x, y) => {
+                self.0.push(x);
+                y
+            }
+        };
+        self.0.push(melded);
+    }
+}
+
+/// Joins the contents of two inlines of the same kind. As in Haskell, the
+/// lists are appended without merging the inlines where they meet.
+fn meld(mut x: Vec<Inline>, y: Vec<Inline>) -> Vec<Inline> {
+    x.extend(y);
+    x
+}
+
+/// Concatenates two sequences of inlines, merging the inlines at the boundary.
+impl Add for Inlines {
+    type Output = Inlines;
+
+    fn add(mut self, other: Inlines) -> Inlines {
+        self += other;
+        self
+    }
+}
+
+impl AddAssign for Inlines {
+    fn add_assign(&mut self, other: Inlines) {
+        let mut other = other.0.into_iter();
+        if let Some(first) = other.next() {
+            self.push(first);
+        }
+        self.0.extend(other);
+    }
+}
+
+impl Extend<Inline> for Inlines {
+    fn extend<I: IntoIterator<Item = Inline>>(&mut self, iter: I) {
+        for inline in iter {
+            self.push(inline);
+        }
+    }
+}
+
+impl Extend<Inlines> for Inlines {
+    fn extend<I: IntoIterator<Item = Inlines>>(&mut self, iter: I) {
+        for inlines in iter {
+            *self += inlines;
+        }
+    }
+}
+
+impl FromIterator<Inline> for Inlines {
+    fn from_iter<I: IntoIterator<Item = Inline>>(iter: I) -> Self {
+        let mut inlines = Inlines::new();
+        inlines.extend(iter);
+        inlines
+    }
+}
+
+impl Sum for Inlines {
+    fn sum<I: Iterator<Item = Inlines>>(iter: I) -> Self {
+        let mut inlines = Inlines::new();
+        inlines.extend(iter);
+        inlines
+    }
+}
+
+impl From<Inline> for Inlines {
+    fn from(inline: Inline) -> Self {
+        Inlines::singleton(inline)
+    }
+}
+
+/// Converts the string with [`text`].
+impl From<&str> for Inlines {
+    fn from(s: &str) -> Self {
+        text(s)
+    }
+}
+
+/// Converts the string with [`text`].
+impl From<String> for Inlines {
+    fn from(s: String) -> Self {
+        text(&s)
+    }
+}
+
+impl Add for Blocks {
+    type Output = Blocks;
+
+    fn add(mut self, other: Blocks) -> Blocks {
+        self += other;
+        self
+    }
+}
+
+impl AddAssign for Blocks {
+    fn add_assign(&mut self, other: Blocks) {
+        self.0.extend(other.0);
+    }
+}
+
+impl Extend<Block> for Blocks {
+    fn extend<I: IntoIterator<Item = Block>>(&mut self, iter: I) {
+        self.0.extend(iter);
+    }
+}
+
+impl Extend<Blocks> for Blocks {
+    fn extend<I: IntoIterator<Item = Blocks>>(&mut self, iter: I) {
+        for blocks in iter {
+            *self += blocks;
+        }
+    }
+}
+
+impl FromIterator<Block> for Blocks {
+    fn from_iter<I: IntoIterator<Item = Block>>(iter: I) -> Self {
+        Many(iter.into_iter().collect())
+    }
+}
+
+impl Sum for Blocks {
+    fn sum<I: Iterator<Item = Blocks>>(iter: I) -> Self {
+        let mut blocks = Blocks::new();
+        blocks.extend(iter);
+        blocks
+    }
+}
+
+impl From<Block> for Blocks {
+    fn from(block: Block) -> Self {
+        Blocks::singleton(block)
+    }
+}
+
+impl From<Table> for Blocks {
+    fn from(table: Table) -> Self {
+        Blocks::singleton(Block::Table(table))
+    }
+}
+
+// Document builders
+
+/// Creates a document from blocks with empty metadata.
+pub fn doc(blocks: impl Into<Blocks>) -> Pandoc {
+    Pandoc {
+        blocks: blocks.into().into_vec(),
+        ..Default::default()
+    }
+}
+
+// Inline list builders
+
+/// Converts text into inlines, breaking it into [`Inline::Str`] elements
+/// separated by [`Inline::Space`] or, where the whitespace contains a
+/// newline, [`Inline::SoftBreak`].
+pub fn text(s: &str) -> Inlines {
+    fn is_space(c: char) -> bool {
+        matches!(c, ' ' | '\r' | '\n' | '\t')
+    }
+
+    let mut inlines = Vec::new();
+    let mut rest = s;
+    while let Some(c) = rest.chars().next() {
+        let space = is_space(c);
+        let end = rest.find(|c| is_space(c) != space).unwrap_or(rest.len());
+        let (chunk, tail) = rest.split_at(end);
+        inlines.push(if !space {
+            Inline::Str(chunk.to_owned())
+        } else if chunk.contains(['\r', '\n']) {
+            Inline::SoftBreak
+        } else {
+            Inline::Space
+        });
+        rest = tail;
+    }
+    Many(inlines)
+}
+
+/// Creates a [`Inline::Str`] without any splitting.
+pub fn str(s: impl Into<String>) -> Inlines {
+    Inlines::singleton(Inline::Str(s.into()))
+}
+
+pub fn emph(inlines: impl Into<Inlines>) -> Inlines {
+    Inlines::singleton(Inline::Emph(inlines.into().into_vec()))
+}
+
+pub fn underline(inlines: impl Into<Inlines>) -> Inlines {
+    Inlines::singleton(Inline::Underline(inlines.into().into_vec()))
+}
+
+pub fn strong(inlines: impl Into<Inlines>) -> Inlines {
+    Inlines::singleton(Inline::Strong(inlines.into().into_vec()))
+}
+
+pub fn strikeout(inlines: impl Into<Inlines>) -> Inlines {
+    Inlines::singleton(Inline::Strikeout(inlines.into().into_vec()))
+}
+
+pub fn superscript(inlines: impl Into<Inlines>) -> Inlines {
+    Inlines::singleton(Inline::Superscript(inlines.into().into_vec()))
+}
+
+pub fn subscript(inlines: impl Into<Inlines>) -> Inlines {
+    Inlines::singleton(Inline::Subscript(inlines.into().into_vec()))
+}
+
+pub fn small_caps(inlines: impl Into<Inlines>) -> Inlines {
+    Inlines::singleton(Inline::SmallCaps(inlines.into().into_vec()))
+}
+
+pub fn single_quoted(inlines: impl Into<Inlines>) -> Inlines {
+    quoted(QuoteType::SingleQuote, inlines)
+}
+
+pub fn double_quoted(inlines: impl Into<Inlines>) -> Inlines {
+    quoted(QuoteType::DoubleQuote, inlines)
+}
+
+pub fn quoted(quote_type: QuoteType, inlines: impl Into<Inlines>) -> Inlines {
+    Inlines::singleton(Inline::Quoted(quote_type, inlines.into().into_vec()))
+}
+
+pub fn cite(citations: Vec<Citation>, inlines: impl Into<Inlines>) -> Inlines {
+    Inlines::singleton(Inline::Cite(citations, inlines.into().into_vec()))
+}
+
+/// Inline code with attributes.
+pub fn code_with(attr: Attr, code: impl Into<String>) -> Inlines {
+    Inlines::singleton(Inline::Code(attr, code.into()))
+}
+
+/// Plain inline code.
+pub fn code(code: impl Into<String>) -> Inlines {
+    code_with(Attr::default(), code)
+}
+
+pub fn space() -> Inlines {
+    Inlines::singleton(Inline::Space)
+}
+
+pub fn soft_break() -> Inlines {
+    Inlines::singleton(Inline::SoftBreak)
+}
+
+pub fn line_break() -> Inlines {
+    Inlines::singleton(Inline::LineBreak)
+}
+
+/// Inline math.
+pub fn math(math: impl Into<String>) -> Inlines {
+    Inlines::singleton(Inline::Math(MathType::InlineMath, math.into()))
+}
+
+/// Display math.
+pub fn display_math(math: impl Into<String>) -> Inlines {
+    Inlines::singleton(Inline::Math(MathType::DisplayMath, math.into()))
+}
+
+pub fn raw_inline(format: impl Into<String>, raw: impl Into<String>) -> Inlines {
+    Inlines::singleton(Inline::RawInline(Format(format.into()), raw.into()))
+}
+
+/// A link with the given URL, title and label.
+pub fn link(
+    url: impl Into<String>,
+    title: impl Into<String>,
+    label: impl Into<Inlines>,
+) -> Inlines {
+    link_with(Attr::default(), url, title, label)
+}
+
+/// A link with attributes and the given URL, title and label.
+pub fn link_with(
+    attr: Attr,
+    url: impl Into<String>,
+    title: impl Into<String>,
+    label: impl Into<Inlines>,
+) -> Inlines {
+    Inlines::singleton(Inline::Link(
+        attr,
+        label.into().into_vec(),
+        Target {
+            url: url.into(),
+            title: title.into(),
+        },
+    ))
+}
+
+/// An image with the given URL, title and alt text.
+pub fn image(url: impl Into<String>, title: impl Into<String>, alt: impl Into<Inlines>) -> Inlines {
+    image_with(Attr::default(), url, title, alt)
+}
+
+/// An image with attributes and the given URL, title and alt text.
+pub fn image_with(
+    attr: Attr,
+    url: impl Into<String>,
+    title: impl Into<String>,
+    alt: impl Into<Inlines>,
+) -> Inlines {
+    Inlines::singleton(Inline::Image(
+        attr,
+        alt.into().into_vec(),
+        Target {
+            url: url.into(),
+            title: title.into(),
+        },
+    ))
+}
+
+pub fn note(blocks: impl Into<Blocks>) -> Inlines {
+    Inlines::singleton(Inline::Note(blocks.into().into_vec()))
+}
+
+pub fn span_with(attr: Attr, inlines: impl Into<Inlines>) -> Inlines {
+    Inlines::singleton(Inline::Span(attr, inlines.into().into_vec()))
+}
+
+/// Trims leading and trailing spaces and soft breaks from inlines.
+pub fn trim_inlines(inlines: Inlines) -> Inlines {
+    fn is_space(inline: &Inline) -> bool {
+        matches!(inline, Inline::Space | Inline::SoftBreak)
+    }
+
+    let mut inlines = inlines.into_vec();
+    let end = inlines
+        .iter()
+        .rposition(|x| !is_space(x))
+        .map_or(0, |i| i + 1);
+    inlines.truncate(end);
+    let start = inlines.iter().position(|x| !is_space(x)).unwrap_or(0);
+    inlines.drain(..start);
+    Many(inlines)
+}
+
+// Block list builders
+
+/// Plain text, or nothing if the inlines are empty.
+pub fn plain(inlines: impl Into<Inlines>) -> Blocks {
+    let inlines = inlines.into();
+    if inlines.is_empty() {
+        Blocks::new()
+    } else {
+        Blocks::singleton(Block::Plain(inlines.into_vec()))
+    }
+}
+
+pub fn para(inlines: impl Into<Inlines>) -> Blocks {
+    Blocks::singleton(Block::Para(inlines.into().into_vec()))
+}
+
+/// A line block with the given lines.
+pub fn line_block(lines: impl IntoIterator<Item = Inlines>) -> Blocks {
+    Blocks::singleton(Block::LineBlock(
+        lines.into_iter().map(Inlines::into_vec).collect(),
+    ))
+}
+
+/// A code block with attributes.
+pub fn code_block_with(attr: Attr, code: impl Into<String>) -> Blocks {
+    Blocks::singleton(Block::CodeBlock(attr, code.into()))
+}
+
+/// A plain code block.
+pub fn code_block(code: impl Into<String>) -> Blocks {
+    code_block_with(Attr::default(), code)
+}
+
+pub fn raw_block(format: impl Into<String>, raw: impl Into<String>) -> Blocks {
+    Blocks::singleton(Block::RawBlock(Format(format.into()), raw.into()))
+}
+
+pub fn block_quote(blocks: impl Into<Blocks>) -> Blocks {
+    Blocks::singleton(Block::BlockQuote(blocks.into().into_vec()))
+}
+
+/// An ordered list with the given list attributes.
+pub fn ordered_list_with(attrs: ListAttributes, items: impl IntoIterator<Item = Blocks>) -> Blocks {
+    Blocks::singleton(Block::OrderedList(
+        attrs,
+        items.into_iter().map(Blocks::into_vec).collect(),
+    ))
+}
+
+/// An ordered list with the default list attributes.
+pub fn ordered_list(items: impl IntoIterator<Item = Blocks>) -> Blocks {
+    ordered_list_with(ListAttributes::default(), items)
+}
+
+pub fn bullet_list(items: impl IntoIterator<Item = Blocks>) -> Blocks {
+    Blocks::singleton(Block::BulletList(
+        items.into_iter().map(Blocks::into_vec).collect(),
+    ))
+}
+
+/// A definition list with a term and one or more definitions for each item.
+pub fn definition_list(items: impl IntoIterator<Item = (Inlines, Vec<Blocks>)>) -> Blocks {
+    Blocks::singleton(Block::DefinitionList(
+        items
+            .into_iter()
+            .map(|(term, definitions)| {
+                (
+                    term.into_vec(),
+                    definitions.into_iter().map(Blocks::into_vec).collect(),
+                )
+            })
+            .collect(),
+    ))
+}
+
+pub fn header(level: i32, inlines: impl Into<Inlines>) -> Blocks {
+    header_with(Attr::default(), level, inlines)
+}
+
+pub fn header_with(attr: Attr, level: i32, inlines: impl Into<Inlines>) -> Blocks {
+    Blocks::singleton(Block::Header(level, attr, inlines.into().into_vec()))
+}
+
+pub fn horizontal_rule() -> Blocks {
+    Blocks::singleton(Block::HorizontalRule)
+}
+
+pub fn figure(caption: Caption, blocks: impl Into<Blocks>) -> Blocks {
+    figure_with(Attr::default(), caption, blocks)
+}
+
+pub fn figure_with(attr: Attr, caption: Caption, blocks: impl Into<Blocks>) -> Blocks {
+    Blocks::singleton(Block::Figure(attr, caption, blocks.into().into_vec()))
+}
+
+/// A caption with an optional short caption.
+pub fn caption(short: Option<Inlines>, long: impl Into<Blocks>) -> Caption {
+    Caption {
+        short: short.map(Inlines::into_vec),
+        long: long.into().into_vec(),
+    }
+}
+
+/// A caption without a short caption.
+pub fn simple_caption(long: impl Into<Blocks>) -> Caption {
+    caption(None, long)
+}
+
+pub fn empty_caption() -> Caption {
+    Caption::default()
+}
+
+pub fn div(blocks: impl Into<Blocks>) -> Blocks {
+    div_with(Attr::default(), blocks)
+}
+
+pub fn div_with(attr: Attr, blocks: impl Into<Blocks>) -> Blocks {
+    Blocks::singleton(Block::Div(attr, blocks.into().into_vec()))
+}
+
+// Table builders
+
+/// A table with the given caption, column specifications, head, bodies and
+/// foot, normalized with [`Table::normalize`].
+pub fn table(
+    caption: Caption,
+    colspecs: Vec<ColSpec>,
+    head: TableHead,
+    bodies: Vec<TableBody>,
+    foot: TableFoot,
+) -> Blocks {
+    let mut table = Table {
+        attr: Attr::default(),
+        caption,
+        colspecs,
+        head,
+        bodies,
+        foot,
+    };
+    table.normalize();
+    Blocks::from(table)
+}
+
+/// A table with a single header row (omitted if `headers` is empty), a
+/// single body and no caption. The number of columns is the length of the
+/// longest row.
+///
+/// ```
+/// use pandoc_types::builder::*;
+/// use pandoc_types::definition::Block;
+///
+/// let blocks = simple_table(
+///     vec![plain("Name"), plain("Value")],
+///     vec![vec![plain("a"), plain("1")], vec![plain("b"), plain("2")]],
+/// );
+///
+/// match &blocks[..] {
+///     [Block::Table(table)] => {
+///         assert_eq!(table.colspecs.len(), 2);
+///         assert_eq!(table.head.rows.len(), 1);
+///         assert_eq!(table.bodies[0].body.len(), 2);
+///     }
+///     _ => panic!("expected a table"),
+/// }
+/// ```
+pub fn simple_table(headers: Vec<Blocks>, rows: Vec<Vec<Blocks>>) -> Blocks {
+    let mut builder = TableBuilder::new();
+    if !headers.is_empty() {
+        builder = builder.header(headers);
+    }
+    Blocks::from(builder.body(rows).build())
+}
+
+/// A table cell with the given alignment, row span and column span.
+pub fn cell(align: Alignment, row_span: i32, col_span: i32, blocks: impl Into<Blocks>) -> Cell {
+    Cell {
+        attr: Attr::default(),
+        align,
+        row_span,
+        col_span,
+        content: blocks.into().into_vec(),
+    }
+}
+
+/// A table cell with default alignment that spans a single row and column.
+pub fn simple_cell(blocks: impl Into<Blocks>) -> Cell {
+    cell(Alignment::AlignDefault, 1, 1, blocks)
+}
+
+pub fn empty_cell() -> Cell {
+    Cell::default()
+}
+
+/// Converts the blocks with [`simple_cell`].
+impl From<Blocks> for Cell {
+    fn from(blocks: Blocks) -> Self {
+        simple_cell(blocks)
+    }
+}
+
+fn row<C: Into<Cell>>(cells: impl IntoIterator<Item = C>) -> Row {
+    Row {
+        attr: Attr::default(),
+        cells: cells.into_iter().map(Into::into).collect(),
+    }
+}
+
+/// A builder for tables that derives the column specifications from the
+/// rows of the table.
+///
+/// Rows can be given as [`Blocks`] (converted with [`simple_cell`]) or as
+/// [`Cell`] to control alignment and spans. The number of columns is the
+/// widest row, counting column spans, or the number of alignments or widths
+/// if that is larger. The table is normalized with [`Table::normalize`], so
+/// shorter rows are padded with empty cells.
+///
+/// ```
+/// use pandoc_types::builder::*;
+/// use pandoc_types::definition::{Alignment, ColWidth};
+///
+/// let table = TableBuilder::new()
+///     .caption(simple_caption(plain("Scores")))
+///     .alignments([Alignment::AlignLeft, Alignment::AlignRight])
+///     .widths([0.75, 0.25])
+///     .header([plain("Name"), plain("Score")])
+///     .body([[plain("Alice"), plain("10")], [plain("Bob"), plain("8")]])
+///     .row_head_columns(1)
+///     .footer([cell(Alignment::AlignDefault, 1, 2, plain("Total: 18"))])
+///     .build();
+///
+/// assert_eq!(table.colspecs.len(), 2);
+/// assert_eq!(table.colspecs[1].0, Alignment::AlignRight);
+/// assert_eq!(table.colspecs[1].1, ColWidth::ColWidth(0.25));
+/// assert_eq!(table.bodies[0].row_head_columns, 1);
+/// assert_eq!(table.foot.rows[0].cells[0].col_span, 2);
+/// ```
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct TableBuilder {
+    attr: Attr,
+    caption: Caption,
+    alignments: Vec<Alignment>,
+    widths: Vec<f64>,
+    head: Vec<Row>,
+    bodies: Vec<TableBody>,
+    foot: Vec<Row>,
+}
+
+impl TableBuilder {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Sets the attributes of the table.
+    pub fn attr(mut self, attr: Attr) -> Self {
+        self.attr = attr;
+        self
+    }
+
+    pub fn caption(mut self, caption: Caption) -> Self {
+        self.caption = caption;
+        self
+    }
+
+    /// Sets the alignment of each column.
+    pub fn alignments(mut self, alignments: impl IntoIterator<Item = Alignment>) -> Self {
+        self.alignments = alignments.into_iter().collect();
+        self
+    }
+
+    /// Sets the width of each column, as a fraction of the text width.
+    pub fn widths(mut self, widths: impl IntoIterator<Item = f64>) -> Self {
+        self.widths = widths.into_iter().collect();
+        self
+    }
+
+    /// Adds a row to the table head.
+    pub fn header<C: Into<Cell>>(mut self, cells: impl IntoIterator<Item = C>) -> Self {
+        self.head.push(row(cells));
+        self
+    }
+
+    /// Adds a table body with the given rows.
+    pub fn body<R, C>(mut self, rows: impl IntoIterator<Item = R>) -> Self
+    where
+        R: IntoIterator<Item = C>,
+        C: Into<Cell>,
+    {
+        self.bodies.push(TableBody {
+            body: rows.into_iter().map(row).collect(),
+            ..Default::default()
+        });
+        self
+    }
+
+    /// Adds a table body as is, to control its attributes and intermediate head.
+    pub fn table_body(mut self, body: TableBody) -> Self {
+        self.bodies.push(body);
+        self
+    }
+
+    /// Sets the number of row head columns of the most recently added body.
+    pub fn row_head_columns(mut self, columns: i32) -> Self {
+        if let Some(body) = self.bodies.last_mut() {
+            body.row_head_columns = columns;
+        }
+        self
+    }
+
+    /// Adds a row to the table foot.
+    pub fn footer<C: Into<Cell>>(mut self, cells: impl IntoIterator<Item = C>) -> Self {
+        self.foot.push(row(cells));
+        self
+    }
+
+    pub fn build(self) -> Table {
+        let widest_row = self
+            .head
+            .iter()
+            .chain(
+                self.bodies
+                    .iter()
+                    .flat_map(|b| b.head.iter().chain(&b.body)),
+            )
+            .chain(&self.foot)
+            .map(|row| {
+                row.cells
+                    .iter()
+                    .map(|cell| cell.col_span.max(1))
+                    .sum::<i32>()
+            })
+            .max()
+            .unwrap_or(0) as usize;
+        let columns = widest_row.max(self.alignments.len()).max(self.widths.len());
+
+        let colspecs = (0..columns)
+            .map(|i| {
+                ColSpec(
+                    self.alignments.get(i).cloned().unwrap_or_default(),
+                    self.widths
+                        .get(i)
+                        .map_or(ColWidth::ColWidthDefault, |w| ColWidth::ColWidth(*w)),
+                )
+            })
+            .collect();
+
+        let mut table = Table {
+            attr: self.attr,
+            caption: self.caption,
+            colspecs,
+            head: TableHead {
+                attr: Attr::default(),
+                rows: self.head,
+            },
+            bodies: self.bodies,
+            foot: TableFoot {
+                attr: Attr::default(),
+                rows: self.foot,
+            },
+        };
+        table.normalize();
+        table
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn text_splits_whitespace() {
+        assert_eq!(
+            text(" a  b\n\tc ").into_vec(),
+            vec![
+                Inline::Space,
+                Inline::Str("a".into()),
+                Inline::Space,
+                Inline::Str("b".into()),
+                Inline::SoftBreak,
+                Inline::Str("c".into()),
+                Inline::Space,
+            ]
+        );
+        assert!(text("").is_empty());
+    }
+
+    #[test]
+    fn concatenation_melds() {
+        assert_eq!(
+            (str("a") + str("b")).into_vec(),
+            vec![Inline::Str("ab".into())]
+        );
+        assert_eq!(
+            (text("a ") + space() + soft_break() + text("b")).into_vec(),
+            vec![
+                Inline::Str("a".into()),
+                Inline::SoftBreak,
+                Inline::Str("b".into()),
+            ]
+        );
+        assert_eq!(
+            (text("a ") + line_break() + text(" b")).into_vec(),
+            vec![
+                Inline::Str("a".into()),
+                Inline::LineBreak,
+                Inline::Str("b".into()),
+            ]
+        );
+        assert_eq!(
+            (emph("a") + emph(text(" b"))).into_vec(),
+            vec![Inline::Emph(vec![
+                Inline::Str("a".into()),
+                Inline::Space,
+                Inline::Str("b".into()),
+            ])]
+        );
+        assert_eq!(
+            (emph("a") + emph("b")).into_vec(),
+            vec![Inline::Emph(vec![
+                Inline::Str("a".into()),
+                Inline::Str("b".into()),
+            ])]
+        );
+        assert_eq!(
+            (code("a") + code("b")).into_vec(),
+            vec![
+                Inline::Code(Attr::default(), "a".into()),
+                Inline::Code(Attr::default(), "b".into()),
+            ]
+        );
+    }
+
+    #[test]
+    fn concatenation_only_melds_boundary() {
+        let inlines = Inlines::from(vec![Inline::Space, Inline::Space]) + space();
+        assert_eq!(inlines.len(), 2);
+        let inlines: Inlines = vec![Inline::Space, Inline::Space].into_iter().collect();
+        assert_eq!(inlines.len(), 1);
+    }
+
+    #[test]
+    fn trim() {
+        assert_eq!(
+            trim_inlines(text("\n a b ") + line_break()).into_vec(),
+            vec![
+                Inline::Str("a".into()),
+                Inline::Space,
+                Inline::Str("b".into()),
+                Inline::LineBreak,
+            ]
+        );
+        assert_eq!(
+            trim_inlines(space() + line_break() + str("a") + soft_break()).into_vec(),
+            vec![Inline::LineBreak, Inline::Str("a".into())]
+        );
+        assert!(trim_inlines(text("   ")).is_empty());
+    }
+
+    #[test]
+    fn blocks() {
+        assert!(plain(Inlines::new()).is_empty());
+        let list = bullet_list(vec![para("a"), plain("b") + code_block("c")]);
+        assert_eq!(
+            list.into_vec(),
+            vec![Block::BulletList(vec![
+                vec![Block::Para(vec![Inline::Str("a".into())])],
+                vec![
+                    Block::Plain(vec![Inline::Str("b".into())]),
+                    Block::CodeBlock(Attr::default(), "c".into()),
+                ],
+            ])]
+        );
+        assert_eq!(
+            div(para("a")).into_vec(),
+            vec![Block::Div(
+                Attr::default(),
+                vec![Block::Para(vec![Inline::Str("a".into())])]
+            )]
+        );
+    }
+
+    #[test]
+    fn simple_table_columns() {
+        let blocks = simple_table(
+            vec![],
+            vec![vec![plain("a")], vec![plain("b"), plain("c"), plain("d")]],
+        );
+        let table = match &blocks[..] {
+            [Block::Table(table)] => table,
+            _ => panic!("expected a table"),
+        };
+        assert_eq!(table.colspecs, vec![ColSpec::default(); 3]);
+        assert!(table.head.rows.is_empty());
+        assert_eq!(table.bodies.len(), 1);
+        assert_eq!(table.bodies[0].body[1].cells.len(), 3);
+        assert_eq!(table.bodies[0].body[1].cells[2], simple_cell(plain("d")));
+        assert_eq!(table.bodies[0].body[0].cells[2], empty_cell());
+    }
+
+    #[test]
+    fn table_builder_spans_and_bodies() {
+        let table = TableBuilder::new()
+            .header([cell(Alignment::AlignCenter, 1, 3, plain("wide"))])
+            .body([[plain("a")]])
+            .body([[plain("b")]])
+            .row_head_columns(1)
+            .alignments([Alignment::AlignLeft])
+            .build();
+        assert_eq!(
+            table.colspecs,
+            vec![
+                ColSpec(Alignment::AlignLeft, ColWidth::ColWidthDefault),
+                ColSpec::default(),
+                ColSpec::default(),
+            ]
+        );
+        assert_eq!(table.bodies[0].row_head_columns, 0);
+        assert_eq!(table.bodies[1].row_head_columns, 1);
+    }
+}
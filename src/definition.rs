@@ -1,22 +1,33 @@
 //! This module contatins the types from [Text.Pandoc.Definition] ported to Rust.
 //!
 //! [Text.Pandoc.Definition]: https://hackage.haskell.org/package/pandoc-types/docs/Text-Pandoc-Definition.html
-use std::collections::HashMap;
-
+pub use convert::*;
+pub use de::*;
+pub use grid::*;
+pub use indexmap::IndexMap;
 pub use iter::*;
+pub use meta::*;
+pub use ser::*;
 use serde::ser::SerializeStruct;
 use serde::{Deserialize, Deserializer, Serialize, Serializer};
 use serde_tuple::{Deserialize_tuple, Serialize_tuple};
+pub use version::*;
 
+mod compat;
+mod convert;
+mod de;
 pub mod extra;
+mod grid;
 mod iter;
-
-const PANDOC_API_VERSION: [i32; 2] = [1, 23];
+mod meta;
+mod normalize;
+mod ser;
+mod version;
 
 #[derive(Debug, Clone, PartialEq, Default)]
 pub struct Pandoc {
     pub blocks: Vec<Block>,
-    pub meta: HashMap<String, MetaValue>,
+    pub meta: Meta,
 }
 
 impl Serialize for Pandoc {
@@ -33,42 +44,20 @@ impl Serialize for Pandoc {
 }
 
 impl<'a> Deserialize<'a> for Pandoc {
+    /// Deserializes a document written by pandoc-types 1.17 or 1.20 to
+    /// 1.23, see [`VersionPolicy`] to accept other versions.
     fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
     where
         D: Deserializer<'a>,
     {
-        #[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
-        #[serde(rename = "Pandoc")]
-        struct Inner {
-            meta: HashMap<String, MetaValue>,
-            blocks: Vec<Block>,
-            #[serde(rename = "pandoc-api-version")]
-            version: Vec<i32>,
-        }
-
-        let value = Inner::deserialize(deserializer)?;
-
-        if value.version.len() < 2
-            || value.version[0] != PANDOC_API_VERSION[0]
-            || value.version[1] != PANDOC_API_VERSION[1]
-        {
-            return Err(serde::de::Error::custom(format!(
-                "expected pandoc-api-version to start with {},{}",
-                PANDOC_API_VERSION[0], PANDOC_API_VERSION[1]
-            )));
-        }
-
-        Ok(Pandoc {
-            meta: value.meta,
-            blocks: value.blocks,
-        })
+        VersionedPandoc::deserialize(deserializer).map(VersionedPandoc::into_inner)
     }
 }
 
 #[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
 #[serde(tag = "t", content = "c")]
 pub enum MetaValue {
-    MetaMap(HashMap<String, MetaValue>),
+    MetaMap(IndexMap<String, MetaValue>),
     MetaList(Vec<MetaValue>),
     MetaBool(bool),
     MetaString(String),
@@ -78,6 +67,7 @@ pub enum MetaValue {
 
 #[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
 #[serde(tag = "t", content = "c")]
+#[allow(clippy::large_enum_variant)]
 pub enum Block {
     /// Plain text, not a paragraph
     Plain(Vec<Inline>),
@@ -111,7 +101,9 @@ pub enum Block {
     Null,
 }
 
-#[derive(Serialize_tuple, Deserialize_tuple, Debug, Clone, PartialEq, Default)]
+/// A table. Tables are (de)serialized by hand in order to also read the
+/// layout used before pandoc-types 1.21, see [`ApiVersion`].
+#[derive(Debug, Clone, PartialEq, Default)]
 pub struct Table {
     pub attr: Attr,
     pub caption: Caption,
@@ -166,34 +158,24 @@ pub enum Inline {
     Span(Attr, Vec<Inline>),
 }
 
-#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Default)]
 #[serde(tag = "t", content = "c")]
 pub enum Alignment {
     AlignLeft,
     AlignRight,
     AlignCenter,
+    #[default]
     AlignDefault,
 }
 
-impl Default for Alignment {
-    fn default() -> Self {
-        Self::AlignDefault
-    }
-}
-
-#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Default)]
 #[serde(tag = "t", content = "c")]
 pub enum ColWidth {
     ColWidth(f64),
+    #[default]
     ColWidthDefault,
 }
 
-impl Default for ColWidth {
-    fn default() -> Self {
-        Self::ColWidthDefault
-    }
-}
-
 #[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Default)]
 pub struct ColSpec(pub Alignment, pub ColWidth);
 
@@ -267,9 +249,10 @@ impl Default for ListAttributes {
     }
 }
 
-#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Default)]
 #[serde(tag = "t", content = "c")]
 pub enum ListNumberStyle {
+    #[default]
     DefaultStyle,
     Example,
     Decimal,
@@ -279,27 +262,16 @@ pub enum ListNumberStyle {
     UpperAlpha,
 }
 
-impl Default for ListNumberStyle {
-    fn default() -> Self {
-        Self::DefaultStyle
-    }
-}
-
-#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Default)]
 #[serde(tag = "t", content = "c")]
 pub enum ListNumberDelim {
+    #[default]
     DefaultDelim,
     Period,
     OneParen,
     TwoParens,
 }
 
-impl Default for ListNumberDelim {
-    fn default() -> Self {
-        Self::DefaultDelim
-    }
-}
-
 #[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
 pub struct Format(pub String);
 
@@ -371,10 +343,18 @@ mod tests {
         .is_err());
 
         assert!(serde_json::from_value::<Pandoc>(json!({
-            "pandoc-api-version": [PANDOC_API_VERSION[0], PANDOC_API_VERSION[1] + 1],
+            "pandoc-api-version": [PANDOC_API_VERSION.major, PANDOC_API_VERSION.minor + 1],
             "meta": {},
             "blocks": [],
         }))
         .is_err());
     }
+
+    #[test]
+    fn meta_order() {
+        let json = r#"{"pandoc-api-version":[1,23],"meta":{"z":{"t":"MetaBool","c":true},"a":{"t":"MetaMap","c":{"y":{"t":"MetaString","c":"1"},"b":{"t":"MetaString","c":"2"}}}},"blocks":[]}"#;
+        let doc: Pandoc = serde_json::from_str(json).unwrap();
+        assert_eq!(doc.meta.keys().collect::<Vec<_>>(), ["z", "a"]);
+        assert_eq!(serde_json::to_string(&doc).unwrap(), json);
+    }
 }
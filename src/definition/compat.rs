@@ -0,0 +1,602 @@
+use std::fmt;
+
+use serde::de::{self, Expected, SeqAccess, Visitor};
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+
+use super::{
+    Alignment, ApiVersion, Attr, Block, Caption, Cell, ColSpec, ColWidth, GridRow, GridSlot,
+    Inline, Pandoc, Row, Table, TableBody, TableFoot, TableHead, TablePart, Target,
+    VersionedPandoc,
+};
+use crate::walk::Walkable;
+
+impl Pandoc {
+    /// Prepares the document to be serialized in the JSON format of the given
+    /// version of pandoc-types, converting the elements that don't exist in
+    /// that version:
+    ///
+    ///   * Before 1.23, a `Figure` containing a single image, with a caption
+    ///     that is a single paragraph, becomes a paragraph containing only
+    ///     the image, with the caption as its alt text and a title prefixed
+    ///     by `fig:`, which is how pandoc used to represent figures. Other
+    ///     figures become a `Div` with class `figure`, with the caption
+    ///     appended in a `Div` with class `caption`; short captions are lost.
+    ///   * Before 1.21, `Underline` becomes a `Span` with class `underline`,
+    ///     and tables are written in the old layout: the caption is flattened
+    ///     to inlines (with paragraphs separated by line breaks), the first
+    ///     row of the table head becomes the header, all other rows become
+    ///     the body, cells spanning several rows or columns are followed by
+    ///     empty cells, and default column widths are written as `0`.
+    ///
+    /// ```
+    /// use pandoc_types::builder::{doc, para, text, underline};
+    /// use pandoc_types::definition::ApiVersion;
+    ///
+    /// let json = serde_json::to_value(
+    ///     doc(para(underline(text("old")))).into_api_version(ApiVersion::V1_20),
+    /// )
+    /// .unwrap();
+    ///
+    /// assert_eq!(json["pandoc-api-version"], serde_json::json!([1, 20]));
+    /// assert_eq!(json["blocks"][0]["c"][0]["t"], "Span");
+    /// ```
+    pub fn into_api_version(self, version: ApiVersion) -> VersionedPandoc {
+        let mut doc = self;
+        if version < ApiVersion::V1_23 {
+            doc = doc.walk(downgrade_figure);
+        }
+        if version < ApiVersion::V1_21 {
+            doc = doc.walk(downgrade_underline);
+        }
+        VersionedPandoc {
+            version,
+            doc,
+            warnings: Vec::new(),
+        }
+    }
+
+    /// Upgrades a document read in the JSON format of the given version.
+    ///
+    /// Tables in the old layout are upgraded when they are deserialized,
+    /// since they can be told apart from the current layout.
+    pub(super) fn upgrade_from(self, version: ApiVersion) -> Pandoc {
+        if version < ApiVersion::V1_23 {
+            self.walk(upgrade_figure)
+        } else {
+            self
+        }
+    }
+}
+
+/// Converts a paragraph containing only an image with a title prefixed by
+/// `fig:` into a `Figure`, like pandoc 3 does when reading such images.
+fn upgrade_figure(block: Block) -> Block {
+    if let Block::Para(inlines) = &block {
+        if let [Inline::Image(attr, alt, target)] = &inlines[..] {
+            if let Some(title) = target.title.strip_prefix("fig:") {
+                let image = Inline::Image(
+                    Attr {
+                        identifier: String::new(),
+                        ..attr.clone()
+                    },
+                    alt.clone(),
+                    Target {
+                        url: target.url.clone(),
+                        title: title.to_owned(),
+                    },
+                );
+                return Block::Figure(
+                    Attr {
+                        identifier: attr.identifier.clone(),
+                        ..Default::default()
+                    },
+                    Caption {
+                        short: None,
+                        long: if alt.is_empty() {
+                            vec![]
+                        } else {
+                            vec![Block::Plain(alt.clone())]
+                        },
+                    },
+                    vec![Block::Plain(vec![image])],
+                );
+            }
+        }
+    }
+    block
+}
+
+fn downgrade_figure(block: Block) -> Block {
+    let (attr, caption, mut content) = match block {
+        Block::Figure(attr, caption, content) => (attr, caption, content),
+        other => return other,
+    };
+
+    let simple_caption = match &caption.long[..] {
+        [] => Some(vec![]),
+        [Block::Plain(inlines)] | [Block::Para(inlines)] => Some(inlines.clone()),
+        _ => None,
+    };
+    if let (Some(caption), [Block::Plain(inlines) | Block::Para(inlines)]) =
+        (simple_caption, &mut content[..])
+    {
+        if let [Inline::Image(image_attr, _, target)] = &mut inlines[..] {
+            let plain_attr = attr.classes.is_empty() && attr.attributes.is_empty();
+            let one_identifier = attr.identifier.is_empty() || image_attr.identifier.is_empty();
+            if plain_attr && one_identifier {
+                if image_attr.identifier.is_empty() {
+                    image_attr.identifier = attr.identifier;
+                }
+                return Block::Para(vec![Inline::Image(
+                    std::mem::take(image_attr),
+                    caption,
+                    Target {
+                        url: std::mem::take(&mut target.url),
+                        title: format!("fig:{}", target.title),
+                    },
+                )]);
+            }
+        }
+    }
+
+    let mut classes = vec!["figure".to_owned()];
+    classes.extend(attr.classes);
+    if !caption.long.is_empty() {
+        content.push(Block::Div(
+            Attr {
+                classes: vec!["caption".to_owned()],
+                ..Default::default()
+            },
+            caption.long,
+        ));
+    }
+    Block::Div(
+        Attr {
+            identifier: attr.identifier,
+            classes,
+            attributes: attr.attributes,
+        },
+        content,
+    )
+}
+
+fn downgrade_underline(inline: Inline) -> Inline {
+    match inline {
+        Inline::Underline(inlines) => Inline::Span(
+            Attr {
+                classes: vec!["underline".to_owned()],
+                ..Default::default()
+            },
+            inlines,
+        ),
+        other => other,
+    }
+}
+
+thread_local! {
+    /// Whether tables are currently being serialized in the layout used before
+    /// pandoc-types 1.21. Serde has no way to pass context down to nested
+    /// values, so this is set for the duration of serializing a
+    /// [`VersionedPandoc`].
+    static LEGACY_TABLES: std::cell::Cell<bool> = const { std::cell::Cell::new(false) };
+}
+
+/// Sets [`LEGACY_TABLES`] and restores its previous value when dropped.
+pub(super) struct LegacyTablesGuard(bool);
+
+impl LegacyTablesGuard {
+    pub(super) fn set(legacy: bool) -> Self {
+        LegacyTablesGuard(LEGACY_TABLES.with(|x| x.replace(legacy)))
+    }
+}
+
+impl Drop for LegacyTablesGuard {
+    fn drop(&mut self) {
+        LEGACY_TABLES.with(|x| x.set(self.0));
+    }
+}
+
+impl Serialize for Table {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        if LEGACY_TABLES.with(|x| x.get()) {
+            return serialize_legacy_table(self, serializer);
+        }
+        (
+            &self.attr,
+            &self.caption,
+            &self.colspecs,
+            &self.head,
+            &self.bodies,
+            &self.foot,
+        )
+            .serialize(serializer)
+    }
+}
+
+fn serialize_legacy_table<S>(table: &Table, serializer: S) -> Result<S::Ok, S::Error>
+where
+    S: Serializer,
+{
+    let mut caption = Vec::new();
+    for block in &table.caption.long {
+        let lines = match block {
+            Block::Plain(inlines) | Block::Para(inlines) => std::slice::from_ref(inlines),
+            Block::LineBlock(lines) => &lines[..],
+            _ => continue,
+        };
+        for line in lines {
+            if !caption.is_empty() {
+                caption.push(Inline::LineBreak);
+            }
+            caption.extend(line.iter().cloned());
+        }
+    }
+
+    let aligns: Vec<&Alignment> = table.colspecs.iter().map(|ColSpec(a, _)| a).collect();
+    let widths: Vec<f64> = table
+        .colspecs
+        .iter()
+        .map(|ColSpec(_, width)| match width {
+            ColWidth::ColWidth(width) => *width,
+            ColWidth::ColWidthDefault => 0.0,
+        })
+        .collect();
+
+    let grid = table.grid();
+    fn cells<'a>(row: &GridRow<'a>) -> Vec<&'a [Block]> {
+        row.slots
+            .iter()
+            .map(|slot| match slot {
+                GridSlot::Origin(cell) => &cell.content[..],
+                _ => &[],
+            })
+            .collect()
+    }
+    let mut rows = grid.rows.iter().peekable();
+    let header = match rows.next_if(|row| row.part == TablePart::Head) {
+        Some(row) => cells(row),
+        None => vec![&[][..]; grid.width],
+    };
+    let rows: Vec<Vec<&[Block]>> = rows.map(cells).collect();
+
+    (caption, aligns, widths, header, rows).serialize(serializer)
+}
+
+impl<'de> Deserialize<'de> for Table {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        deserializer.deserialize_seq(TableVisitor)
+    }
+}
+
+/// Deserializes tables in both the current layout and the layout used
+/// before pandoc-types 1.21, which are told apart by their first element.
+struct TableVisitor;
+
+impl<'de> Visitor<'de> for TableVisitor {
+    type Value = Table;
+
+    fn expecting(&self, formatter: &mut fmt::Formatter<'_>) -> fmt::Result {
+        formatter.write_str("a table")
+    }
+
+    fn visit_seq<A>(self, mut seq: A) -> Result<Self::Value, A::Error>
+    where
+        A: SeqAccess<'de>,
+    {
+        #[derive(Deserialize)]
+        #[serde(untagged)]
+        enum Start {
+            Attr(Attr),
+            Caption(Vec<Inline>),
+        }
+
+        match next_element(&mut seq, 0, &self)? {
+            Start::Attr(attr) => Ok(Table {
+                attr,
+                caption: next_element(&mut seq, 1, &self)?,
+                colspecs: next_element(&mut seq, 2, &self)?,
+                head: next_element(&mut seq, 3, &self)?,
+                bodies: next_element(&mut seq, 4, &self)?,
+                foot: next_element(&mut seq, 5, &self)?,
+            }),
+            Start::Caption(caption) => Ok(upgrade_table(
+                caption,
+                next_element(&mut seq, 1, &self)?,
+                next_element(&mut seq, 2, &self)?,
+                next_element(&mut seq, 3, &self)?,
+                next_element(&mut seq, 4, &self)?,
+            )),
+        }
+    }
+}
+
+fn next_element<'de, T, A>(
+    seq: &mut A,
+    index: usize,
+    expected: &dyn Expected,
+) -> Result<T, A::Error>
+where
+    T: Deserialize<'de>,
+    A: SeqAccess<'de>,
+{
+    seq.next_element()?
+        .ok_or_else(|| de::Error::invalid_length(index, expected))
+}
+
+/// Converts a table in the layout used before pandoc-types 1.21.
+fn upgrade_table(
+    caption: Vec<Inline>,
+    aligns: Vec<Alignment>,
+    widths: Vec<f64>,
+    header: Vec<Vec<Block>>,
+    rows: Vec<Vec<Vec<Block>>>,
+) -> Table {
+    fn row(cells: Vec<Vec<Block>>) -> Row {
+        Row {
+            attr: Attr::default(),
+            cells: cells
+                .into_iter()
+                .map(|content| Cell {
+                    content,
+                    ..Default::default()
+                })
+                .collect(),
+        }
+    }
+
+    let colspecs = aligns
+        .into_iter()
+        .zip(widths.into_iter().chain(std::iter::repeat(0.0)))
+        .map(|(align, width)| {
+            if width > 0.0 {
+                ColSpec(align, ColWidth::ColWidth(width))
+            } else {
+                ColSpec(align, ColWidth::ColWidthDefault)
+            }
+        })
+        .collect();
+
+    let mut table = Table {
+        attr: Attr::default(),
+        caption: Caption {
+            short: None,
+            long: if caption.is_empty() {
+                vec![]
+            } else {
+                vec![Block::Plain(caption)]
+            },
+        },
+        colspecs,
+        head: TableHead {
+            attr: Attr::default(),
+            rows: if header.iter().all(Vec::is_empty) {
+                vec![]
+            } else {
+                vec![row(header)]
+            },
+        },
+        bodies: vec![TableBody {
+            body: rows.into_iter().map(row).collect(),
+            ..Default::default()
+        }],
+        foot: TableFoot::default(),
+    };
+    table.normalize();
+    table
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn str(s: &str) -> Inline {
+        Inline::Str(s.into())
+    }
+
+    fn legacy_table_json() -> serde_json::Value {
+        json!({
+            "t": "Table",
+            "c": [
+                [{"t": "Str", "c": "caption"}],
+                [{"t": "AlignLeft"}, {"t": "AlignDefault"}],
+                [0.5, 0.0],
+                [[{"t": "Plain", "c": [{"t": "Str", "c": "a"}]}], []],
+                [[[{"t": "Plain", "c": [{"t": "Str", "c": "1"}]}], [{"t": "Plain", "c": [{"t": "Str", "c": "2"}]}]]],
+            ],
+        })
+    }
+
+    fn cell(content: &str) -> Cell {
+        Cell {
+            content: vec![Block::Plain(vec![str(content)])],
+            ..Default::default()
+        }
+    }
+
+    fn upgraded_table() -> Table {
+        Table {
+            caption: Caption {
+                short: None,
+                long: vec![Block::Plain(vec![str("caption")])],
+            },
+            colspecs: vec![
+                ColSpec(Alignment::AlignLeft, ColWidth::ColWidth(0.5)),
+                ColSpec(Alignment::AlignDefault, ColWidth::ColWidthDefault),
+            ],
+            head: TableHead {
+                attr: Attr::default(),
+                rows: vec![Row {
+                    attr: Attr::default(),
+                    cells: vec![cell("a"), Cell::default()],
+                }],
+            },
+            bodies: vec![TableBody {
+                body: vec![Row {
+                    attr: Attr::default(),
+                    cells: vec![cell("1"), cell("2")],
+                }],
+                ..Default::default()
+            }],
+            ..Default::default()
+        }
+    }
+
+    fn legacy_doc(version: [u32; 2], blocks: serde_json::Value) -> serde_json::Value {
+        json!({
+            "pandoc-api-version": version,
+            "meta": {},
+            "blocks": blocks,
+        })
+    }
+
+    #[test]
+    fn read_legacy_table() {
+        let doc: Pandoc =
+            serde_json::from_value(legacy_doc([1, 17], json!([legacy_table_json()]))).unwrap();
+        assert_eq!(doc.blocks, vec![Block::Table(upgraded_table())]);
+    }
+
+    #[test]
+    fn write_legacy_table() {
+        let doc = Pandoc {
+            blocks: vec![Block::Table(upgraded_table())],
+            ..Default::default()
+        };
+        let json = serde_json::to_value(doc.clone().into_api_version(ApiVersion::V1_17)).unwrap();
+        assert_eq!(json, legacy_doc([1, 17], json!([legacy_table_json()])));
+
+        // Tables are written in the current layout outside of a legacy document.
+        let json = serde_json::to_value(&doc).unwrap();
+        assert_eq!(json["blocks"][0]["c"].as_array().unwrap().len(), 6);
+    }
+
+    #[test]
+    fn figures() {
+        let image = |id: &str, title: &str, alt: Vec<Inline>| {
+            Inline::Image(
+                Attr {
+                    identifier: id.into(),
+                    classes: vec!["c".into()],
+                    attributes: vec![],
+                },
+                alt,
+                Target {
+                    url: "a.png".into(),
+                    title: title.into(),
+                },
+            )
+        };
+        let legacy = vec![Block::Para(vec![image(
+            "fig",
+            "fig:title",
+            vec![str("alt")],
+        )])];
+        let figure = Block::Figure(
+            Attr {
+                identifier: "fig".into(),
+                ..Default::default()
+            },
+            Caption {
+                short: None,
+                long: vec![Block::Plain(vec![str("alt")])],
+            },
+            vec![Block::Plain(vec![image("", "title", vec![str("alt")])])],
+        );
+
+        let doc: Pandoc =
+            serde_json::from_value(legacy_doc([1, 22], serde_json::to_value(&legacy).unwrap()))
+                .unwrap();
+        assert_eq!(doc.blocks, vec![figure.clone()]);
+
+        let doc = doc.into_api_version(ApiVersion::V1_22).into_inner();
+        assert_eq!(doc.blocks, legacy);
+
+        // Current documents are not upgraded.
+        let doc: Pandoc =
+            serde_json::from_value(legacy_doc([1, 23], serde_json::to_value(&legacy).unwrap()))
+                .unwrap();
+        assert_eq!(doc.blocks, legacy);
+    }
+
+    #[test]
+    fn round_trip_legacy_documents() {
+        let figure = json!([{
+            "t": "Para",
+            "c": [{
+                "t": "Image",
+                "c": [["fig", [], []], [{"t": "Str", "c": "alt"}], ["a.png", "fig:title"]],
+            }],
+        }]);
+        let json = json!({
+            "pandoc-api-version": [1, 22, 2, 1],
+            "meta": {},
+            "blocks": figure,
+        });
+        let doc: VersionedPandoc = serde_json::from_value(json.clone()).unwrap();
+        assert!(matches!(doc.doc().blocks[..], [Block::Figure(..)]));
+        assert_eq!(serde_json::to_value(&doc).unwrap(), json);
+
+        let json = legacy_doc([1, 20], json!([legacy_table_json()]));
+        let doc: VersionedPandoc = serde_json::from_value(json.clone()).unwrap();
+        assert_eq!(doc.doc().blocks, vec![Block::Table(upgraded_table())]);
+        assert_eq!(serde_json::to_value(&doc).unwrap(), json);
+    }
+
+    #[test]
+    fn complex_figure() {
+        let figure = Block::Figure(
+            Attr {
+                identifier: "fig".into(),
+                ..Default::default()
+            },
+            Caption {
+                short: None,
+                long: vec![Block::Para(vec![str("caption")])],
+            },
+            vec![Block::Para(vec![str("a")]), Block::Para(vec![str("b")])],
+        );
+        let doc = Pandoc {
+            blocks: vec![figure],
+            ..Default::default()
+        };
+        let doc = doc.into_api_version(ApiVersion::V1_22).into_inner();
+        assert_eq!(
+            doc.blocks,
+            vec![Block::Div(
+                Attr {
+                    identifier: "fig".into(),
+                    classes: vec!["figure".into()],
+                    attributes: vec![],
+                },
+                vec![
+                    Block::Para(vec![str("a")]),
+                    Block::Para(vec![str("b")]),
+                    Block::Div(
+                        Attr {
+                            classes: vec!["caption".into()],
+                            ..Default::default()
+                        },
+                        vec![Block::Para(vec![str("caption")])],
+                    ),
+                ],
+            )]
+        );
+    }
+
+    #[test]
+    fn unsupported_versions() {
+        for version in [[1, 16], [1, 24], [2, 17]] {
+            assert!(serde_json::from_value::<Pandoc>(legacy_doc(version, json!([]))).is_err());
+        }
+        for version in [[1, 17], [1, 20], [1, 21], [1, 22], [1, 23]] {
+            assert!(serde_json::from_value::<Pandoc>(legacy_doc(version, json!([]))).is_ok());
+        }
+    }
+}
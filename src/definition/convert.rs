@@ -0,0 +1,349 @@
+use std::collections::{BTreeMap, HashMap};
+use std::hash::{BuildHasher, Hash};
+
+use super::{Block, IndexMap, Inline, IterBlocks, IterInlines, Meta, MetaValue, Stringify};
+use crate::builder::{text, Blocks, Inlines};
+
+/// Conversion of a value into a [`MetaValue`], like Haskell's `ToMetaValue`
+/// class.
+///
+/// Strings and numbers become `MetaString`, sequences become `MetaList` and
+/// maps with string keys become `MetaMap`. Note that `Vec<Inline>` and
+/// `Vec<Block>` become `MetaInlines` and `MetaBlocks` rather than lists.
+///
+/// ```
+/// use pandoc_types::definition::{HasMeta, Meta, MetaValue};
+///
+/// let mut meta = Meta::null_meta();
+/// meta.set_meta("tags", vec!["a", "b"]);
+///
+/// assert_eq!(
+///     meta.lookup("tags"),
+///     Some(&MetaValue::MetaList(vec![
+///         MetaValue::MetaString("a".into()),
+///         MetaValue::MetaString("b".into()),
+///     ]))
+/// );
+/// ```
+pub trait ToMetaValue {
+    fn to_meta_value(self) -> MetaValue;
+}
+
+/// Conversion of a [`MetaValue`] into a value, the inverse of
+/// [`ToMetaValue`].
+///
+/// Conversions are lenient, since pandoc doesn't keep the types of YAML
+/// values: a string field usually ends up as `MetaInlines`, which is
+/// stringified when converting to a `String`; numbers and booleans are
+/// parsed from strings; and a single value is accepted where a list is
+/// expected. Returns `None` if the value can't be converted.
+///
+/// ```
+/// use pandoc_types::definition::{FromMetaValue, Inline, MetaValue};
+///
+/// let value = MetaValue::MetaInlines(vec![
+///     Inline::Str("Hello".into()),
+///     Inline::Space,
+///     Inline::Emph(vec![Inline::Str("world".into())]),
+/// ]);
+///
+/// assert_eq!(String::from_meta_value(&value), Some("Hello world".to_owned()));
+/// assert_eq!(Vec::<String>::from_meta_value(&value), Some(vec!["Hello world".to_owned()]));
+/// assert_eq!(i32::from_meta_value(&value), None);
+/// ```
+pub trait FromMetaValue: Sized {
+    fn from_meta_value(value: &MetaValue) -> Option<Self>;
+}
+
+impl Meta {
+    /// Retrieves the metadata value for the given key, converted with
+    /// [`FromMetaValue`].
+    pub fn lookup_as<T: FromMetaValue>(&self, key: &str) -> Option<T> {
+        self.lookup(key).and_then(T::from_meta_value)
+    }
+}
+
+/// Converts a textual metadata value into a string, with the paragraphs of
+/// `MetaBlocks` separated by blank lines.
+pub(crate) fn meta_string(value: &MetaValue) -> Option<String> {
+    fn paragraphs<'a>(blocks: impl IntoIterator<Item = &'a Block>, out: &mut Vec<String>) {
+        for block in blocks {
+            let mut s = String::new();
+            for inline in block.iter_inlines() {
+                inline.stringify_to(&mut s);
+            }
+            if !s.is_empty() {
+                out.push(s);
+            }
+            paragraphs(block.iter_blocks(), out);
+        }
+    }
+
+    match value {
+        MetaValue::MetaString(s) => Some(s.clone()),
+        MetaValue::MetaInlines(inlines) => Some(inlines.stringify()),
+        MetaValue::MetaBlocks(blocks) => {
+            let mut out = Vec::new();
+            paragraphs(blocks, &mut out);
+            Some(out.join("\n\n"))
+        }
+        _ => None,
+    }
+}
+
+impl ToMetaValue for MetaValue {
+    fn to_meta_value(self) -> MetaValue {
+        self
+    }
+}
+
+impl FromMetaValue for MetaValue {
+    fn from_meta_value(value: &MetaValue) -> Option<Self> {
+        Some(value.clone())
+    }
+}
+
+impl ToMetaValue for bool {
+    fn to_meta_value(self) -> MetaValue {
+        MetaValue::MetaBool(self)
+    }
+}
+
+impl FromMetaValue for bool {
+    fn from_meta_value(value: &MetaValue) -> Option<Self> {
+        match value {
+            MetaValue::MetaBool(b) => Some(*b),
+            other => match meta_string(other)?.trim() {
+                "true" | "True" | "TRUE" => Some(true),
+                "false" | "False" | "FALSE" => Some(false),
+                _ => None,
+            },
+        }
+    }
+}
+
+impl ToMetaValue for String {
+    fn to_meta_value(self) -> MetaValue {
+        MetaValue::MetaString(self)
+    }
+}
+
+impl ToMetaValue for &str {
+    fn to_meta_value(self) -> MetaValue {
+        MetaValue::MetaString(self.to_owned())
+    }
+}
+
+impl FromMetaValue for String {
+    fn from_meta_value(value: &MetaValue) -> Option<Self> {
+        match value {
+            MetaValue::MetaBool(b) => Some(b.to_string()),
+            other => meta_string(other),
+        }
+    }
+}
+
+macro_rules! number_meta_value {
+    ($($t:ty),*) => {
+        $(
+            impl ToMetaValue for $t {
+                fn to_meta_value(self) -> MetaValue {
+                    MetaValue::MetaString(self.to_string())
+                }
+            }
+
+            impl FromMetaValue for $t {
+                fn from_meta_value(value: &MetaValue) -> Option<Self> {
+                    meta_string(value)?.trim().parse().ok()
+                }
+            }
+        )*
+    };
+}
+
+number_meta_value!(i8, i16, i32, i64, i128, isize, u8, u16, u32, u64, u128, usize, f32, f64);
+
+impl ToMetaValue for Vec<Inline> {
+    fn to_meta_value(self) -> MetaValue {
+        MetaValue::MetaInlines(self)
+    }
+}
+
+impl FromMetaValue for Vec<Inline> {
+    fn from_meta_value(value: &MetaValue) -> Option<Self> {
+        match value {
+            MetaValue::MetaString(s) => Some(text(s).into_vec()),
+            MetaValue::MetaInlines(inlines) => Some(inlines.clone()),
+            MetaValue::MetaBlocks(blocks) => match &blocks[..] {
+                [Block::Plain(inlines)] | [Block::Para(inlines)] => Some(inlines.clone()),
+                _ => None,
+            },
+            _ => None,
+        }
+    }
+}
+
+impl ToMetaValue for Vec<Block> {
+    fn to_meta_value(self) -> MetaValue {
+        MetaValue::MetaBlocks(self)
+    }
+}
+
+impl FromMetaValue for Vec<Block> {
+    fn from_meta_value(value: &MetaValue) -> Option<Self> {
+        match value {
+            MetaValue::MetaString(s) => Some(vec![Block::Plain(text(s).into_vec())]),
+            MetaValue::MetaInlines(inlines) => Some(vec![Block::Plain(inlines.clone())]),
+            MetaValue::MetaBlocks(blocks) => Some(blocks.clone()),
+            _ => None,
+        }
+    }
+}
+
+impl ToMetaValue for Inlines {
+    fn to_meta_value(self) -> MetaValue {
+        MetaValue::MetaInlines(self.into_vec())
+    }
+}
+
+impl FromMetaValue for Inlines {
+    fn from_meta_value(value: &MetaValue) -> Option<Self> {
+        Vec::<Inline>::from_meta_value(value).map(Inlines::from)
+    }
+}
+
+impl ToMetaValue for Blocks {
+    fn to_meta_value(self) -> MetaValue {
+        MetaValue::MetaBlocks(self.into_vec())
+    }
+}
+
+impl FromMetaValue for Blocks {
+    fn from_meta_value(value: &MetaValue) -> Option<Self> {
+        Vec::<Block>::from_meta_value(value).map(Blocks::from)
+    }
+}
+
+impl<T: ToMetaValue> ToMetaValue for Vec<T> {
+    fn to_meta_value(self) -> MetaValue {
+        MetaValue::MetaList(self.into_iter().map(T::to_meta_value).collect())
+    }
+}
+
+impl<T: FromMetaValue> FromMetaValue for Vec<T> {
+    fn from_meta_value(value: &MetaValue) -> Option<Self> {
+        match value {
+            MetaValue::MetaList(values) => values.iter().map(T::from_meta_value).collect(),
+            other => T::from_meta_value(other).map(|x| vec![x]),
+        }
+    }
+}
+
+macro_rules! map_meta_value {
+    ($map:ident <$($bound:ident),*> $(, $s:ident: $s_bound:ident)?) => {
+        impl<K, V $(, $s)?> ToMetaValue for $map<K, V $(, $s)?>
+        where
+            K: Into<String>,
+            V: ToMetaValue,
+        {
+            fn to_meta_value(self) -> MetaValue {
+                MetaValue::MetaMap(
+                    self.into_iter()
+                        .map(|(k, v)| (k.into(), v.to_meta_value()))
+                        .collect(),
+                )
+            }
+        }
+
+        impl<K, V $(, $s)?> FromMetaValue for $map<K, V $(, $s)?>
+        where
+            K: From<String> $(+ $bound)*,
+            V: FromMetaValue,
+            $($s: $s_bound + Default,)?
+        {
+            fn from_meta_value(value: &MetaValue) -> Option<Self> {
+                match value {
+                    MetaValue::MetaMap(map) => map
+                        .iter()
+                        .map(|(k, v)| Some((K::from(k.clone()), V::from_meta_value(v)?)))
+                        .collect(),
+                    _ => None,
+                }
+            }
+        }
+    };
+}
+
+map_meta_value!(IndexMap<Hash, Eq>, S: BuildHasher);
+map_meta_value!(HashMap<Hash, Eq>, S: BuildHasher);
+map_meta_value!(BTreeMap<Ord>);
+
+impl ToMetaValue for Meta {
+    fn to_meta_value(self) -> MetaValue {
+        MetaValue::MetaMap(self.0)
+    }
+}
+
+impl FromMetaValue for Meta {
+    fn from_meta_value(value: &MetaValue) -> Option<Self> {
+        match value {
+            MetaValue::MetaMap(map) => Some(Meta(map.clone())),
+            _ => None,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::builder::{emph, para, text};
+
+    #[test]
+    fn round_trips() {
+        assert_eq!(bool::from_meta_value(&true.to_meta_value()), Some(true));
+        assert_eq!(i64::from_meta_value(&(-3i64).to_meta_value()), Some(-3));
+        assert_eq!(f64::from_meta_value(&1.5.to_meta_value()), Some(1.5));
+
+        let tags = vec!["a".to_owned(), "b".to_owned()];
+        assert_eq!(
+            Vec::<String>::from_meta_value(&tags.clone().to_meta_value()),
+            Some(tags)
+        );
+
+        let mut map = BTreeMap::new();
+        map.insert("x".to_owned(), vec![1, 2]);
+        assert_eq!(
+            BTreeMap::<String, Vec<u8>>::from_meta_value(&map.clone().to_meta_value()),
+            Some(map)
+        );
+    }
+
+    #[test]
+    fn lenient() {
+        let inlines = (text("Hello") + emph("world")).to_meta_value();
+        assert!(matches!(inlines, MetaValue::MetaInlines(_)));
+        assert_eq!(
+            String::from_meta_value(&inlines),
+            Some("Helloworld".to_owned())
+        );
+
+        let blocks = (para(text("one")) + para(text("two"))).to_meta_value();
+        assert_eq!(
+            String::from_meta_value(&blocks),
+            Some("one\n\ntwo".to_owned())
+        );
+        assert_eq!(
+            u8::from_meta_value(&para(text(" 2 ")).to_meta_value()),
+            Some(2)
+        );
+        assert_eq!(
+            bool::from_meta_value(&MetaValue::MetaInlines(vec![Inline::Str("true".into())])),
+            Some(true)
+        );
+        assert_eq!(u8::from_meta_value(&MetaValue::MetaBool(true)), None);
+        assert_eq!(
+            Vec::<Inline>::from_meta_value(&MetaValue::MetaString("a b".into())),
+            Some(text("a b").into_vec())
+        );
+    }
+}
@@ -0,0 +1,624 @@
+use std::borrow::Cow;
+use std::fmt;
+
+use serde::de::value::{BorrowedStrDeserializer, StringDeserializer};
+use serde::de::{self, Expected, IntoDeserializer, Unexpected, Visitor};
+use serde::{ser, Deserialize};
+
+use super::convert::meta_string;
+use super::{IndexMap, Meta, MetaValue};
+
+/// An error converting between metadata and Rust values with serde.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MetaError {
+    path: Option<String>,
+    message: String,
+}
+
+impl MetaError {
+    /// The path of the metadata value where the error happened, such as
+    /// `authors[1].name`. This is empty for the root value.
+    pub fn path(&self) -> &str {
+        self.path.as_deref().unwrap_or_default()
+    }
+
+    pub fn message(&self) -> &str {
+        &self.message
+    }
+
+    /// Sets the path of the error unless it was set by a nested value.
+    pub(super) fn at(mut self, path: &str) -> Self {
+        if self.path.is_none() {
+            self.path = Some(path.to_owned());
+        }
+        self
+    }
+
+    /// Prefixes the path of the error with a key or index of the value
+    /// containing it.
+    pub(super) fn within(mut self, segment: &str) -> Self {
+        self.path = Some(match self.path.as_deref() {
+            None | Some("") => segment.to_owned(),
+            Some(path) if path.starts_with('[') => format!("{}{}", segment, path),
+            Some(path) => format!("{}.{}", segment, path),
+        });
+        self
+    }
+}
+
+impl fmt::Display for MetaError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.path() {
+            "" => f.write_str(&self.message),
+            path => write!(f, "{}: {}", path, self.message),
+        }
+    }
+}
+
+impl std::error::Error for MetaError {}
+
+impl ser::Error for MetaError {
+    fn custom<T: fmt::Display>(msg: T) -> Self {
+        de::Error::custom(msg)
+    }
+}
+
+impl de::Error for MetaError {
+    fn custom<T: fmt::Display>(msg: T) -> Self {
+        MetaError {
+            path: None,
+            message: msg.to_string(),
+        }
+    }
+}
+
+/// Deserializes a value from the fields of document metadata.
+///
+/// Metadata is interpreted leniently, like [`FromMetaValue`]: `MetaInlines`
+/// and `MetaBlocks` are stringified where a string is expected, numbers and
+/// booleans are parsed from strings, and a single value is accepted where a
+/// sequence is expected. Types from the pandoc AST such as [`Inline`] can't
+/// be deserialized this way, since their serde representation is the JSON
+/// format; use [`FromMetaValue`] on the fields instead.
+///
+/// ```
+/// use pandoc_types::definition::{from_meta, HasMeta, Inline, Meta, MetaValue};
+/// use serde::Deserialize;
+///
+/// #[derive(Deserialize)]
+/// struct Frontmatter {
+///     title: String,
+///     tags: Vec<String>,
+///     #[serde(default)]
+///     draft: bool,
+/// }
+///
+/// let mut meta = Meta::null_meta();
+/// meta.set_meta("title", vec![Inline::Str("Hello".into())]);
+/// meta.set_meta("tags", "rust");
+///
+/// let frontmatter: Frontmatter = from_meta(&meta).unwrap();
+/// assert_eq!(frontmatter.title, "Hello");
+/// assert_eq!(frontmatter.tags, vec!["rust"]);
+/// assert!(!frontmatter.draft);
+///
+/// meta.set_meta("draft", vec!["yes", "no"]);
+/// let err = from_meta::<Frontmatter>(&meta).err().unwrap();
+/// assert_eq!(err.path(), "draft");
+/// ```
+///
+/// [`FromMetaValue`]: super::FromMetaValue
+/// [`Inline`]: super::Inline
+pub fn from_meta<'de, T: Deserialize<'de>>(meta: &'de Meta) -> Result<T, MetaError> {
+    T::deserialize(MetaDeserializer::from_meta(meta))
+}
+
+/// Deserializes a value from a metadata value, see [`from_meta`].
+pub fn from_meta_value<'de, T: Deserialize<'de>>(value: &'de MetaValue) -> Result<T, MetaError> {
+    T::deserialize(MetaDeserializer::new(value))
+}
+
+/// A serde deserializer for metadata, see [`from_meta`].
+pub struct MetaDeserializer<'de> {
+    node: Node<'de>,
+    path: String,
+}
+
+#[derive(Clone, Copy)]
+enum Node<'de> {
+    Value(&'de MetaValue),
+    Map(&'de IndexMap<String, MetaValue>),
+    /// A key of a map, which may be parsed as a number like other strings.
+    Key(&'de str),
+}
+
+impl<'de> MetaDeserializer<'de> {
+    pub fn new(value: &'de MetaValue) -> Self {
+        MetaDeserializer {
+            node: Node::Value(value),
+            path: String::new(),
+        }
+    }
+
+    pub fn from_meta(meta: &'de Meta) -> Self {
+        MetaDeserializer {
+            node: Node::Map(meta),
+            path: String::new(),
+        }
+    }
+
+    fn string(&self) -> Option<Cow<'de, str>> {
+        match self.node {
+            Node::Value(MetaValue::MetaString(s)) => Some(Cow::Borrowed(s)),
+            Node::Value(MetaValue::MetaBool(b)) => Some(Cow::Owned(b.to_string())),
+            Node::Value(value) => meta_string(value).map(Cow::Owned),
+            Node::Key(key) => Some(Cow::Borrowed(key)),
+            Node::Map(_) => None,
+        }
+    }
+
+    fn unexpected(&self) -> Unexpected<'de> {
+        match self.node {
+            Node::Value(MetaValue::MetaBool(b)) => Unexpected::Bool(*b),
+            Node::Value(MetaValue::MetaString(s)) => Unexpected::Str(s),
+            Node::Value(MetaValue::MetaInlines(_)) => Unexpected::Other("inlines"),
+            Node::Value(MetaValue::MetaBlocks(_)) => Unexpected::Other("blocks"),
+            Node::Value(MetaValue::MetaList(_)) => Unexpected::Seq,
+            Node::Value(MetaValue::MetaMap(_)) | Node::Map(_) => Unexpected::Map,
+            Node::Key(key) => Unexpected::Str(key),
+        }
+    }
+
+    fn invalid_type(&self, expected: &dyn Expected) -> MetaError {
+        de::Error::invalid_type(self.unexpected(), expected)
+    }
+
+    fn parse<T: std::str::FromStr>(&self, expected: &dyn Expected) -> Result<T, MetaError> {
+        if let Node::Value(MetaValue::MetaBool(_)) = self.node {
+            return Err(self.invalid_type(expected));
+        }
+        match self.string() {
+            Some(s) => s
+                .trim()
+                .parse()
+                .map_err(|_| de::Error::invalid_value(Unexpected::Str(&s), expected)),
+            None => Err(self.invalid_type(expected)),
+        }
+    }
+
+    fn map(&self) -> Option<&'de IndexMap<String, MetaValue>> {
+        match self.node {
+            Node::Value(MetaValue::MetaMap(map)) | Node::Map(map) => Some(map),
+            _ => None,
+        }
+    }
+
+    fn visit_seq<V: Visitor<'de>>(&self, visitor: V) -> Result<V::Value, MetaError> {
+        let values = match self.node {
+            Node::Value(MetaValue::MetaList(values)) => &values[..],
+            Node::Value(value) => std::slice::from_ref(value),
+            Node::Map(_) | Node::Key(_) => return Err(self.invalid_type(&visitor)),
+        };
+        let mut seq = SeqAccess {
+            iter: values.iter().enumerate(),
+            path: &self.path,
+        };
+        let value = visitor.visit_seq(&mut seq)?;
+        match seq.iter.len() {
+            0 => Ok(value),
+            remaining => Err(de::Error::invalid_length(
+                values.len() - remaining,
+                &"fewer elements in sequence",
+            )),
+        }
+    }
+
+    fn visit_map<V: Visitor<'de>>(&self, visitor: V) -> Result<V::Value, MetaError> {
+        match self.map() {
+            Some(map) => visitor.visit_map(MapAccess {
+                iter: map.iter(),
+                value: None,
+                path: &self.path,
+            }),
+            None => Err(self.invalid_type(&visitor)),
+        }
+    }
+
+    fn visit_any<V: Visitor<'de>>(&self, visitor: V) -> Result<V::Value, MetaError> {
+        match self.node {
+            Node::Value(MetaValue::MetaBool(b)) => visitor.visit_bool(*b),
+            Node::Value(MetaValue::MetaString(s)) => visitor.visit_borrowed_str(s),
+            Node::Key(key) => visitor.visit_borrowed_str(key),
+            Node::Value(MetaValue::MetaList(_)) => self.visit_seq(visitor),
+            Node::Value(MetaValue::MetaMap(_)) | Node::Map(_) => self.visit_map(visitor),
+            Node::Value(value) => visitor.visit_string(meta_string(value).unwrap_or_default()),
+        }
+    }
+
+    fn visit_str<V: Visitor<'de>>(&self, visitor: V) -> Result<V::Value, MetaError> {
+        match self.string() {
+            Some(Cow::Borrowed(s)) => visitor.visit_borrowed_str(s),
+            Some(Cow::Owned(s)) => visitor.visit_string(s),
+            None => Err(self.invalid_type(&visitor)),
+        }
+    }
+
+    fn visit_enum<V: Visitor<'de>>(&self, visitor: V) -> Result<V::Value, MetaError> {
+        if let Some(map) = self.map() {
+            return match map.iter().next() {
+                Some((variant, value)) if map.len() == 1 => visitor.visit_enum(EnumAccess {
+                    variant,
+                    value: MetaDeserializer {
+                        node: Node::Value(value),
+                        path: join(&self.path, variant),
+                    },
+                }),
+                _ => Err(de::Error::invalid_value(
+                    Unexpected::Map,
+                    &"a map with a single key",
+                )),
+            };
+        }
+        match self.string() {
+            Some(Cow::Borrowed(s)) => {
+                visitor.visit_enum(BorrowedStrDeserializer::<MetaError>::new(s))
+            }
+            Some(Cow::Owned(s)) => visitor.visit_enum(StringDeserializer::<MetaError>::new(s)),
+            None => Err(self.invalid_type(&visitor)),
+        }
+    }
+}
+
+fn join(path: &str, key: &str) -> String {
+    if path.is_empty() {
+        key.to_owned()
+    } else {
+        format!("{}.{}", path, key)
+    }
+}
+
+macro_rules! deserialize_number {
+    ($($method:ident => $visit:ident,)*) => {
+        $(
+            fn $method<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, MetaError> {
+                self.parse(&visitor)
+                    .and_then(|n| visitor.$visit(n))
+                    .map_err(|e| e.at(&self.path))
+            }
+        )*
+    };
+}
+
+macro_rules! deserialize_with {
+    ($($method:ident($($arg:ident: $ty:ty),*) => $visit:ident,)*) => {
+        $(
+            fn $method<V: Visitor<'de>>(
+                self,
+                $($arg: $ty,)*
+                visitor: V,
+            ) -> Result<V::Value, MetaError> {
+                $(let _ = $arg;)*
+                self.$visit(visitor).map_err(|e| e.at(&self.path))
+            }
+        )*
+    };
+}
+
+impl<'de> de::Deserializer<'de> for MetaDeserializer<'de> {
+    type Error = MetaError;
+
+    deserialize_number! {
+        deserialize_i8 => visit_i8,
+        deserialize_i16 => visit_i16,
+        deserialize_i32 => visit_i32,
+        deserialize_i64 => visit_i64,
+        deserialize_i128 => visit_i128,
+        deserialize_u8 => visit_u8,
+        deserialize_u16 => visit_u16,
+        deserialize_u32 => visit_u32,
+        deserialize_u64 => visit_u64,
+        deserialize_u128 => visit_u128,
+        deserialize_f32 => visit_f32,
+        deserialize_f64 => visit_f64,
+        deserialize_char => visit_char,
+    }
+
+    deserialize_with! {
+        deserialize_any() => visit_any,
+        deserialize_str() => visit_str,
+        deserialize_string() => visit_str,
+        deserialize_bytes() => visit_str,
+        deserialize_byte_buf() => visit_str,
+        deserialize_identifier() => visit_str,
+        deserialize_seq() => visit_seq,
+        deserialize_tuple(_len: usize) => visit_seq,
+        deserialize_tuple_struct(_name: &'static str, _len: usize) => visit_seq,
+        deserialize_map() => visit_map,
+        deserialize_struct(_name: &'static str, _fields: &'static [&'static str]) => visit_map,
+        deserialize_enum(_name: &'static str, _variants: &'static [&'static str]) => visit_enum,
+    }
+
+    fn deserialize_bool<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, MetaError> {
+        let result = match self.node {
+            Node::Value(MetaValue::MetaBool(b)) => visitor.visit_bool(*b),
+            _ => match self.string().as_deref().map(str::trim) {
+                Some("true" | "True" | "TRUE") => visitor.visit_bool(true),
+                Some("false" | "False" | "FALSE") => visitor.visit_bool(false),
+                _ => Err(self.invalid_type(&visitor)),
+            },
+        };
+        result.map_err(|e| e.at(&self.path))
+    }
+
+    fn deserialize_option<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, MetaError> {
+        let path = self.path.clone();
+        visitor.visit_some(self).map_err(|e| e.at(&path))
+    }
+
+    fn deserialize_unit<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, MetaError> {
+        visitor
+            .visit_unit()
+            .map_err(|e: MetaError| e.at(&self.path))
+    }
+
+    fn deserialize_unit_struct<V: Visitor<'de>>(
+        self,
+        _name: &'static str,
+        visitor: V,
+    ) -> Result<V::Value, MetaError> {
+        self.deserialize_unit(visitor)
+    }
+
+    fn deserialize_newtype_struct<V: Visitor<'de>>(
+        self,
+        _name: &'static str,
+        visitor: V,
+    ) -> Result<V::Value, MetaError> {
+        let path = self.path.clone();
+        visitor.visit_newtype_struct(self).map_err(|e| e.at(&path))
+    }
+
+    fn deserialize_ignored_any<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, MetaError> {
+        visitor.visit_unit()
+    }
+}
+
+struct SeqAccess<'a, 'de> {
+    iter: std::iter::Enumerate<std::slice::Iter<'de, MetaValue>>,
+    path: &'a str,
+}
+
+impl<'de> de::SeqAccess<'de> for SeqAccess<'_, 'de> {
+    type Error = MetaError;
+
+    fn next_element_seed<T>(&mut self, seed: T) -> Result<Option<T::Value>, MetaError>
+    where
+        T: de::DeserializeSeed<'de>,
+    {
+        match self.iter.next() {
+            Some((i, value)) => seed
+                .deserialize(MetaDeserializer {
+                    node: Node::Value(value),
+                    path: format!("{}[{}]", self.path, i),
+                })
+                .map(Some),
+            None => Ok(None),
+        }
+    }
+
+    fn size_hint(&self) -> Option<usize> {
+        Some(self.iter.len())
+    }
+}
+
+struct MapAccess<'a, 'de> {
+    iter: indexmap::map::Iter<'de, String, MetaValue>,
+    value: Option<(&'de String, &'de MetaValue)>,
+    path: &'a str,
+}
+
+impl<'de> de::MapAccess<'de> for MapAccess<'_, 'de> {
+    type Error = MetaError;
+
+    fn next_key_seed<K>(&mut self, seed: K) -> Result<Option<K::Value>, MetaError>
+    where
+        K: de::DeserializeSeed<'de>,
+    {
+        match self.iter.next() {
+            Some((key, value)) => {
+                self.value = Some((key, value));
+                seed.deserialize(MetaDeserializer {
+                    node: Node::Key(key),
+                    path: self.path.to_owned(),
+                })
+                .map(Some)
+            }
+            None => Ok(None),
+        }
+    }
+
+    fn next_value_seed<V>(&mut self, seed: V) -> Result<V::Value, MetaError>
+    where
+        V: de::DeserializeSeed<'de>,
+    {
+        let (key, value) = self
+            .value
+            .take()
+            .ok_or_else(|| de::Error::custom("value is missing"))?;
+        seed.deserialize(MetaDeserializer {
+            node: Node::Value(value),
+            path: join(self.path, key),
+        })
+    }
+
+    fn size_hint(&self) -> Option<usize> {
+        Some(self.iter.len())
+    }
+}
+
+struct EnumAccess<'de> {
+    variant: &'de str,
+    value: MetaDeserializer<'de>,
+}
+
+impl<'de> de::EnumAccess<'de> for EnumAccess<'de> {
+    type Error = MetaError;
+    type Variant = MetaDeserializer<'de>;
+
+    fn variant_seed<V>(self, seed: V) -> Result<(V::Value, Self::Variant), MetaError>
+    where
+        V: de::DeserializeSeed<'de>,
+    {
+        let variant = seed.deserialize(BorrowedStrDeserializer::new(self.variant))?;
+        Ok((variant, self.value))
+    }
+}
+
+impl<'de> de::VariantAccess<'de> for MetaDeserializer<'de> {
+    type Error = MetaError;
+
+    fn unit_variant(self) -> Result<(), MetaError> {
+        Ok(())
+    }
+
+    fn newtype_variant_seed<T>(self, seed: T) -> Result<T::Value, MetaError>
+    where
+        T: de::DeserializeSeed<'de>,
+    {
+        seed.deserialize(self)
+    }
+
+    fn tuple_variant<V: Visitor<'de>>(
+        self,
+        _len: usize,
+        visitor: V,
+    ) -> Result<V::Value, MetaError> {
+        self.visit_seq(visitor).map_err(|e| e.at(&self.path))
+    }
+
+    fn struct_variant<V: Visitor<'de>>(
+        self,
+        _fields: &'static [&'static str],
+        visitor: V,
+    ) -> Result<V::Value, MetaError> {
+        self.visit_map(visitor).map_err(|e| e.at(&self.path))
+    }
+}
+
+impl<'de> IntoDeserializer<'de, MetaError> for &'de MetaValue {
+    type Deserializer = MetaDeserializer<'de>;
+
+    fn into_deserializer(self) -> Self::Deserializer {
+        MetaDeserializer::new(self)
+    }
+}
+
+impl<'de> IntoDeserializer<'de, MetaError> for &'de Meta {
+    type Deserializer = MetaDeserializer<'de>;
+
+    fn into_deserializer(self) -> Self::Deserializer {
+        MetaDeserializer::from_meta(self)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::builder::{para, text};
+    use crate::definition::{HasMeta, Inline, ToMetaValue};
+    use std::collections::BTreeMap;
+
+    #[derive(Deserialize, Debug, PartialEq)]
+    struct Author<'a> {
+        name: String,
+        #[serde(borrow)]
+        email: Option<&'a str>,
+    }
+
+    #[derive(Deserialize, Debug, PartialEq)]
+    #[serde(rename_all = "lowercase")]
+    enum Status {
+        Draft,
+        Published { year: u32 },
+    }
+
+    #[derive(Deserialize, Debug, PartialEq)]
+    struct Frontmatter<'a> {
+        title: String,
+        #[serde(borrow)]
+        author: Vec<Author<'a>>,
+        weight: f64,
+        draft: bool,
+        status: Status,
+        extra: BTreeMap<String, String>,
+        abstract_: Option<String>,
+    }
+
+    fn meta() -> Meta {
+        let mut author = Meta::null_meta();
+        author.set_meta("name", text("Jane Doe"));
+        author.set_meta("email", "jane@example.com");
+
+        let mut extra = Meta::null_meta();
+        extra.set_meta("description", para(text("Some")) + para(text("text")));
+
+        let mut meta = Meta::null_meta();
+        meta.set_meta("title", vec![Inline::Str("Title".into())]);
+        meta.set_meta("author", author);
+        meta.set_meta("weight", text("1.5"));
+        meta.set_meta("draft", true);
+        meta.set_meta("status", text("draft"));
+        meta.set_meta("extra", extra);
+        meta
+    }
+
+    #[test]
+    fn frontmatter() {
+        let meta = meta();
+        let frontmatter: Frontmatter<'_> = from_meta(&meta).unwrap();
+        assert_eq!(
+            frontmatter,
+            Frontmatter {
+                title: "Title".into(),
+                author: vec![Author {
+                    name: "Jane Doe".into(),
+                    email: Some("jane@example.com"),
+                }],
+                weight: 1.5,
+                draft: true,
+                status: Status::Draft,
+                extra: [("description".to_owned(), "Some\n\ntext".to_owned())].into(),
+                abstract_: None,
+            }
+        );
+    }
+
+    #[test]
+    fn enums() {
+        let mut meta = Meta::null_meta();
+        let mut published = Meta::null_meta();
+        published.set_meta("year", 2024);
+        meta.set_meta("published", published);
+        let value = meta.to_meta_value();
+        assert_eq!(
+            from_meta_value::<Status>(&value),
+            Ok(Status::Published { year: 2024 })
+        );
+    }
+
+    #[test]
+    fn error_paths() {
+        let mut meta = meta();
+        meta.set_meta("weight", "heavy");
+        let err = from_meta::<Frontmatter<'_>>(&meta).unwrap_err();
+        assert_eq!(err.path(), "weight");
+
+        let mut meta = self::meta();
+        let mut author = Meta::null_meta();
+        author.set_meta("name", vec![1, 2]);
+        meta.set_meta("author", vec![Meta::null_meta(), author]);
+        let err = from_meta::<Frontmatter<'_>>(&meta).unwrap_err();
+        assert_eq!(err.path(), "author[0]");
+        assert_eq!(err.to_string(), "author[0]: missing field `name`");
+    }
+}
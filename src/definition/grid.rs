@@ -0,0 +1,298 @@
+use super::{Cell, Row, Table};
+
+/// The part of a table that a row belongs to.
+///
+/// Row spans never extend past the end of a part, so each part is laid out
+/// independently.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TablePart {
+    /// A row of the table head.
+    Head,
+    /// A row of the intermediate head of the table body with the given index.
+    BodyHead(usize),
+    /// A row of the table body with the given index.
+    Body(usize),
+    /// A row of the table foot.
+    Foot,
+}
+
+/// A position in the grid of a table.
+#[derive(Debug, Clone, PartialEq)]
+pub enum GridSlot<'a> {
+    /// The top-left position of a cell.
+    Origin(&'a Cell),
+    /// A position covered by the spans of the cell whose origin is at the
+    /// given grid row and column.
+    Covered { row: usize, column: usize },
+    /// A position not covered by any cell.
+    Empty,
+}
+
+/// A problem found while laying out the cells of a table.
+///
+/// Rows are indexed from the top of the grid, and cells by their index in
+/// [`Row::cells`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum GridIssue {
+    /// The cell has a row or column span less than one, which is treated as one.
+    InvalidSpan { row: usize, cell: usize },
+    /// The cell's column span covers positions already occupied by cells
+    /// spanning down from previous rows, so the span was truncated.
+    Overlap { row: usize, cell: usize },
+    /// The cell extends past the last column, so its column span was
+    /// truncated, or the cell was dropped if it starts past the last column.
+    TooWide { row: usize, cell: usize },
+    /// The cell's row span extends past the end of its table part, so it was
+    /// truncated.
+    TooTall { row: usize, cell: usize },
+    /// No cell covers the position.
+    Missing { row: usize, column: usize },
+}
+
+/// A row of the grid of a table.
+#[derive(Debug, Clone, PartialEq)]
+pub struct GridRow<'a> {
+    pub part: TablePart,
+    pub row: &'a Row,
+    pub slots: Vec<GridSlot<'a>>,
+}
+
+/// The cells of a table resolved into a rectangular grid, with one column
+/// per column specification, taking the row and column spans into account.
+///
+/// Cells are placed left to right into the first positions not covered by
+/// cells spanning down from previous rows, the same way pandoc's writers
+/// interpret tables.
+#[derive(Debug, Clone, PartialEq)]
+pub struct TableGrid<'a> {
+    pub width: usize,
+    pub rows: Vec<GridRow<'a>>,
+    pub issues: Vec<GridIssue>,
+}
+
+impl<'a> TableGrid<'a> {
+    /// Returns true if the cells of the table fill the grid exactly.
+    pub fn is_valid(&self) -> bool {
+        self.issues.is_empty()
+    }
+
+    /// Returns the slot at the given grid row and column.
+    pub fn get(&self, row: usize, column: usize) -> Option<&GridSlot<'a>> {
+        self.rows.get(row)?.slots.get(column)
+    }
+
+    /// Returns the cell covering the given grid row and column, along with the
+    /// row and column of its origin.
+    pub fn cell_at(&self, row: usize, column: usize) -> Option<(usize, usize, &'a Cell)> {
+        match self.get(row, column)? {
+            GridSlot::Origin(cell) => Some((row, column, cell)),
+            GridSlot::Covered { row, column } => match self.get(*row, *column)? {
+                GridSlot::Origin(cell) => Some((*row, *column, cell)),
+                _ => None,
+            },
+            GridSlot::Empty => None,
+        }
+    }
+}
+
+impl Table {
+    /// Resolves the cells of the table into a rectangular grid.
+    ///
+    /// ```
+    /// use pandoc_types::builder::{cell, plain, TableBuilder};
+    /// use pandoc_types::definition::{Alignment::AlignDefault, GridSlot};
+    ///
+    /// let table = TableBuilder::new()
+    ///     .body([
+    ///         vec![cell(AlignDefault, 2, 1, plain("a")), cell(AlignDefault, 1, 1, plain("b"))],
+    ///         vec![cell(AlignDefault, 1, 1, plain("c"))],
+    ///     ])
+    ///     .build();
+    ///
+    /// let grid = table.grid();
+    /// assert!(grid.is_valid());
+    /// assert_eq!(grid.get(1, 0), Some(&GridSlot::Covered { row: 0, column: 0 }));
+    /// assert_eq!(grid.cell_at(1, 1).map(|(r, c, _)| (r, c)), Some((1, 1)));
+    /// ```
+    pub fn grid(&self) -> TableGrid<'_> {
+        let mut grid = TableGrid {
+            width: self.colspecs.len(),
+            rows: Vec::new(),
+            issues: Vec::new(),
+        };
+        layout_part(&mut grid, TablePart::Head, &self.head.rows);
+        for (i, body) in self.bodies.iter().enumerate() {
+            layout_part(&mut grid, TablePart::BodyHead(i), &body.head);
+            layout_part(&mut grid, TablePart::Body(i), &body.body);
+        }
+        layout_part(&mut grid, TablePart::Foot, &self.foot.rows);
+        grid
+    }
+}
+
+fn layout_part<'a>(grid: &mut TableGrid<'a>, part: TablePart, rows: &'a [Row]) {
+    let start = grid.rows.len();
+    let end = start + rows.len();
+    for row in rows {
+        grid.rows.push(GridRow {
+            part,
+            row,
+            slots: vec![GridSlot::Empty; grid.width],
+        });
+    }
+
+    for (r, row) in (start..end).zip(rows) {
+        let mut column = 0;
+        for (i, cell) in row.cells.iter().enumerate() {
+            if cell.row_span < 1 || cell.col_span < 1 {
+                grid.issues.push(GridIssue::InvalidSpan { row: r, cell: i });
+            }
+
+            while column < grid.width && grid.rows[r].slots[column] != GridSlot::Empty {
+                column += 1;
+            }
+            if column >= grid.width {
+                grid.issues.push(GridIssue::TooWide { row: r, cell: i });
+                continue;
+            }
+
+            let wanted = cell.col_span.max(1) as usize;
+            let mut width = 0;
+            while width < wanted
+                && column + width < grid.width
+                && grid.rows[r].slots[column + width] == GridSlot::Empty
+            {
+                width += 1;
+            }
+            if width < wanted {
+                grid.issues.push(if column + width == grid.width {
+                    GridIssue::TooWide { row: r, cell: i }
+                } else {
+                    GridIssue::Overlap { row: r, cell: i }
+                });
+            }
+
+            let wanted = cell.row_span.max(1) as usize;
+            let height = wanted.min(end - r);
+            if height < wanted {
+                grid.issues.push(GridIssue::TooTall { row: r, cell: i });
+            }
+
+            for slots in grid.rows[r..r + height].iter_mut() {
+                for slot in &mut slots.slots[column..column + width] {
+                    *slot = GridSlot::Covered { row: r, column };
+                }
+            }
+            grid.rows[r].slots[column] = GridSlot::Origin(cell);
+            column += width;
+        }
+    }
+
+    for r in start..end {
+        for (column, slot) in grid.rows[r].slots.iter().enumerate() {
+            if *slot == GridSlot::Empty {
+                grid.issues.push(GridIssue::Missing { row: r, column });
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::definition::{ColSpec, TableBody, TableHead};
+
+    fn cell(row_span: i32, col_span: i32) -> Cell {
+        Cell {
+            row_span,
+            col_span,
+            ..Default::default()
+        }
+    }
+
+    fn row(cells: Vec<Cell>) -> Row {
+        Row {
+            attr: Default::default(),
+            cells,
+        }
+    }
+
+    fn table(columns: usize, head: Vec<Row>, body: Vec<Row>) -> Table {
+        Table {
+            colspecs: vec![ColSpec::default(); columns],
+            head: TableHead {
+                attr: Default::default(),
+                rows: head,
+            },
+            bodies: vec![TableBody {
+                body,
+                ..Default::default()
+            }],
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn spans() {
+        let table = table(
+            3,
+            vec![row(vec![cell(1, 3)])],
+            vec![row(vec![cell(2, 2), cell(1, 1)]), row(vec![cell(1, 1)])],
+        );
+        let grid = table.grid();
+        assert!(grid.is_valid(), "{:?}", grid.issues);
+        assert_eq!(grid.rows[0].part, TablePart::Head);
+        assert_eq!(grid.rows[1].part, TablePart::Body(0));
+        assert_eq!(
+            grid.rows[0].slots[2],
+            GridSlot::Covered { row: 0, column: 0 }
+        );
+        assert_eq!(
+            grid.rows[2].slots[1],
+            GridSlot::Covered { row: 1, column: 0 }
+        );
+        assert_eq!(
+            grid.rows[2].slots[2],
+            GridSlot::Origin(&table.bodies[0].body[1].cells[0])
+        );
+    }
+
+    #[test]
+    fn issues() {
+        let table = table(
+            2,
+            vec![row(vec![cell(2, 1), cell(1, 1)])],
+            vec![
+                row(vec![cell(1, 1), cell(1, 1), cell(1, 1)]),
+                row(vec![cell(1, 3)]),
+                row(vec![cell(0, 1)]),
+            ],
+        );
+        let grid = table.grid();
+        assert_eq!(
+            grid.issues,
+            vec![
+                GridIssue::TooTall { row: 0, cell: 0 },
+                GridIssue::TooWide { row: 1, cell: 2 },
+                GridIssue::TooWide { row: 2, cell: 0 },
+                GridIssue::InvalidSpan { row: 3, cell: 0 },
+                GridIssue::Missing { row: 3, column: 1 },
+            ]
+        );
+    }
+
+    #[test]
+    fn overlap() {
+        let table = table(
+            3,
+            vec![],
+            vec![
+                row(vec![cell(1, 1), cell(2, 1), cell(1, 1)]),
+                row(vec![cell(1, 2), cell(1, 1)]),
+            ],
+        );
+        let grid = table.grid();
+        assert_eq!(grid.issues, vec![GridIssue::Overlap { row: 1, cell: 0 }]);
+        assert_eq!(grid.cell_at(1, 2).map(|(r, c, _)| (r, c)), Some((1, 2)));
+    }
+}
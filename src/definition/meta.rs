@@ -0,0 +1,199 @@
+use std::ops::{Deref, DerefMut};
+
+use serde::{Deserialize, Serialize};
+
+use super::{Block, IndexMap, Inline, MetaValue, Pandoc, ToMetaValue};
+
+/// Metadata for a document: title, authors, date, and any other fields.
+///
+/// This dereferences to the underlying map, which keeps the fields in the
+/// order they were inserted or read.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Default)]
+#[serde(transparent)]
+pub struct Meta(pub IndexMap<String, MetaValue>);
+
+impl Meta {
+    /// Returns empty metadata.
+    pub fn null_meta() -> Self {
+        Meta::default()
+    }
+
+    /// Returns true if there are no metadata fields.
+    pub fn is_null_meta(&self) -> bool {
+        self.0.is_empty()
+    }
+
+    /// Retrieves the metadata value for the given key.
+    pub fn lookup(&self, key: &str) -> Option<&MetaValue> {
+        self.0.get(key)
+    }
+
+    /// Extracts the document title from the metadata.
+    ///
+    /// ```
+    /// use pandoc_types::definition::{HasMeta, Inline, Meta, MetaValue};
+    ///
+    /// let mut meta = Meta::null_meta();
+    /// meta.set_meta("title", MetaValue::MetaString("A title".into()));
+    ///
+    /// assert_eq!(meta.doc_title(), vec![Inline::Str("A title".into())]);
+    /// ```
+    pub fn doc_title(&self) -> Vec<Inline> {
+        self.lookup("title").and_then(inlines).unwrap_or_default()
+    }
+
+    /// Extracts the document authors from the metadata, which may be a
+    /// single author or a list of them.
+    pub fn doc_authors(&self) -> Vec<Vec<Inline>> {
+        match self.lookup("author") {
+            Some(MetaValue::MetaList(values)) => values.iter().filter_map(inlines).collect(),
+            Some(value) => inlines(value).into_iter().collect(),
+            None => Vec::new(),
+        }
+    }
+
+    /// Extracts the date from the metadata.
+    pub fn doc_date(&self) -> Vec<Inline> {
+        self.lookup("date").and_then(inlines).unwrap_or_default()
+    }
+}
+
+/// Converts a metadata value to inlines, the way `docTitle`, `docAuthors` and
+/// `docDate` do.
+fn inlines(value: &MetaValue) -> Option<Vec<Inline>> {
+    match value {
+        MetaValue::MetaString(s) => Some(vec![Inline::Str(s.clone())]),
+        MetaValue::MetaInlines(inlines) => Some(inlines.clone()),
+        MetaValue::MetaBlocks(blocks) => match &blocks[..] {
+            [Block::Plain(inlines)] | [Block::Para(inlines)] => Some(inlines.clone()),
+            _ => None,
+        },
+        _ => None,
+    }
+}
+
+impl Deref for Meta {
+    type Target = IndexMap<String, MetaValue>;
+
+    fn deref(&self) -> &Self::Target {
+        &self.0
+    }
+}
+
+impl DerefMut for Meta {
+    fn deref_mut(&mut self) -> &mut Self::Target {
+        &mut self.0
+    }
+}
+
+impl From<IndexMap<String, MetaValue>> for Meta {
+    fn from(map: IndexMap<String, MetaValue>) -> Self {
+        Meta(map)
+    }
+}
+
+impl From<Meta> for IndexMap<String, MetaValue> {
+    fn from(meta: Meta) -> Self {
+        meta.0
+    }
+}
+
+impl FromIterator<(String, MetaValue)> for Meta {
+    fn from_iter<I: IntoIterator<Item = (String, MetaValue)>>(iter: I) -> Self {
+        Meta(iter.into_iter().collect())
+    }
+}
+
+impl Extend<(String, MetaValue)> for Meta {
+    fn extend<I: IntoIterator<Item = (String, MetaValue)>>(&mut self, iter: I) {
+        self.0.extend(iter)
+    }
+}
+
+impl IntoIterator for Meta {
+    type Item = (String, MetaValue);
+    type IntoIter = indexmap::map::IntoIter<String, MetaValue>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.0.into_iter()
+    }
+}
+
+impl<'a> IntoIterator for &'a Meta {
+    type Item = (&'a String, &'a MetaValue);
+    type IntoIter = indexmap::map::Iter<'a, String, MetaValue>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.0.iter()
+    }
+}
+
+/// Types that carry metadata, like Haskell's `HasMeta` class.
+pub trait HasMeta {
+    /// Sets a metadata field, replacing any previous value. A new field is
+    /// added after the existing ones.
+    fn set_meta(&mut self, key: impl Into<String>, value: impl ToMetaValue);
+
+    /// Removes a metadata field, keeping the order of the other fields.
+    fn delete_meta(&mut self, key: &str);
+}
+
+impl HasMeta for Meta {
+    fn set_meta(&mut self, key: impl Into<String>, value: impl ToMetaValue) {
+        self.0.insert(key.into(), value.to_meta_value());
+    }
+
+    fn delete_meta(&mut self, key: &str) {
+        self.0.shift_remove(key);
+    }
+}
+
+impl HasMeta for Pandoc {
+    fn set_meta(&mut self, key: impl Into<String>, value: impl ToMetaValue) {
+        self.meta.set_meta(key, value)
+    }
+
+    fn delete_meta(&mut self, key: &str) {
+        self.meta.delete_meta(key)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn str(s: &str) -> Inline {
+        Inline::Str(s.into())
+    }
+
+    #[test]
+    fn accessors() {
+        let mut doc = Pandoc::default();
+        assert!(doc.meta.is_null_meta());
+        assert!(doc.meta.doc_authors().is_empty());
+
+        doc.set_meta(
+            "title",
+            MetaValue::MetaBlocks(vec![Block::Para(vec![str("Title")])]),
+        );
+        doc.set_meta(
+            "author",
+            MetaValue::MetaList(vec![
+                MetaValue::MetaInlines(vec![str("A")]),
+                MetaValue::MetaString("B".into()),
+                MetaValue::MetaBool(true),
+            ]),
+        );
+        doc.set_meta("date", MetaValue::MetaInlines(vec![str("today")]));
+        assert_eq!(doc.meta.doc_title(), vec![str("Title")]);
+        assert_eq!(doc.meta.doc_authors(), vec![vec![str("A")], vec![str("B")]]);
+        assert_eq!(doc.meta.doc_date(), vec![str("today")]);
+
+        doc.set_meta("author", MetaValue::MetaString("C".into()));
+        assert_eq!(doc.meta.doc_authors(), vec![vec![str("C")]]);
+
+        doc.delete_meta("title");
+        assert_eq!(doc.meta.keys().collect::<Vec<_>>(), ["author", "date"]);
+        assert!(doc.meta.lookup("title").is_none());
+    }
+}
@@ -0,0 +1,230 @@
+use std::iter;
+
+use super::{Cell, Row, Table};
+
+impl Table {
+    /// Normalizes the table so that its cells exactly fill a grid with one
+    /// column per column specification.
+    ///
+    /// This has the same semantics as `normalizeTableHead`,
+    /// `normalizeTableBody` and `normalizeTableFoot` from pandoc's
+    /// [Text.Pandoc.Builder]:
+    ///
+    ///   * row spans are clamped between one and the number of rows left in
+    ///     the table part;
+    ///   * column spans are reduced so that cells don't overlap cells from
+    ///     previous rows or extend past the last column;
+    ///   * rows that are too short are padded with empty cells, and cells
+    ///     that don't fit in a row are dropped;
+    ///   * `row_head_columns` is clamped between zero and the number of
+    ///     columns, and cells in a table body don't cross the boundary
+    ///     between the row head and the rest of the row.
+    ///
+    /// ```
+    /// use pandoc_types::definition::{Cell, ColSpec, Row, Table, TableBody};
+    ///
+    /// let mut table = Table {
+    ///     colspecs: vec![ColSpec::default(); 2],
+    ///     bodies: vec![TableBody {
+    ///         row_head_columns: 3,
+    ///         body: vec![Row {
+    ///             attr: Default::default(),
+    ///             cells: vec![Cell { col_span: 3, ..Default::default() }],
+    ///         }],
+    ///         ..Default::default()
+    ///     }],
+    ///     ..Default::default()
+    /// };
+    ///
+    /// table.normalize();
+    ///
+    /// assert_eq!(table.bodies[0].row_head_columns, 2);
+    /// assert_eq!(table.bodies[0].body[0].cells, vec![Cell { col_span: 2, ..Default::default() }]);
+    /// ```
+    ///
+    /// [Text.Pandoc.Builder]: https://hackage.haskell.org/package/pandoc-types/docs/Text-Pandoc-Builder.html
+    pub fn normalize(&mut self) {
+        let width = self.colspecs.len();
+        normalize_header_section(width, &mut self.head.rows);
+        for body in &mut self.bodies {
+            body.row_head_columns = body.row_head_columns.clamp(0, width as i32);
+            normalize_header_section(width, &mut body.head);
+            normalize_body_section(width, body.row_head_columns as usize, &mut body.body);
+        }
+        normalize_header_section(width, &mut self.foot.rows);
+    }
+}
+
+/// Clamps the row span of each cell between one and the number of rows left.
+fn clip_rows(rows: &mut [Row]) {
+    let len = rows.len();
+    for (i, row) in rows.iter_mut().enumerate() {
+        for cell in &mut row.cells {
+            cell.row_span = cell.row_span.clamp(1, (len - i) as i32);
+        }
+    }
+}
+
+/// Takes the cells of a row, followed by as many empty cells as needed.
+fn row_cells(row: &mut Row) -> impl Iterator<Item = Cell> {
+    std::mem::take(&mut row.cells)
+        .into_iter()
+        .chain(iter::repeat_with(Cell::default))
+}
+
+fn normalize_header_section(width: usize, rows: &mut [Row]) {
+    clip_rows(rows);
+    let mut hang = vec![1; width];
+    for row in rows {
+        let mut cells = row_cells(row);
+        let (new_hang, placed) = place_row_section(&hang, &mut cells);
+        hang = new_hang;
+        row.cells = placed;
+    }
+}
+
+fn normalize_body_section(width: usize, row_head_columns: usize, rows: &mut [Row]) {
+    clip_rows(rows);
+    let mut head_hang = vec![1; row_head_columns];
+    let mut body_hang = vec![1; width - row_head_columns];
+    for row in rows {
+        let mut cells = row_cells(row);
+        let (new_head_hang, mut placed) = place_row_section(&head_hang, &mut cells);
+        let (new_body_hang, body) = place_row_section(&body_hang, &mut cells);
+        head_hang = new_head_hang;
+        body_hang = new_body_hang;
+        placed.extend(body);
+        row.cells = placed;
+    }
+}
+
+/// Places cells on a grid row in order, each at the first available position
+/// from the left, reducing their column span if they would overlap a cell
+/// from a previous row, and stopping once the row is filled.
+///
+/// The "overhang" `old_hang` holds, for each column, the number of rows the
+/// cell covering that column in the previous row still extends down,
+/// counting the previous row. A position is available if its overhang is
+/// one. Returns the overhang of this row and the cells that were placed.
+fn place_row_section(
+    old_hang: &[i32],
+    cells: &mut impl Iterator<Item = Cell>,
+) -> (Vec<i32>, Vec<Cell>) {
+    let mut new_hang = Vec::with_capacity(old_hang.len());
+    let mut placed = Vec::new();
+    let mut column = 0;
+    while column < old_hang.len() {
+        if old_hang[column] > 1 {
+            new_hang.push(old_hang[column] - 1);
+            column += 1;
+            continue;
+        }
+        let mut cell = match cells.next() {
+            Some(cell) => cell,
+            None => break,
+        };
+        let available = old_hang[column..]
+            .iter()
+            .take(cell.col_span.max(1) as usize)
+            .take_while(|&&hang| hang == 1)
+            .count();
+        cell.col_span = available as i32;
+        new_hang.extend(iter::repeat_n(cell.row_span, available));
+        placed.push(cell);
+        column += available;
+    }
+    (new_hang, placed)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::definition::{ColSpec, TableBody, TableFoot, TableHead};
+
+    fn cell(row_span: i32, col_span: i32) -> Cell {
+        Cell {
+            row_span,
+            col_span,
+            ..Default::default()
+        }
+    }
+
+    fn row(cells: Vec<Cell>) -> Row {
+        Row {
+            attr: Default::default(),
+            cells,
+        }
+    }
+
+    #[test]
+    fn pads_clamps_and_truncates() {
+        let mut table = Table {
+            colspecs: vec![ColSpec::default(); 3],
+            head: TableHead {
+                attr: Default::default(),
+                rows: vec![row(vec![cell(5, 2)]), row(vec![cell(1, 1), cell(1, 1)])],
+            },
+            foot: TableFoot {
+                attr: Default::default(),
+                rows: vec![row(vec![cell(0, 0), cell(1, 1), cell(1, 1), cell(1, 1)])],
+            },
+            ..Default::default()
+        };
+        table.normalize();
+        assert_eq!(
+            table.head.rows,
+            vec![row(vec![cell(2, 2), cell(1, 1)]), row(vec![cell(1, 1)])]
+        );
+        assert_eq!(
+            table.foot.rows,
+            vec![row(vec![cell(1, 1), cell(1, 1), cell(1, 1)])]
+        );
+        assert!(table.grid().is_valid());
+    }
+
+    #[test]
+    fn overlapping_spans() {
+        let mut table = Table {
+            colspecs: vec![ColSpec::default(); 3],
+            bodies: vec![TableBody {
+                body: vec![
+                    row(vec![cell(1, 1), cell(2, 1)]),
+                    row(vec![cell(1, 3), cell(1, 1)]),
+                ],
+                ..Default::default()
+            }],
+            ..Default::default()
+        };
+        table.normalize();
+        assert_eq!(
+            table.bodies[0].body,
+            vec![
+                row(vec![cell(1, 1), cell(2, 1), cell(1, 1)]),
+                row(vec![cell(1, 1), cell(1, 1)]),
+            ]
+        );
+        assert!(table.grid().is_valid());
+    }
+
+    #[test]
+    fn row_head_columns() {
+        let mut table = Table {
+            colspecs: vec![ColSpec::default(); 3],
+            bodies: vec![TableBody {
+                row_head_columns: 1,
+                body: vec![row(vec![cell(1, 2), cell(1, 2)])],
+                ..Default::default()
+            }],
+            ..Default::default()
+        };
+        table.normalize();
+        assert_eq!(
+            table.bodies[0].body,
+            vec![row(vec![cell(1, 1), cell(1, 2)])]
+        );
+
+        table.bodies[0].row_head_columns = -1;
+        table.normalize();
+        assert_eq!(table.bodies[0].row_head_columns, 0);
+    }
+}
@@ -0,0 +1,496 @@
+use serde::ser::{self, Serialize};
+
+use super::{IndexMap, Meta, MetaError, MetaValue};
+use crate::builder::text;
+
+/// Serializes a value into a metadata value.
+///
+/// Structs and maps become `MetaMap`, sequences and tuples become
+/// `MetaList`, booleans become `MetaBool`, and strings and numbers become
+/// `MetaString`. `None` and `()` become `MetaBool(false)`, which templates
+/// treat as unset; use `#[serde(skip_serializing_if = "Option::is_none")]`
+/// to omit the field instead. Enums are represented the same way as by
+/// [`from_meta`](super::from_meta): unit variants as their name, and other
+/// variants as a map with the variant name as the only key.
+///
+/// See [`MetaSerializer`] to serialize strings as `MetaInlines`.
+///
+/// ```
+/// use pandoc_types::definition::{to_meta_value, MetaValue};
+/// use serde::Serialize;
+///
+/// #[derive(Serialize)]
+/// struct Build {
+///     version: &'static str,
+///     number: u32,
+///     release: bool,
+/// }
+///
+/// let build = Build { version: "1.2", number: 42, release: true };
+/// let value = to_meta_value(&build).unwrap();
+///
+/// let MetaValue::MetaMap(map) = value else { panic!() };
+/// assert_eq!(map["version"], MetaValue::MetaString("1.2".into()));
+/// assert_eq!(map["number"], MetaValue::MetaString("42".into()));
+/// assert_eq!(map["release"], MetaValue::MetaBool(true));
+/// ```
+pub fn to_meta_value<T: Serialize + ?Sized>(value: &T) -> Result<MetaValue, MetaError> {
+    value.serialize(MetaSerializer::new())
+}
+
+/// Serializes a struct or map into document metadata, see [`to_meta_value`].
+pub fn to_meta<T: Serialize + ?Sized>(value: &T) -> Result<Meta, MetaError> {
+    MetaSerializer::new().to_meta(value)
+}
+
+/// A serde serializer producing [`MetaValue`]s, see [`to_meta_value`].
+#[derive(Debug, Clone, Copy, Default)]
+pub struct MetaSerializer {
+    inline_strings: bool,
+}
+
+impl MetaSerializer {
+    pub fn new() -> Self {
+        MetaSerializer::default()
+    }
+
+    /// Serializes strings as `MetaInlines` instead of `MetaString`, which is
+    /// how pandoc reads strings from YAML metadata. Pandoc escapes inlines
+    /// for the output format when they are used in templates, but not
+    /// `MetaString` values.
+    ///
+    /// ```
+    /// use pandoc_types::definition::{Inline, MetaSerializer, MetaValue};
+    /// use serde::Serialize;
+    ///
+    /// let value = "a & b".serialize(MetaSerializer::new().inline_strings(true)).unwrap();
+    /// assert_eq!(
+    ///     value,
+    ///     MetaValue::MetaInlines(vec![
+    ///         Inline::Str("a".into()),
+    ///         Inline::Space,
+    ///         Inline::Str("&".into()),
+    ///         Inline::Space,
+    ///         Inline::Str("b".into()),
+    ///     ])
+    /// );
+    /// ```
+    pub fn inline_strings(self, inline_strings: bool) -> Self {
+        MetaSerializer { inline_strings }
+    }
+
+    /// Serializes a struct or map into document metadata.
+    pub fn to_meta<T: Serialize + ?Sized>(self, value: &T) -> Result<Meta, MetaError> {
+        match value.serialize(self)? {
+            MetaValue::MetaMap(map) => Ok(Meta(map)),
+            _ => Err(ser::Error::custom("metadata must be a struct or map")),
+        }
+    }
+
+    fn string(self, s: &str) -> MetaValue {
+        if self.inline_strings {
+            MetaValue::MetaInlines(text(s).into_vec())
+        } else {
+            MetaValue::MetaString(s.to_owned())
+        }
+    }
+}
+
+macro_rules! serialize_display {
+    ($($method:ident($t:ty),)*) => {
+        $(
+            fn $method(self, v: $t) -> Result<MetaValue, MetaError> {
+                Ok(MetaValue::MetaString(v.to_string()))
+            }
+        )*
+    };
+}
+
+impl ser::Serializer for MetaSerializer {
+    type Ok = MetaValue;
+    type Error = MetaError;
+    type SerializeSeq = SerializeList;
+    type SerializeTuple = SerializeList;
+    type SerializeTupleStruct = SerializeList;
+    type SerializeTupleVariant = SerializeList;
+    type SerializeMap = SerializeMap;
+    type SerializeStruct = SerializeMap;
+    type SerializeStructVariant = SerializeMap;
+
+    serialize_display! {
+        serialize_i8(i8),
+        serialize_i16(i16),
+        serialize_i32(i32),
+        serialize_i64(i64),
+        serialize_i128(i128),
+        serialize_u8(u8),
+        serialize_u16(u16),
+        serialize_u32(u32),
+        serialize_u64(u64),
+        serialize_u128(u128),
+        serialize_f32(f32),
+        serialize_f64(f64),
+    }
+
+    fn serialize_bool(self, v: bool) -> Result<MetaValue, MetaError> {
+        Ok(MetaValue::MetaBool(v))
+    }
+
+    fn serialize_char(self, v: char) -> Result<MetaValue, MetaError> {
+        Ok(self.string(v.encode_utf8(&mut [0; 4])))
+    }
+
+    fn serialize_str(self, v: &str) -> Result<MetaValue, MetaError> {
+        Ok(self.string(v))
+    }
+
+    fn serialize_bytes(self, v: &[u8]) -> Result<MetaValue, MetaError> {
+        v.serialize(self)
+    }
+
+    fn serialize_none(self) -> Result<MetaValue, MetaError> {
+        Ok(MetaValue::MetaBool(false))
+    }
+
+    fn serialize_some<T: Serialize + ?Sized>(self, value: &T) -> Result<MetaValue, MetaError> {
+        value.serialize(self)
+    }
+
+    fn serialize_unit(self) -> Result<MetaValue, MetaError> {
+        Ok(MetaValue::MetaBool(false))
+    }
+
+    fn serialize_unit_struct(self, _name: &'static str) -> Result<MetaValue, MetaError> {
+        self.serialize_unit()
+    }
+
+    fn serialize_unit_variant(
+        self,
+        _name: &'static str,
+        _index: u32,
+        variant: &'static str,
+    ) -> Result<MetaValue, MetaError> {
+        Ok(self.string(variant))
+    }
+
+    fn serialize_newtype_struct<T: Serialize + ?Sized>(
+        self,
+        _name: &'static str,
+        value: &T,
+    ) -> Result<MetaValue, MetaError> {
+        value.serialize(self)
+    }
+
+    fn serialize_newtype_variant<T: Serialize + ?Sized>(
+        self,
+        _name: &'static str,
+        _index: u32,
+        variant: &'static str,
+        value: &T,
+    ) -> Result<MetaValue, MetaError> {
+        let mut map = IndexMap::new();
+        map.insert(variant.to_owned(), value.serialize(self)?);
+        Ok(MetaValue::MetaMap(map))
+    }
+
+    fn serialize_seq(self, len: Option<usize>) -> Result<SerializeList, MetaError> {
+        Ok(SerializeList {
+            serializer: self,
+            variant: None,
+            values: Vec::with_capacity(len.unwrap_or_default()),
+        })
+    }
+
+    fn serialize_tuple(self, len: usize) -> Result<SerializeList, MetaError> {
+        self.serialize_seq(Some(len))
+    }
+
+    fn serialize_tuple_struct(
+        self,
+        _name: &'static str,
+        len: usize,
+    ) -> Result<SerializeList, MetaError> {
+        self.serialize_seq(Some(len))
+    }
+
+    fn serialize_tuple_variant(
+        self,
+        _name: &'static str,
+        _index: u32,
+        variant: &'static str,
+        len: usize,
+    ) -> Result<SerializeList, MetaError> {
+        Ok(SerializeList {
+            variant: Some(variant),
+            ..self.serialize_seq(Some(len))?
+        })
+    }
+
+    fn serialize_map(self, _len: Option<usize>) -> Result<SerializeMap, MetaError> {
+        Ok(SerializeMap {
+            serializer: self,
+            variant: None,
+            map: IndexMap::new(),
+            key: None,
+        })
+    }
+
+    fn serialize_struct(self, _name: &'static str, len: usize) -> Result<SerializeMap, MetaError> {
+        self.serialize_map(Some(len))
+    }
+
+    fn serialize_struct_variant(
+        self,
+        _name: &'static str,
+        _index: u32,
+        variant: &'static str,
+        len: usize,
+    ) -> Result<SerializeMap, MetaError> {
+        Ok(SerializeMap {
+            variant: Some(variant),
+            ..self.serialize_map(Some(len))?
+        })
+    }
+}
+
+/// Wraps the value of an enum variant in a map with the variant name as key.
+fn variant_value(variant: Option<&'static str>, value: MetaValue) -> MetaValue {
+    match variant {
+        Some(variant) => {
+            let mut map = IndexMap::new();
+            map.insert(variant.to_owned(), value);
+            MetaValue::MetaMap(map)
+        }
+        None => value,
+    }
+}
+
+#[doc(hidden)]
+pub struct SerializeList {
+    serializer: MetaSerializer,
+    variant: Option<&'static str>,
+    values: Vec<MetaValue>,
+}
+
+impl SerializeList {
+    fn push<T: Serialize + ?Sized>(&mut self, value: &T) -> Result<(), MetaError> {
+        let index = self.values.len();
+        let value = value
+            .serialize(self.serializer)
+            .map_err(|e| e.within(&format!("[{}]", index)))?;
+        self.values.push(value);
+        Ok(())
+    }
+
+    fn finish(self) -> Result<MetaValue, MetaError> {
+        Ok(variant_value(
+            self.variant,
+            MetaValue::MetaList(self.values),
+        ))
+    }
+}
+
+impl ser::SerializeSeq for SerializeList {
+    type Ok = MetaValue;
+    type Error = MetaError;
+
+    fn serialize_element<T: Serialize + ?Sized>(&mut self, value: &T) -> Result<(), MetaError> {
+        self.push(value)
+    }
+
+    fn end(self) -> Result<MetaValue, MetaError> {
+        self.finish()
+    }
+}
+
+impl ser::SerializeTuple for SerializeList {
+    type Ok = MetaValue;
+    type Error = MetaError;
+
+    fn serialize_element<T: Serialize + ?Sized>(&mut self, value: &T) -> Result<(), MetaError> {
+        self.push(value)
+    }
+
+    fn end(self) -> Result<MetaValue, MetaError> {
+        self.finish()
+    }
+}
+
+impl ser::SerializeTupleStruct for SerializeList {
+    type Ok = MetaValue;
+    type Error = MetaError;
+
+    fn serialize_field<T: Serialize + ?Sized>(&mut self, value: &T) -> Result<(), MetaError> {
+        self.push(value)
+    }
+
+    fn end(self) -> Result<MetaValue, MetaError> {
+        self.finish()
+    }
+}
+
+impl ser::SerializeTupleVariant for SerializeList {
+    type Ok = MetaValue;
+    type Error = MetaError;
+
+    fn serialize_field<T: Serialize + ?Sized>(&mut self, value: &T) -> Result<(), MetaError> {
+        self.push(value)
+    }
+
+    fn end(self) -> Result<MetaValue, MetaError> {
+        self.finish()
+    }
+}
+
+#[doc(hidden)]
+pub struct SerializeMap {
+    serializer: MetaSerializer,
+    variant: Option<&'static str>,
+    map: IndexMap<String, MetaValue>,
+    key: Option<String>,
+}
+
+impl SerializeMap {
+    fn insert<T: Serialize + ?Sized>(&mut self, key: String, value: &T) -> Result<(), MetaError> {
+        let value = value
+            .serialize(self.serializer)
+            .map_err(|e| e.within(&key))?;
+        self.map.insert(key, value);
+        Ok(())
+    }
+
+    fn finish(self) -> Result<MetaValue, MetaError> {
+        Ok(variant_value(self.variant, MetaValue::MetaMap(self.map)))
+    }
+}
+
+impl ser::SerializeMap for SerializeMap {
+    type Ok = MetaValue;
+    type Error = MetaError;
+
+    fn serialize_key<T: Serialize + ?Sized>(&mut self, key: &T) -> Result<(), MetaError> {
+        // Keys are always strings, regardless of `inline_strings`.
+        self.key = Some(match key.serialize(MetaSerializer::new())? {
+            MetaValue::MetaString(s) => s,
+            MetaValue::MetaBool(b) => b.to_string(),
+            _ => return Err(ser::Error::custom("map keys must be strings or numbers")),
+        });
+        Ok(())
+    }
+
+    fn serialize_value<T: Serialize + ?Sized>(&mut self, value: &T) -> Result<(), MetaError> {
+        let key = self
+            .key
+            .take()
+            .ok_or_else(|| ser::Error::custom("serialize_value called before serialize_key"))?;
+        self.insert(key, value)
+    }
+
+    fn end(self) -> Result<MetaValue, MetaError> {
+        self.finish()
+    }
+}
+
+impl ser::SerializeStruct for SerializeMap {
+    type Ok = MetaValue;
+    type Error = MetaError;
+
+    fn serialize_field<T: Serialize + ?Sized>(
+        &mut self,
+        key: &'static str,
+        value: &T,
+    ) -> Result<(), MetaError> {
+        self.insert(key.to_owned(), value)
+    }
+
+    fn end(self) -> Result<MetaValue, MetaError> {
+        self.finish()
+    }
+}
+
+impl ser::SerializeStructVariant for SerializeMap {
+    type Ok = MetaValue;
+    type Error = MetaError;
+
+    fn serialize_field<T: Serialize + ?Sized>(
+        &mut self,
+        key: &'static str,
+        value: &T,
+    ) -> Result<(), MetaError> {
+        self.insert(key.to_owned(), value)
+    }
+
+    fn end(self) -> Result<MetaValue, MetaError> {
+        self.finish()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::definition::{from_meta, Inline};
+    use serde::{Deserialize, Serialize};
+    use std::collections::BTreeMap;
+
+    #[derive(Serialize, Deserialize, Debug, PartialEq)]
+    enum Kind {
+        Article,
+        Book { isbn: String },
+    }
+
+    #[derive(Serialize, Deserialize, Debug, PartialEq)]
+    struct Entry {
+        id: String,
+        year: i32,
+        tags: Vec<String>,
+        kind: Kind,
+        extra: BTreeMap<u32, bool>,
+        #[serde(skip_serializing_if = "Option::is_none")]
+        note: Option<String>,
+    }
+
+    fn entry() -> Entry {
+        Entry {
+            id: "doe2020".into(),
+            year: 2020,
+            tags: vec!["a".into(), "b c".into()],
+            kind: Kind::Book { isbn: "123".into() },
+            extra: [(1, true)].into(),
+            note: None,
+        }
+    }
+
+    #[test]
+    fn round_trip() {
+        let meta = to_meta(&entry()).unwrap();
+        assert_eq!(
+            meta.keys().collect::<Vec<_>>(),
+            ["id", "year", "tags", "kind", "extra"]
+        );
+        assert_eq!(meta["year"], MetaValue::MetaString("2020".into()));
+        assert_eq!(from_meta::<Entry>(&meta), Ok(entry()));
+
+        let meta = MetaSerializer::new()
+            .inline_strings(true)
+            .to_meta(&entry())
+            .unwrap();
+        assert_eq!(
+            meta["id"],
+            MetaValue::MetaInlines(vec![Inline::Str("doe2020".into())])
+        );
+        assert_eq!(from_meta::<Entry>(&meta), Ok(entry()));
+    }
+
+    #[test]
+    fn errors() {
+        assert!(to_meta(&[1, 2]).is_err());
+
+        let mut map = BTreeMap::new();
+        map.insert("outer", BTreeMap::from([(vec![1], 1)]));
+        let err = to_meta(&map).unwrap_err();
+        assert_eq!(err.path(), "outer");
+
+        let err = to_meta_value(&[map]).unwrap_err();
+        assert_eq!(err.path(), "[0].outer");
+    }
+}
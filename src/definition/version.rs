@@ -0,0 +1,432 @@
+use std::borrow::Cow;
+use std::fmt;
+
+use serde::de::{self, DeserializeSeed};
+use serde::ser::SerializeStruct;
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+
+use super::compat::LegacyTablesGuard;
+use super::{Block, Meta, Pandoc};
+
+/// The version of the pandoc-types API implemented by this crate, which is
+/// written in the `pandoc-api-version` field when serializing a [`Pandoc`].
+pub const PANDOC_API_VERSION: ApiVersion = ApiVersion {
+    major: 1,
+    minor: 23,
+    patch: 0,
+    revision: 0,
+};
+
+/// A version of the pandoc-types API, which determines the JSON format of
+/// documents.
+///
+/// Versions have up to four components, following the Haskell package
+/// versioning policy: changes to `major` and `minor` may change the format,
+/// while changes to `patch` and `revision` don't. Missing components are
+/// zero.
+///
+/// Documents in any of the released formats, 1.17 and 1.20 to 1.23, can be
+/// deserialized into
+/// [`Pandoc`] and are upgraded to the current AST. Documents can be written
+/// in an older format with [`Pandoc::into_api_version`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Default)]
+pub struct ApiVersion {
+    pub major: u32,
+    pub minor: u32,
+    pub patch: u32,
+    pub revision: u32,
+}
+
+impl ApiVersion {
+    /// pandoc-types 1.17, used by pandoc 1.18 to 2.7.
+    pub const V1_17: ApiVersion = ApiVersion::new(1, 17);
+    /// pandoc-types 1.20, used by pandoc 2.8 and 2.9.
+    pub const V1_20: ApiVersion = ApiVersion::new(1, 20);
+    /// pandoc-types 1.21, used by pandoc 2.10, which introduced the current
+    /// table layout and `Underline`.
+    pub const V1_21: ApiVersion = ApiVersion::new(1, 21);
+    /// pandoc-types 1.22, used by pandoc 2.11 to 2.19.
+    pub const V1_22: ApiVersion = ApiVersion::new(1, 22);
+    /// pandoc-types 1.23, used by pandoc 3.0 onwards, which introduced `Figure`.
+    pub const V1_23: ApiVersion = ApiVersion::new(1, 23);
+
+    pub const fn new(major: u32, minor: u32) -> Self {
+        ApiVersion {
+            major,
+            minor,
+            patch: 0,
+            revision: 0,
+        }
+    }
+
+    /// Returns true if both versions have the same major and minor components,
+    /// and so the same JSON format.
+    pub fn same_format(self, other: ApiVersion) -> bool {
+        (self.major, self.minor) == (other.major, other.minor)
+    }
+
+    /// The released formats, oldest first. Versions 1.18 and 1.19 were
+    /// never released.
+    const RELEASED: [ApiVersion; 5] = [
+        ApiVersion::V1_17,
+        ApiVersion::V1_20,
+        ApiVersion::V1_21,
+        ApiVersion::V1_22,
+        ApiVersion::V1_23,
+    ];
+
+    /// Returns true if documents of this version can be read by this crate.
+    pub fn is_supported(self) -> bool {
+        ApiVersion::RELEASED
+            .iter()
+            .any(|version| version.same_format(self))
+    }
+
+    /// Returns the components of the version, omitting trailing zeros after
+    /// the minor version.
+    fn components(&self) -> Vec<u32> {
+        let mut components = vec![self.major, self.minor, self.patch, self.revision];
+        while components.len() > 2 && components.last() == Some(&0) {
+            components.pop();
+        }
+        components
+    }
+}
+
+impl fmt::Display for ApiVersion {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for (i, component) in self.components().iter().enumerate() {
+            if i > 0 {
+                f.write_str(".")?;
+            }
+            write!(f, "{}", component)?;
+        }
+        Ok(())
+    }
+}
+
+impl Serialize for ApiVersion {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        self.components().serialize(serializer)
+    }
+}
+
+impl<'de> Deserialize<'de> for ApiVersion {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        match Vec::<u32>::deserialize(deserializer)?[..] {
+            [major, minor] => Ok(ApiVersion::new(major, minor)),
+            [major, minor, patch] => Ok(ApiVersion {
+                patch,
+                ..ApiVersion::new(major, minor)
+            }),
+            [major, minor, patch, revision] => Ok(ApiVersion {
+                major,
+                minor,
+                patch,
+                revision,
+            }),
+            ref other => Err(de::Error::invalid_length(
+                other.len(),
+                &"two to four version components",
+            )),
+        }
+    }
+}
+
+/// Which values of `pandoc-api-version` are accepted when deserializing a
+/// document.
+///
+/// The policy is used as a [`DeserializeSeed`], which produces a
+/// [`VersionedPandoc`]:
+///
+/// ```
+/// use pandoc_types::definition::{ApiVersion, VersionPolicy};
+/// use serde::de::DeserializeSeed;
+///
+/// let json = r#"{"pandoc-api-version":[1,23,2],"meta":{},"blocks":[]}"#;
+///
+/// let mut deserializer = serde_json::Deserializer::from_str(json);
+/// assert!(VersionPolicy::Strict.deserialize(&mut deserializer).is_err());
+///
+/// let mut deserializer = serde_json::Deserializer::from_str(json);
+/// let doc = VersionPolicy::AnyPatch.deserialize(&mut deserializer).unwrap();
+/// assert_eq!(doc.version().to_string(), "1.23.2");
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum VersionPolicy {
+    /// Only accept [`PANDOC_API_VERSION`] exactly.
+    Strict,
+    /// Accept versions with the same major and minor components as
+    /// [`PANDOC_API_VERSION`], with any patch and revision.
+    AnyPatch,
+    /// Accept any version this crate can read, 1.17 or 1.20 to 1.23 with any
+    /// patch and revision. Older documents are upgraded. This is the policy
+    /// used by `Pandoc`'s implementation of `Deserialize`.
+    #[default]
+    Supported,
+    /// Accept any version, reading documents of unsupported versions as if
+    /// they were of the current version and recording a warning in
+    /// [`VersionedPandoc::warnings`]. Reading still fails if the document
+    /// contains elements this crate doesn't know about.
+    Lenient,
+}
+
+impl VersionPolicy {
+    /// Checks a version against the policy. Returns a warning for versions
+    /// accepted despite not being supported.
+    pub fn check(self, version: ApiVersion) -> Result<Option<VersionWarning>, VersionError> {
+        let accepted = match self {
+            VersionPolicy::Strict => version == PANDOC_API_VERSION,
+            VersionPolicy::AnyPatch => version.same_format(PANDOC_API_VERSION),
+            VersionPolicy::Supported => version.is_supported(),
+            VersionPolicy::Lenient => true,
+        };
+        if !accepted {
+            return Err(VersionError {
+                version,
+                policy: self,
+            });
+        }
+        if version.is_supported() {
+            Ok(None)
+        } else {
+            Ok(Some(VersionWarning::Unsupported(version)))
+        }
+    }
+}
+
+/// An error from a [`VersionPolicy`] rejecting the version of a document.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct VersionError {
+    version: ApiVersion,
+    policy: VersionPolicy,
+}
+
+impl VersionError {
+    /// The version of the document.
+    pub fn version(&self) -> ApiVersion {
+        self.version
+    }
+
+    /// The policy that rejected the version.
+    pub fn policy(&self) -> VersionPolicy {
+        self.policy
+    }
+}
+
+impl fmt::Display for VersionError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "unsupported pandoc-api-version {}, expected ",
+            self.version
+        )?;
+        match self.policy {
+            VersionPolicy::Strict => write!(f, "{}", PANDOC_API_VERSION),
+            VersionPolicy::AnyPatch => write!(
+                f,
+                "{}.*",
+                ApiVersion::new(PANDOC_API_VERSION.major, PANDOC_API_VERSION.minor)
+            ),
+            _ => write!(
+                f,
+                "{} or between {} and {}",
+                ApiVersion::V1_17,
+                ApiVersion::V1_20,
+                PANDOC_API_VERSION
+            ),
+        }
+    }
+}
+
+impl std::error::Error for VersionError {}
+
+/// A warning about the version of a document accepted by a
+/// [`VersionPolicy`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum VersionWarning {
+    /// The version isn't supported by this crate, and the document was read
+    /// as if it was of [`PANDOC_API_VERSION`].
+    Unsupported(ApiVersion),
+}
+
+impl fmt::Display for VersionWarning {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            VersionWarning::Unsupported(version) => write!(
+                f,
+                "pandoc-api-version {} is not supported, reading as {}",
+                version, PANDOC_API_VERSION
+            ),
+        }
+    }
+}
+
+impl<'de> DeserializeSeed<'de> for VersionPolicy {
+    type Value = VersionedPandoc;
+
+    fn deserialize<D>(self, deserializer: D) -> Result<Self::Value, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        #[derive(Deserialize)]
+        #[serde(rename = "Pandoc")]
+        struct Inner {
+            meta: Meta,
+            blocks: Vec<Block>,
+            #[serde(rename = "pandoc-api-version")]
+            version: ApiVersion,
+        }
+
+        let value = Inner::deserialize(deserializer)?;
+        let warnings = self
+            .check(value.version)
+            .map_err(de::Error::custom)?
+            .into_iter()
+            .collect();
+
+        let doc = Pandoc {
+            meta: value.meta,
+            blocks: value.blocks,
+        };
+        Ok(VersionedPandoc {
+            version: value.version,
+            doc: doc.upgrade_from(value.version),
+            warnings,
+        })
+    }
+}
+
+/// A document along with the version of its JSON format.
+///
+/// When deserialized, this holds the version the document was read from,
+/// along with any warnings from the [`VersionPolicy`]; the document itself
+/// has been upgraded to the current AST. When serialized, the document is
+/// written in the format of the version, see [`Pandoc::into_api_version`].
+#[derive(Debug, Clone, PartialEq)]
+pub struct VersionedPandoc {
+    pub(super) version: ApiVersion,
+    pub(super) doc: Pandoc,
+    pub(super) warnings: Vec<VersionWarning>,
+}
+
+impl VersionedPandoc {
+    pub fn version(&self) -> ApiVersion {
+        self.version
+    }
+
+    pub fn doc(&self) -> &Pandoc {
+        &self.doc
+    }
+
+    pub fn warnings(&self) -> &[VersionWarning] {
+        &self.warnings
+    }
+
+    pub fn into_inner(self) -> Pandoc {
+        self.doc
+    }
+}
+
+impl Serialize for VersionedPandoc {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        // Documents read from an older version have been upgraded, so they
+        // are converted back before being written.
+        let doc = match self.version < ApiVersion::V1_23 {
+            true => Cow::Owned(self.doc.clone().into_api_version(self.version).doc),
+            false => Cow::Borrowed(&self.doc),
+        };
+        let _guard = LegacyTablesGuard::set(self.version < ApiVersion::V1_21);
+        let mut value = serializer.serialize_struct("Pandoc", 3)?;
+        value.serialize_field("pandoc-api-version", &self.version)?;
+        value.serialize_field("meta", &doc.meta)?;
+        value.serialize_field("blocks", &doc.blocks)?;
+        value.end()
+    }
+}
+
+impl<'de> Deserialize<'de> for VersionedPandoc {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        VersionPolicy::default().deserialize(deserializer)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn read(policy: VersionPolicy, version: serde_json::Value) -> Result<VersionedPandoc, String> {
+        let value = json!({
+            "pandoc-api-version": version,
+            "meta": {},
+            "blocks": [],
+        });
+        policy.deserialize(value).map_err(|e| e.to_string())
+    }
+
+    #[test]
+    fn versions() {
+        assert_eq!(
+            serde_json::to_value(PANDOC_API_VERSION).unwrap(),
+            json!([1, 23])
+        );
+        assert_eq!(
+            serde_json::to_value(ApiVersion::V1_22).unwrap(),
+            json!([1, 22])
+        );
+        assert_eq!(
+            serde_json::from_value::<ApiVersion>(json!([1, 22, 2, 1])).unwrap(),
+            ApiVersion {
+                major: 1,
+                minor: 22,
+                patch: 2,
+                revision: 1
+            }
+        );
+        assert!(serde_json::from_value::<ApiVersion>(json!([1])).is_err());
+        assert!(ApiVersion::new(1, 22) < PANDOC_API_VERSION);
+    }
+
+    #[test]
+    fn policies() {
+        use VersionPolicy::*;
+
+        assert!(read(Strict, json!([1, 23])).is_ok());
+        let err = Supported.check(ApiVersion::new(1, 19)).unwrap_err();
+        assert_eq!(err.version(), ApiVersion::new(1, 19));
+        assert_eq!(err.policy(), Supported);
+        assert!(read(Strict, json!([1, 23, 1])).is_err());
+        assert!(read(AnyPatch, json!([1, 23, 5])).is_ok());
+        assert!(read(AnyPatch, json!([1, 22])).is_err());
+        assert!(read(Supported, json!([1, 17, 0, 4])).is_ok());
+        assert!(read(Supported, json!([1, 20, 1])).is_ok());
+        assert!(read(Supported, json!([1, 18])).is_err());
+        assert!(read(Supported, json!([1, 19, 2])).is_err());
+        assert!(read(Supported, json!([1, 24])).is_err());
+
+        let doc = read(Lenient, json!([1, 24])).unwrap();
+        assert_eq!(doc.version(), ApiVersion::new(1, 24));
+        assert_eq!(
+            doc.warnings(),
+            [VersionWarning::Unsupported(ApiVersion::new(1, 24))]
+        );
+        assert!(read(Lenient, json!([1, 23, 1]))
+            .unwrap()
+            .warnings()
+            .is_empty());
+    }
+}
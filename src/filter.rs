@@ -0,0 +1,532 @@
+//! This module contains a port of the filter functions of [Text.Pandoc.JSON]
+//! to Rust.
+//!
+//! A JSON filter is a program that reads a document in pandoc's JSON format
+//! from standard input, transforms it, and writes it to standard output. It
+//! is run by pandoc with `pandoc --filter`, which passes the name of the
+//! output format as the first argument.
+//!
+//! ```no_run
+//! use pandoc_types::definition::Inline;
+//! use pandoc_types::filter::to_json_filter;
+//!
+//! fn main() {
+//!     to_json_filter(|inline: Inline| match inline {
+//!         Inline::Emph(inlines) => Inline::Strong(inlines),
+//!         other => other,
+//!     });
+//! }
+//! ```
+//!
+//! [Text.Pandoc.JSON]: https://hackage.haskell.org/package/pandoc-types/docs/Text-Pandoc-JSON.html
+use std::error::Error;
+use std::ffi::OsString;
+use std::io::{self, Read, Write};
+use std::marker::PhantomData;
+use std::path::PathBuf;
+use std::process::{Command, Stdio};
+
+use crate::definition::{Block, Citation, Format, Inline, MetaValue, Pandoc, VersionedPandoc};
+use crate::walk::Walkable;
+
+/// The error type of filters.
+pub type FilterError = Box<dyn Error + Send + Sync>;
+
+/// Functions that can be turned into a JSON filter, like Haskell's
+/// `ToJSONFilter` class.
+///
+/// This is implemented for closures that take one of [`Inline`], [`Block`],
+/// `Vec<Inline>`, `Vec<Block>`, [`MetaValue`], [`Citation`] or [`Pandoc`],
+/// optionally preceded by the output format as an `Option<&Format>`. The
+/// closure is applied to every such element in the document, bottom-up, as
+/// with [`Walkable::walk`].
+///
+/// The closure can return the same type, or, for [`Inline`] and [`Block`],
+/// a `Vec` of elements to replace the element with. Either can be wrapped
+/// in a `Result`, in which case the filter stops at the first error.
+///
+/// The `Marker` type parameter only distinguishes the implementations for
+/// different kinds of closures, and is inferred.
+pub trait ToJsonFilter<Marker> {
+    /// Applies the filter to a document.
+    fn apply(&mut self, doc: Pandoc, format: Option<&Format>) -> Result<Pandoc, FilterError>;
+}
+
+/// Marks [`ToJsonFilter`] implementations for closures returning a single
+/// element.
+pub struct One;
+
+/// Marks [`ToJsonFilter`] implementations for closures returning a list of
+/// elements.
+pub struct Many;
+
+/// Marks [`ToJsonFilter`] implementations for closures that take the output
+/// format.
+pub struct WithFormat<M>(M);
+
+/// The types of elements a filter can be applied to.
+pub trait FilterNode: Sized {
+    #[doc(hidden)]
+    fn walk_one<F>(doc: Pandoc, f: F) -> Result<Pandoc, FilterError>
+    where
+        F: FnMut(Self) -> Result<Self, FilterError>;
+}
+
+/// The types of elements that a filter can replace with a list of elements.
+pub trait FilterListNode: FilterNode {
+    #[doc(hidden)]
+    fn walk_many<F>(doc: Pandoc, f: F) -> Result<Pandoc, FilterError>
+    where
+        F: FnMut(Self) -> Result<Vec<Self>, FilterError>;
+}
+
+/// The values a filter closure can return for elements of type `T`: a `T`,
+/// or a `Result` with a `T`.
+pub trait FilterOutput<T> {
+    fn into_filter_result(self) -> Result<T, FilterError>;
+}
+
+impl<T: FilterNode> FilterOutput<T> for T {
+    fn into_filter_result(self) -> Result<T, FilterError> {
+        Ok(self)
+    }
+}
+
+impl<T: FilterNode, E: Into<FilterError>> FilterOutput<T> for Result<T, E> {
+    fn into_filter_result(self) -> Result<T, FilterError> {
+        self.map_err(Into::into)
+    }
+}
+
+macro_rules! walk_filter_node {
+    ($($t:ty),*) => {
+        $(
+            impl FilterNode for $t {
+                fn walk_one<F>(doc: Pandoc, f: F) -> Result<Pandoc, FilterError>
+                where
+                    F: FnMut(Self) -> Result<Self, FilterError>,
+                {
+                    doc.walk_m(f)
+                }
+            }
+        )*
+    };
+}
+
+walk_filter_node!(Inline, Block, Vec<Inline>, Vec<Block>, MetaValue, Citation);
+
+macro_rules! walk_filter_list_node {
+    ($($t:ty),*) => {
+        $(
+            impl FilterListNode for $t {
+                fn walk_many<F>(doc: Pandoc, mut f: F) -> Result<Pandoc, FilterError>
+                where
+                    F: FnMut(Self) -> Result<Vec<Self>, FilterError>,
+                {
+                    doc.walk_m(|xs: Vec<$t>| {
+                        let mut result = Vec::with_capacity(xs.len());
+                        for x in xs {
+                            result.extend(f(x)?);
+                        }
+                        Ok(result)
+                    })
+                }
+            }
+        )*
+    };
+}
+
+walk_filter_list_node!(Inline, Block);
+
+impl FilterNode for Pandoc {
+    fn walk_one<F>(doc: Pandoc, mut f: F) -> Result<Pandoc, FilterError>
+    where
+        F: FnMut(Self) -> Result<Self, FilterError>,
+    {
+        f(doc)
+    }
+}
+
+impl<F, T, R> ToJsonFilter<(One, T, R)> for F
+where
+    F: FnMut(T) -> R,
+    T: FilterNode,
+    R: FilterOutput<T>,
+{
+    fn apply(&mut self, doc: Pandoc, _format: Option<&Format>) -> Result<Pandoc, FilterError> {
+        T::walk_one(doc, |x| self(x).into_filter_result())
+    }
+}
+
+impl<F, T, R> ToJsonFilter<(Many, T, R)> for F
+where
+    F: FnMut(T) -> R,
+    T: FilterListNode,
+    R: FilterOutput<Vec<T>>,
+{
+    fn apply(&mut self, doc: Pandoc, _format: Option<&Format>) -> Result<Pandoc, FilterError> {
+        T::walk_many(doc, |x| self(x).into_filter_result())
+    }
+}
+
+impl<F, T, R> ToJsonFilter<WithFormat<(One, T, R)>> for F
+where
+    F: FnMut(Option<&Format>, T) -> R,
+    T: FilterNode,
+    R: FilterOutput<T>,
+{
+    fn apply(&mut self, doc: Pandoc, format: Option<&Format>) -> Result<Pandoc, FilterError> {
+        T::walk_one(doc, |x| self(format, x).into_filter_result())
+    }
+}
+
+impl<F, T, R> ToJsonFilter<WithFormat<(Many, T, R)>> for F
+where
+    F: FnMut(Option<&Format>, T) -> R,
+    T: FilterListNode,
+    R: FilterOutput<Vec<T>>,
+{
+    fn apply(&mut self, doc: Pandoc, format: Option<&Format>) -> Result<Pandoc, FilterError> {
+        T::walk_many(doc, |x| self(format, x).into_filter_result())
+    }
+}
+
+/// Runs a JSON filter: reads a document from standard input, applies the
+/// filter with the output format given as the first command line argument,
+/// and writes the result to standard output.
+///
+/// If anything fails, the error is printed to standard error and the process
+/// exits with status 1.
+///
+/// The document is written back in the version of the JSON format it was
+/// read in, so filters work with older versions of pandoc.
+pub fn to_json_filter<M>(filter: impl ToJsonFilter<M>) {
+    let format = std::env::args().nth(1).map(Format);
+    let result = run_json_filter(filter, io::stdin().lock(), io::stdout().lock(), format);
+    if let Err(err) = result {
+        eprintln!("{}", err);
+        std::process::exit(1);
+    }
+}
+
+/// Runs a JSON filter on the given input and output, see [`to_json_filter`].
+///
+/// ```
+/// use pandoc_types::definition::{Block, Format, Inline};
+/// use pandoc_types::filter::run_json_filter;
+///
+/// let input = r#"{"pandoc-api-version":[1,23],"meta":{},"blocks":[{"t":"Para","c":[{"t":"Str","c":"a"}]}]}"#;
+/// let mut output = Vec::new();
+///
+/// run_json_filter(
+///     |format: Option<&Format>, block: Block| match block {
+///         Block::Para(_) if format == Some(&Format("html".into())) => vec![],
+///         other => vec![other],
+///     },
+///     input.as_bytes(),
+///     &mut output,
+///     Some(Format("html".into())),
+/// )
+/// .unwrap();
+///
+/// assert_eq!(
+///     String::from_utf8(output).unwrap(),
+///     r#"{"pandoc-api-version":[1,23],"meta":{},"blocks":[]}"#
+/// );
+/// ```
+pub fn run_json_filter<M>(
+    mut filter: impl ToJsonFilter<M>,
+    input: impl Read,
+    mut output: impl Write,
+    format: Option<Format>,
+) -> Result<(), FilterError> {
+    let versioned: VersionedPandoc = serde_json::from_reader(io::BufReader::new(input))?;
+    let version = versioned.version();
+    let doc = filter.apply(versioned.into_inner(), format.as_ref())?;
+    serde_json::to_writer(&mut output, &doc.into_api_version(version))?;
+    output.flush()?;
+    Ok(())
+}
+
+/// A stage of an in-process filter [`Pipeline`].
+///
+/// Unlike JSON filters, filters implementing this trait operate directly on
+/// a [`Pandoc`] value, so chaining them doesn't require encoding and
+/// decoding JSON between stages.
+pub trait Filter {
+    /// Applies the filter to a document, given the output format.
+    fn filter(&mut self, doc: Pandoc, format: Option<&Format>) -> Result<Pandoc, FilterError>;
+}
+
+/// A [`Filter`] made from a closure, see [`filter_fn`].
+pub struct FnFilter<F, M> {
+    f: F,
+    marker: PhantomData<fn() -> M>,
+}
+
+/// Turns any closure accepted by [`to_json_filter`] into a [`Filter`].
+pub fn filter_fn<F, M>(f: F) -> FnFilter<F, M>
+where
+    F: ToJsonFilter<M>,
+{
+    FnFilter {
+        f,
+        marker: PhantomData,
+    }
+}
+
+impl<F, M> Filter for FnFilter<F, M>
+where
+    F: ToJsonFilter<M>,
+{
+    fn filter(&mut self, doc: Pandoc, format: Option<&Format>) -> Result<Pandoc, FilterError> {
+        self.f.apply(doc, format)
+    }
+}
+
+/// Marks the [`ToJsonFilter`] implementation for [`Filter`]s, which lets a
+/// [`Pipeline`] be run as a JSON filter.
+pub struct AsFilter;
+
+impl<F: Filter> ToJsonFilter<AsFilter> for F {
+    fn apply(&mut self, doc: Pandoc, format: Option<&Format>) -> Result<Pandoc, FilterError> {
+        self.filter(doc, format)
+    }
+}
+
+/// A sequence of filters applied in order to the same document.
+///
+/// ```
+/// use pandoc_types::builder::{doc, para, text};
+/// use pandoc_types::definition::{Block, Inline};
+/// use pandoc_types::filter::{Filter, Pipeline};
+///
+/// let mut pipeline = Pipeline::new()
+///     .then_fn(|inline: Inline| match inline {
+///         Inline::Str(s) => Inline::Str(s.to_uppercase()),
+///         other => other,
+///     })
+///     .then_fn(|block: Block| vec![block.clone(), block]);
+///
+/// let result = pipeline.filter(doc(para(text("a"))), None).unwrap();
+/// assert_eq!(result, doc(para(text("A")) + para(text("A"))));
+/// ```
+#[derive(Default)]
+pub struct Pipeline {
+    stages: Vec<Box<dyn Filter>>,
+}
+
+impl Pipeline {
+    pub fn new() -> Self {
+        Pipeline::default()
+    }
+
+    /// Adds a filter at the end of the pipeline.
+    pub fn then(mut self, filter: impl Filter + 'static) -> Self {
+        self.stages.push(Box::new(filter));
+        self
+    }
+
+    /// Adds a closure at the end of the pipeline, see [`filter_fn`].
+    pub fn then_fn<F, M>(self, f: F) -> Self
+    where
+        F: ToJsonFilter<M> + 'static,
+        M: 'static,
+    {
+        self.then(filter_fn(f))
+    }
+
+    pub fn len(&self) -> usize {
+        self.stages.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.stages.is_empty()
+    }
+}
+
+impl Filter for Pipeline {
+    fn filter(&mut self, doc: Pandoc, format: Option<&Format>) -> Result<Pandoc, FilterError> {
+        self.stages
+            .iter_mut()
+            .try_fold(doc, |doc, stage| stage.filter(doc, format))
+    }
+}
+
+/// A [`Filter`] that runs an external JSON filter executable, the same way
+/// `pandoc --filter` does: the document is written to its standard input,
+/// the output format is passed as its first argument, and the filtered
+/// document is read from its standard output. Its standard error is
+/// inherited.
+///
+/// Unlike pandoc, this doesn't pick an interpreter based on the file
+/// extension; use [`ExternalFilter::with_args`] to run a script through an
+/// interpreter.
+#[derive(Debug, Clone)]
+pub struct ExternalFilter {
+    program: PathBuf,
+    args: Vec<OsString>,
+}
+
+impl ExternalFilter {
+    pub fn new(program: impl Into<PathBuf>) -> Self {
+        ExternalFilter {
+            program: program.into(),
+            args: Vec::new(),
+        }
+    }
+
+    /// Runs the program with the given arguments before the output format,
+    /// for example `ExternalFilter::with_args("python3", ["filter.py"])`.
+    pub fn with_args<I>(program: impl Into<PathBuf>, args: I) -> Self
+    where
+        I: IntoIterator,
+        I::Item: Into<OsString>,
+    {
+        ExternalFilter {
+            program: program.into(),
+            args: args.into_iter().map(Into::into).collect(),
+        }
+    }
+}
+
+impl Filter for ExternalFilter {
+    fn filter(&mut self, doc: Pandoc, format: Option<&Format>) -> Result<Pandoc, FilterError> {
+        let input = serde_json::to_vec(&doc)?;
+
+        let mut command = Command::new(&self.program);
+        command.args(&self.args);
+        if let Some(Format(format)) = format {
+            command.arg(format);
+        }
+        let mut child = command
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .spawn()
+            .map_err(|e| format!("could not run filter {}: {}", self.program.display(), e))?;
+
+        // Write on another thread, since the filter may start writing its
+        // output before it has read all of its input.
+        let mut stdin = child.stdin.take().expect("stdin is piped");
+        let output = std::thread::scope(|scope| {
+            let writer = scope.spawn(move || stdin.write_all(&input));
+            let output = child.wait_with_output();
+            // A filter that exits without reading all its input closes the
+            // pipe, which is reported by its exit status instead.
+            let _ = writer.join();
+            output
+        })?;
+
+        if !output.status.success() {
+            return Err(format!(
+                "filter {} failed with {}",
+                self.program.display(),
+                output.status
+            )
+            .into());
+        }
+        Ok(serde_json::from_slice(&output.stdout)?)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::builder::{doc, emph, para, text};
+
+    fn apply<M>(mut filter: impl ToJsonFilter<M>) -> Result<Pandoc, FilterError> {
+        filter.apply(doc(para(text("a b") + emph("c"))), None)
+    }
+
+    #[test]
+    fn closures() {
+        let upper = |inline: Inline| match inline {
+            Inline::Str(s) => Inline::Str(s.to_uppercase()),
+            other => other,
+        };
+        assert_eq!(apply(upper).unwrap(), doc(para(text("A B") + emph("C"))));
+
+        let drop_spaces = |inline: Inline| match inline {
+            Inline::Space => vec![],
+            other => vec![other],
+        };
+        assert_eq!(
+            apply(drop_spaces).unwrap(),
+            doc(para(vec![
+                Inline::Str("a".into()),
+                Inline::Str("b".into()),
+                Inline::Emph(vec![Inline::Str("c".into())]),
+            ]))
+        );
+
+        let reverse = |mut inlines: Vec<Inline>| {
+            inlines.reverse();
+            inlines
+        };
+        assert_eq!(apply(reverse).unwrap(), doc(para(emph("c") + text("b a"))));
+
+        let clear = |mut doc: Pandoc| -> Result<Pandoc, &'static str> {
+            doc.blocks.clear();
+            Ok(doc)
+        };
+        assert_eq!(apply(clear).unwrap(), Pandoc::default());
+    }
+
+    #[test]
+    fn errors() {
+        let fail =
+            |block: Block| -> Result<Vec<Block>, String> { Err(format!("unexpected {:?}", block)) };
+        assert!(apply(fail).is_err());
+
+        let mut output = Vec::new();
+        assert!(run_json_filter(|x: Inline| x, &b"{}"[..], &mut output, None).is_err());
+    }
+
+    #[test]
+    fn keeps_version() {
+        let input = r#"{"pandoc-api-version":[1,20],"meta":{},"blocks":[{"t":"Para","c":[{"t":"Underline","c":[]}]}]}"#;
+        let mut output = Vec::new();
+        run_json_filter(|x: Block| x, input.as_bytes(), &mut output, None).unwrap();
+        let output: serde_json::Value = serde_json::from_slice(&output).unwrap();
+        assert_eq!(output["pandoc-api-version"], serde_json::json!([1, 20]));
+        assert_eq!(output["blocks"][0]["c"][0]["t"], "Span");
+    }
+
+    #[test]
+    fn pipeline() {
+        let mut pipeline = Pipeline::new()
+            .then_fn(
+                |format: Option<&Format>, inline: Inline| match (format, inline) {
+                    (Some(Format(f)), Inline::Str(_)) => Inline::Str(f.clone()),
+                    (_, other) => other,
+                },
+            )
+            .then(Pipeline::new().then_fn(|mut inlines: Vec<Inline>| {
+                inlines.truncate(1);
+                inlines
+            }));
+        assert_eq!(pipeline.len(), 2);
+        assert_eq!(
+            pipeline
+                .filter(doc(para(text("a b"))), Some(&Format("html".into())))
+                .unwrap(),
+            doc(para(text("html")))
+        );
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn external() {
+        let mut pipeline = Pipeline::new()
+            .then(ExternalFilter::new("cat"))
+            .then_fn(|inline: Inline| vec![inline.clone(), inline]);
+        let a = Inline::Str("a".into());
+        assert_eq!(
+            pipeline.filter(doc(para(text("a"))), None).unwrap(),
+            doc(para(vec![a.clone(), a]))
+        );
+
+        let mut failing = ExternalFilter::with_args("sh", ["-c", "exit 3"]);
+        assert!(failing.filter(doc(para(text("a"))), None).is_err());
+    }
+}
@@ -1,3 +1,10 @@
 #![warn(clippy::all, rust_2018_idioms)]
 
+#[cfg(any(test, feature = "proptest"))]
+pub mod arbitrary;
+pub mod builder;
 pub mod definition;
+pub mod filter;
+pub mod readers;
+pub mod walk;
+pub mod writers;
@@ -0,0 +1,17 @@
+//! This module contains readers that parse documents from other formats
+//! without calling the Pandoc executable.
+//!
+//! ```
+//! use pandoc_types::builder::{para, text};
+//! use pandoc_types::readers::native::read_native_blocks;
+//!
+//! let blocks = read_native_blocks(r#"[Para [Str "Hello",Space,Str "world"]]"#).unwrap();
+//! assert_eq!(blocks, para(text("Hello world")).into_vec());
+//! ```
+
+#[cfg(feature = "comrak")]
+pub mod comrak;
+pub mod djot;
+pub mod native;
+#[cfg(feature = "pulldown-cmark")]
+pub mod pulldown_cmark;
@@ -0,0 +1,772 @@
+//! A reader for the AST of [comrak], a GitHub-Flavored Markdown parser. It
+//! is only available with the `comrak` feature.
+//!
+//! The reader takes the root of a document parsed by comrak, along with the
+//! options it was parsed with, and maps its nodes onto the AST the way
+//! Pandoc reads the corresponding GFM extensions:
+//!
+//! - paragraphs in tight lists become [`Block::Plain`];
+//! - task list items start with `☐` or `☒`;
+//! - description lists become definition lists, with one definition per
+//!   term;
+//! - alerts such as `> [!NOTE]` become divs with the class of the alert,
+//!   holding a div with the class `title` and then the content;
+//! - footnotes are placed where they are referenced;
+//! - headings get identifiers when `header_ids` is set, the same ones
+//!   comrak's HTML output uses without the prefix;
+//! - links whose text is their URL get the class `uri` or `email`, wiki
+//!   links the class `wikilink`, and spoilers are spans with the class
+//!   `spoiler`;
+//! - YAML front matter becomes the metadata of the document, with strings
+//!   parsed as Markdown. Keys ending with `_` are ignored, as in Pandoc.
+//!
+//! When the `sourcepos` render option is set, source positions are recorded
+//! in `data-pos` attributes, like Pandoc's `sourcepos` extension: elements
+//! that have attributes get one directly, and other blocks and inlines are
+//! wrapped in a div or span that has it.
+//!
+//! ```
+//! use comrak::{parse_document, Arena, Options};
+//! use pandoc_types::builder::*;
+//! use pandoc_types::definition::MetaValue;
+//! use pandoc_types::readers::comrak::read_comrak;
+//!
+//! let mut options = Options::default();
+//! options.extension.front_matter_delimiter = Some("---".into());
+//! options.extension.strikethrough = true;
+//!
+//! let arena = Arena::new();
+//! let root = parse_document(&arena, "---\ntitle: A *title*\n---\n\nSome ~~old~~ text\n", &options);
+//! let doc = read_comrak(root, &options);
+//!
+//! assert_eq!(
+//!     doc.meta.lookup("title"),
+//!     Some(&MetaValue::MetaInlines((text("A ") + emph("title")).into_vec()))
+//! );
+//! assert_eq!(doc.blocks, para(text("Some ") + strikeout("old") + text(" text")).into_vec());
+//! ```
+//!
+//! [comrak]: https://docs.rs/comrak
+use std::collections::{HashMap, HashSet};
+
+use comrak::html::collect_text;
+use comrak::nodes::{AlertType, AstNode, ListDelimType, ListType, NodeValue, TableAlignment};
+use comrak::{parse_document, Anchorizer, Arena, Options};
+use yaml_rust2::{Yaml, YamlLoader};
+
+use crate::definition::{
+    Alignment, Attr, Block, Caption, Cell, ColSpec, ColWidth, Format, Inline, ListAttributes,
+    ListNumberDelim, ListNumberStyle, MathType, Meta, MetaValue, Pandoc, Row, Table, TableBody,
+    TableFoot, TableHead, Target,
+};
+
+/// Reads a document parsed by comrak with the given options.
+pub fn read_comrak<'a>(root: &'a AstNode<'a>, options: &Options<'_>) -> Pandoc {
+    let mut reader = Reader::new(root, options);
+    let blocks = reader.blocks(root, false);
+    Pandoc {
+        meta: reader.meta,
+        blocks,
+    }
+}
+
+struct Reader<'a, 'o, 'c> {
+    options: &'o Options<'c>,
+    /// The footnote definitions, by name.
+    footnotes: HashMap<String, &'a AstNode<'a>>,
+    /// The footnotes being read, to stop footnotes that refer to themselves.
+    active: HashSet<String>,
+    anchorizer: Anchorizer,
+    meta: Meta,
+}
+
+impl<'a, 'o, 'c> Reader<'a, 'o, 'c> {
+    fn new(root: &'a AstNode<'a>, options: &'o Options<'c>) -> Self {
+        let mut footnotes = HashMap::new();
+        for node in root.descendants() {
+            if let NodeValue::FootnoteDefinition(definition) = &node.data.borrow().value {
+                footnotes.entry(definition.name.clone()).or_insert(node);
+            }
+        }
+        Reader {
+            options,
+            footnotes,
+            active: HashSet::new(),
+            anchorizer: Anchorizer::new(),
+            meta: Meta::default(),
+        }
+    }
+
+    /// Reads the children of a node. Paragraphs become [`Block::Plain`] if
+    /// `tight` is set.
+    fn blocks(&mut self, node: &'a AstNode<'a>, tight: bool) -> Vec<Block> {
+        node.children()
+            .filter_map(|child| self.block(child, tight))
+            .collect()
+    }
+
+    fn block(&mut self, node: &'a AstNode<'a>, tight: bool) -> Option<Block> {
+        let value = node.data.borrow().value.clone();
+        let block = match value {
+            NodeValue::FrontMatter(text) => return self.front_matter(&text),
+            NodeValue::Paragraph if tight => Block::Plain(self.inlines(node)),
+            NodeValue::Paragraph => Block::Para(self.inlines(node)),
+            NodeValue::Heading(heading) => {
+                let mut attr = Attr::default();
+                if let Some(prefix) = &self.options.extension.header_ids {
+                    let mut text = Vec::new();
+                    collect_text(node, &mut text);
+                    let id = self
+                        .anchorizer
+                        .anchorize(String::from_utf8_lossy(&text).into_owned());
+                    attr.identifier = format!("{}{}", prefix, id);
+                }
+                Block::Header(heading.level.into(), attr, self.inlines(node))
+            }
+            NodeValue::BlockQuote | NodeValue::MultilineBlockQuote(_) => {
+                Block::BlockQuote(self.blocks(node, false))
+            }
+            NodeValue::Alert(alert) => {
+                let (class, default_title) = match alert.alert_type {
+                    AlertType::Note => ("note", "Note"),
+                    AlertType::Tip => ("tip", "Tip"),
+                    AlertType::Important => ("important", "Important"),
+                    AlertType::Warning => ("warning", "Warning"),
+                    AlertType::Caution => ("caution", "Caution"),
+                };
+                let mut title = Vec::new();
+                push_text(&mut title, alert.title.as_deref().unwrap_or(default_title));
+                let title = Block::Div(
+                    Attr {
+                        classes: vec!["title".into()],
+                        ..Attr::default()
+                    },
+                    vec![Block::Para(title)],
+                );
+                let attr = Attr {
+                    classes: vec![class.into()],
+                    ..Attr::default()
+                };
+                Block::Div(attr, [vec![title], self.blocks(node, false)].concat())
+            }
+            NodeValue::List(list) => {
+                let items = node
+                    .children()
+                    .map(|item| {
+                        let mut blocks = self.blocks(item, list.tight);
+                        if let NodeValue::TaskItem(symbol) = item.data.borrow().value {
+                            add_checkbox(&mut blocks, symbol.is_some());
+                        }
+                        blocks
+                    })
+                    .collect();
+                match list.list_type {
+                    ListType::Bullet => Block::BulletList(items),
+                    ListType::Ordered => {
+                        let attrs = ListAttributes {
+                            start_number: i32::try_from(list.start).unwrap_or(i32::MAX),
+                            style: ListNumberStyle::Decimal,
+                            delim: match list.delimiter {
+                                ListDelimType::Period => ListNumberDelim::Period,
+                                ListDelimType::Paren => ListNumberDelim::OneParen,
+                            },
+                        };
+                        Block::OrderedList(attrs, items)
+                    }
+                }
+            }
+            NodeValue::DescriptionList => {
+                let mut items = Vec::new();
+                for item in node.children() {
+                    let NodeValue::DescriptionItem(description) = item.data.borrow().value else {
+                        continue;
+                    };
+                    let mut term = Vec::new();
+                    let mut definitions = Vec::new();
+                    for part in item.children() {
+                        match part.data.borrow().value {
+                            NodeValue::DescriptionTerm => {
+                                for paragraph in part.children() {
+                                    term.extend(self.inlines(paragraph));
+                                }
+                            }
+                            NodeValue::DescriptionDetails => {
+                                definitions.push(self.blocks(part, description.tight));
+                            }
+                            _ => {}
+                        }
+                    }
+                    items.push((term, definitions));
+                }
+                Block::DefinitionList(items)
+            }
+            NodeValue::CodeBlock(code) => {
+                let attr = Attr {
+                    classes: code
+                        .info
+                        .split_whitespace()
+                        .next()
+                        .map(|lang| vec![lang.to_owned()])
+                        .unwrap_or_default(),
+                    ..Attr::default()
+                };
+                let literal = code.literal.strip_suffix('\n').unwrap_or(&code.literal);
+                Block::CodeBlock(attr, literal.to_owned())
+            }
+            NodeValue::HtmlBlock(html) => Block::RawBlock(
+                Format("html".into()),
+                html.literal.trim_end_matches('\n').to_owned(),
+            ),
+            NodeValue::Raw(raw) => Block::RawBlock(Format("html".into()), raw),
+            NodeValue::ThematicBreak => Block::HorizontalRule,
+            NodeValue::Table(table) => self.table(node, &table.alignments),
+            // Definitions are read where they are referenced.
+            NodeValue::FootnoteDefinition(_) => return None,
+            _ => Block::Div(Attr::default(), self.blocks(node, tight)),
+        };
+        Some(self.block_position(node, block))
+    }
+
+    fn table(&mut self, node: &'a AstNode<'a>, alignments: &[TableAlignment]) -> Block {
+        let mut head = Vec::new();
+        let mut body = Vec::new();
+        for row in node.children() {
+            let NodeValue::TableRow(is_head) = row.data.borrow().value else {
+                continue;
+            };
+            let cells = row
+                .children()
+                .map(|cell| {
+                    let inlines = self.inlines(cell);
+                    Cell {
+                        content: match inlines.is_empty() {
+                            true => Vec::new(),
+                            false => vec![Block::Plain(inlines)],
+                        },
+                        ..Cell::default()
+                    }
+                })
+                .collect();
+            let row = Row {
+                attr: Attr::default(),
+                cells,
+            };
+            match is_head {
+                true => head.push(row),
+                false => body.push(row),
+            }
+        }
+        let colspecs = alignments
+            .iter()
+            .map(|alignment| {
+                let alignment = match alignment {
+                    TableAlignment::None => Alignment::AlignDefault,
+                    TableAlignment::Left => Alignment::AlignLeft,
+                    TableAlignment::Center => Alignment::AlignCenter,
+                    TableAlignment::Right => Alignment::AlignRight,
+                };
+                ColSpec(alignment, ColWidth::ColWidthDefault)
+            })
+            .collect();
+        Block::Table(Table {
+            attr: Attr::default(),
+            caption: Caption::default(),
+            colspecs,
+            head: TableHead {
+                attr: Attr::default(),
+                rows: head,
+            },
+            bodies: match body.is_empty() {
+                true => Vec::new(),
+                false => vec![TableBody {
+                    attr: Attr::default(),
+                    row_head_columns: 0,
+                    head: Vec::new(),
+                    body,
+                }],
+            },
+            foot: TableFoot::default(),
+        })
+    }
+
+    fn inlines(&mut self, node: &'a AstNode<'a>) -> Vec<Inline> {
+        let mut inlines = Vec::new();
+        for child in node.children() {
+            self.inline(child, &mut inlines);
+        }
+        inlines
+    }
+
+    fn inline(&mut self, node: &'a AstNode<'a>, out: &mut Vec<Inline>) {
+        let value = node.data.borrow().value.clone();
+        let inline = match value {
+            NodeValue::Text(text) | NodeValue::EscapedTag(text) => {
+                if !self.options.render.sourcepos || text.trim().is_empty() {
+                    return push_text(out, &text);
+                }
+                let mut inlines = Vec::new();
+                push_text(&mut inlines, &text);
+                Inline::Span(Attr::default(), inlines)
+            }
+            NodeValue::SoftBreak => return out.push(Inline::SoftBreak),
+            NodeValue::LineBreak => return out.push(Inline::LineBreak),
+            NodeValue::Code(code) => Inline::Code(Attr::default(), code.literal),
+            NodeValue::Math(math) => {
+                let math_type = match math.display_math {
+                    true => MathType::DisplayMath,
+                    false => MathType::InlineMath,
+                };
+                Inline::Math(math_type, math.literal)
+            }
+            NodeValue::HtmlInline(html) | NodeValue::Raw(html) => {
+                Inline::RawInline(Format("html".into()), html)
+            }
+            NodeValue::Emph => Inline::Emph(self.inlines(node)),
+            NodeValue::Strong => Inline::Strong(self.inlines(node)),
+            NodeValue::Strikethrough => Inline::Strikeout(self.inlines(node)),
+            NodeValue::Superscript => Inline::Superscript(self.inlines(node)),
+            NodeValue::Subscript => Inline::Subscript(self.inlines(node)),
+            NodeValue::Underline => Inline::Underline(self.inlines(node)),
+            NodeValue::SpoileredText => {
+                let attr = Attr {
+                    classes: vec!["spoiler".into()],
+                    ..Attr::default()
+                };
+                Inline::Span(attr, self.inlines(node))
+            }
+            NodeValue::Link(link) => {
+                let mut text = Vec::new();
+                collect_text(node, &mut text);
+                let text = String::from_utf8_lossy(&text);
+                let class = if link.url == text {
+                    Some("uri")
+                } else if link.url.strip_prefix("mailto:") == Some(&text) {
+                    Some("email")
+                } else {
+                    None
+                };
+                let attr = Attr {
+                    classes: class.into_iter().map(str::to_owned).collect(),
+                    ..Attr::default()
+                };
+                let target = Target {
+                    url: link.url,
+                    title: link.title,
+                };
+                Inline::Link(attr, self.inlines(node), target)
+            }
+            NodeValue::WikiLink(link) => {
+                let attr = Attr {
+                    classes: vec!["wikilink".into()],
+                    ..Attr::default()
+                };
+                let target = Target {
+                    url: link.url,
+                    title: String::new(),
+                };
+                Inline::Link(attr, self.inlines(node), target)
+            }
+            NodeValue::Image(link) => {
+                let target = Target {
+                    url: link.url,
+                    title: link.title,
+                };
+                Inline::Image(Attr::default(), self.inlines(node), target)
+            }
+            NodeValue::FootnoteReference(reference) => {
+                return self.note(&reference.name, out);
+            }
+            _ => return out.extend(self.inlines(node)),
+        };
+        out.push(self.inline_position(node, inline));
+    }
+
+    /// Reads the footnote with the given name, or keeps the reference as
+    /// text if there is no such footnote.
+    fn note(&mut self, name: &str, out: &mut Vec<Inline>) {
+        match self.footnotes.get(name).copied() {
+            Some(definition) if self.active.insert(name.to_owned()) => {
+                let blocks = self.blocks(definition, false);
+                self.active.remove(name);
+                out.push(Inline::Note(blocks));
+            }
+            _ => push_text(out, &format!("[^{}]", name)),
+        }
+    }
+
+    /// Adds the source position of a node to the attributes of a block, or
+    /// wraps the block in a div holding it.
+    fn block_position(&self, node: &'a AstNode<'a>, block: Block) -> Block {
+        if !self.options.render.sourcepos {
+            return block;
+        }
+        let pos = node.data.borrow().sourcepos.to_string();
+        match block {
+            Block::Header(level, attr, inlines) => {
+                Block::Header(level, with_pos(attr, pos), inlines)
+            }
+            Block::CodeBlock(attr, code) => Block::CodeBlock(with_pos(attr, pos), code),
+            Block::Div(attr, blocks) if attr != Attr::default() => {
+                Block::Div(with_pos(attr, pos), blocks)
+            }
+            Block::Table(mut table) => {
+                table.attr = with_pos(table.attr, pos);
+                Block::Table(table)
+            }
+            block => Block::Div(with_pos(Attr::default(), pos), vec![block]),
+        }
+    }
+
+    /// Adds the source position of a node to the attributes of an inline,
+    /// or wraps the inline in a span holding it.
+    fn inline_position(&self, node: &'a AstNode<'a>, inline: Inline) -> Inline {
+        if !self.options.render.sourcepos {
+            return inline;
+        }
+        let pos = node.data.borrow().sourcepos.to_string();
+        match inline {
+            Inline::Code(attr, code) => Inline::Code(with_pos(attr, pos), code),
+            Inline::Link(attr, inlines, target) => {
+                Inline::Link(with_pos(attr, pos), inlines, target)
+            }
+            Inline::Image(attr, inlines, target) => {
+                Inline::Image(with_pos(attr, pos), inlines, target)
+            }
+            Inline::Span(attr, inlines) => Inline::Span(with_pos(attr, pos), inlines),
+            inline => Inline::Span(with_pos(Attr::default(), pos), vec![inline]),
+        }
+    }
+
+    /// Reads YAML front matter into the metadata, or keeps it as a raw
+    /// block if it isn't a YAML mapping.
+    fn front_matter(&mut self, text: &str) -> Option<Block> {
+        let mut lines = text.lines();
+        let delimiter = match &self.options.extension.front_matter_delimiter {
+            Some(delimiter) => delimiter.as_str(),
+            None => lines.clone().next().unwrap_or_default().trim(),
+        };
+        let yaml: Vec<&str> = lines
+            .by_ref()
+            .skip(1)
+            .take_while(|line| line.trim() != delimiter)
+            .collect();
+        let yaml = yaml.join("\n");
+        match YamlLoader::load_from_str(&yaml).as_deref() {
+            Ok([Yaml::Hash(hash)]) => {
+                for (key, value) in hash {
+                    let Some(key) = scalar(key) else { continue };
+                    if key.ends_with('_') {
+                        continue;
+                    }
+                    if let Some(value) = self.meta_value(value) {
+                        self.meta.insert(key, value);
+                    }
+                }
+                None
+            }
+            Ok([]) => None,
+            _ => Some(Block::RawBlock(Format("yaml".into()), yaml)),
+        }
+    }
+
+    fn meta_value(&self, yaml: &Yaml) -> Option<MetaValue> {
+        let value = match yaml {
+            Yaml::Boolean(b) => MetaValue::MetaBool(*b),
+            Yaml::Array(array) => MetaValue::MetaList(
+                array
+                    .iter()
+                    .filter_map(|value| self.meta_value(value))
+                    .collect(),
+            ),
+            Yaml::Hash(hash) => MetaValue::MetaMap(
+                hash.iter()
+                    .filter_map(|(key, value)| {
+                        let key = scalar(key).filter(|key| !key.ends_with('_'))?;
+                        Some((key, self.meta_value(value)?))
+                    })
+                    .collect(),
+            ),
+            yaml => self.markdown(&scalar(yaml)?),
+        };
+        Some(value)
+    }
+
+    /// Parses a metadata string as Markdown, giving inlines if it is a
+    /// single paragraph.
+    fn markdown(&self, text: &str) -> MetaValue {
+        let mut options = self.options.clone();
+        options.extension.front_matter_delimiter = None;
+        options.render.sourcepos = false;
+        let arena = Arena::new();
+        let root = parse_document(&arena, text, &options);
+        let mut blocks = Reader::new(root, &options).blocks(root, false);
+        match blocks.as_mut_slice() {
+            [] => MetaValue::MetaInlines(Vec::new()),
+            [Block::Para(inlines)] => MetaValue::MetaInlines(std::mem::take(inlines)),
+            _ => MetaValue::MetaBlocks(blocks),
+        }
+    }
+}
+
+/// Returns the text of a YAML scalar.
+fn scalar(yaml: &Yaml) -> Option<String> {
+    match yaml {
+        Yaml::String(s) | Yaml::Real(s) => Some(s.clone()),
+        Yaml::Integer(i) => Some(i.to_string()),
+        Yaml::Boolean(b) => Some(b.to_string()),
+        _ => None,
+    }
+}
+
+fn with_pos(mut attr: Attr, pos: String) -> Attr {
+    attr.attributes.push(("data-pos".into(), pos));
+    attr
+}
+
+/// Adds text to a list of inlines, splitting it into words and spaces and
+/// joining it to the word before it.
+fn push_text(inlines: &mut Vec<Inline>, text: &str) {
+    for c in text.chars() {
+        match (c, inlines.last_mut()) {
+            (' ' | '\t' | '\n', Some(Inline::Space)) => {}
+            (' ' | '\t' | '\n', _) => inlines.push(Inline::Space),
+            (c, Some(Inline::Str(word))) => word.push(c),
+            (c, _) => inlines.push(Inline::Str(c.into())),
+        }
+    }
+}
+
+/// Puts a checkbox at the start of the first paragraph of a task list item,
+/// looking inside the divs that hold source positions.
+fn add_checkbox(blocks: &mut Vec<Block>, checked: bool) {
+    let checkbox = [
+        Inline::Str(if checked { "☒" } else { "☐" }.into()),
+        Inline::Space,
+    ];
+    match blocks.first_mut() {
+        Some(Block::Plain(inlines) | Block::Para(inlines)) => {
+            inlines.splice(0..0, checkbox);
+        }
+        Some(Block::Div(attr, blocks)) if attr.classes.is_empty() && attr.identifier.is_empty() => {
+            add_checkbox(blocks, checked)
+        }
+        _ => blocks.insert(0, Block::Plain(checkbox.to_vec())),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::builder::*;
+    use indexmap::IndexMap;
+
+    fn options() -> Options<'static> {
+        let mut options = Options::default();
+        options.extension.strikethrough = true;
+        options.extension.table = true;
+        options.extension.autolink = true;
+        options.extension.tasklist = true;
+        options.extension.footnotes = true;
+        options.extension.description_lists = true;
+        options.extension.front_matter_delimiter = Some("---".into());
+        options.extension.alerts = true;
+        options.extension.wikilinks_title_after_pipe = true;
+        options.extension.spoiler = true;
+        options
+    }
+
+    fn read_with(input: &str, options: &Options<'_>) -> Pandoc {
+        let arena = Arena::new();
+        read_comrak(parse_document(&arena, input, options), options)
+    }
+
+    fn read(input: &str) -> Vec<Block> {
+        read_with(input, &options()).blocks
+    }
+
+    fn attr(id: &str, classes: &[&str], attributes: &[(&str, &str)]) -> Attr {
+        Attr {
+            identifier: id.into(),
+            classes: classes.iter().map(|&class| class.into()).collect(),
+            attributes: attributes
+                .iter()
+                .map(|&(key, value)| (key.into(), value.into()))
+                .collect(),
+        }
+    }
+
+    #[test]
+    fn blocks() {
+        let mut options = options();
+        options.extension.header_ids = Some("h-".into());
+        assert_eq!(
+            read_with(
+                "# A title\n\n> quoted\n\n***\n\n```rust ignore\nlet x;\n```\n\n<div>\nhi\n</div>\n",
+                &options
+            )
+            .blocks,
+            (header_with(attr("h-a-title", &[], &[]), 1, "A title")
+                + block_quote(para("quoted"))
+                + horizontal_rule()
+                + code_block_with(attr("", &["rust"], &[]), "let x;")
+                + raw_block("html", "<div>\nhi\n</div>"))
+            .into_vec()
+        );
+    }
+
+    #[test]
+    fn lists() {
+        let attrs = ListAttributes {
+            start_number: 3,
+            style: ListNumberStyle::Decimal,
+            delim: ListNumberDelim::OneParen,
+        };
+        assert_eq!(
+            read("- [ ] todo\n- [x] done\n\n3) one\n\n4) two\n"),
+            (bullet_list([plain(text("☐ todo")), plain(text("☒ done"))])
+                + ordered_list_with(attrs, [para("one"), para("two")]))
+            .into_vec()
+        );
+    }
+
+    #[test]
+    fn description_lists() {
+        assert_eq!(
+            read("Term *one*\n\n: Details\n\nTerm two\n\n: More\n\n  details\n"),
+            definition_list([
+                (text("Term ") + emph("one"), vec![para("Details")]),
+                (text("Term two"), vec![para("More") + para("details")]),
+            ])
+            .into_vec()
+        );
+    }
+
+    #[test]
+    fn alerts() {
+        assert_eq!(
+            read("> [!NOTE]\n> Read this.\n\n> [!WARNING] Careful now\n> Or not.\n"),
+            (div_with(
+                attr("", &["note"], &[]),
+                div_with(attr("", &["title"], &[]), para("Note")) + para(text("Read this."))
+            ) + div_with(
+                attr("", &["warning"], &[]),
+                div_with(attr("", &["title"], &[]), para(text("Careful now")))
+                    + para(text("Or not."))
+            ))
+            .into_vec()
+        );
+    }
+
+    #[test]
+    fn front_matter() {
+        let doc = read_with(
+            "---\ntitle: A *title*\ndraft: true\ntags: [a, b]\nauthor:\n  name: Me\nabstract: |\n  One.\n\n  Two.\nnotes_: skipped\n---\n\nText\n",
+            &options(),
+        );
+        let mut author = IndexMap::new();
+        author.insert(
+            "name".to_owned(),
+            MetaValue::MetaInlines(text("Me").into_vec()),
+        );
+        let expected: Meta = [
+            (
+                "title",
+                MetaValue::MetaInlines((text("A ") + emph("title")).into_vec()),
+            ),
+            ("draft", MetaValue::MetaBool(true)),
+            (
+                "tags",
+                MetaValue::MetaList(vec![
+                    MetaValue::MetaInlines(text("a").into_vec()),
+                    MetaValue::MetaInlines(text("b").into_vec()),
+                ]),
+            ),
+            ("author", MetaValue::MetaMap(author)),
+            (
+                "abstract",
+                MetaValue::MetaBlocks((para("One.") + para("Two.")).into_vec()),
+            ),
+        ]
+        .into_iter()
+        .map(|(key, value)| (key.to_owned(), value))
+        .collect();
+        assert_eq!(doc.meta, expected);
+        assert_eq!(doc.blocks, para("Text").into_vec());
+
+        let doc = read_with("---\n- not\n- a map\n---\n\nText\n", &options());
+        assert!(doc.meta.is_empty());
+        assert_eq!(
+            doc.blocks,
+            (raw_block("yaml", "- not\n- a map") + para("Text")).into_vec()
+        );
+    }
+
+    #[test]
+    fn inlines() {
+        assert_eq!(
+            read("Go to http://x.org, [[Page|the page]] or <me@x.org>. ||Secret|| ~~old~~ `code`"),
+            para(
+                text("Go to ")
+                    + link_with(attr("", &["uri"], &[]), "http://x.org", "", "http://x.org")
+                    + text(", ")
+                    + link_with(attr("", &["wikilink"], &[]), "Page", "", text("the page"))
+                    + text(" or ")
+                    + link_with(attr("", &["email"], &[]), "mailto:me@x.org", "", "me@x.org")
+                    + text(". ")
+                    + span_with(attr("", &["spoiler"], &[]), "Secret")
+                    + space()
+                    + strikeout("old")
+                    + space()
+                    + code("code")
+            )
+            .into_vec()
+        );
+    }
+
+    #[test]
+    fn notes() {
+        assert_eq!(
+            read("A[^a] and[^missing].\n\n[^a]: Note[^a].\n"),
+            para(str("A") + note(para("Note[^a].")) + text(" and[^missing].")).into_vec()
+        );
+    }
+
+    #[test]
+    fn table() {
+        assert_eq!(
+            read("| a | b |\n|:--|--:|\n| 1 |   |\n"),
+            vec![Block::Table(
+                TableBuilder::new()
+                    .alignments([Alignment::AlignLeft, Alignment::AlignRight])
+                    .header([plain("a"), plain("b")])
+                    .body([[plain("1"), Blocks::default()]])
+                    .build()
+            )]
+        );
+    }
+
+    #[test]
+    fn sourcepos() {
+        let mut options = options();
+        options.render.sourcepos = true;
+        let pos = |pos| attr("", &[], &[("data-pos", pos)]);
+        assert_eq!(
+            read_with("# Hi\n\nSome `code` here\n\n> [!TIP]\n> Tip.\n", &options).blocks,
+            (header_with(pos("1:1-1:4"), 1, span_with(pos("1:3-1:4"), "Hi"))
+                + div_with(
+                    pos("3:1-3:16"),
+                    para(
+                        span_with(pos("3:1-3:5"), text("Some "))
+                            + code_with(pos("3:6-3:11"), "code")
+                            + span_with(pos("3:12-3:16"), text(" here"))
+                    )
+                )
+                + div_with(
+                    attr("", &["tip"], &[("data-pos", "5:1-6:6")]),
+                    div_with(attr("", &["title"], &[]), para("Tip"))
+                        + div_with(pos("6:3-6:6"), para(span_with(pos("6:3-6:6"), "Tip.")))
+                ))
+            .into_vec()
+        );
+    }
+}
@@ -0,0 +1,2134 @@
+//! A reader for [djot](https://djot.net), like `pandoc -f djot`.
+//!
+//! Djot elements map onto the AST the way Pandoc maps them:
+//!
+//! - headings at the top level start sections, which become divs with the
+//!   class `section` and the heading's identifier, generated from its text
+//!   unless one is given;
+//! - highlighted `{=text=}` and inserted `{+text+}` text become spans with
+//!   the classes `mark` and `inserted`, and deleted `{-text-}` text becomes
+//!   strikeout;
+//! - symbols such as `:smile:` become spans with the class `symbol` holding
+//!   the name, unless [`DjotOptions::symbols`] has a replacement for them;
+//! - task list items become bullet list items starting with `☐` or `☒`;
+//! - autolinks get the class `uri` or `email`;
+//! - attributes on elements that have none in the AST, such as paragraphs
+//!   or emphasis, wrap them in a div or span.
+//!
+//! ```
+//! use pandoc_types::builder::*;
+//! use pandoc_types::readers::djot::{read_djot, DjotOptions};
+//!
+//! let doc = read_djot("Some _emphasized_ [text]{.big}...\n", &DjotOptions::default());
+//! assert_eq!(
+//!     doc.blocks,
+//!     para(
+//!         text("Some ")
+//!             + emph("emphasized")
+//!             + space()
+//!             + span_with(
+//!                 pandoc_types::definition::Attr {
+//!                     classes: vec!["big".into()],
+//!                     ..Default::default()
+//!                 },
+//!                 "text"
+//!             )
+//!             + str("…")
+//!     )
+//!     .into_vec()
+//! );
+//! ```
+use std::collections::{HashMap, HashSet};
+
+use crate::definition::{
+    Alignment, Attr, Block, Caption, Cell, ColSpec, ColWidth, Format, IndexMap, Inline,
+    ListAttributes, ListNumberDelim, ListNumberStyle, MathType, Pandoc, QuoteType, Row, Stringify,
+    Table, TableBody, TableFoot, TableHead, Target,
+};
+
+/// Options for reading and writing djot.
+#[derive(Debug, Clone, PartialEq)]
+pub struct DjotOptions {
+    /// Whether straight quotes, `...` and runs of hyphens are read as
+    /// quotations, ellipses and dashes, as djot specifies. When this is
+    /// off, they are kept as written. The writer writes ellipses and dashes
+    /// as `...`, `--` and `---` when this is on.
+    pub smart: bool,
+    /// Text to replace symbols such as `:smile:` with, by name. Symbols
+    /// that aren't in the map are read as spans with the class `symbol`.
+    pub symbols: IndexMap<String, String>,
+}
+
+impl Default for DjotOptions {
+    fn default() -> Self {
+        Self {
+            smart: true,
+            symbols: IndexMap::new(),
+        }
+    }
+}
+
+/// Parses a djot document. Every input is a valid djot document, so this
+/// can't fail.
+pub fn read_djot(input: &str, options: &DjotOptions) -> Pandoc {
+    let lines: Vec<String> = input
+        .lines()
+        .map(|line| line.replace('\t', "    "))
+        .collect();
+    let mut parser = BlockParser::default();
+    let (nodes, _) = parser.blocks(&lines, None);
+    let mut converter = Converter {
+        options,
+        references: parser.references,
+        footnotes: parser.footnotes,
+        headings: HashMap::new(),
+    };
+    let blocks = converter.document(nodes);
+    Pandoc {
+        blocks,
+        meta: Default::default(),
+    }
+}
+
+/// A block parsed from the lines of the input. Its inline content is kept
+/// as text until all references and footnotes are known.
+#[derive(Debug, Clone)]
+struct Node {
+    attr: Attr,
+    kind: NodeKind,
+}
+
+#[derive(Debug, Clone)]
+enum NodeKind {
+    Para(String),
+    Heading(i32, String),
+    BlockQuote(Vec<Node>),
+    Code(Option<String>, String),
+    Raw(String, String),
+    ThematicBreak,
+    Div(Option<String>, Vec<Node>),
+    List(List),
+    Table(Vec<TableLine>, Option<String>),
+}
+
+#[derive(Debug, Clone)]
+struct List {
+    kind: ListKind,
+    start: i32,
+    tight: bool,
+    items: Vec<ListItem>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum ListKind {
+    Bullet(char),
+    Task(char),
+    Ordered(ListNumberStyle, ListNumberDelim),
+    Definition,
+}
+
+#[derive(Debug, Clone)]
+struct ListItem {
+    /// Whether the box of a task list item is checked.
+    checked: bool,
+    blocks: Vec<Node>,
+}
+
+#[derive(Debug, Clone)]
+enum TableLine {
+    Separator(Vec<Alignment>),
+    Row(Vec<String>),
+}
+
+/// The marker of a list item.
+struct Marker {
+    kind: ListKind,
+    number: i32,
+    checked: bool,
+    /// The indentation of the marker.
+    indent: usize,
+    /// The width of the marker and the space after it.
+    width: usize,
+}
+
+#[derive(Default)]
+struct BlockParser {
+    references: HashMap<String, (String, Attr)>,
+    footnotes: HashMap<String, Vec<Node>>,
+}
+
+impl BlockParser {
+    /// Parses lines into blocks, up to the closing fence of a div with the
+    /// given number of colons. Returns the blocks and the number of lines
+    /// read.
+    fn blocks(&mut self, lines: &[String], fence: Option<usize>) -> (Vec<Node>, usize) {
+        let mut nodes = Vec::new();
+        let mut attr = Attr::default();
+        let mut i = 0;
+        while i < lines.len() {
+            let line = lines[i].trim();
+            if line.is_empty() {
+                i += 1;
+                continue;
+            }
+            if fence.is_some_and(|fence| closes_div(line, fence)) {
+                return (nodes, i + 1);
+            }
+            if line.starts_with('{') {
+                if let Some((block_attr, n)) = block_attributes(&lines[i..]) {
+                    merge_attr(&mut attr, block_attr);
+                    i += n;
+                    continue;
+                }
+            }
+            if line.starts_with('[') {
+                if let Some(n) = self.definition(&lines[i..], &mut attr) {
+                    i += n;
+                    continue;
+                }
+            }
+            let (kind, n) = self.block(&lines[i..], fence);
+            nodes.push(Node {
+                attr: std::mem::take(&mut attr),
+                kind,
+            });
+            i += n;
+        }
+        (nodes, i)
+    }
+
+    /// Parses the block starting at the first line.
+    fn block(&mut self, lines: &[String], fence: Option<usize>) -> (NodeKind, usize) {
+        let line = lines[0].trim();
+        let indent = lines[0].len() - lines[0].trim_start().len();
+
+        let level = line.bytes().take_while(|&b| b == b'#').count();
+        if (1..=6).contains(&level) && matches!(line.as_bytes().get(level), None | Some(b' ')) {
+            let (text, n) = paragraph(lines, fence, |line| {
+                let hashes = line.bytes().take_while(|&b| b == b'#').count();
+                match hashes == level && matches!(line.as_bytes().get(level), None | Some(b' ')) {
+                    true => &line[level..],
+                    false => line,
+                }
+            });
+            return (NodeKind::Heading(level as i32, text), n);
+        }
+
+        if line == ">" || line.starts_with("> ") {
+            let mut quoted = Vec::new();
+            let mut i = 0;
+            while i < lines.len() {
+                let line = lines[i].trim_start();
+                if line.trim_end() == ">" || line.starts_with("> ") {
+                    quoted.push(line[1..].strip_prefix(' ').unwrap_or("").to_owned());
+                } else if !line.is_empty() && quoted.last().is_some_and(|l| !l.trim().is_empty()) {
+                    // A lazy continuation of a paragraph.
+                    quoted.push(line.to_owned());
+                } else {
+                    break;
+                }
+                i += 1;
+            }
+            let (nodes, _) = self.blocks(&quoted, None);
+            return (NodeKind::BlockQuote(nodes), i);
+        }
+
+        let ticks = line.bytes().take_while(|&b| b == b'`').count();
+        if ticks >= 3 && !line[ticks..].contains('`') {
+            let info = line[ticks..].trim();
+            let mut code = Vec::new();
+            let mut i = 1;
+            while i < lines.len() {
+                let content = lines[i].trim();
+                if content.len() >= ticks && content.bytes().all(|b| b == b'`') {
+                    i += 1;
+                    break;
+                }
+                let strip = lines[i]
+                    .bytes()
+                    .take(indent)
+                    .take_while(|&b| b == b' ')
+                    .count();
+                code.push(&lines[i][strip..]);
+                i += 1;
+            }
+            let code = code.join("\n");
+            let kind = match info.strip_prefix('=') {
+                Some(format) if !format.is_empty() => NodeKind::Raw(format.to_owned(), code),
+                _ => NodeKind::Code(info.split_whitespace().next().map(str::to_owned), code),
+            };
+            return (kind, i);
+        }
+
+        let colons = line.bytes().take_while(|&b| b == b':').count();
+        if colons >= 3 {
+            let class = line[colons..].trim();
+            if !class.contains(char::is_whitespace) {
+                let (nodes, n) = self.blocks(&lines[1..], Some(colons));
+                let class = Some(class.to_owned()).filter(|class| !class.is_empty());
+                return (NodeKind::Div(class, nodes), n + 1);
+            }
+        }
+
+        if is_thematic_break(line) {
+            return (NodeKind::ThematicBreak, 1);
+        }
+
+        if let Some(marker) = list_marker(&lines[0], None) {
+            let (list, n) = self.list(lines, marker);
+            return (NodeKind::List(list), n);
+        }
+
+        if line.starts_with('|') {
+            if let Some((table, n)) = table(lines) {
+                return (table, n);
+            }
+        }
+
+        let (text, n) = paragraph(lines, fence, |line| line);
+        (NodeKind::Para(text), n)
+    }
+
+    /// Parses the definition of a reference or footnote, returning the
+    /// number of lines read. The attributes before a reference apply to the
+    /// links using it.
+    fn definition(&mut self, lines: &[String], attr: &mut Attr) -> Option<usize> {
+        let line = lines[0].trim();
+        let end = line.find("]:")?;
+        let label = line.strip_prefix('[')?.get(..end - 1)?;
+        if label.is_empty() || label.contains(['[', ']']) {
+            return None;
+        }
+        let rest = line[end + 2..].trim();
+        if let Some(label) = label.strip_prefix('^') {
+            // The content of a footnote continues on indented lines.
+            let mut content = vec![rest.to_owned()];
+            let mut i = 1;
+            while i < lines.len() {
+                let line = &lines[i];
+                let blank = line.trim().is_empty();
+                let indented = line.starts_with(' ');
+                let lazy = !blank && content.last().is_some_and(|l| !l.trim().is_empty());
+                if !(blank || indented || lazy) {
+                    break;
+                }
+                content.push(line.clone());
+                i += 1;
+            }
+            while content.last().is_some_and(|l| l.trim().is_empty()) {
+                content.pop();
+                i -= 1;
+            }
+            *attr = Attr::default();
+            if content.is_empty() {
+                self.footnotes.insert(normalize_label(label), Vec::new());
+                return Some(1);
+            }
+            let strip = content[1..]
+                .iter()
+                .filter(|line| !line.trim().is_empty())
+                .map(|line| line.len() - line.trim_start().len())
+                .min()
+                .unwrap_or(0);
+            for line in &mut content[1..] {
+                let n = line.len().min(strip);
+                line.replace_range(..n, "");
+            }
+            let (nodes, _) = self.blocks(&content, None);
+            self.footnotes.insert(normalize_label(label), nodes);
+            return Some(i);
+        }
+        // The destination may continue on indented lines.
+        let mut url = rest.to_owned();
+        let mut i = 1;
+        while i < lines.len() && lines[i].starts_with(' ') && !lines[i].trim().is_empty() {
+            url.push_str(lines[i].trim());
+            i += 1;
+        }
+        self.references
+            .entry(normalize_label(label))
+            .or_insert((url, std::mem::take(attr)));
+        Some(i)
+    }
+
+    fn list(&mut self, lines: &[String], first: Marker) -> (List, usize) {
+        let mut list = List {
+            kind: first.kind.clone(),
+            start: first.number,
+            tight: true,
+            items: Vec::new(),
+        };
+        let mut marker = first;
+        let mut i = 0;
+        loop {
+            let mut content = vec![lines[i][marker.indent + marker.width..].to_owned()];
+            i += 1;
+            while i < lines.len() {
+                let line = &lines[i];
+                let blank = line.trim().is_empty();
+                let line_indent = line.len() - line.trim_start_matches(' ').len();
+                if blank {
+                    content.push(String::new());
+                } else if line_indent > marker.indent {
+                    let strip = line_indent.min(marker.indent + marker.width);
+                    content.push(line[strip..].to_owned());
+                } else if content.last().is_some_and(|l| !l.trim().is_empty())
+                    && list_marker(line, None).is_none()
+                {
+                    // A lazy continuation of a paragraph.
+                    content.push(line.trim_start().to_owned());
+                } else {
+                    break;
+                }
+                i += 1;
+            }
+            let mut blank_after = false;
+            while content.last().is_some_and(|l| l.trim().is_empty()) {
+                content.pop();
+                blank_after = true;
+            }
+            // A blank line between blocks of an item makes the list loose,
+            // unless it comes before a sublist.
+            // The blank line after the term of a definition doesn't count.
+            for (j, line) in content.iter().enumerate() {
+                let after_term = list.kind == ListKind::Definition
+                    && content[..j].iter().skip(1).all(|l| l.trim().is_empty());
+                if line.trim().is_empty() && !after_term {
+                    let next = content[j + 1..].iter().find(|l| !l.trim().is_empty());
+                    if next.is_some_and(|next| list_marker(next, None).is_none()) {
+                        list.tight = false;
+                    }
+                }
+            }
+            if list.kind == ListKind::Definition && !content.is_empty() {
+                // The term is the first line of the item, which can't be
+                // continued.
+                content.insert(1, String::new());
+            }
+            let (blocks, _) = self.blocks(&content, None);
+            list.items.push(ListItem {
+                checked: marker.checked,
+                blocks,
+            });
+
+            let Some(next) = lines
+                .get(i)
+                .and_then(|line| list_marker(line, Some(list.kind.clone())))
+            else {
+                // Keep trailing blank lines for the enclosing blocks.
+                break;
+            };
+            if blank_after {
+                list.tight = false;
+            }
+            marker = next;
+        }
+        (list, i)
+    }
+}
+
+/// Collects the lines of a paragraph or heading, up to a blank line or the
+/// closing fence of the enclosing div, with `strip` applied to each line.
+fn paragraph(
+    lines: &[String],
+    fence: Option<usize>,
+    strip: impl Fn(&str) -> &str,
+) -> (String, usize) {
+    let mut text = vec![strip(lines[0].trim()).trim()];
+    let mut i = 1;
+    while i < lines.len() {
+        let line = lines[i].trim();
+        if line.is_empty() || fence.is_some_and(|fence| closes_div(line, fence)) {
+            break;
+        }
+        text.push(strip(line).trim());
+        i += 1;
+    }
+    (text.join("\n"), i)
+}
+
+fn closes_div(line: &str, fence: usize) -> bool {
+    line.len() >= fence && line.bytes().all(|b| b == b':')
+}
+
+fn is_thematic_break(line: &str) -> bool {
+    let marks = line.chars().filter(|c| matches!(c, '*' | '-')).count();
+    marks >= 3 && line.chars().all(|c| matches!(c, '*' | '-' | ' '))
+}
+
+/// Parses a list marker at the start of a line. If `expected` is given,
+/// only markers continuing a list of that kind are recognized, which also
+/// decides whether letters such as `i` are roman numerals.
+fn list_marker(line: &str, expected: Option<ListKind>) -> Option<Marker> {
+    let text = line.trim_start();
+    let indent = line.len() - text.len();
+    let (marker, rest) = text.split_once(' ').unwrap_or((text, ""));
+    if marker.is_empty() {
+        return None;
+    }
+    let mut width = (marker.len() + 1).min(text.len());
+    let mut checked = false;
+    let (kind, number) = match marker {
+        "-" | "+" | "*" => {
+            let bullet = marker.chars().next().unwrap();
+            match rest.get(..3) {
+                Some("[ ]" | "[x]" | "[X]")
+                    if matches!(rest.as_bytes().get(3), None | Some(b' ')) =>
+                {
+                    checked = rest.as_bytes()[1] != b' ';
+                    width = (width + 4).min(text.len());
+                    (ListKind::Task(bullet), 1)
+                }
+                _ => (ListKind::Bullet(bullet), 1),
+            }
+        }
+        ":" => (ListKind::Definition, 1),
+        _ => {
+            let (body, delim) = if let Some(body) = marker.strip_prefix('(') {
+                (body.strip_suffix(')')?, ListNumberDelim::TwoParens)
+            } else if let Some(body) = marker.strip_suffix(')') {
+                (body, ListNumberDelim::OneParen)
+            } else {
+                (marker.strip_suffix('.')?, ListNumberDelim::Period)
+            };
+            let style = match &expected {
+                Some(ListKind::Ordered(style, _)) => Some(style.clone()),
+                _ => None,
+            };
+            let (style, number) = ordinal(body, style)?;
+            (ListKind::Ordered(style, delim), number)
+        }
+    };
+    if expected.is_some_and(|expected| expected != kind) {
+        return None;
+    }
+    Some(Marker {
+        kind,
+        number,
+        checked,
+        indent,
+        width,
+    })
+}
+
+/// Parses the number of an ordered list item in the given style, or in the
+/// style it looks like.
+fn ordinal(body: &str, style: Option<ListNumberStyle>) -> Option<(ListNumberStyle, i32)> {
+    use ListNumberStyle::*;
+    let style = match style {
+        Some(style) => style,
+        None if body.bytes().all(|b| b.is_ascii_digit()) => Decimal,
+        None if body == "i" => LowerRoman,
+        None if body == "I" => UpperRoman,
+        None if body.len() == 1 && body.bytes().all(|b| b.is_ascii_lowercase()) => LowerAlpha,
+        None if body.len() == 1 && body.bytes().all(|b| b.is_ascii_uppercase()) => UpperAlpha,
+        None if body.bytes().all(|b| b"ivxlcdm".contains(&b)) => LowerRoman,
+        None if body.bytes().all(|b| b"IVXLCDM".contains(&b)) => UpperRoman,
+        None => return None,
+    };
+    let number = match style {
+        _ if body.is_empty() => return None,
+        Decimal if body.len() <= 9 && body.bytes().all(|b| b.is_ascii_digit()) => {
+            body.parse().ok()?
+        }
+        LowerAlpha if body.len() == 1 && body.bytes().all(|b| b.is_ascii_lowercase()) => {
+            (body.as_bytes()[0] - b'a') as i32 + 1
+        }
+        UpperAlpha if body.len() == 1 && body.bytes().all(|b| b.is_ascii_uppercase()) => {
+            (body.as_bytes()[0] - b'A') as i32 + 1
+        }
+        LowerRoman if body.bytes().all(|b| b"ivxlcdm".contains(&b)) => roman_value(body)?,
+        UpperRoman if body.bytes().all(|b| b"IVXLCDM".contains(&b)) => roman_value(body)?,
+        _ => return None,
+    };
+    Some((style, number))
+}
+
+fn roman_value(numeral: &str) -> Option<i32> {
+    let values: Vec<i32> = numeral
+        .chars()
+        .map(|c| match c.to_ascii_lowercase() {
+            'i' => 1,
+            'v' => 5,
+            'x' => 10,
+            'l' => 50,
+            'c' => 100,
+            'd' => 500,
+            _ => 1000,
+        })
+        .collect();
+    let mut total = 0i32;
+    for (i, value) in values.iter().enumerate() {
+        match values.get(i + 1) {
+            Some(next) if next > value => total -= value,
+            _ => total = total.checked_add(*value)?,
+        }
+    }
+    Some(total)
+}
+
+/// Parses the lines of a pipe table and its caption, if the first line is
+/// a table row.
+fn table(lines: &[String]) -> Option<(NodeKind, usize)> {
+    let mut rows = Vec::new();
+    let mut i = 0;
+    while let Some(row) = lines.get(i).and_then(|line| table_line(line.trim())) {
+        rows.push(row);
+        i += 1;
+    }
+    if rows.is_empty() {
+        return None;
+    }
+    let mut caption = None;
+    let j = i + lines[i..]
+        .iter()
+        .take_while(|l| l.trim().is_empty())
+        .count();
+    if let Some(line) = lines.get(j).map(|line| line.trim()) {
+        if line == "^" || line.starts_with("^ ") {
+            let (text, n) = paragraph(&lines[j..], None, |line| line);
+            caption = Some(text[1..].trim_start().to_owned());
+            i = j + n;
+        }
+    }
+    Some((NodeKind::Table(rows, caption), i))
+}
+
+fn table_line(line: &str) -> Option<TableLine> {
+    let inner = line.strip_prefix('|')?.strip_suffix('|')?;
+    if inner.ends_with('\\') {
+        return None;
+    }
+    let cells = split_cells(inner);
+    let alignments: Option<Vec<Alignment>> = cells
+        .iter()
+        .map(|cell| {
+            let cell = cell.trim();
+            let dashes = cell.trim_start_matches(':').trim_end_matches(':');
+            if dashes.is_empty() || !dashes.bytes().all(|b| b == b'-') {
+                return None;
+            }
+            Some(match (cell.starts_with(':'), cell.ends_with(':')) {
+                (true, true) => Alignment::AlignCenter,
+                (true, false) => Alignment::AlignLeft,
+                (false, true) => Alignment::AlignRight,
+                (false, false) => Alignment::AlignDefault,
+            })
+        })
+        .collect();
+    Some(match alignments {
+        Some(alignments) => TableLine::Separator(alignments),
+        None => TableLine::Row(cells.iter().map(|cell| cell.trim().to_owned()).collect()),
+    })
+}
+
+/// Splits a table row at the pipes that aren't escaped or inside verbatim
+/// text.
+fn split_cells(row: &str) -> Vec<String> {
+    let chars: Vec<char> = row.chars().collect();
+    let mut cells = vec![String::new()];
+    let mut i = 0;
+    while i < chars.len() {
+        match chars[i] {
+            '\\' if i + 1 < chars.len() => {
+                cells.last_mut().unwrap().extend(&chars[i..i + 2]);
+                i += 2;
+            }
+            '`' => {
+                let n = chars[i..].iter().take_while(|&&c| c == '`').count();
+                let mut end = i + n;
+                while end < chars.len() {
+                    let m = chars[end..].iter().take_while(|&&c| c == '`').count();
+                    match m {
+                        0 => end += 1,
+                        m if m == n => {
+                            end += m;
+                            break;
+                        }
+                        m => end += m,
+                    }
+                }
+                cells.last_mut().unwrap().extend(&chars[i..end]);
+                i = end;
+            }
+            '|' => {
+                cells.push(String::new());
+                i += 1;
+            }
+            c => {
+                cells.last_mut().unwrap().push(c);
+                i += 1;
+            }
+        }
+    }
+    cells
+}
+
+/// Parses attributes on lines of their own, which may span several lines,
+/// returning them and the number of lines they take up.
+fn block_attributes(lines: &[String]) -> Option<(Attr, usize)> {
+    let mut text = String::new();
+    for (i, line) in lines.iter().enumerate() {
+        let line = line.trim();
+        if line.is_empty() {
+            return None;
+        }
+        if i > 0 {
+            text.push('\n');
+        }
+        text.push_str(line);
+        let chars: Vec<char> = text.chars().collect();
+        match parse_attributes(&chars, 0) {
+            Some((attr, end)) if end == chars.len() => return Some((attr, i + 1)),
+            Some(_) => return None,
+            None if line.ends_with('}') && !line.contains('"') => return None,
+            None => {}
+        }
+    }
+    None
+}
+
+fn is_name_char(c: char) -> bool {
+    c.is_alphanumeric() || matches!(c, '_' | '-' | ':')
+}
+
+/// Parses attributes such as `{#id .class key="value"}` starting at
+/// `start`, returning them and the position after them.
+fn parse_attributes(chars: &[char], start: usize) -> Option<(Attr, usize)> {
+    if chars.get(start) != Some(&'{') {
+        return None;
+    }
+    let name = |i: &mut usize| {
+        let begin = *i;
+        while chars.get(*i).is_some_and(|&c| is_name_char(c)) {
+            *i += 1;
+        }
+        Some(chars[begin..*i].iter().collect::<String>()).filter(|name| !name.is_empty())
+    };
+    let mut attr = Attr::default();
+    let mut i = start + 1;
+    loop {
+        while chars.get(i).is_some_and(|c| c.is_whitespace()) {
+            i += 1;
+        }
+        match *chars.get(i)? {
+            '}' => return Some((attr, i + 1)),
+            '#' => {
+                i += 1;
+                attr.identifier = name(&mut i)?;
+            }
+            '.' => {
+                i += 1;
+                attr.classes.push(name(&mut i)?);
+            }
+            '%' => {
+                i += 1 + chars[i + 1..].iter().position(|&c| c == '%')? + 1;
+            }
+            c if is_name_char(c) => {
+                let key = name(&mut i)?;
+                if chars.get(i) != Some(&'=') {
+                    return None;
+                }
+                i += 1;
+                let value = if chars.get(i) == Some(&'"') {
+                    let mut value = String::new();
+                    i += 1;
+                    loop {
+                        match *chars.get(i)? {
+                            '"' => break,
+                            '\\' => {
+                                value.push(*chars.get(i + 1)?);
+                                i += 1;
+                            }
+                            '\n' => value.push(' '),
+                            c => value.push(c),
+                        }
+                        i += 1;
+                    }
+                    i += 1;
+                    value
+                } else {
+                    name(&mut i)?
+                };
+                if key == "class" {
+                    attr.classes
+                        .extend(value.split_whitespace().map(str::to_owned));
+                } else {
+                    attr.attributes.push((key, value));
+                }
+            }
+            _ => return None,
+        }
+    }
+}
+
+fn merge_attr(attr: &mut Attr, other: Attr) {
+    if !other.identifier.is_empty() {
+        attr.identifier = other.identifier;
+    }
+    attr.classes.extend(other.classes);
+    attr.attributes.extend(other.attributes);
+}
+
+fn normalize_label(label: &str) -> String {
+    label.split_whitespace().collect::<Vec<_>>().join(" ")
+}
+
+/// Generates the identifier of a heading from its text, by dropping
+/// punctuation and joining the words with hyphens.
+pub(crate) fn auto_identifier(text: &str) -> String {
+    let id = text
+        .split(|c: char| c.is_whitespace() || c.is_ascii_punctuation() && !matches!(c, '_' | '-'))
+        .filter(|word| !word.is_empty())
+        .collect::<Vec<_>>()
+        .join("-");
+    if id.is_empty() {
+        "section".to_owned()
+    } else {
+        id
+    }
+}
+
+/// Turns the blocks parsed from the input into the AST, parsing their
+/// inline content.
+struct Converter<'a> {
+    options: &'a DjotOptions,
+    /// The destinations and attributes of references, by label.
+    references: HashMap<String, (String, Attr)>,
+    footnotes: HashMap<String, Vec<Node>>,
+    /// Links to the sections, by the text of their headings.
+    headings: HashMap<String, String>,
+}
+
+impl Converter<'_> {
+    /// Converts the top level blocks, where headings start sections. The
+    /// inline content of headings is parsed before the rest, so that links
+    /// can refer to headings further down.
+    fn document(&mut self, nodes: Vec<Node>) -> Vec<Block> {
+        let mut used: HashSet<String> = nodes
+            .iter()
+            .filter(|node| matches!(node.kind, NodeKind::Heading(..)))
+            .map(|node| node.attr.identifier.clone())
+            .filter(|id| !id.is_empty())
+            .collect();
+        let mut headings = Vec::new();
+        for node in &nodes {
+            let NodeKind::Heading(_, text) = &node.kind else {
+                headings.push(None);
+                continue;
+            };
+            let inlines = self.inlines(text);
+            let text = inlines.stringify();
+            let id = match node.attr.identifier.as_str() {
+                "" => {
+                    let base = auto_identifier(&text);
+                    let mut id = base.clone();
+                    let mut n = 0;
+                    while used.contains(&id) {
+                        n += 1;
+                        id = format!("{}-{}", base, n);
+                    }
+                    used.insert(id.clone());
+                    id
+                }
+                id => id.to_owned(),
+            };
+            self.headings
+                .entry(normalize_label(&text))
+                .or_insert_with(|| format!("#{}", id));
+            headings.push(Some((id, inlines)));
+        }
+
+        let mut items = Vec::new();
+        for (Node { mut attr, kind }, heading) in nodes.into_iter().zip(headings) {
+            match (kind, heading) {
+                (NodeKind::Heading(level, _), Some((id, inlines))) => {
+                    attr.identifier.clear();
+                    items.push((Some((level, id)), Block::Header(level, attr, inlines)));
+                }
+                (kind, _) => items.push((None, self.block(Node { attr, kind }))),
+            }
+        }
+        sections(&mut items.into_iter().peekable(), 0)
+    }
+
+    fn blocks(&mut self, nodes: Vec<Node>) -> Vec<Block> {
+        nodes.into_iter().map(|node| self.block(node)).collect()
+    }
+
+    fn block(&mut self, node: Node) -> Block {
+        let classes = |class: Option<String>| Attr {
+            classes: class.into_iter().collect(),
+            ..Attr::default()
+        };
+        let block = match node.kind {
+            NodeKind::Para(text) => Block::Para(self.inlines(&text)),
+            NodeKind::Heading(level, text) => {
+                Block::Header(level, Attr::default(), self.inlines(&text))
+            }
+            NodeKind::BlockQuote(nodes) => Block::BlockQuote(self.blocks(nodes)),
+            NodeKind::Code(lang, code) => Block::CodeBlock(classes(lang), code),
+            NodeKind::Raw(format, code) => Block::RawBlock(Format(format), code),
+            NodeKind::ThematicBreak => Block::HorizontalRule,
+            NodeKind::Div(class, nodes) => Block::Div(classes(class), self.blocks(nodes)),
+            NodeKind::List(list) => self.list(list),
+            NodeKind::Table(lines, caption) => self.table(lines, caption),
+        };
+        with_attr(block, node.attr)
+    }
+
+    fn list(&mut self, list: List) -> Block {
+        let mut items: Vec<Vec<Block>> = Vec::new();
+        for item in list.items {
+            let mut blocks = self.blocks(item.blocks);
+            if list.tight {
+                for block in &mut blocks {
+                    if let Block::Para(inlines) = block {
+                        *block = Block::Plain(std::mem::take(inlines));
+                    }
+                }
+            }
+            if let ListKind::Task(_) = list.kind {
+                let checkbox = Inline::Str(if item.checked { "☒" } else { "☐" }.into());
+                match blocks.first_mut() {
+                    Some(Block::Plain(inlines) | Block::Para(inlines)) => {
+                        inlines.splice(0..0, [checkbox, Inline::Space]);
+                    }
+                    _ => blocks.insert(0, Block::Plain(vec![checkbox])),
+                }
+            }
+            items.push(blocks);
+        }
+        match list.kind {
+            ListKind::Bullet(_) | ListKind::Task(_) => Block::BulletList(items),
+            ListKind::Ordered(style, delim) => Block::OrderedList(
+                ListAttributes {
+                    start_number: list.start,
+                    style,
+                    delim,
+                },
+                items,
+            ),
+            ListKind::Definition => Block::DefinitionList(
+                items
+                    .into_iter()
+                    .map(|mut blocks| {
+                        let term = match blocks.first_mut() {
+                            Some(Block::Plain(inlines) | Block::Para(inlines)) => {
+                                let term = std::mem::take(inlines);
+                                blocks.remove(0);
+                                term
+                            }
+                            _ => Vec::new(),
+                        };
+                        let definitions = match blocks.is_empty() {
+                            true => Vec::new(),
+                            false => vec![blocks],
+                        };
+                        (term, definitions)
+                    })
+                    .collect(),
+            ),
+        }
+    }
+
+    fn table(&mut self, lines: Vec<TableLine>, caption: Option<String>) -> Block {
+        // The rows above the first separator line are the header.
+        let mut alignments = None;
+        let mut head = Vec::new();
+        let mut rows = Vec::new();
+        for line in lines {
+            match line {
+                TableLine::Separator(aligns) if alignments.is_none() => {
+                    alignments = Some(aligns);
+                    head = std::mem::take(&mut rows);
+                }
+                TableLine::Separator(_) => {}
+                TableLine::Row(cells) => rows.push(cells),
+            }
+        }
+        let alignments = alignments.unwrap_or_default();
+        let columns = head
+            .iter()
+            .chain(&rows)
+            .map(Vec::len)
+            .chain([alignments.len()])
+            .max()
+            .unwrap_or(0);
+        let mut row = |cells: Vec<String>| Row {
+            attr: Attr::default(),
+            cells: (0..columns)
+                .map(|i| {
+                    let inlines = cells
+                        .get(i)
+                        .map(|text| self.inlines(text))
+                        .unwrap_or_default();
+                    Cell {
+                        content: match inlines.is_empty() {
+                            true => Vec::new(),
+                            false => vec![Block::Plain(inlines)],
+                        },
+                        ..Cell::default()
+                    }
+                })
+                .collect(),
+        };
+        let head: Vec<Row> = head.into_iter().map(&mut row).collect();
+        let body: Vec<Row> = rows.into_iter().map(&mut row).collect();
+        let long = match caption {
+            Some(text) => vec![Block::Plain(self.inlines(&text))],
+            None => Vec::new(),
+        };
+        Block::Table(Table {
+            attr: Attr::default(),
+            caption: Caption { short: None, long },
+            colspecs: (0..columns)
+                .map(|i| {
+                    let align = alignments.get(i).cloned();
+                    ColSpec(
+                        align.unwrap_or(Alignment::AlignDefault),
+                        ColWidth::ColWidthDefault,
+                    )
+                })
+                .collect(),
+            head: TableHead {
+                attr: Attr::default(),
+                rows: head,
+            },
+            bodies: match body.is_empty() {
+                true => Vec::new(),
+                false => vec![TableBody {
+                    attr: Attr::default(),
+                    row_head_columns: 0,
+                    head: Vec::new(),
+                    body,
+                }],
+            },
+            foot: TableFoot::default(),
+        })
+    }
+
+    fn inlines(&mut self, text: &str) -> Vec<Inline> {
+        InlineParser {
+            chars: text.chars().collect(),
+            pos: 0,
+            pieces: Vec::new(),
+            openers: Vec::new(),
+            word: String::new(),
+            converter: self,
+        }
+        .parse()
+    }
+
+    fn note(&mut self, label: &str) -> Inline {
+        let label = normalize_label(label);
+        // The note is taken out while it is converted, so that a note
+        // referring to itself doesn't recurse forever.
+        match self.footnotes.remove(&label) {
+            Some(nodes) => {
+                let blocks = self.blocks(nodes.clone());
+                self.footnotes.insert(label, nodes);
+                Inline::Note(blocks)
+            }
+            None => Inline::Note(Vec::new()),
+        }
+    }
+
+    /// Looks up the destination and attributes of a reference link.
+    fn reference(&self, label: &str) -> (String, Attr) {
+        let label = normalize_label(label);
+        if let Some(reference) = self.references.get(&label) {
+            return reference.clone();
+        }
+        match self.headings.get(&label) {
+            Some(url) => (url.clone(), Attr::default()),
+            None => (String::new(), Attr::default()),
+        }
+    }
+}
+
+/// Nests the blocks following each heading deeper than `level` into its
+/// section.
+fn sections(
+    items: &mut std::iter::Peekable<impl Iterator<Item = (Option<(i32, String)>, Block)>>,
+    level: i32,
+) -> Vec<Block> {
+    let mut blocks = Vec::new();
+    while let Some((heading, _)) = items.peek() {
+        if heading.as_ref().is_some_and(|(l, _)| *l <= level) {
+            break;
+        }
+        let (heading, block) = items.next().unwrap();
+        match heading {
+            Some((level, identifier)) => {
+                let mut content = vec![block];
+                content.extend(sections(items, level));
+                let attr = Attr {
+                    identifier,
+                    classes: vec!["section".into()],
+                    attributes: Vec::new(),
+                };
+                blocks.push(Block::Div(attr, content));
+            }
+            None => blocks.push(block),
+        }
+    }
+    blocks
+}
+
+/// Adds block attributes to a block, wrapping it in a div if it can't have
+/// attributes.
+fn with_attr(block: Block, attr: Attr) -> Block {
+    if attr == Attr::default() {
+        return block;
+    }
+    match block {
+        Block::Header(level, mut a, inlines) => {
+            merge_attr(&mut a, attr);
+            Block::Header(level, a, inlines)
+        }
+        Block::CodeBlock(mut a, code) => {
+            merge_attr(&mut a, attr);
+            Block::CodeBlock(a, code)
+        }
+        Block::Div(mut a, blocks) => {
+            merge_attr(&mut a, attr);
+            Block::Div(a, blocks)
+        }
+        Block::Table(mut table) => {
+            merge_attr(&mut table.attr, attr);
+            Block::Table(table)
+        }
+        block => Block::Div(attr, vec![block]),
+    }
+}
+
+/// Adds inline attributes to an inline, wrapping it in a span if it can't
+/// have attributes. A `title` attribute of a link or image becomes its
+/// title.
+fn add_attr(inline: Inline, attr: Attr) -> Inline {
+    fn title(attr: &mut Attr, target: &mut Target) {
+        if let Some(i) = attr.attributes.iter().position(|(key, _)| key == "title") {
+            target.title = attr.attributes.remove(i).1;
+        }
+    }
+    match inline {
+        Inline::Code(mut a, code) => {
+            merge_attr(&mut a, attr);
+            Inline::Code(a, code)
+        }
+        Inline::Link(mut a, inlines, mut target) => {
+            merge_attr(&mut a, attr);
+            title(&mut a, &mut target);
+            Inline::Link(a, inlines, target)
+        }
+        Inline::Image(mut a, inlines, mut target) => {
+            merge_attr(&mut a, attr);
+            title(&mut a, &mut target);
+            Inline::Image(a, inlines, target)
+        }
+        Inline::Span(mut a, inlines) => {
+            merge_attr(&mut a, attr);
+            Inline::Span(a, inlines)
+        }
+        inline => Inline::Span(attr, vec![inline]),
+    }
+}
+
+/// The kinds of delimiters that open and close inline containers.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Delim {
+    Emph,
+    Strong,
+    Superscript,
+    Subscript,
+    Mark,
+    Insert,
+    Delete,
+    DoubleQuote,
+    SingleQuote,
+    Bracket,
+    Image,
+}
+
+enum Piece {
+    Inline(Inline),
+    /// A delimiter that may be closed later, which is kept as text if it
+    /// isn't. `start` is the position after it.
+    Opener {
+        delim: Delim,
+        text: String,
+        start: usize,
+    },
+}
+
+/// Parses inline content. Openers are kept in the output until a matching
+/// closer turns everything after them into a container; openers between
+/// the two are left as text, as djot specifies.
+struct InlineParser<'c, 'a> {
+    converter: &'c mut Converter<'a>,
+    chars: Vec<char>,
+    pos: usize,
+    pieces: Vec<Piece>,
+    /// The positions in `pieces` of the openers that may still be closed.
+    openers: Vec<usize>,
+    /// The text of the current word.
+    word: String,
+}
+
+impl InlineParser<'_, '_> {
+    fn parse(mut self) -> Vec<Inline> {
+        while let Some(&c) = self.chars.get(self.pos) {
+            match c {
+                '\\' => self.escape(),
+                '`' => self.verbatim(None),
+                '$' => self.math(),
+                '_' => self.delimiter(Delim::Emph),
+                '*' => self.delimiter(Delim::Strong),
+                '^' => self.delimiter(Delim::Superscript),
+                '~' => self.delimiter(Delim::Subscript),
+                '{' => self.brace(),
+                '=' | '+' if self.peek(1) == Some('}') => {
+                    let delim = if c == '=' { Delim::Mark } else { Delim::Insert };
+                    self.forced_close(delim);
+                }
+                '-' => self.hyphens(),
+                '"' | '\'' if self.converter.options.smart => self.quote(c),
+                '.' if self.converter.options.smart
+                    && self.peek(1) == Some('.')
+                    && self.peek(2) == Some('.') =>
+                {
+                    self.word.push('…');
+                    self.pos += 3;
+                }
+                '[' if self.peek(1) == Some('^') && self.footnote() => {}
+                '[' => self.open(Delim::Bracket, "[", 1),
+                '!' if self.peek(1) == Some('[') => self.open(Delim::Image, "![", 2),
+                ']' => self.close_bracket(),
+                '<' if self.autolink() => {}
+                ':' if self.symbol() => {}
+                '\n' => {
+                    self.break_line(Inline::SoftBreak);
+                    self.pos += 1;
+                }
+                c if c.is_whitespace() => {
+                    self.flush();
+                    if !matches!(
+                        self.pieces.last(),
+                        None | Some(Piece::Inline(
+                            Inline::Space | Inline::SoftBreak | Inline::LineBreak
+                        ))
+                    ) {
+                        self.pieces.push(Piece::Inline(Inline::Space));
+                    }
+                    self.pos += 1;
+                }
+                c => {
+                    self.word.push(c);
+                    self.pos += 1;
+                }
+            }
+        }
+        self.flush();
+        let mut inlines = finish(self.pieces);
+        while matches!(inlines.last(), Some(Inline::Space | Inline::SoftBreak)) {
+            inlines.pop();
+        }
+        inlines
+    }
+
+    fn peek(&self, offset: usize) -> Option<char> {
+        self.chars.get(self.pos + offset).copied()
+    }
+
+    fn previous(&self) -> Option<char> {
+        self.pos.checked_sub(1).map(|i| self.chars[i])
+    }
+
+    fn flush(&mut self) {
+        if !self.word.is_empty() {
+            let word = std::mem::take(&mut self.word);
+            self.pieces.push(Piece::Inline(Inline::Str(word)));
+        }
+    }
+
+    fn push(&mut self, inline: Inline) {
+        self.flush();
+        self.pieces.push(Piece::Inline(inline));
+    }
+
+    fn break_line(&mut self, inline: Inline) {
+        self.flush();
+        if matches!(self.pieces.last(), Some(Piece::Inline(Inline::Space))) {
+            self.pieces.pop();
+        }
+        self.pieces.push(Piece::Inline(inline));
+    }
+
+    fn escape(&mut self) {
+        match self.peek(1) {
+            Some('\n') => {
+                self.break_line(Inline::LineBreak);
+                self.pos += 2;
+            }
+            Some(' ') => {
+                self.word.push('\u{a0}');
+                self.pos += 2;
+            }
+            Some(c) if c.is_ascii_punctuation() => {
+                self.word.push(c);
+                self.pos += 2;
+            }
+            _ => {
+                self.word.push('\\');
+                self.pos += 1;
+            }
+        }
+    }
+
+    /// Parses verbatim text, which is math if `math` is given.
+    fn verbatim(&mut self, math: Option<MathType>) {
+        let ticks = self.chars[self.pos..]
+            .iter()
+            .take_while(|&&c| c == '`')
+            .count();
+        let start = self.pos + ticks;
+        let mut end = start;
+        let close = loop {
+            if end >= self.chars.len() {
+                break None;
+            }
+            let run = self.chars[end..].iter().take_while(|&&c| c == '`').count();
+            match run {
+                0 => end += 1,
+                run if run == ticks => break Some(end),
+                run => end += run,
+            }
+        };
+        let (end, next) = match close {
+            Some(end) => (end, end + ticks),
+            None => (self.chars.len(), self.chars.len()),
+        };
+        let mut content: String = self.chars[start..end].iter().collect();
+        // A space separating backticks in the content from the delimiters
+        // is dropped.
+        if content.starts_with(" `") {
+            content.remove(0);
+        }
+        if content.ends_with("` ") {
+            content.pop();
+        }
+        self.pos = next;
+        let inline = match math {
+            Some(math_type) => Inline::Math(math_type, content),
+            None => match self.raw_format() {
+                Some(format) => Inline::RawInline(Format(format), content),
+                None => Inline::Code(Attr::default(), content),
+            },
+        };
+        self.push(inline);
+    }
+
+    /// Parses the `{=format}` marking verbatim text as raw content.
+    fn raw_format(&mut self) -> Option<String> {
+        if self.peek(0) != Some('{') || self.peek(1) != Some('=') {
+            return None;
+        }
+        let rest = &self.chars[self.pos + 2..];
+        let len = rest.iter().position(|&c| c == '}')?;
+        let format: String = rest[..len].iter().collect();
+        if format.is_empty() || format.contains(char::is_whitespace) {
+            return None;
+        }
+        self.pos += len + 3;
+        Some(format)
+    }
+
+    fn math(&mut self) {
+        let dollars = if self.peek(1) == Some('$') { 2 } else { 1 };
+        if self.peek(dollars) == Some('`') {
+            self.pos += dollars;
+            let math_type = match dollars {
+                2 => MathType::DisplayMath,
+                _ => MathType::InlineMath,
+            };
+            self.verbatim(Some(math_type));
+        } else {
+            self.word.push('$');
+            self.pos += 1;
+        }
+    }
+
+    fn open(&mut self, delim: Delim, text: &str, len: usize) {
+        self.flush();
+        self.pos += len;
+        self.openers.push(self.pieces.len());
+        self.pieces.push(Piece::Opener {
+            delim,
+            text: text.to_owned(),
+            start: self.pos,
+        });
+    }
+
+    /// Returns the index in `openers` of the last opener of the given kind.
+    fn find_opener(&self, matches: impl Fn(Delim) -> bool) -> Option<usize> {
+        self.openers.iter().rposition(|&i| match &self.pieces[i] {
+            Piece::Opener { delim, .. } => matches(*delim),
+            Piece::Inline(_) => false,
+        })
+    }
+
+    /// Closes the last opener of the given kind, if there is one and the
+    /// container wouldn't be empty, returning the content after it.
+    fn take_content(&mut self, k: usize) -> Vec<Inline> {
+        self.flush();
+        let index = self.openers[k];
+        self.openers.truncate(k);
+        let content = self.pieces.split_off(index + 1);
+        self.pieces.pop();
+        finish(content)
+    }
+
+    fn close(&mut self, delim: Delim) -> bool {
+        let Some(k) = self.find_opener(|d| d == delim) else {
+            return false;
+        };
+        if self.openers[k] + 1 == self.pieces.len() && self.word.is_empty() {
+            return false;
+        }
+        let content = self.take_content(k);
+        let span = |class: &str, content| {
+            let attr = Attr {
+                classes: vec![class.to_owned()],
+                ..Attr::default()
+            };
+            Inline::Span(attr, content)
+        };
+        let inline = match delim {
+            Delim::Emph => Inline::Emph(content),
+            Delim::Strong => Inline::Strong(content),
+            Delim::Superscript => Inline::Superscript(content),
+            Delim::Subscript => Inline::Subscript(content),
+            Delim::Mark => span("mark", content),
+            Delim::Insert => span("inserted", content),
+            Delim::Delete => Inline::Strikeout(content),
+            Delim::DoubleQuote => Inline::Quoted(QuoteType::DoubleQuote, content),
+            Delim::SingleQuote => Inline::Quoted(QuoteType::SingleQuote, content),
+            Delim::Bracket | Delim::Image => unreachable!("brackets are closed by links"),
+        };
+        self.pieces.push(Piece::Inline(inline));
+        true
+    }
+
+    /// Handles a delimiter that opens or closes depending on whether it
+    /// follows or precedes whitespace, such as `_`.
+    fn delimiter(&mut self, delim: Delim) {
+        let c = self.chars[self.pos];
+        let forced = self.peek(1) == Some('}');
+        let can_close = forced || self.previous().is_some_and(|c| !c.is_whitespace());
+        let can_open = !forced && self.peek(1).is_some_and(|c| !c.is_whitespace());
+        if can_close && self.close(delim) {
+            self.pos += if forced { 2 } else { 1 };
+        } else if can_open {
+            self.open(delim, &c.to_string(), 1);
+        } else {
+            self.word.push(c);
+            self.pos += 1;
+        }
+    }
+
+    fn forced_close(&mut self, delim: Delim) {
+        if self.close(delim) {
+            self.pos += 2;
+        } else {
+            self.word.push(self.chars[self.pos]);
+            self.pos += 1;
+        }
+    }
+
+    fn brace(&mut self) {
+        if let Some((attr, end)) = parse_attributes(&self.chars, self.pos) {
+            self.pos = end;
+            self.attach(attr);
+            return;
+        }
+        let smart = self.converter.options.smart;
+        let delim = match self.peek(1) {
+            Some('_') => Delim::Emph,
+            Some('*') => Delim::Strong,
+            Some('^') => Delim::Superscript,
+            Some('~') => Delim::Subscript,
+            Some('=') => Delim::Mark,
+            Some('+') => Delim::Insert,
+            Some('-') => Delim::Delete,
+            Some('"') if smart => Delim::DoubleQuote,
+            Some('\'') if smart => Delim::SingleQuote,
+            _ => {
+                self.word.push('{');
+                self.pos += 1;
+                return;
+            }
+        };
+        let text: String = self.chars[self.pos..self.pos + 2].iter().collect();
+        self.open(delim, &text, 2);
+    }
+
+    /// Attaches attributes to the element before them, or to the word
+    /// before them if that is text.
+    fn attach(&mut self, attr: Attr) {
+        self.flush();
+        if attr == Attr::default() {
+            return;
+        }
+        let words = self
+            .pieces
+            .iter()
+            .rev()
+            .take_while(|piece| matches!(piece, Piece::Inline(Inline::Str(_))))
+            .count();
+        if words > 0 {
+            let start = self.pieces.len() - words;
+            let word = finish(self.pieces.split_off(start));
+            self.pieces.push(Piece::Inline(Inline::Span(attr, word)));
+            return;
+        }
+        match self.pieces.pop() {
+            Some(Piece::Inline(inline))
+                if !matches!(
+                    inline,
+                    Inline::Space | Inline::SoftBreak | Inline::LineBreak
+                ) =>
+            {
+                self.pieces.push(Piece::Inline(add_attr(inline, attr)));
+            }
+            Some(piece) => self.pieces.push(piece),
+            None => {}
+        }
+    }
+
+    fn hyphens(&mut self) {
+        let closes_delete = |parser: &Self| parser.find_opener(|d| d == Delim::Delete).is_some();
+        if self.peek(1) == Some('}') && closes_delete(self) && self.close(Delim::Delete) {
+            self.pos += 2;
+            return;
+        }
+        let mut n = self.chars[self.pos..]
+            .iter()
+            .take_while(|&&c| c == '-')
+            .count();
+        // Leave the last hyphen to close a deletion.
+        if n > 1 && self.peek(n) == Some('}') && closes_delete(self) {
+            n -= 1;
+        }
+        if self.converter.options.smart && n > 1 {
+            let (em, en) = match n {
+                n if n % 3 == 0 => (n / 3, 0),
+                n if n % 2 == 0 => (0, n / 2),
+                n if n % 3 == 2 => (n / 3, 1),
+                n => ((n - 4) / 3, 2),
+            };
+            self.word.push_str(&"—".repeat(em));
+            self.word.push_str(&"–".repeat(en));
+        } else {
+            self.word.push_str(&"-".repeat(n));
+        }
+        self.pos += n;
+    }
+
+    fn quote(&mut self, c: char) {
+        let delim = match c {
+            '"' => Delim::DoubleQuote,
+            _ => Delim::SingleQuote,
+        };
+        let single = c == '\'';
+        let previous = self.previous();
+        let next = self.peek(1);
+        let forced = next == Some('}');
+        let can_close = forced
+            || previous.is_some_and(|c| !c.is_whitespace())
+                && !(single && next.is_some_and(char::is_alphanumeric));
+        let can_open = !forced
+            && next.is_some_and(|c| !c.is_whitespace())
+            && !(single && previous.is_some_and(char::is_alphanumeric));
+        if can_close && self.close(delim) {
+            self.pos += if forced { 2 } else { 1 };
+        } else if can_open {
+            self.open(delim, &c.to_string(), 1);
+        } else {
+            // An apostrophe, or a quote that isn't part of a pair.
+            let quote = match c {
+                '\'' => '’',
+                _ if previous.is_some_and(|c| !c.is_whitespace()) => '”',
+                _ => '“',
+            };
+            self.word.push(quote);
+            self.pos += 1;
+        }
+    }
+
+    /// Parses a footnote reference such as `[^1]`.
+    fn footnote(&mut self) -> bool {
+        let rest = &self.chars[self.pos + 2..];
+        let Some(len) = rest.iter().position(|&c| c == ']' || c == '[' || c == '\n') else {
+            return false;
+        };
+        if rest[len] != ']' || len == 0 {
+            return false;
+        }
+        let label: String = rest[..len].iter().collect();
+        let note = self.converter.note(&label);
+        self.push(note);
+        self.pos += len + 3;
+        true
+    }
+
+    fn close_bracket(&mut self) {
+        let Some(k) = self.find_opener(|d| matches!(d, Delim::Bracket | Delim::Image)) else {
+            self.word.push(']');
+            self.pos += 1;
+            return;
+        };
+        let Piece::Opener { delim, start, .. } = self.pieces[self.openers[k]] else {
+            unreachable!("openers point to openers");
+        };
+        let after = self.pos + 1;
+        let target = match self.chars.get(after) {
+            Some('(') => self.destination(after),
+            Some('[') => self.reference_label(after, start),
+            _ => None,
+        };
+        if let Some((url, attr, next)) = target {
+            let content = self.take_content(k);
+            let target = Target {
+                url,
+                title: String::new(),
+            };
+            let inline = match delim {
+                Delim::Image => Inline::Image(Attr::default(), content, target),
+                _ => Inline::Link(Attr::default(), content, target),
+            };
+            self.pieces.push(Piece::Inline(add_attr(inline, attr)));
+            self.pos = next;
+            return;
+        }
+        if delim == Delim::Bracket {
+            if let Some((attr, next)) = parse_attributes(&self.chars, after) {
+                let content = self.take_content(k);
+                self.pieces.push(Piece::Inline(Inline::Span(attr, content)));
+                self.pos = next;
+                return;
+            }
+        }
+        // Neither a link nor a span: the brackets are text.
+        self.openers.remove(k);
+        self.word.push(']');
+        self.pos += 1;
+    }
+
+    /// Parses an inline destination such as `(url)`, returning the URL,
+    /// no attributes and the position after it.
+    fn destination(&self, open: usize) -> Option<(String, Attr, usize)> {
+        let mut url = String::new();
+        let mut depth = 0;
+        let mut i = open + 1;
+        loop {
+            match *self.chars.get(i)? {
+                '\\' if self
+                    .chars
+                    .get(i + 1)
+                    .is_some_and(char::is_ascii_punctuation) =>
+                {
+                    url.push(self.chars[i + 1]);
+                    i += 1;
+                }
+                '(' => {
+                    depth += 1;
+                    url.push('(');
+                }
+                ')' if depth == 0 => break,
+                ')' => {
+                    depth -= 1;
+                    url.push(')');
+                }
+                '\n' => {}
+                c => url.push(c),
+            }
+            i += 1;
+        }
+        Some((url.trim().to_owned(), Attr::default(), i + 1))
+    }
+
+    /// Parses a reference such as `[label]`, or `[]` to use the text of
+    /// the link as its label.
+    fn reference_label(&self, open: usize, text_start: usize) -> Option<(String, Attr, usize)> {
+        let rest = &self.chars[open + 1..];
+        let len = rest.iter().position(|&c| c == ']' || c == '[')?;
+        if rest[len] != ']' {
+            return None;
+        }
+        let label: String = match len {
+            0 => self.chars[text_start..self.pos].iter().collect(),
+            _ => rest[..len].iter().collect(),
+        };
+        let (url, attr) = self.converter.reference(&label);
+        Some((url, attr, open + len + 2))
+    }
+
+    /// Parses an autolink such as `<https://djot.net>` or
+    /// `<me@example.com>`.
+    fn autolink(&mut self) -> bool {
+        let rest = &self.chars[self.pos + 1..];
+        let Some(len) = rest
+            .iter()
+            .position(|&c| c == '>' || c == '<' || c.is_whitespace())
+        else {
+            return false;
+        };
+        if rest[len] != '>' || len == 0 {
+            return false;
+        }
+        let text: String = rest[..len].iter().collect();
+        let (class, url) = if text.contains(':') {
+            ("uri", text.clone())
+        } else if text.contains('@') {
+            ("email", format!("mailto:{}", text))
+        } else {
+            return false;
+        };
+        let attr = Attr {
+            classes: vec![class.to_owned()],
+            ..Attr::default()
+        };
+        let target = Target {
+            url,
+            title: String::new(),
+        };
+        self.push(Inline::Link(attr, vec![Inline::Str(text)], target));
+        self.pos += len + 2;
+        true
+    }
+
+    /// Parses a symbol such as `:smile:`.
+    fn symbol(&mut self) -> bool {
+        let rest = &self.chars[self.pos + 1..];
+        let len = rest
+            .iter()
+            .take_while(|&&c| c.is_ascii_alphanumeric() || matches!(c, '_' | '+' | '-'))
+            .count();
+        if len == 0 || rest.get(len) != Some(&':') {
+            return false;
+        }
+        let name: String = rest[..len].iter().collect();
+        match self.converter.options.symbols.get(&name) {
+            Some(text) => self.word.push_str(text),
+            None => {
+                let attr = Attr {
+                    classes: vec!["symbol".into()],
+                    ..Attr::default()
+                };
+                self.push(Inline::Span(attr, vec![Inline::Str(name)]));
+            }
+        }
+        self.pos += len + 2;
+        true
+    }
+}
+
+/// Turns pieces into inlines, keeping the openers that weren't closed as
+/// text and merging adjacent text.
+fn finish(pieces: Vec<Piece>) -> Vec<Inline> {
+    let mut inlines: Vec<Inline> = Vec::new();
+    for piece in pieces {
+        let inline = match piece {
+            Piece::Inline(inline) => inline,
+            Piece::Opener { delim, text, .. } => Inline::Str(match delim {
+                Delim::DoubleQuote => text.replace('"', "“"),
+                Delim::SingleQuote => text.replace('\'', "’"),
+                _ => text,
+            }),
+        };
+        match (inlines.last_mut(), inline) {
+            (Some(Inline::Str(s)), Inline::Str(text)) => s.push_str(&text),
+            (_, inline) => inlines.push(inline),
+        }
+    }
+    inlines
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::builder::*;
+    use proptest::prelude::*;
+
+    fn attr<const N: usize, const M: usize>(
+        id: &str,
+        classes: [&str; N],
+        attributes: [(&str, &str); M],
+    ) -> Attr {
+        Attr {
+            identifier: id.into(),
+            classes: classes.map(Into::into).into(),
+            attributes: attributes.map(|(k, v)| (k.into(), v.into())).into(),
+        }
+    }
+
+    fn djot(input: &str) -> Vec<Block> {
+        read_djot(input, &DjotOptions::default()).blocks
+    }
+
+    fn inlines(input: &str) -> Inlines {
+        match djot(input).as_slice() {
+            [Block::Para(inlines)] => inlines.clone().into(),
+            blocks => panic!("expected a paragraph, got {:?}", blocks),
+        }
+    }
+
+    fn section(id: &str, blocks: Blocks) -> Blocks {
+        div_with(attr(id, ["section"], []), blocks)
+    }
+
+    #[test]
+    fn sections() {
+        let input = "# One\n\ntext\n\n## Two *b*\n\n{#mine}\n# One\n\n### One\n";
+        assert_eq!(
+            djot(input),
+            (section(
+                "One",
+                header(1, "One")
+                    + para("text")
+                    + section("Two-b", header(2, text("Two ") + strong("b")))
+            ) + section(
+                "mine",
+                header(1, "One") + section("One-1", header(3, "One"))
+            ))
+            .into_vec()
+        );
+        // Headings inside other blocks don't start sections, and links can
+        // refer to headings by their text.
+        assert_eq!(
+            djot("> # Quoted\n\nSee [Later][] or [here][Later].\n\n# Later\n"),
+            (block_quote(header(1, "Quoted"))
+                + para(
+                    text("See ")
+                        + link("#Later", "", "Later")
+                        + text(" or ")
+                        + link("#Later", "", "here")
+                        + str(".")
+                )
+                + section("Later", header(1, "Later")))
+            .into_vec()
+        );
+    }
+
+    #[test]
+    fn lists() {
+        assert_eq!(
+            djot("- a\n- b\n\n  - c\n\n- d\n"),
+            bullet_list([para("a"), para("b") + bullet_list([plain("c")]), para("d")]).into_vec()
+        );
+        assert_eq!(
+            djot("- a\n\n  - b\n  - c\n- d\n"),
+            bullet_list([
+                plain("a") + bullet_list([plain("b"), plain("c")]),
+                plain("d")
+            ])
+            .into_vec()
+        );
+        let upper = ListAttributes {
+            start_number: 3,
+            style: ListNumberStyle::UpperRoman,
+            delim: ListNumberDelim::TwoParens,
+        };
+        assert_eq!(
+            djot("(III) x\n(IV) y\n\ni. a\n\nb) b\n"),
+            (ordered_list_with(upper, [plain("x"), plain("y")])
+                + ordered_list_with(
+                    ListAttributes {
+                        start_number: 1,
+                        style: ListNumberStyle::LowerRoman,
+                        delim: ListNumberDelim::Period,
+                    },
+                    [plain("a")]
+                )
+                + ordered_list_with(
+                    ListAttributes {
+                        start_number: 2,
+                        style: ListNumberStyle::LowerAlpha,
+                        delim: ListNumberDelim::OneParen,
+                    },
+                    [plain("b")]
+                ))
+            .into_vec()
+        );
+        assert_eq!(
+            djot("- [ ] to do\n- [x] done\n"),
+            bullet_list([plain("☐ to do"), plain("☒ done")]).into_vec()
+        );
+        // The blank line after a term doesn't make the list loose.
+        assert_eq!(
+            djot(": apple\n\n  red fruit\n: orange\n\n  orange fruit\n"),
+            definition_list([
+                (text("apple"), vec![plain("red fruit")]),
+                (text("orange"), vec![plain("orange fruit")]),
+            ])
+            .into_vec()
+        );
+        assert_eq!(
+            djot(": apple\n\n  red fruit\n\n  > quote\n"),
+            definition_list([(
+                text("apple"),
+                vec![para("red fruit") + block_quote(para("quote"))]
+            )])
+            .into_vec()
+        );
+    }
+
+    #[test]
+    fn blocks() {
+        let input = "> quoted\nlazy\n>\n> > nested\n\n\
+                     ```` rust\nfn main() {}\n```\n````\n\n\
+                     ``` =html\n<br>\n```\n\n\
+                     :::: warning\n::: note\ninside\n:::\n::::\n\n\
+                     - - -\n";
+        assert_eq!(
+            djot(input),
+            (block_quote(para("quoted\nlazy") + block_quote(para("nested")))
+                + code_block_with(attr("", ["rust"], []), "fn main() {}\n```")
+                + raw_block("html", "<br>")
+                + div_with(
+                    attr("", ["warning"], []),
+                    div_with(attr("", ["note"], []), para("inside"))
+                )
+                + horizontal_rule())
+            .into_vec()
+        );
+    }
+
+    #[test]
+    fn tables() {
+        let input = "| a | b \\| c |\n|:--|--:|\n| `|` | 2 |\n| 3 |\n\n^ The *caption*\n";
+        let Block::Table(parsed) = &djot(input)[0] else {
+            panic!("expected a table");
+        };
+        let expected = TableBuilder::new()
+            .caption(Caption {
+                short: None,
+                long: plain(text("The ") + strong("caption")).into_vec(),
+            })
+            .alignments([Alignment::AlignLeft, Alignment::AlignRight])
+            .header([plain("a"), plain("b | c")])
+            .body([
+                vec![
+                    cell(Alignment::AlignDefault, 1, 1, plain(code("|"))),
+                    cell(Alignment::AlignDefault, 1, 1, plain("2")),
+                ],
+                vec![
+                    cell(Alignment::AlignDefault, 1, 1, plain("3")),
+                    cell(Alignment::AlignDefault, 1, 1, Blocks::new()),
+                ],
+            ])
+            .build();
+        assert_eq!(parsed, &expected);
+        // Without a separator line, all rows are in the body.
+        let Block::Table(parsed) = &djot("| a |\n")[0] else {
+            panic!("expected a table");
+        };
+        assert!(parsed.head.rows.is_empty());
+        assert_eq!(parsed.bodies[0].body.len(), 1);
+    }
+
+    #[test]
+    fn attributes() {
+        let input = "{#id .a\n key=\"a \\\"value\\\"\"}\n{.b % comment %}\nPara.\n\n\
+                     {.numberLines}\n``` rust\nx\n```\n";
+        assert_eq!(
+            djot(input),
+            (div_with(
+                attr("id", ["a", "b"], [("key", "a \"value\"")]),
+                para("Para.")
+            ) + code_block_with(attr("", ["rust", "numberLines"], []), "x"))
+            .into_vec()
+        );
+        assert_eq!(
+            inlines("a word{.x} _emph_{#e} `code`{.c} [link](u){title=\"T\" .l} [span]{key=v}"),
+            text("a ")
+                + span_with(attr("", ["x"], []), "word")
+                + space()
+                + span_with(attr("e", [], []), emph("emph"))
+                + space()
+                + code_with(attr("", ["c"], []), "code")
+                + space()
+                + link_with(attr("", ["l"], []), "u", "T", "link")
+                + space()
+                + span_with(attr("", [], [("key", "v")]), "span")
+        );
+    }
+
+    #[test]
+    fn emphasis() {
+        assert_eq!(
+            inlines("_a *b_ c* x_y_z {_ forced _} *a* * b*"),
+            emph(text("a *b"))
+                + text(" c* x")
+                + emph("y")
+                + text("z ")
+                + emph(text(" forced "))
+                + space()
+                + strong("a")
+                + text(" * b*")
+        );
+        assert_eq!(
+            inlines("{=mark=} {+ins+} {-del-} H~2~O x^2^ __"),
+            span_with(attr("", ["mark"], []), "mark")
+                + space()
+                + span_with(attr("", ["inserted"], []), "ins")
+                + space()
+                + strikeout("del")
+                + space()
+                + str("H")
+                + subscript("2")
+                + text("O x")
+                + superscript("2")
+                + text(" __")
+        );
+    }
+
+    #[test]
+    fn verbatim() {
+        assert_eq!(
+            inlines("`a` `` `b` `` $`x^2` $$`y` `<b>`{=html} `open"),
+            code("a")
+                + space()
+                + code("`b`")
+                + space()
+                + math("x^2")
+                + space()
+                + display_math("y")
+                + space()
+                + raw_inline("html", "<b>")
+                + space()
+                + code("open")
+        );
+    }
+
+    #[test]
+    fn links() {
+        let input =
+            "[a](/u(1)) [b][ref] [ref][] ![img](i.png) <https://x.y> <me@x.y> [c][none]\n\n\
+                     {.r}\n[ref]: /very/\n  long\n";
+        assert_eq!(
+            djot(input),
+            para(
+                link("/u(1)", "", "a")
+                    + space()
+                    + link_with(attr("", ["r"], []), "/very/long", "", "b")
+                    + space()
+                    + link_with(attr("", ["r"], []), "/very/long", "", "ref")
+                    + space()
+                    + image("i.png", "", "img")
+                    + space()
+                    + link_with(attr("", ["uri"], []), "https://x.y", "", "https://x.y")
+                    + space()
+                    + link_with(attr("", ["email"], []), "mailto:me@x.y", "", "me@x.y")
+                    + space()
+                    + link("", "", "c")
+            )
+            .into_vec()
+        );
+    }
+
+    #[test]
+    fn footnotes() {
+        let input = "A[^1] b[^long].\n\n[^1]: One.\n\n[^long]: First.\n\n    Second[^long].\nlazy\n\nAfter.\n";
+        assert_eq!(
+            djot(input),
+            (para(
+                str("A")
+                    + note(para("One."))
+                    + text(" b")
+                    + note(
+                        para("First.")
+                            + para(str("Second") + note(Blocks::new()) + text(".\nlazy"))
+                    )
+                    + str(".")
+            ) + para("After."))
+            .into_vec()
+        );
+    }
+
+    #[test]
+    fn smart_punctuation() {
+        assert_eq!(
+            inlines("\"Hi,\" she said, 'it's 5--7 and 1---2...' \"no"),
+            quoted(QuoteType::DoubleQuote, "Hi,")
+                + text(" she said, ")
+                + quoted(QuoteType::SingleQuote, "it’s 5–7 and 1—2…")
+                + text(" “no")
+        );
+        assert_eq!(inlines("a-----b ----"), text("a—–b ––"));
+        let options = DjotOptions {
+            smart: false,
+            ..DjotOptions::default()
+        };
+        assert_eq!(
+            read_djot("\"Hi\" 'a' -- ...\n", &options).blocks,
+            para("\"Hi\" 'a' -- ...").into_vec()
+        );
+    }
+
+    #[test]
+    fn symbols() {
+        let mut options = DjotOptions::default();
+        options.symbols.insert("heart".into(), "❤".into());
+        assert_eq!(
+            read_djot("I :heart: :rust:, at 10:30.\n", &options).blocks,
+            para(text("I ❤ ") + span_with(attr("", ["symbol"], []), "rust") + text(", at 10:30."))
+                .into_vec()
+        );
+    }
+
+    #[test]
+    fn escapes() {
+        assert_eq!(
+            inlines("\\*a\\* \\q a\\ b line\\\nbreak"),
+            text("*a* \\q a\u{a0}b line") + line_break() + str("break")
+        );
+    }
+
+    #[test]
+    fn odd_input() {
+        for input in [
+            ":",
+            ": ",
+            ":\n",
+            ":\n\n: b\n",
+            "- a\n\u{3000}\u{3000}b",
+            ">\u{3000}",
+            "[^a]:",
+            "[^a]:\n\n",
+        ] {
+            read_djot(input, &DjotOptions::default());
+        }
+        assert_eq!(
+            djot("a[^a]\n\n[^a]:\n"),
+            para(text("a") + note(Blocks::new())).into_vec()
+        );
+        assert_eq!(
+            djot("- a\n\u{3000}\u{3000}b"),
+            bullet_list([plain(text("a\nb"))]).into_vec()
+        );
+    }
+
+    proptest! {
+        #[test]
+        fn never_panics(input in any::<String>()) {
+            read_djot(&input, &DjotOptions::default());
+        }
+
+        #[test]
+        fn never_panics_on_markup(input in "([-:*+.()\\[\\]{}#>|`_\\\\ a1\u{3000}\n])*") {
+            read_djot(&input, &DjotOptions::default());
+        }
+    }
+}
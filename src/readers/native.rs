@@ -0,0 +1,948 @@
+//! A reader for Pandoc's native format, like `pandoc -f native`.
+//!
+//! The native format is the Haskell `show` representation of the AST, which
+//! is what `pandoc -t native` writes and what Pandoc's bug reports, test
+//! suites and documentation quote. Both the compact form, such as
+//! `[Para [Str "a",Space]]`, and the pretty-printed form written by
+//! [`write_native`](crate::writers::native::write_native) are accepted, with
+//! any whitespace between tokens.
+//!
+//! ```
+//! use pandoc_types::builder::*;
+//! use pandoc_types::readers::native::read_native_inlines;
+//!
+//! let inlines = read_native_inlines(r#"[Emph [Str "Caf\233"],Space,Str "\8212"]"#).unwrap();
+//! assert_eq!(inlines, (emph("Café") + text(" —")).into_vec());
+//! ```
+use std::fmt;
+
+use crate::definition::{
+    Alignment, Attr, Block, Caption, Cell, Citation, CitationMode, ColSpec, ColWidth, Format,
+    IndexMap, Inline, ListAttributes, ListNumberDelim, ListNumberStyle, MathType, Meta, MetaValue,
+    Pandoc, QuoteType, Row, Table, TableBody, TableFoot, TableHead, Target,
+};
+
+/// An error parsing the native format.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct NativeError {
+    line: usize,
+    column: usize,
+    message: String,
+}
+
+impl NativeError {
+    /// The line of the input where the error happened, starting from 1.
+    pub fn line(&self) -> usize {
+        self.line
+    }
+
+    /// The column of the input where the error happened, in characters
+    /// starting from 1.
+    pub fn column(&self) -> usize {
+        self.column
+    }
+
+    pub fn message(&self) -> &str {
+        &self.message
+    }
+}
+
+impl fmt::Display for NativeError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "line {}, column {}: {}",
+            self.line, self.column, self.message
+        )
+    }
+}
+
+impl std::error::Error for NativeError {}
+
+/// Parses a document in the native format.
+///
+/// Like Pandoc's native reader, this also accepts a list of blocks, a
+/// single block, a list of inlines or a single inline, which become a
+/// document without metadata. Inlines are wrapped in a `Plain` block.
+pub fn read_native(input: &str) -> Result<Pandoc, NativeError> {
+    let mut parser = Parser::new(input);
+    let doc = match parser.first_constructor() {
+        Some("Pandoc") => parser.pandoc()?,
+        Some(name) if INLINE_CONSTRUCTORS.contains(&name) => Pandoc {
+            blocks: vec![Block::Plain(parser.one_or_many(Parser::inline)?)],
+            meta: Meta::default(),
+        },
+        _ => Pandoc {
+            blocks: parser.one_or_many(Parser::block)?,
+            meta: Meta::default(),
+        },
+    };
+    parser.end()?;
+    Ok(doc)
+}
+
+/// Parses a list of blocks in the native format, or a single block.
+pub fn read_native_blocks(input: &str) -> Result<Vec<Block>, NativeError> {
+    let mut parser = Parser::new(input);
+    let blocks = parser.one_or_many(Parser::block)?;
+    parser.end()?;
+    Ok(blocks)
+}
+
+/// Parses a list of inlines in the native format, or a single inline.
+pub fn read_native_inlines(input: &str) -> Result<Vec<Inline>, NativeError> {
+    let mut parser = Parser::new(input);
+    let inlines = parser.one_or_many(Parser::inline)?;
+    parser.end()?;
+    Ok(inlines)
+}
+
+const INLINE_CONSTRUCTORS: [&str; 20] = [
+    "Str",
+    "Emph",
+    "Underline",
+    "Strong",
+    "Strikeout",
+    "Superscript",
+    "Subscript",
+    "SmallCaps",
+    "Quoted",
+    "Cite",
+    "Code",
+    "Space",
+    "SoftBreak",
+    "LineBreak",
+    "Math",
+    "RawInline",
+    "Link",
+    "Image",
+    "Note",
+    "Span",
+];
+
+/// The names of the ASCII control characters in Haskell string escapes,
+/// ordered so that `SOH` is tried before `SO`.
+const ASCII_ESCAPES: [(&str, char); 34] = [
+    ("NUL", '\x00'),
+    ("SOH", '\x01'),
+    ("STX", '\x02'),
+    ("ETX", '\x03'),
+    ("EOT", '\x04'),
+    ("ENQ", '\x05'),
+    ("ACK", '\x06'),
+    ("BEL", '\x07'),
+    ("BS", '\x08'),
+    ("HT", '\x09'),
+    ("LF", '\x0a'),
+    ("VT", '\x0b'),
+    ("FF", '\x0c'),
+    ("CR", '\x0d'),
+    ("SO", '\x0e'),
+    ("SI", '\x0f'),
+    ("DLE", '\x10'),
+    ("DC1", '\x11'),
+    ("DC2", '\x12'),
+    ("DC3", '\x13'),
+    ("DC4", '\x14'),
+    ("NAK", '\x15'),
+    ("SYN", '\x16'),
+    ("ETB", '\x17'),
+    ("CAN", '\x18'),
+    ("EM", '\x19'),
+    ("SUB", '\x1a'),
+    ("ESC", '\x1b'),
+    ("FS", '\x1c'),
+    ("GS", '\x1d'),
+    ("RS", '\x1e'),
+    ("US", '\x1f'),
+    ("SP", ' '),
+    ("DEL", '\x7f'),
+];
+
+/// A recursive descent parser for the `show` syntax of the AST types.
+struct Parser<'a> {
+    input: &'a str,
+    pos: usize,
+}
+
+impl<'a> Parser<'a> {
+    fn new(input: &'a str) -> Self {
+        Parser { input, pos: 0 }
+    }
+
+    fn error_at(&self, pos: usize, message: impl Into<String>) -> NativeError {
+        let before = &self.input[..pos];
+        let line_start = before.rfind('\n').map_or(0, |i| i + 1);
+        NativeError {
+            line: before.matches('\n').count() + 1,
+            column: before[line_start..].chars().count() + 1,
+            message: message.into(),
+        }
+    }
+
+    fn error(&mut self, message: impl Into<String>) -> NativeError {
+        self.skip_whitespace();
+        self.error_at(self.pos, message)
+    }
+
+    fn rest(&self) -> &'a str {
+        &self.input[self.pos..]
+    }
+
+    fn skip_whitespace(&mut self) {
+        let rest = self.rest();
+        self.pos += rest.len() - rest.trim_start().len();
+    }
+
+    fn peek(&mut self) -> Option<char> {
+        self.skip_whitespace();
+        self.rest().chars().next()
+    }
+
+    fn eat(&mut self, c: char) -> bool {
+        if self.peek() == Some(c) {
+            self.pos += c.len_utf8();
+            true
+        } else {
+            false
+        }
+    }
+
+    fn expect(&mut self, c: char) -> Result<(), NativeError> {
+        match self.eat(c) {
+            true => Ok(()),
+            false => Err(self.error(format!("expected `{}`", c))),
+        }
+    }
+
+    fn end(&mut self) -> Result<(), NativeError> {
+        match self.peek() {
+            None => Ok(()),
+            Some(_) => Err(self.error("expected the end of the input")),
+        }
+    }
+
+    /// Returns the first constructor of the input, skipping brackets.
+    fn first_constructor(&self) -> Option<&'a str> {
+        let rest = self
+            .rest()
+            .trim_start_matches(|c: char| c.is_whitespace() || c == '[' || c == '(');
+        let len = rest
+            .find(|c: char| !(c.is_alphanumeric() || c == '_' || c == '\''))
+            .unwrap_or(rest.len());
+        Some(&rest[..len]).filter(|name| !name.is_empty())
+    }
+
+    fn identifier(&mut self) -> Result<&'a str, NativeError> {
+        self.skip_whitespace();
+        let rest = self.rest();
+        let len = rest
+            .find(|c: char| !(c.is_alphanumeric() || c == '_' || c == '\''))
+            .unwrap_or(rest.len());
+        if len == 0 || !rest.starts_with(|c: char| c.is_alphabetic()) {
+            return Err(self.error("expected a constructor"));
+        }
+        self.pos += len;
+        Ok(&rest[..len])
+    }
+
+    fn keyword(&mut self, keyword: &str) -> Result<(), NativeError> {
+        self.skip_whitespace();
+        let pos = self.pos;
+        match self.identifier() {
+            Ok(name) if name == keyword => Ok(()),
+            _ => Err(self.error_at(pos, format!("expected `{}`", keyword))),
+        }
+    }
+
+    /// Parses a constructor, returning an error naming the expected type
+    /// if it isn't one of `names`.
+    fn constructor(&mut self, what: &str, names: &[&str]) -> Result<&'a str, NativeError> {
+        self.skip_whitespace();
+        let pos = self.pos;
+        let name = self.identifier()?;
+        match names.contains(&name) {
+            true => Ok(name),
+            false => Err(self.error_at(pos, format!("unknown {} `{}`", what, name))),
+        }
+    }
+
+    /// Parses a value that may be wrapped in parentheses.
+    fn parens<T>(
+        &mut self,
+        f: impl FnOnce(&mut Self) -> Result<T, NativeError>,
+    ) -> Result<T, NativeError> {
+        if self.eat('(') {
+            let value = self.parens(f)?;
+            self.expect(')')?;
+            Ok(value)
+        } else {
+            f(self)
+        }
+    }
+
+    fn list<T>(
+        &mut self,
+        mut f: impl FnMut(&mut Self) -> Result<T, NativeError>,
+    ) -> Result<Vec<T>, NativeError> {
+        self.parens(|p| {
+            p.expect('[')?;
+            let mut items = Vec::new();
+            if p.eat(']') {
+                return Ok(items);
+            }
+            loop {
+                items.push(f(p)?);
+                if !p.eat(',') {
+                    p.expect(']')?;
+                    return Ok(items);
+                }
+            }
+        })
+    }
+
+    fn pair<A, B>(
+        &mut self,
+        f: impl FnOnce(&mut Self) -> Result<A, NativeError>,
+        g: impl FnOnce(&mut Self) -> Result<B, NativeError>,
+    ) -> Result<(A, B), NativeError> {
+        self.expect('(')?;
+        let a = f(self)?;
+        self.expect(',')?;
+        let b = g(self)?;
+        self.expect(')')?;
+        Ok((a, b))
+    }
+
+    /// Parses a list, or a single item as a list of one.
+    fn one_or_many<T>(
+        &mut self,
+        mut f: impl FnMut(&mut Self) -> Result<T, NativeError>,
+    ) -> Result<Vec<T>, NativeError> {
+        let rest = self
+            .rest()
+            .trim_start_matches(|c: char| c.is_whitespace() || c == '(');
+        match rest.starts_with('[') {
+            true => self.list(f),
+            false => Ok(vec![f(self)?]),
+        }
+    }
+
+    fn field(&mut self, name: &str) -> Result<(), NativeError> {
+        self.keyword(name)?;
+        self.expect('=')
+    }
+
+    fn string(&mut self) -> Result<String, NativeError> {
+        if self.peek() != Some('"') {
+            return Err(self.error("expected a string"));
+        }
+        let start = self.pos;
+        self.pos += 1;
+        let mut s = String::new();
+        loop {
+            let rest = self.rest();
+            match rest.chars().next() {
+                None => return Err(self.error_at(start, "unterminated string")),
+                Some('"') => {
+                    self.pos += 1;
+                    return Ok(s);
+                }
+                Some('\\') => {
+                    let (c, len) = escape_sequence(&rest[1..])
+                        .ok_or_else(|| self.error_at(self.pos, "invalid escape sequence"))?;
+                    s.extend(c);
+                    self.pos += 1 + len;
+                }
+                Some(c) => {
+                    s.push(c);
+                    self.pos += c.len_utf8();
+                }
+            }
+        }
+    }
+
+    fn int(&mut self) -> Result<i32, NativeError> {
+        self.parens(|p| {
+            p.skip_whitespace();
+            let start = p.pos;
+            let negative = p.eat('-');
+            p.skip_whitespace();
+            let digits = p.rest().len()
+                - p.rest()
+                    .trim_start_matches(|c: char| c.is_ascii_digit())
+                    .len();
+            let text = &p.rest()[..digits];
+            p.pos += digits;
+            let value = match negative {
+                true => format!("-{}", text).parse(),
+                false => text.parse(),
+            };
+            value.map_err(|_| p.error_at(start, "expected an integer"))
+        })
+    }
+
+    fn double(&mut self) -> Result<f64, NativeError> {
+        self.parens(|p| {
+            p.skip_whitespace();
+            let start = p.pos;
+            let negative = p.eat('-');
+            p.skip_whitespace();
+            let rest = p.rest();
+            let len = match rest {
+                _ if rest.starts_with("Infinity") => "Infinity".len(),
+                _ if rest.starts_with("NaN") => "NaN".len(),
+                _ => rest
+                    .find(|c: char| {
+                        !(c.is_ascii_alphanumeric() || c == '.' || c == '-' || c == '+')
+                    })
+                    .unwrap_or(rest.len()),
+            };
+            let text = &rest[..len];
+            p.pos += len;
+            let value = match text {
+                "Infinity" => Some(f64::INFINITY),
+                "NaN" => Some(f64::NAN),
+                _ if text.starts_with(|c: char| c.is_ascii_digit()) => text.parse().ok(),
+                _ => None,
+            };
+            match value {
+                Some(x) if negative => Ok(-x),
+                Some(x) => Ok(x),
+                None => Err(p.error_at(start, "expected a number")),
+            }
+        })
+    }
+
+    fn bool(&mut self) -> Result<bool, NativeError> {
+        match self.constructor("Bool", &["True", "False"])? {
+            "True" => Ok(true),
+            _ => Ok(false),
+        }
+    }
+
+    fn pandoc(&mut self) -> Result<Pandoc, NativeError> {
+        self.parens(|p| {
+            p.keyword("Pandoc")?;
+            let meta = p.meta()?;
+            let blocks = p.blocks()?;
+            Ok(Pandoc { blocks, meta })
+        })
+    }
+
+    fn meta(&mut self) -> Result<Meta, NativeError> {
+        self.parens(|p| {
+            p.keyword("Meta")?;
+            p.expect('{')?;
+            p.field("unMeta")?;
+            let map = p.map()?;
+            p.expect('}')?;
+            Ok(Meta(map))
+        })
+    }
+
+    fn map(&mut self) -> Result<IndexMap<String, MetaValue>, NativeError> {
+        self.parens(|p| {
+            p.keyword("fromList")?;
+            let entries = p.list(|p| p.pair(Parser::string, Parser::meta_value))?;
+            Ok(entries.into_iter().collect())
+        })
+    }
+
+    fn meta_value(&mut self) -> Result<MetaValue, NativeError> {
+        self.parens(|p| {
+            let name = p.constructor(
+                "MetaValue",
+                &[
+                    "MetaMap",
+                    "MetaList",
+                    "MetaBool",
+                    "MetaString",
+                    "MetaInlines",
+                    "MetaBlocks",
+                ],
+            )?;
+            Ok(match name {
+                "MetaMap" => MetaValue::MetaMap(p.map()?),
+                "MetaList" => MetaValue::MetaList(p.list(Parser::meta_value)?),
+                "MetaBool" => MetaValue::MetaBool(p.bool()?),
+                "MetaString" => MetaValue::MetaString(p.string()?),
+                "MetaInlines" => MetaValue::MetaInlines(p.inlines()?),
+                _ => MetaValue::MetaBlocks(p.blocks()?),
+            })
+        })
+    }
+
+    fn blocks(&mut self) -> Result<Vec<Block>, NativeError> {
+        self.list(Parser::block)
+    }
+
+    fn inlines(&mut self) -> Result<Vec<Inline>, NativeError> {
+        self.list(Parser::inline)
+    }
+
+    fn block(&mut self) -> Result<Block, NativeError> {
+        self.parens(|p| {
+            let name = p.constructor(
+                "Block",
+                &[
+                    "Plain",
+                    "Para",
+                    "LineBlock",
+                    "CodeBlock",
+                    "RawBlock",
+                    "BlockQuote",
+                    "OrderedList",
+                    "BulletList",
+                    "DefinitionList",
+                    "Header",
+                    "HorizontalRule",
+                    "Table",
+                    "Figure",
+                    "Div",
+                    "Null",
+                ],
+            )?;
+            Ok(match name {
+                "Plain" => Block::Plain(p.inlines()?),
+                "Para" => Block::Para(p.inlines()?),
+                "LineBlock" => Block::LineBlock(p.list(Parser::inlines)?),
+                "CodeBlock" => Block::CodeBlock(p.attr()?, p.string()?),
+                "RawBlock" => Block::RawBlock(p.format()?, p.string()?),
+                "BlockQuote" => Block::BlockQuote(p.blocks()?),
+                "OrderedList" => Block::OrderedList(p.list_attributes()?, p.list(Parser::blocks)?),
+                "BulletList" => Block::BulletList(p.list(Parser::blocks)?),
+                "DefinitionList" => Block::DefinitionList(
+                    p.list(|p| p.pair(Parser::inlines, |p| p.list(Parser::blocks)))?,
+                ),
+                "Header" => Block::Header(p.int()?, p.attr()?, p.inlines()?),
+                "HorizontalRule" => Block::HorizontalRule,
+                "Table" => Block::Table(Table {
+                    attr: p.attr()?,
+                    caption: p.caption()?,
+                    colspecs: p.list(Parser::col_spec)?,
+                    head: p.table_head()?,
+                    bodies: p.list(Parser::table_body)?,
+                    foot: p.table_foot()?,
+                }),
+                "Figure" => Block::Figure(p.attr()?, p.caption()?, p.blocks()?),
+                "Div" => Block::Div(p.attr()?, p.blocks()?),
+                _ => Block::Null,
+            })
+        })
+    }
+
+    fn attr(&mut self) -> Result<Attr, NativeError> {
+        self.expect('(')?;
+        let identifier = self.string()?;
+        self.expect(',')?;
+        let classes = self.list(Parser::string)?;
+        self.expect(',')?;
+        let attributes = self.list(|p| p.pair(Parser::string, Parser::string))?;
+        self.expect(')')?;
+        Ok(Attr {
+            identifier,
+            classes,
+            attributes,
+        })
+    }
+
+    fn format(&mut self) -> Result<Format, NativeError> {
+        self.parens(|p| {
+            p.keyword("Format")?;
+            Ok(Format(p.string()?))
+        })
+    }
+
+    fn list_attributes(&mut self) -> Result<ListAttributes, NativeError> {
+        self.expect('(')?;
+        let start_number = self.int()?;
+        self.expect(',')?;
+        let style = match self.constructor(
+            "ListNumberStyle",
+            &[
+                "DefaultStyle",
+                "Example",
+                "Decimal",
+                "LowerRoman",
+                "UpperRoman",
+                "LowerAlpha",
+                "UpperAlpha",
+            ],
+        )? {
+            "DefaultStyle" => ListNumberStyle::DefaultStyle,
+            "Example" => ListNumberStyle::Example,
+            "Decimal" => ListNumberStyle::Decimal,
+            "LowerRoman" => ListNumberStyle::LowerRoman,
+            "UpperRoman" => ListNumberStyle::UpperRoman,
+            "LowerAlpha" => ListNumberStyle::LowerAlpha,
+            _ => ListNumberStyle::UpperAlpha,
+        };
+        self.expect(',')?;
+        let delim = match self.constructor(
+            "ListNumberDelim",
+            &["DefaultDelim", "Period", "OneParen", "TwoParens"],
+        )? {
+            "DefaultDelim" => ListNumberDelim::DefaultDelim,
+            "Period" => ListNumberDelim::Period,
+            "OneParen" => ListNumberDelim::OneParen,
+            _ => ListNumberDelim::TwoParens,
+        };
+        self.expect(')')?;
+        Ok(ListAttributes {
+            start_number,
+            style,
+            delim,
+        })
+    }
+
+    fn caption(&mut self) -> Result<Caption, NativeError> {
+        self.parens(|p| {
+            p.keyword("Caption")?;
+            let short = p.parens(|p| match p.constructor("Maybe", &["Just", "Nothing"])? {
+                "Just" => Ok(Some(p.inlines()?)),
+                _ => Ok(None),
+            })?;
+            Ok(Caption {
+                short,
+                long: p.blocks()?,
+            })
+        })
+    }
+
+    fn col_spec(&mut self) -> Result<ColSpec, NativeError> {
+        let (align, width) = self.pair(Parser::alignment, |p| {
+            p.parens(
+                |p| match p.constructor("ColWidth", &["ColWidth", "ColWidthDefault"])? {
+                    "ColWidth" => Ok(ColWidth::ColWidth(p.double()?)),
+                    _ => Ok(ColWidth::ColWidthDefault),
+                },
+            )
+        })?;
+        Ok(ColSpec(align, width))
+    }
+
+    fn alignment(&mut self) -> Result<Alignment, NativeError> {
+        Ok(
+            match self.constructor(
+                "Alignment",
+                &["AlignLeft", "AlignRight", "AlignCenter", "AlignDefault"],
+            )? {
+                "AlignLeft" => Alignment::AlignLeft,
+                "AlignRight" => Alignment::AlignRight,
+                "AlignCenter" => Alignment::AlignCenter,
+                _ => Alignment::AlignDefault,
+            },
+        )
+    }
+
+    /// Parses a number wrapped in a newtype constructor, like `RowSpan 1`.
+    fn newtype_int(&mut self, name: &str) -> Result<i32, NativeError> {
+        self.parens(|p| {
+            p.keyword(name)?;
+            p.int()
+        })
+    }
+
+    fn table_head(&mut self) -> Result<TableHead, NativeError> {
+        self.parens(|p| {
+            p.keyword("TableHead")?;
+            Ok(TableHead {
+                attr: p.attr()?,
+                rows: p.list(Parser::row)?,
+            })
+        })
+    }
+
+    fn table_body(&mut self) -> Result<TableBody, NativeError> {
+        self.parens(|p| {
+            p.keyword("TableBody")?;
+            Ok(TableBody {
+                attr: p.attr()?,
+                row_head_columns: p.newtype_int("RowHeadColumns")?,
+                head: p.list(Parser::row)?,
+                body: p.list(Parser::row)?,
+            })
+        })
+    }
+
+    fn table_foot(&mut self) -> Result<TableFoot, NativeError> {
+        self.parens(|p| {
+            p.keyword("TableFoot")?;
+            Ok(TableFoot {
+                attr: p.attr()?,
+                rows: p.list(Parser::row)?,
+            })
+        })
+    }
+
+    fn row(&mut self) -> Result<Row, NativeError> {
+        self.parens(|p| {
+            p.keyword("Row")?;
+            Ok(Row {
+                attr: p.attr()?,
+                cells: p.list(Parser::cell)?,
+            })
+        })
+    }
+
+    fn cell(&mut self) -> Result<Cell, NativeError> {
+        self.parens(|p| {
+            p.keyword("Cell")?;
+            Ok(Cell {
+                attr: p.attr()?,
+                align: p.alignment()?,
+                row_span: p.newtype_int("RowSpan")?,
+                col_span: p.newtype_int("ColSpan")?,
+                content: p.blocks()?,
+            })
+        })
+    }
+
+    fn target(&mut self) -> Result<Target, NativeError> {
+        let (url, title) = self.pair(Parser::string, Parser::string)?;
+        Ok(Target { url, title })
+    }
+
+    fn inline(&mut self) -> Result<Inline, NativeError> {
+        self.parens(|p| {
+            let name = p.constructor("Inline", &INLINE_CONSTRUCTORS)?;
+            Ok(match name {
+                "Str" => Inline::Str(p.string()?),
+                "Emph" => Inline::Emph(p.inlines()?),
+                "Underline" => Inline::Underline(p.inlines()?),
+                "Strong" => Inline::Strong(p.inlines()?),
+                "Strikeout" => Inline::Strikeout(p.inlines()?),
+                "Superscript" => Inline::Superscript(p.inlines()?),
+                "Subscript" => Inline::Subscript(p.inlines()?),
+                "SmallCaps" => Inline::SmallCaps(p.inlines()?),
+                "Quoted" => {
+                    let quote = match p.constructor("QuoteType", &["SingleQuote", "DoubleQuote"])? {
+                        "SingleQuote" => QuoteType::SingleQuote,
+                        _ => QuoteType::DoubleQuote,
+                    };
+                    Inline::Quoted(quote, p.inlines()?)
+                }
+                "Cite" => Inline::Cite(p.list(Parser::citation)?, p.inlines()?),
+                "Code" => Inline::Code(p.attr()?, p.string()?),
+                "Space" => Inline::Space,
+                "SoftBreak" => Inline::SoftBreak,
+                "LineBreak" => Inline::LineBreak,
+                "Math" => {
+                    let math_type =
+                        match p.constructor("MathType", &["DisplayMath", "InlineMath"])? {
+                            "DisplayMath" => MathType::DisplayMath,
+                            _ => MathType::InlineMath,
+                        };
+                    Inline::Math(math_type, p.string()?)
+                }
+                "RawInline" => Inline::RawInline(p.format()?, p.string()?),
+                "Link" => Inline::Link(p.attr()?, p.inlines()?, p.target()?),
+                "Image" => Inline::Image(p.attr()?, p.inlines()?, p.target()?),
+                "Note" => Inline::Note(p.blocks()?),
+                _ => Inline::Span(p.attr()?, p.inlines()?),
+            })
+        })
+    }
+
+    fn citation(&mut self) -> Result<Citation, NativeError> {
+        self.parens(|p| {
+            p.keyword("Citation")?;
+            p.expect('{')?;
+            p.field("citationId")?;
+            let citation_id = p.string()?;
+            p.expect(',')?;
+            p.field("citationPrefix")?;
+            let citation_prefix = p.inlines()?;
+            p.expect(',')?;
+            p.field("citationSuffix")?;
+            let citation_suffix = p.inlines()?;
+            p.expect(',')?;
+            p.field("citationMode")?;
+            let citation_mode = match p.constructor(
+                "CitationMode",
+                &["AuthorInText", "SuppressAuthor", "NormalCitation"],
+            )? {
+                "AuthorInText" => CitationMode::AuthorInText,
+                "SuppressAuthor" => CitationMode::SuppressAuthor,
+                _ => CitationMode::NormalCitation,
+            };
+            p.expect(',')?;
+            p.field("citationNoteNum")?;
+            let citation_note_num = p.int()?;
+            p.expect(',')?;
+            p.field("citationHash")?;
+            let citation_hash = p.int()?;
+            p.expect('}')?;
+            Ok(Citation {
+                citation_id,
+                citation_prefix,
+                citation_suffix,
+                citation_mode,
+                citation_note_num,
+                citation_hash,
+            })
+        })
+    }
+}
+
+/// Parses the escape sequence after a backslash in a Haskell string,
+/// returning the character it stands for, if any, and its length.
+fn escape_sequence(s: &str) -> Option<(Option<char>, usize)> {
+    let c = s.chars().next()?;
+    let simple = match c {
+        'a' => Some('\x07'),
+        'b' => Some('\x08'),
+        'f' => Some('\x0c'),
+        'n' => Some('\n'),
+        'r' => Some('\r'),
+        't' => Some('\t'),
+        'v' => Some('\x0b'),
+        '\\' | '"' | '\'' => Some(c),
+        _ => None,
+    };
+    if let Some(c) = simple {
+        return Some((Some(c), 1));
+    }
+    let number = |digits: &str, radix: u32, prefix: usize| {
+        let len = digits
+            .find(|c: char| !c.is_digit(radix))
+            .unwrap_or(digits.len());
+        let code = u32::from_str_radix(&digits[..len], radix).ok()?;
+        Some((Some(char::from_u32(code)?), prefix + len))
+    };
+    match c {
+        // An empty escape, which separates a numeric escape from a digit.
+        '&' => Some((None, 1)),
+        '0'..='9' => number(s, 10, 0),
+        'x' => number(&s[1..], 16, 1),
+        'o' => number(&s[1..], 8, 1),
+        '^' => {
+            let c = s[1..].chars().next().filter(|c| ('@'..='_').contains(c))?;
+            Some((char::from_u32(c as u32 - '@' as u32), 2))
+        }
+        // A gap of whitespace between two backslashes, which is ignored.
+        c if c.is_whitespace() => {
+            let len = s.find(|c: char| !c.is_whitespace())?;
+            s[len..].starts_with('\\').then_some((None, len + 1))
+        }
+        _ => ASCII_ESCAPES
+            .iter()
+            .find(|(name, _)| s.starts_with(name))
+            .map(|(name, c)| (Some(*c), name.len())),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::builder::*;
+    use crate::writers::native::{blocks_to_native, write_native};
+    use crate::writers::WriterOptions;
+
+    #[test]
+    fn compact_syntax() {
+        let blocks = read_native_blocks(
+            r#"[Header 1 ("intro",["unnumbered"],[("k","v")]) [Str "Intro"],Para [Str "a",Space,Link ("",[],[]) [Str "b"] ("/url","")],RawBlock (Format "html") "<hr>"]"#,
+        )
+        .unwrap();
+        let attr = Attr {
+            identifier: "intro".into(),
+            classes: vec!["unnumbered".into()],
+            attributes: vec![("k".into(), "v".into())],
+        };
+        let expected = header_with(attr, 1, "Intro")
+            + para(text("a ") + link("/url", "", "b"))
+            + raw_block("html", "<hr>");
+        assert_eq!(blocks, expected.into_vec());
+    }
+
+    #[test]
+    fn pretty_syntax() {
+        let input = "[ OrderedList\n    ( -2 , Decimal , Period )\n    [ [ Plain [ Str \"a\" ] ] ]\n, Header (-1) ( \"\" , [] , [] ) []\n]\n";
+        let blocks = read_native_blocks(input).unwrap();
+        let attrs = ListAttributes {
+            start_number: -2,
+            style: ListNumberStyle::Decimal,
+            delim: ListNumberDelim::Period,
+        };
+        let expected = ordered_list_with(attrs, [plain("a")]) + header(-1, Inlines::new());
+        assert_eq!(blocks, expected.into_vec());
+
+        // The output of the writer reads back as the same blocks.
+        let blocks = para(text(&"word ".repeat(20)) + emph("x")).into_vec();
+        let output = blocks_to_native(&blocks, &WriterOptions::default());
+        assert_eq!(output.lines().count(), 44);
+        assert_eq!(read_native_blocks(&output).unwrap(), blocks);
+    }
+
+    #[test]
+    fn strings() {
+        let inlines = read_native_inlines(
+            r#"[Str "\8217\&9 \x41\o101 \SOH\SO\&H \^A\DEL \"\\\n", Str "a\   \b"]"#,
+        )
+        .unwrap();
+        assert_eq!(
+            inlines,
+            vec![
+                Inline::Str("’9 AA \x01\x0eH \x01\x7f \"\\\n".into()),
+                Inline::Str("ab".into()),
+            ]
+        );
+    }
+
+    #[test]
+    fn documents() {
+        let input = r#"Pandoc (Meta {unMeta = fromList [("title",MetaInlines [Str "T"]),("draft",MetaBool False)]}) [Table ("",[],[]) (Caption Nothing []) [(AlignLeft,ColWidth 5.0e-2),(AlignDefault,ColWidthDefault)] (TableHead ("",[],[]) []) [TableBody ("",[],[]) (RowHeadColumns 0) [] [Row ("",[],[]) [Cell ("",[],[]) AlignDefault (RowSpan 1) (ColSpan 2) [Plain [Math InlineMath "x"]]]]] (TableFoot ("",[],[]) [])]"#;
+        let doc = read_native(input).unwrap();
+        assert_eq!(doc.meta.0.len(), 2);
+        assert_eq!(doc.meta.0["draft"], MetaValue::MetaBool(false));
+        let Block::Table(table) = &doc.blocks[0] else {
+            panic!("expected a table");
+        };
+        assert_eq!(table.colspecs[0].1, ColWidth::ColWidth(0.05));
+        assert_eq!(table.bodies[0].body[0].cells[0].col_span, 2);
+        assert_eq!(
+            read_native(&write_native(&doc, &WriterOptions::default())).unwrap(),
+            doc
+        );
+    }
+
+    #[test]
+    fn fragments() {
+        assert_eq!(read_native("[]").unwrap(), Pandoc::default());
+        assert_eq!(read_native("Para [Str \"a\"]").unwrap(), doc(para("a")));
+        assert_eq!(read_native("[Str \"a\"]").unwrap(), doc(plain("a")));
+        assert_eq!(read_native("(Str \"a\")").unwrap(), doc(plain("a")));
+        assert_eq!(read_native_inlines("Space").unwrap(), vec![Inline::Space]);
+    }
+
+    #[test]
+    fn citations() {
+        let input = r#"[Cite [Citation {citationId = "doe", citationPrefix = [Str "see"], citationSuffix = [], citationMode = SuppressAuthor, citationNoteNum = -1, citationHash = 0}] [Str "[see -@doe]"]]"#;
+        let inlines = read_native_inlines(input).unwrap();
+        let Inline::Cite(citations, _) = &inlines[0] else {
+            panic!("expected a citation");
+        };
+        assert_eq!(citations[0].citation_id, "doe");
+        assert_eq!(citations[0].citation_mode, CitationMode::SuppressAuthor);
+        assert_eq!(citations[0].citation_note_num, -1);
+    }
+
+    #[test]
+    fn errors() {
+        let error = read_native_blocks("[Para [Str \"a\"],\n  Parra []]").unwrap_err();
+        assert_eq!((error.line(), error.column()), (2, 3));
+        assert_eq!(error.to_string(), "line 2, column 3: unknown Block `Parra`");
+
+        let error = read_native_inlines("[Str \"a\" Space]").unwrap_err();
+        assert_eq!(error.message(), "expected `]`");
+        assert_eq!(error.column(), 10);
+
+        let error = read_native_inlines("[Str \"a\\q\"]").unwrap_err();
+        assert_eq!(error.message(), "invalid escape sequence");
+
+        let error = read_native_blocks("[Null] x").unwrap_err();
+        assert_eq!(error.message(), "expected the end of the input");
+    }
+}
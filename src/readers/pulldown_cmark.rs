@@ -0,0 +1,706 @@
+//! A reader for the events of [pulldown-cmark], a CommonMark parser widely
+//! used in the Rust ecosystem. It is only available with the
+//! `pulldown-cmark` feature.
+//!
+//! The reader accepts any stream of events, so the parser can be configured
+//! with whichever [`Options`](pulldown_cmark::Options) are needed, and the
+//! events can be filtered before they are read. Events map onto the AST the
+//! way Pandoc reads the corresponding CommonMark extensions:
+//!
+//! - items of tight lists hold [`Block::Plain`] rather than paragraphs;
+//! - task list items start with `☐` or `☒`;
+//! - footnotes are placed where they are referenced, and definitions that
+//!   are never referenced are dropped;
+//! - GitHub alerts such as `> [!NOTE]` become divs with the class of the
+//!   alert, holding a div with the class `title` and then the content;
+//! - autolinks get the class `uri` or `email`, and wiki links the class
+//!   `wikilink`;
+//! - HTML becomes raw blocks and inlines in the `html` format, and metadata
+//!   blocks become raw blocks in the `yaml` or `toml` format.
+//!
+//! Ordered lists are always read as decimal numbers followed by a period,
+//! since the events don't record the delimiter.
+//!
+//! ```
+//! use pandoc_types::builder::*;
+//! use pandoc_types::readers::pulldown_cmark::read_events;
+//! use pulldown_cmark::{Options, Parser};
+//!
+//! let parser = Parser::new_ext("Some ~~struck~~ $x^2$ text", Options::all());
+//! assert_eq!(
+//!     read_events(parser).blocks,
+//!     para(text("Some ") + strikeout("struck") + space() + math("x^2") + text(" text")).into_vec()
+//! );
+//! ```
+//!
+//! [pulldown-cmark]: https://docs.rs/pulldown-cmark
+use std::collections::{HashMap, HashSet};
+use std::ops::Range;
+
+use pulldown_cmark::{
+    BlockQuoteKind, CodeBlockKind, CowStr, Event, LinkType, MetadataBlockKind, Tag,
+};
+
+use crate::definition::{
+    Alignment, Attr, Block, Caption, Cell, ColSpec, ColWidth, Format, Inline, ListAttributes,
+    ListNumberDelim, ListNumberStyle, MathType, Pandoc, Row, Table, TableBody, TableFoot,
+    TableHead, Target,
+};
+
+/// Reads a stream of pulldown-cmark events into a document.
+pub fn read_events<'a>(events: impl IntoIterator<Item = Event<'a>>) -> Pandoc {
+    let events: Vec<Event<'a>> = events.into_iter().collect();
+    let mut reader = Reader {
+        events: &events,
+        definitions: HashMap::new(),
+        skip: HashMap::new(),
+        active: HashSet::new(),
+    };
+    reader.find_definitions();
+    let blocks = reader.blocks(0..events.len());
+    Pandoc {
+        blocks,
+        meta: Default::default(),
+    }
+}
+
+/// A node of the document, produced when an event or an element ends and
+/// added to the element around it.
+#[allow(clippy::large_enum_variant)]
+enum Node {
+    Block(Block),
+    Inline(Inline),
+    /// Text of a code, HTML or metadata block.
+    Text(String),
+    Item(Vec<Block>),
+    Term(Vec<Inline>),
+    Definition(Vec<Block>),
+    Row {
+        head: bool,
+        cells: Vec<Cell>,
+    },
+    Cell(Vec<Inline>),
+}
+
+/// An element whose end hasn't been reached yet.
+struct Frame<'a> {
+    tag: Tag<'a>,
+    nodes: Vec<Node>,
+}
+
+impl Frame<'_> {
+    /// Returns whether text in the element is kept verbatim.
+    fn is_verbatim(&self) -> bool {
+        matches!(
+            self.tag,
+            Tag::CodeBlock(_) | Tag::HtmlBlock | Tag::MetadataBlock(_)
+        )
+    }
+}
+
+struct Reader<'e, 'a> {
+    events: &'e [Event<'a>],
+    /// The events between the start and end of each footnote definition,
+    /// by lowercased label.
+    definitions: HashMap<String, Range<usize>>,
+    /// The index of the end of each footnote definition, by the index of
+    /// its start.
+    skip: HashMap<usize, usize>,
+    /// The footnotes being read, to stop footnotes that refer to themselves.
+    active: HashSet<String>,
+}
+
+impl<'a> Reader<'_, 'a> {
+    fn find_definitions(&mut self) {
+        let mut open = Vec::new();
+        for (i, event) in self.events.iter().enumerate() {
+            match event {
+                Event::Start(Tag::FootnoteDefinition(label)) => open.push(Some((i, label))),
+                Event::Start(_) => open.push(None),
+                Event::End(_) => {
+                    if let Some(Some((start, label))) = open.pop() {
+                        self.skip.insert(start, i);
+                        self.definitions
+                            .entry(label.to_lowercase())
+                            .or_insert(start + 1..i);
+                    }
+                }
+                _ => {}
+            }
+        }
+    }
+
+    /// Reads the events in `range`, leaving out footnote definitions.
+    fn blocks(&mut self, range: Range<usize>) -> Vec<Block> {
+        let events = self.events;
+        let mut root = Vec::new();
+        let mut stack: Vec<Frame<'a>> = Vec::new();
+        let mut i = range.start;
+        while i < range.end {
+            match &events[i] {
+                Event::Start(Tag::FootnoteDefinition(_)) => {
+                    i = self.skip.get(&i).copied().unwrap_or(range.end);
+                }
+                Event::Start(tag) => stack.push(Frame {
+                    tag: tag.clone(),
+                    nodes: Vec::new(),
+                }),
+                // Ends without a start are ignored.
+                Event::End(_) => {
+                    if let Some(frame) = stack.pop() {
+                        let node = close(frame);
+                        stack
+                            .last_mut()
+                            .map_or(&mut root, |top| &mut top.nodes)
+                            .push(node);
+                    }
+                }
+                event => {
+                    let verbatim = stack.last().is_some_and(Frame::is_verbatim);
+                    let nodes = stack.last_mut().map_or(&mut root, |top| &mut top.nodes);
+                    self.event(event, verbatim, nodes);
+                }
+            }
+            i += 1;
+        }
+        // Elements that are still open end with the events.
+        while let Some(frame) = stack.pop() {
+            let node = close(frame);
+            stack
+                .last_mut()
+                .map_or(&mut root, |top| &mut top.nodes)
+                .push(node);
+        }
+        block_nodes(root)
+    }
+
+    /// Reads an event that doesn't start or end an element into the nodes
+    /// of the element around it.
+    fn event(&mut self, event: &Event<'a>, verbatim: bool, nodes: &mut Vec<Node>) {
+        let node = match event {
+            Event::Text(text) | Event::Html(text) if verbatim => Node::Text(text.to_string()),
+            Event::Text(text) => return push_text(nodes, text),
+            Event::Code(code) => Node::Inline(Inline::Code(Attr::default(), code.to_string())),
+            Event::InlineMath(tex) => {
+                Node::Inline(Inline::Math(MathType::InlineMath, tex.to_string()))
+            }
+            Event::DisplayMath(tex) => {
+                Node::Inline(Inline::Math(MathType::DisplayMath, tex.to_string()))
+            }
+            Event::Html(html) => Node::Block(Block::RawBlock(
+                Format("html".into()),
+                html.trim_end_matches('\n').to_owned(),
+            )),
+            Event::InlineHtml(html) => {
+                Node::Inline(Inline::RawInline(Format("html".into()), html.to_string()))
+            }
+            Event::FootnoteReference(label) => Node::Inline(self.note(label)),
+            Event::SoftBreak => Node::Inline(Inline::SoftBreak),
+            Event::HardBreak => Node::Inline(Inline::LineBreak),
+            Event::Rule => Node::Block(Block::HorizontalRule),
+            Event::TaskListMarker(checked) => {
+                let checkbox = if *checked { "☒" } else { "☐" };
+                nodes.push(Node::Inline(Inline::Str(checkbox.into())));
+                Node::Inline(Inline::Space)
+            }
+            Event::Start(_) | Event::End(_) => return,
+        };
+        nodes.push(node);
+    }
+
+    /// Reads the footnote with the given label, or returns the reference as
+    /// text if there is no such footnote.
+    fn note(&mut self, label: &CowStr<'a>) -> Inline {
+        let key = label.to_lowercase();
+        match self.definitions.get(&key).cloned() {
+            Some(range) if self.active.insert(key.clone()) => {
+                let blocks = self.blocks(range);
+                self.active.remove(&key);
+                Inline::Note(blocks)
+            }
+            _ => Inline::Str(format!("[^{}]", label)),
+        }
+    }
+}
+
+/// Adds text to the inlines of an element, splitting it into words, spaces
+/// and soft breaks, and joining it to the word before it.
+fn push_text(nodes: &mut Vec<Node>, text: &str) {
+    for c in text.chars() {
+        let inline = match c {
+            ' ' | '\t' => Inline::Space,
+            '\n' => Inline::SoftBreak,
+            c => {
+                match nodes.last_mut() {
+                    Some(Node::Inline(Inline::Str(word))) => word.push(c),
+                    _ => nodes.push(Node::Inline(Inline::Str(c.into()))),
+                }
+                continue;
+            }
+        };
+        match nodes.last_mut() {
+            Some(Node::Inline(last @ Inline::Space)) if inline == Inline::SoftBreak => {
+                *last = inline;
+            }
+            Some(Node::Inline(Inline::Space | Inline::SoftBreak)) => {}
+            _ => nodes.push(Node::Inline(inline)),
+        }
+    }
+}
+
+/// Collects the blocks among `nodes`, putting runs of inlines into
+/// [`Block::Plain`].
+fn block_nodes(nodes: Vec<Node>) -> Vec<Block> {
+    let mut blocks = Vec::new();
+    let mut inlines = Vec::new();
+    for node in nodes {
+        match node {
+            Node::Inline(inline) => inlines.push(inline),
+            Node::Block(block) => {
+                if !inlines.is_empty() {
+                    blocks.push(Block::Plain(std::mem::take(&mut inlines)));
+                }
+                blocks.push(block);
+            }
+            _ => {}
+        }
+    }
+    if !inlines.is_empty() {
+        blocks.push(Block::Plain(inlines));
+    }
+    blocks
+}
+
+fn inline_nodes(nodes: Vec<Node>) -> Vec<Inline> {
+    let mut inlines: Vec<Inline> = nodes
+        .into_iter()
+        .filter_map(|node| match node {
+            Node::Inline(inline) => Some(inline),
+            _ => None,
+        })
+        .collect();
+    while matches!(inlines.last(), Some(Inline::Space | Inline::SoftBreak)) {
+        inlines.pop();
+    }
+    inlines
+}
+
+fn verbatim(nodes: Vec<Node>) -> String {
+    let mut text = String::new();
+    for node in nodes {
+        if let Node::Text(s) = node {
+            text.push_str(&s);
+        }
+    }
+    if text.ends_with('\n') {
+        text.pop();
+    }
+    text
+}
+
+/// Turns an element into a node once its end is reached.
+fn close(frame: Frame<'_>) -> Node {
+    let nodes = frame.nodes;
+    let block = match frame.tag {
+        Tag::Paragraph => Block::Para(inline_nodes(nodes)),
+        Tag::Heading {
+            level,
+            id,
+            classes,
+            attrs,
+        } => {
+            let attr = Attr {
+                identifier: id.map(|id| id.to_string()).unwrap_or_default(),
+                classes: classes.iter().map(|class| class.to_string()).collect(),
+                attributes: attrs
+                    .iter()
+                    .map(|(key, value)| {
+                        let value = value.as_ref().map(|value| value.to_string());
+                        (key.to_string(), value.unwrap_or_default())
+                    })
+                    .collect(),
+            };
+            Block::Header(level as i32, attr, inline_nodes(nodes))
+        }
+        Tag::BlockQuote(None) => Block::BlockQuote(block_nodes(nodes)),
+        Tag::BlockQuote(Some(kind)) => {
+            let (class, title) = match kind {
+                BlockQuoteKind::Note => ("note", "Note"),
+                BlockQuoteKind::Tip => ("tip", "Tip"),
+                BlockQuoteKind::Important => ("important", "Important"),
+                BlockQuoteKind::Warning => ("warning", "Warning"),
+                BlockQuoteKind::Caution => ("caution", "Caution"),
+            };
+            let title = Block::Div(
+                Attr {
+                    classes: vec!["title".into()],
+                    ..Attr::default()
+                },
+                vec![Block::Para(vec![Inline::Str(title.into())])],
+            );
+            let attr = Attr {
+                classes: vec![class.into()],
+                ..Attr::default()
+            };
+            Block::Div(attr, [vec![title], block_nodes(nodes)].concat())
+        }
+        Tag::CodeBlock(kind) => {
+            let classes = match kind {
+                CodeBlockKind::Fenced(info) => info
+                    .split_whitespace()
+                    .next()
+                    .map(|lang| vec![lang.to_owned()])
+                    .unwrap_or_default(),
+                CodeBlockKind::Indented => Vec::new(),
+            };
+            let attr = Attr {
+                classes,
+                ..Attr::default()
+            };
+            Block::CodeBlock(attr, verbatim(nodes))
+        }
+        Tag::HtmlBlock => Block::RawBlock(Format("html".into()), verbatim(nodes)),
+        Tag::MetadataBlock(kind) => {
+            let format = match kind {
+                MetadataBlockKind::YamlStyle => "yaml",
+                MetadataBlockKind::PlusesStyle => "toml",
+            };
+            Block::RawBlock(Format(format.into()), verbatim(nodes))
+        }
+        Tag::List(start) => {
+            let items = nodes
+                .into_iter()
+                .filter_map(|node| match node {
+                    Node::Item(blocks) => Some(blocks),
+                    _ => None,
+                })
+                .collect();
+            match start {
+                Some(start) => {
+                    let attrs = ListAttributes {
+                        start_number: i32::try_from(start).unwrap_or(i32::MAX),
+                        style: ListNumberStyle::Decimal,
+                        delim: ListNumberDelim::Period,
+                    };
+                    Block::OrderedList(attrs, items)
+                }
+                None => Block::BulletList(items),
+            }
+        }
+        Tag::Item => return Node::Item(block_nodes(nodes)),
+        // Definitions are read where they are referenced.
+        Tag::FootnoteDefinition(_) => Block::Null,
+        Tag::DefinitionList => {
+            let mut items: Vec<(Vec<Inline>, Vec<Vec<Block>>)> = Vec::new();
+            for node in nodes {
+                match node {
+                    Node::Term(term) => items.push((term, Vec::new())),
+                    Node::Definition(blocks) => match items.last_mut() {
+                        Some((_, definitions)) => definitions.push(blocks),
+                        None => items.push((Vec::new(), vec![blocks])),
+                    },
+                    _ => {}
+                }
+            }
+            Block::DefinitionList(items)
+        }
+        Tag::DefinitionListTitle => return Node::Term(inline_nodes(nodes)),
+        Tag::DefinitionListDefinition => return Node::Definition(block_nodes(nodes)),
+        Tag::Table(alignments) => table(&alignments, nodes),
+        Tag::TableHead | Tag::TableRow => {
+            let cells = nodes
+                .into_iter()
+                .filter_map(|node| match node {
+                    Node::Cell(inlines) => Some(Cell {
+                        content: match inlines.is_empty() {
+                            true => Vec::new(),
+                            false => vec![Block::Plain(inlines)],
+                        },
+                        ..Cell::default()
+                    }),
+                    _ => None,
+                })
+                .collect();
+            let head = matches!(frame.tag, Tag::TableHead);
+            return Node::Row { head, cells };
+        }
+        Tag::TableCell => return Node::Cell(inline_nodes(nodes)),
+        Tag::Emphasis => return Node::Inline(Inline::Emph(inline_nodes(nodes))),
+        Tag::Strong => return Node::Inline(Inline::Strong(inline_nodes(nodes))),
+        Tag::Strikethrough => return Node::Inline(Inline::Strikeout(inline_nodes(nodes))),
+        Tag::Superscript => return Node::Inline(Inline::Superscript(inline_nodes(nodes))),
+        Tag::Subscript => return Node::Inline(Inline::Subscript(inline_nodes(nodes))),
+        Tag::Link {
+            link_type,
+            dest_url,
+            title,
+            ..
+        } => {
+            let (class, url) = match link_type {
+                LinkType::Autolink => (Some("uri"), dest_url.to_string()),
+                LinkType::Email => (Some("email"), format!("mailto:{}", dest_url)),
+                LinkType::WikiLink { .. } => (Some("wikilink"), dest_url.to_string()),
+                _ => (None, dest_url.to_string()),
+            };
+            let attr = Attr {
+                classes: class.into_iter().map(str::to_owned).collect(),
+                ..Attr::default()
+            };
+            let target = Target {
+                url,
+                title: title.to_string(),
+            };
+            return Node::Inline(Inline::Link(attr, inline_nodes(nodes), target));
+        }
+        Tag::Image {
+            dest_url, title, ..
+        } => {
+            let target = Target {
+                url: dest_url.to_string(),
+                title: title.to_string(),
+            };
+            return Node::Inline(Inline::Image(Attr::default(), inline_nodes(nodes), target));
+        }
+    };
+    Node::Block(block)
+}
+
+fn table(alignments: &[pulldown_cmark::Alignment], nodes: Vec<Node>) -> Block {
+    let mut head = Vec::new();
+    let mut body = Vec::new();
+    for node in nodes {
+        if let Node::Row {
+            head: is_head,
+            cells,
+        } = node
+        {
+            let row = Row {
+                attr: Attr::default(),
+                cells,
+            };
+            match is_head {
+                true => head.push(row),
+                false => body.push(row),
+            }
+        }
+    }
+    let colspecs = alignments
+        .iter()
+        .map(|alignment| {
+            let alignment = match alignment {
+                pulldown_cmark::Alignment::None => Alignment::AlignDefault,
+                pulldown_cmark::Alignment::Left => Alignment::AlignLeft,
+                pulldown_cmark::Alignment::Center => Alignment::AlignCenter,
+                pulldown_cmark::Alignment::Right => Alignment::AlignRight,
+            };
+            ColSpec(alignment, ColWidth::ColWidthDefault)
+        })
+        .collect();
+    Block::Table(Table {
+        attr: Attr::default(),
+        caption: Caption::default(),
+        colspecs,
+        head: TableHead {
+            attr: Attr::default(),
+            rows: head,
+        },
+        bodies: match body.is_empty() {
+            true => Vec::new(),
+            false => vec![TableBody {
+                attr: Attr::default(),
+                row_head_columns: 0,
+                head: Vec::new(),
+                body,
+            }],
+        },
+        foot: TableFoot::default(),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::builder::*;
+    use pulldown_cmark::{Options, Parser};
+
+    fn read(input: &str) -> Vec<Block> {
+        let options = (Options::all() - Options::ENABLE_OLD_FOOTNOTES) | Options::ENABLE_FOOTNOTES;
+        read_events(Parser::new_ext(input, options)).blocks
+    }
+
+    fn attr(id: &str, classes: &[&str], attributes: &[(&str, &str)]) -> Attr {
+        Attr {
+            identifier: id.into(),
+            classes: classes.iter().map(|&class| class.into()).collect(),
+            attributes: attributes
+                .iter()
+                .map(|&(key, value)| (key.into(), value.into()))
+                .collect(),
+        }
+    }
+
+    #[test]
+    fn blocks() {
+        assert_eq!(
+            read("# Title {#top .big}\n\n> quoted\n\n***\n\n```rust ignore\nlet x;\n```\n\n    indented\n\n<div>\nhi\n</div>\n"),
+            (header_with(attr("top", &["big"], &[]), 1, "Title")
+                + block_quote(para("quoted"))
+                + horizontal_rule()
+                + code_block_with(attr("", &["rust"], &[]), "let x;")
+                + code_block_with(Attr::default(), "indented")
+                + raw_block("html", "<div>\nhi\n</div>"))
+            .into_vec()
+        );
+    }
+
+    #[test]
+    fn lists() {
+        let ordered = |start, items| {
+            let attrs = ListAttributes {
+                start_number: start,
+                style: ListNumberStyle::Decimal,
+                delim: ListNumberDelim::Period,
+            };
+            ordered_list_with(attrs, items)
+        };
+        assert_eq!(
+            read("- a\n- b\n\n3. c\n\n4. d\n"),
+            (bullet_list([plain("a"), plain("b")]) + ordered(3, [para("c"), para("d")])).into_vec()
+        );
+        assert_eq!(
+            read("- [ ] to do\n- [x] done\n"),
+            bullet_list([plain("☐ to do"), plain("☒ done")]).into_vec()
+        );
+        assert_eq!(
+            read("term\n: definition\n"),
+            definition_list([(text("term"), vec![plain("definition")])]).into_vec()
+        );
+    }
+
+    #[test]
+    fn tables() {
+        let expected = TableBuilder::new()
+            .alignments([Alignment::AlignDefault, Alignment::AlignRight])
+            .header([plain("a"), plain("b")])
+            .body([vec![plain(emph("c")), Blocks::default()]])
+            .build();
+        assert_eq!(
+            read("| a | b |\n|---|--:|\n| *c* | |\n"),
+            vec![Block::Table(expected)]
+        );
+    }
+
+    #[test]
+    fn inlines() {
+        assert_eq!(
+            read("*a* **b** ~~c~~ `d` $e$ $$f$$ <b>g</b> h\\\ni\nj"),
+            para(
+                emph("a")
+                    + space()
+                    + strong("b")
+                    + space()
+                    + strikeout("c")
+                    + space()
+                    + code("d")
+                    + space()
+                    + math("e")
+                    + space()
+                    + display_math("f")
+                    + space()
+                    + raw_inline("html", "<b>")
+                    + str("g")
+                    + raw_inline("html", "</b>")
+                    + text(" h")
+                    + line_break()
+                    + str("i")
+                    + soft_break()
+                    + str("j")
+            )
+            .into_vec()
+        );
+        assert_eq!(read("a&amp;b\\*c"), para(str("a&b*c")).into_vec());
+    }
+
+    #[test]
+    fn links() {
+        assert_eq!(
+            read("[a](/u \"t\") ![b](i.png) <http://x.org> <me@x.org>"),
+            para(
+                link_with(Attr::default(), "/u", "t", "a")
+                    + space()
+                    + image("i.png", "", "b")
+                    + space()
+                    + link_with(attr("", &["uri"], &[]), "http://x.org", "", "http://x.org")
+                    + space()
+                    + link_with(attr("", &["email"], &[]), "mailto:me@x.org", "", "me@x.org")
+            )
+            .into_vec()
+        );
+    }
+
+    #[test]
+    fn footnotes() {
+        assert_eq!(
+            read("a[^1] b[^n]\n\n[^1]: one\n\n[^n]: two\n\n    more\n\n[^unused]: three\n"),
+            para(
+                str("a")
+                    + note(para("one"))
+                    + space()
+                    + str("b")
+                    + note(para("two") + para("more"))
+            )
+            .into_vec()
+        );
+
+        // Definitions that refer to themselves, and references without a
+        // definition, are read as text.
+        let events = [
+            Event::Start(Tag::Paragraph),
+            Event::FootnoteReference("x".into()),
+            Event::FootnoteReference("y".into()),
+            Event::End(pulldown_cmark::TagEnd::Paragraph),
+            Event::Start(Tag::FootnoteDefinition("X".into())),
+            Event::FootnoteReference("x".into()),
+            Event::End(pulldown_cmark::TagEnd::FootnoteDefinition),
+        ];
+        assert_eq!(
+            read_events(events).blocks,
+            para(note(plain("[^x]")) + str("[^y]")).into_vec()
+        );
+    }
+
+    #[test]
+    fn extensions() {
+        assert_eq!(
+            read("---\ntitle: x\n---\n\n> [!WARNING]\n> careful\n"),
+            (raw_block("yaml", "title: x")
+                + div_with(
+                    attr("", &["warning"], &[]),
+                    div_with(attr("", &["title"], &[]), para("Warning")) + para("careful")
+                ))
+            .into_vec()
+        );
+        assert_eq!(
+            read("~a~ ^b^ [[Page]]"),
+            para(
+                subscript("a")
+                    + space()
+                    + superscript("b")
+                    + space()
+                    + link_with(attr("", &["wikilink"], &[]), "Page", "", "Page")
+            )
+            .into_vec()
+        );
+    }
+
+    #[test]
+    fn unbalanced() {
+        let events = [
+            Event::End(pulldown_cmark::TagEnd::Emphasis),
+            Event::Start(Tag::Paragraph),
+            Event::Start(Tag::Emphasis),
+            Event::Text("a".into()),
+        ];
+        assert_eq!(read_events(events).blocks, para(emph("a")).into_vec());
+    }
+}
@@ -0,0 +1,1227 @@
+//! This module contains a port of [Text.Pandoc.Walk] to Rust.
+//!
+//! Unlike [`IterBlocks`](crate::definition::IterBlocks) and
+//! [`IterInlines`](crate::definition::IterInlines), which only yield the
+//! immediately contained children, a walk visits every element of the
+//! requested type anywhere inside a value, including inside metadata, table
+//! cells, figure and table captions, footnotes and citations.
+//!
+//! As in Haskell, walks are bottom-up: the children of an element are
+//! transformed before the element itself is passed to the function. Queries
+//! are top-down, visiting an element before its children.
+//!
+//! ```
+//! use pandoc_types::definition::{Block, Inline, Pandoc};
+//! use pandoc_types::walk::Walkable;
+//!
+//! let doc = Pandoc {
+//!     blocks: vec![Block::Para(vec![Inline::Emph(vec![Inline::Str("hi".into())])])],
+//!     ..Default::default()
+//! };
+//!
+//! let doc = doc.walk(|inline: Inline| match inline {
+//!     Inline::Str(s) => Inline::Str(s.to_uppercase()),
+//!     other => other,
+//! });
+//!
+//! assert_eq!(
+//!     doc.blocks,
+//!     vec![Block::Para(vec![Inline::Emph(vec![Inline::Str("HI".into())])])]
+//! );
+//! ```
+//!
+//! [Text.Pandoc.Walk]: https://hackage.haskell.org/package/pandoc-types/docs/Text-Pandoc-Walk.html
+use std::convert::Infallible;
+use std::ops::ControlFlow;
+
+use crate::definition::{
+    Block, Caption, Cell, Citation, CitationMode, Inline, MetaValue, Pandoc, Row, Table, TableBody,
+    TableFoot, TableHead,
+};
+
+use private::{Order, Sealed};
+pub use private::{Traverse, WalkTarget};
+
+/// A trait for values that contain elements of type `T` somewhere inside them.
+///
+/// `T` can be any of [`Inline`], [`Block`], `Vec<Inline>`, `Vec<Block>`,
+/// [`MetaValue`] or [`Citation`]. Walking over `Vec<Inline>` or `Vec<Block>`
+/// passes every list of inlines or blocks to the function, which makes it
+/// possible to replace one element with several, or to look at neighbouring
+/// elements.
+pub trait Walkable<T> {
+    /// Applies a transformation to every `T` contained in `self`, bottom-up.
+    ///
+    /// This is the equivalent of Haskell's `walk`.
+    fn walk<F>(self, f: F) -> Self
+    where
+        F: FnMut(T) -> T,
+        Self: Sized;
+
+    /// Applies a fallible transformation to every `T` contained in `self`,
+    /// bottom-up, stopping at the first error.
+    ///
+    /// This is the equivalent of Haskell's `walkM` in the `Either` monad.
+    /// Other effects can be expressed through the state captured by the
+    /// closure.
+    fn walk_m<E, F>(self, f: F) -> Result<Self, E>
+    where
+        F: FnMut(T) -> Result<T, E>,
+        Self: Sized;
+
+    /// Runs a query on every `T` contained in `self`, collecting the results.
+    ///
+    /// This is the equivalent of Haskell's `query`, and like it visits each
+    /// element before the elements inside it. The function can return
+    /// any [`IntoIterator`] (such as an [`Option`] or a [`Vec`]) and the
+    /// results are gathered into any collection that can be extended with
+    /// its items.
+    ///
+    /// ```
+    /// use pandoc_types::definition::*;
+    /// use pandoc_types::walk::*;
+    ///
+    /// let block = Block::Para(vec![
+    ///     Inline::Link(
+    ///         Default::default(),
+    ///         vec![Inline::Str("a".into())],
+    ///         Target { url: "https://example.com".into(), title: "".into() },
+    ///     ),
+    /// ]);
+    ///
+    /// let urls: Vec<String> = block.query(|inline: &Inline| match inline {
+    ///     Inline::Link(_, _, target) => Some(target.url.clone()),
+    ///     _ => None,
+    /// });
+    /// assert_eq!(urls, vec!["https://example.com".to_owned()]);
+    /// ```
+    fn query<C, I, F>(&self, f: F) -> C
+    where
+        F: FnMut(&T) -> I,
+        I: IntoIterator,
+        C: Default + Extend<I::Item>;
+
+    /// Calls `f` on every `T` contained in `self`, bottom-up, allowing each
+    /// one to be modified in place. The walk stops as soon as `f` returns
+    /// [`ControlFlow::Break`], and the break value is returned.
+    ///
+    /// ```
+    /// use std::ops::ControlFlow;
+    ///
+    /// use pandoc_types::definition::{Block, Inline};
+    /// use pandoc_types::walk::Walkable;
+    ///
+    /// let mut blocks = vec![
+    ///     Block::Para(vec![Inline::Str("a".into())]),
+    ///     Block::Para(vec![Inline::Str("stop".into()), Inline::Str("b".into())]),
+    /// ];
+    ///
+    /// let flow = blocks.walk_mut(|inline: &mut Inline| match inline {
+    ///     Inline::Str(s) if s == "stop" => ControlFlow::Break(()),
+    ///     Inline::Str(s) => {
+    ///         *s = s.to_uppercase();
+    ///         ControlFlow::Continue(())
+    ///     }
+    ///     _ => ControlFlow::Continue(()),
+    /// });
+    ///
+    /// assert_eq!(flow, ControlFlow::Break(()));
+    /// assert_eq!(
+    ///     blocks,
+    ///     vec![
+    ///         Block::Para(vec![Inline::Str("A".into())]),
+    ///         Block::Para(vec![Inline::Str("stop".into()), Inline::Str("b".into())]),
+    ///     ]
+    /// );
+    /// ```
+    fn walk_mut<B, F>(&mut self, f: F) -> ControlFlow<B>
+    where
+        F: FnMut(&mut T) -> ControlFlow<B>;
+
+    /// Calls `f` on every `T` contained in `self`, bottom-up. The walk stops
+    /// as soon as `f` returns [`ControlFlow::Break`], and the break value is
+    /// returned.
+    ///
+    /// This is useful to search a document without visiting all of it.
+    ///
+    /// ```
+    /// use std::ops::ControlFlow;
+    ///
+    /// use pandoc_types::definition::{Block, Inline};
+    /// use pandoc_types::walk::Walkable;
+    ///
+    /// let block = Block::BlockQuote(vec![Block::Header(
+    ///     1,
+    ///     Default::default(),
+    ///     vec![Inline::Str("title".into())],
+    /// )]);
+    ///
+    /// let found = block.walk_ref(|block: &Block| match block {
+    ///     Block::Header(level, _, _) => ControlFlow::Break(*level),
+    ///     _ => ControlFlow::Continue(()),
+    /// });
+    /// assert_eq!(found, ControlFlow::Break(1));
+    /// ```
+    fn walk_ref<B, F>(&self, f: F) -> ControlFlow<B>
+    where
+        F: FnMut(&T) -> ControlFlow<B>;
+
+    /// Calls a fallible function on every `T` contained in `self`, bottom-up,
+    /// allowing each one to be modified in place. The walk stops at the
+    /// first error, which is returned.
+    ///
+    /// Unlike [`Walkable::walk_m`], the elements are modified in place, so
+    /// the changes made before the error remain visible.
+    ///
+    /// ```
+    /// use pandoc_types::definition::{Block, Format, Inline};
+    /// use pandoc_types::walk::Walkable;
+    ///
+    /// let mut block = Block::Para(vec![Inline::RawInline(
+    ///     Format("include".into()),
+    ///     "missing.md".into(),
+    /// )]);
+    ///
+    /// let result = block.try_walk(|inline: &mut Inline| match inline {
+    ///     Inline::RawInline(Format(format), path) if format == "include" => {
+    ///         Err(format!("could not include {}", path))
+    ///     }
+    ///     _ => Ok(()),
+    /// });
+    /// assert_eq!(result, Err("could not include missing.md".to_owned()));
+    /// ```
+    fn try_walk<E, F>(&mut self, f: F) -> Result<(), E>
+    where
+        F: FnMut(&mut T) -> Result<(), E>;
+}
+
+impl<T, W> Walkable<T> for W
+where
+    T: WalkTarget,
+    W: Traverse,
+{
+    fn walk<F>(self, mut f: F) -> Self
+    where
+        F: FnMut(T) -> T,
+    {
+        match self.walk_m(|x| Ok::<_, Infallible>(f(x))) {
+            Ok(value) => value,
+            Err(never) => match never {},
+        }
+    }
+
+    fn walk_m<E, F>(mut self, mut f: F) -> Result<Self, E>
+    where
+        F: FnMut(T) -> Result<T, E>,
+    {
+        let flow = self.traverse_mut(&mut |x: &mut T| {
+            let value = std::mem::replace(x, T::placeholder());
+            match f(value) {
+                Ok(value) => {
+                    *x = value;
+                    ControlFlow::Continue(())
+                }
+                Err(err) => ControlFlow::Break(err),
+            }
+        });
+        match flow {
+            ControlFlow::Continue(()) => Ok(self),
+            ControlFlow::Break(err) => Err(err),
+        }
+    }
+
+    fn query<C, I, F>(&self, mut f: F) -> C
+    where
+        F: FnMut(&T) -> I,
+        I: IntoIterator,
+        C: Default + Extend<I::Item>,
+    {
+        let mut result = C::default();
+        let _ = self.traverse(Order::TopDown, &mut |x: &T| -> ControlFlow<Infallible> {
+            result.extend(f(x));
+            ControlFlow::Continue(())
+        });
+        result
+    }
+
+    fn walk_mut<B, F>(&mut self, mut f: F) -> ControlFlow<B>
+    where
+        F: FnMut(&mut T) -> ControlFlow<B>,
+    {
+        self.traverse_mut(&mut f)
+    }
+
+    fn walk_ref<B, F>(&self, mut f: F) -> ControlFlow<B>
+    where
+        F: FnMut(&T) -> ControlFlow<B>,
+    {
+        self.traverse(Order::BottomUp, &mut f)
+    }
+
+    fn try_walk<E, F>(&mut self, mut f: F) -> Result<(), E>
+    where
+        F: FnMut(&mut T) -> Result<(), E>,
+    {
+        let flow = self.traverse_mut(&mut |x: &mut T| match f(x) {
+            Ok(()) => ControlFlow::Continue(()),
+            Err(err) => ControlFlow::Break(err),
+        });
+        match flow {
+            ControlFlow::Continue(()) => Ok(()),
+            ControlFlow::Break(err) => Err(err),
+        }
+    }
+}
+
+mod private {
+    use std::ops::ControlFlow;
+
+    use crate::definition::{Block, Citation, Inline, MetaValue};
+
+    /// A supertrait that can't be named outside of this crate, so that
+    /// [`WalkTarget`] and [`Traverse`] can't be implemented there.
+    pub trait Sealed {}
+
+    /// The types of elements that can be visited by a walk.
+    ///
+    /// This trait is sealed and cannot be implemented outside of this crate.
+    pub trait WalkTarget: Sized + Sealed {
+        /// A cheap value used while an element is moved out of the tree.
+        fn placeholder() -> Self;
+
+        fn from_inline(_: &Inline) -> Option<&Self> {
+            None
+        }
+
+        fn from_inline_mut(_: &mut Inline) -> Option<&mut Self> {
+            None
+        }
+
+        fn from_inlines(_: &Vec<Inline>) -> Option<&Self> {
+            None
+        }
+
+        fn from_inlines_mut(_: &mut Vec<Inline>) -> Option<&mut Self> {
+            None
+        }
+
+        fn from_block(_: &Block) -> Option<&Self> {
+            None
+        }
+
+        fn from_block_mut(_: &mut Block) -> Option<&mut Self> {
+            None
+        }
+
+        fn from_blocks(_: &Vec<Block>) -> Option<&Self> {
+            None
+        }
+
+        fn from_blocks_mut(_: &mut Vec<Block>) -> Option<&mut Self> {
+            None
+        }
+
+        fn from_meta_value(_: &MetaValue) -> Option<&Self> {
+            None
+        }
+
+        fn from_meta_value_mut(_: &mut MetaValue) -> Option<&mut Self> {
+            None
+        }
+
+        fn from_citation(_: &Citation) -> Option<&Self> {
+            None
+        }
+
+        fn from_citation_mut(_: &mut Citation) -> Option<&mut Self> {
+            None
+        }
+    }
+
+    /// Whether a traversal visits an element before or after its children.
+    #[derive(Clone, Copy, PartialEq, Eq, Debug)]
+    pub enum Order {
+        TopDown,
+        BottomUp,
+    }
+
+    /// The types of values that can be walked.
+    ///
+    /// This trait is sealed and cannot be implemented outside of this crate.
+    pub trait Traverse: Sealed {
+        /// Calls `f` on every `T` inside `self`, in the given order.
+        fn traverse<T, B, F>(&self, order: Order, f: &mut F) -> ControlFlow<B>
+        where
+            T: WalkTarget,
+            F: FnMut(&T) -> ControlFlow<B>;
+
+        /// Calls `f` on every `T` inside `self`, bottom-up, allowing each one to be modified.
+        fn traverse_mut<T, B, F>(&mut self, f: &mut F) -> ControlFlow<B>
+        where
+            T: WalkTarget,
+            F: FnMut(&mut T) -> ControlFlow<B>;
+    }
+}
+
+impl Sealed for Pandoc {}
+impl Sealed for MetaValue {}
+impl Sealed for Block {}
+impl Sealed for Inline {}
+impl Sealed for Citation {}
+impl Sealed for Table {}
+impl Sealed for Caption {}
+impl Sealed for TableHead {}
+impl Sealed for TableBody {}
+impl Sealed for TableFoot {}
+impl Sealed for Row {}
+impl Sealed for Cell {}
+impl Sealed for Vec<Inline> {}
+impl Sealed for Vec<Block> {}
+
+impl WalkTarget for Inline {
+    fn placeholder() -> Self {
+        Inline::Space
+    }
+
+    fn from_inline(x: &Inline) -> Option<&Self> {
+        Some(x)
+    }
+
+    fn from_inline_mut(x: &mut Inline) -> Option<&mut Self> {
+        Some(x)
+    }
+}
+
+impl WalkTarget for Vec<Inline> {
+    fn placeholder() -> Self {
+        Vec::new()
+    }
+
+    fn from_inlines(x: &Vec<Inline>) -> Option<&Self> {
+        Some(x)
+    }
+
+    fn from_inlines_mut(x: &mut Vec<Inline>) -> Option<&mut Self> {
+        Some(x)
+    }
+}
+
+impl WalkTarget for Block {
+    fn placeholder() -> Self {
+        Block::Null
+    }
+
+    fn from_block(x: &Block) -> Option<&Self> {
+        Some(x)
+    }
+
+    fn from_block_mut(x: &mut Block) -> Option<&mut Self> {
+        Some(x)
+    }
+}
+
+impl WalkTarget for Vec<Block> {
+    fn placeholder() -> Self {
+        Vec::new()
+    }
+
+    fn from_blocks(x: &Vec<Block>) -> Option<&Self> {
+        Some(x)
+    }
+
+    fn from_blocks_mut(x: &mut Vec<Block>) -> Option<&mut Self> {
+        Some(x)
+    }
+}
+
+impl WalkTarget for MetaValue {
+    fn placeholder() -> Self {
+        MetaValue::MetaBool(false)
+    }
+
+    fn from_meta_value(x: &MetaValue) -> Option<&Self> {
+        Some(x)
+    }
+
+    fn from_meta_value_mut(x: &mut MetaValue) -> Option<&mut Self> {
+        Some(x)
+    }
+}
+
+impl WalkTarget for Citation {
+    fn placeholder() -> Self {
+        Citation {
+            citation_id: String::new(),
+            citation_prefix: Vec::new(),
+            citation_suffix: Vec::new(),
+            citation_mode: CitationMode::NormalCitation,
+            citation_note_num: 0,
+            citation_hash: 0,
+        }
+    }
+
+    fn from_citation(x: &Citation) -> Option<&Self> {
+        Some(x)
+    }
+
+    fn from_citation_mut(x: &mut Citation) -> Option<&mut Self> {
+        Some(x)
+    }
+}
+
+/// Calls `f` on `x` if `x` is the target type, otherwise continues.
+fn visit<'a, S, T, B, F>(x: &'a S, cast: fn(&'a S) -> Option<&'a T>, f: &mut F) -> ControlFlow<B>
+where
+    F: FnMut(&T) -> ControlFlow<B>,
+{
+    match cast(x) {
+        Some(x) => f(x),
+        None => ControlFlow::Continue(()),
+    }
+}
+
+/// Calls `f` on `x` if `x` is the target type, otherwise continues.
+fn visit_mut<'a, S, T, B, F>(
+    x: &'a mut S,
+    cast: fn(&'a mut S) -> Option<&'a mut T>,
+    f: &mut F,
+) -> ControlFlow<B>
+where
+    F: FnMut(&mut T) -> ControlFlow<B>,
+{
+    match cast(x) {
+        Some(x) => f(x),
+        None => ControlFlow::Continue(()),
+    }
+}
+
+impl Traverse for Pandoc {
+    fn traverse<T, B, F>(&self, order: Order, f: &mut F) -> ControlFlow<B>
+    where
+        T: WalkTarget,
+        F: FnMut(&T) -> ControlFlow<B>,
+    {
+        for value in self.meta.values() {
+            value.traverse(order, f)?;
+        }
+        self.blocks.traverse(order, f)
+    }
+
+    fn traverse_mut<T, B, F>(&mut self, f: &mut F) -> ControlFlow<B>
+    where
+        T: WalkTarget,
+        F: FnMut(&mut T) -> ControlFlow<B>,
+    {
+        for value in self.meta.values_mut() {
+            value.traverse_mut(f)?;
+        }
+        self.blocks.traverse_mut(f)
+    }
+}
+
+impl Traverse for MetaValue {
+    fn traverse<T, B, F>(&self, order: Order, f: &mut F) -> ControlFlow<B>
+    where
+        T: WalkTarget,
+        F: FnMut(&T) -> ControlFlow<B>,
+    {
+        if order == Order::TopDown {
+            visit(self, T::from_meta_value, f)?;
+        }
+        match self {
+            MetaValue::MetaMap(map) => {
+                for value in map.values() {
+                    value.traverse(order, f)?;
+                }
+            }
+            MetaValue::MetaList(values) => values.traverse(order, f)?,
+            MetaValue::MetaInlines(inlines) => inlines.traverse(order, f)?,
+            MetaValue::MetaBlocks(blocks) => blocks.traverse(order, f)?,
+            MetaValue::MetaBool(_) => {}
+            MetaValue::MetaString(_) => {}
+        }
+        match order {
+            Order::TopDown => ControlFlow::Continue(()),
+            Order::BottomUp => visit(self, T::from_meta_value, f),
+        }
+    }
+
+    fn traverse_mut<T, B, F>(&mut self, f: &mut F) -> ControlFlow<B>
+    where
+        T: WalkTarget,
+        F: FnMut(&mut T) -> ControlFlow<B>,
+    {
+        match self {
+            MetaValue::MetaMap(map) => {
+                for value in map.values_mut() {
+                    value.traverse_mut(f)?;
+                }
+            }
+            MetaValue::MetaList(values) => values.traverse_mut(f)?,
+            MetaValue::MetaInlines(inlines) => inlines.traverse_mut(f)?,
+            MetaValue::MetaBlocks(blocks) => blocks.traverse_mut(f)?,
+            MetaValue::MetaBool(_) => {}
+            MetaValue::MetaString(_) => {}
+        }
+        visit_mut(self, T::from_meta_value_mut, f)
+    }
+}
+
+impl Traverse for Block {
+    fn traverse<T, B, F>(&self, order: Order, f: &mut F) -> ControlFlow<B>
+    where
+        T: WalkTarget,
+        F: FnMut(&T) -> ControlFlow<B>,
+    {
+        if order == Order::TopDown {
+            visit(self, T::from_block, f)?;
+        }
+        match self {
+            Block::Plain(inlines) => inlines.traverse(order, f)?,
+            Block::Para(inlines) => inlines.traverse(order, f)?,
+            Block::LineBlock(lines) => {
+                for line in lines {
+                    line.traverse(order, f)?;
+                }
+            }
+            Block::BlockQuote(blocks) => blocks.traverse(order, f)?,
+            Block::OrderedList(_, items) => {
+                for item in items {
+                    item.traverse(order, f)?;
+                }
+            }
+            Block::BulletList(items) => {
+                for item in items {
+                    item.traverse(order, f)?;
+                }
+            }
+            Block::DefinitionList(definitions) => {
+                for (term, definitions) in definitions {
+                    term.traverse(order, f)?;
+                    for definition in definitions {
+                        definition.traverse(order, f)?;
+                    }
+                }
+            }
+            Block::Header(_, _, inlines) => inlines.traverse(order, f)?,
+            Block::Table(table) => table.traverse(order, f)?,
+            Block::Figure(_, caption, blocks) => {
+                caption.traverse(order, f)?;
+                blocks.traverse(order, f)?;
+            }
+            Block::Div(_, blocks) => blocks.traverse(order, f)?,
+            Block::CodeBlock(_, _) => {}
+            Block::RawBlock(_, _) => {}
+            Block::HorizontalRule => {}
+            Block::Null => {}
+        }
+        match order {
+            Order::TopDown => ControlFlow::Continue(()),
+            Order::BottomUp => visit(self, T::from_block, f),
+        }
+    }
+
+    fn traverse_mut<T, B, F>(&mut self, f: &mut F) -> ControlFlow<B>
+    where
+        T: WalkTarget,
+        F: FnMut(&mut T) -> ControlFlow<B>,
+    {
+        match self {
+            Block::Plain(inlines) => inlines.traverse_mut(f)?,
+            Block::Para(inlines) => inlines.traverse_mut(f)?,
+            Block::LineBlock(lines) => {
+                for line in lines {
+                    line.traverse_mut(f)?;
+                }
+            }
+            Block::BlockQuote(blocks) => blocks.traverse_mut(f)?,
+            Block::OrderedList(_, items) => {
+                for item in items {
+                    item.traverse_mut(f)?;
+                }
+            }
+            Block::BulletList(items) => {
+                for item in items {
+                    item.traverse_mut(f)?;
+                }
+            }
+            Block::DefinitionList(definitions) => {
+                for (term, definitions) in definitions {
+                    term.traverse_mut(f)?;
+                    for definition in definitions {
+                        definition.traverse_mut(f)?;
+                    }
+                }
+            }
+            Block::Header(_, _, inlines) => inlines.traverse_mut(f)?,
+            Block::Table(table) => table.traverse_mut(f)?,
+            Block::Figure(_, caption, blocks) => {
+                caption.traverse_mut(f)?;
+                blocks.traverse_mut(f)?;
+            }
+            Block::Div(_, blocks) => blocks.traverse_mut(f)?,
+            Block::CodeBlock(_, _) => {}
+            Block::RawBlock(_, _) => {}
+            Block::HorizontalRule => {}
+            Block::Null => {}
+        }
+        visit_mut(self, T::from_block_mut, f)
+    }
+}
+
+impl Traverse for Inline {
+    fn traverse<T, B, F>(&self, order: Order, f: &mut F) -> ControlFlow<B>
+    where
+        T: WalkTarget,
+        F: FnMut(&T) -> ControlFlow<B>,
+    {
+        if order == Order::TopDown {
+            visit(self, T::from_inline, f)?;
+        }
+        match self {
+            Inline::Emph(inlines) => inlines.traverse(order, f)?,
+            Inline::Underline(inlines) => inlines.traverse(order, f)?,
+            Inline::Strong(inlines) => inlines.traverse(order, f)?,
+            Inline::Strikeout(inlines) => inlines.traverse(order, f)?,
+            Inline::Superscript(inlines) => inlines.traverse(order, f)?,
+            Inline::Subscript(inlines) => inlines.traverse(order, f)?,
+            Inline::SmallCaps(inlines) => inlines.traverse(order, f)?,
+            Inline::Quoted(_, inlines) => inlines.traverse(order, f)?,
+            Inline::Cite(citations, inlines) => {
+                citations.traverse(order, f)?;
+                inlines.traverse(order, f)?;
+            }
+            Inline::Link(_, inlines, _) => inlines.traverse(order, f)?,
+            Inline::Image(_, inlines, _) => inlines.traverse(order, f)?,
+            Inline::Note(blocks) => blocks.traverse(order, f)?,
+            Inline::Span(_, inlines) => inlines.traverse(order, f)?,
+            Inline::Str(_) => {}
+            Inline::Code(_, _) => {}
+            Inline::Space => {}
+            Inline::SoftBreak => {}
+            Inline::LineBreak => {}
+            Inline::Math(_, _) => {}
+            Inline::RawInline(_, _) => {}
+        }
+        match order {
+            Order::TopDown => ControlFlow::Continue(()),
+            Order::BottomUp => visit(self, T::from_inline, f),
+        }
+    }
+
+    fn traverse_mut<T, B, F>(&mut self, f: &mut F) -> ControlFlow<B>
+    where
+        T: WalkTarget,
+        F: FnMut(&mut T) -> ControlFlow<B>,
+    {
+        match self {
+            Inline::Emph(inlines) => inlines.traverse_mut(f)?,
+            Inline::Underline(inlines) => inlines.traverse_mut(f)?,
+            Inline::Strong(inlines) => inlines.traverse_mut(f)?,
+            Inline::Strikeout(inlines) => inlines.traverse_mut(f)?,
+            Inline::Superscript(inlines) => inlines.traverse_mut(f)?,
+            Inline::Subscript(inlines) => inlines.traverse_mut(f)?,
+            Inline::SmallCaps(inlines) => inlines.traverse_mut(f)?,
+            Inline::Quoted(_, inlines) => inlines.traverse_mut(f)?,
+            Inline::Cite(citations, inlines) => {
+                citations.traverse_mut(f)?;
+                inlines.traverse_mut(f)?;
+            }
+            Inline::Link(_, inlines, _) => inlines.traverse_mut(f)?,
+            Inline::Image(_, inlines, _) => inlines.traverse_mut(f)?,
+            Inline::Note(blocks) => blocks.traverse_mut(f)?,
+            Inline::Span(_, inlines) => inlines.traverse_mut(f)?,
+            Inline::Str(_) => {}
+            Inline::Code(_, _) => {}
+            Inline::Space => {}
+            Inline::SoftBreak => {}
+            Inline::LineBreak => {}
+            Inline::Math(_, _) => {}
+            Inline::RawInline(_, _) => {}
+        }
+        visit_mut(self, T::from_inline_mut, f)
+    }
+}
+
+impl Traverse for Citation {
+    fn traverse<T, B, F>(&self, order: Order, f: &mut F) -> ControlFlow<B>
+    where
+        T: WalkTarget,
+        F: FnMut(&T) -> ControlFlow<B>,
+    {
+        if order == Order::TopDown {
+            visit(self, T::from_citation, f)?;
+        }
+        self.citation_prefix.traverse(order, f)?;
+        self.citation_suffix.traverse(order, f)?;
+        match order {
+            Order::TopDown => ControlFlow::Continue(()),
+            Order::BottomUp => visit(self, T::from_citation, f),
+        }
+    }
+
+    fn traverse_mut<T, B, F>(&mut self, f: &mut F) -> ControlFlow<B>
+    where
+        T: WalkTarget,
+        F: FnMut(&mut T) -> ControlFlow<B>,
+    {
+        self.citation_prefix.traverse_mut(f)?;
+        self.citation_suffix.traverse_mut(f)?;
+        visit_mut(self, T::from_citation_mut, f)
+    }
+}
+
+impl Traverse for Table {
+    fn traverse<T, B, F>(&self, order: Order, f: &mut F) -> ControlFlow<B>
+    where
+        T: WalkTarget,
+        F: FnMut(&T) -> ControlFlow<B>,
+    {
+        self.caption.traverse(order, f)?;
+        self.head.traverse(order, f)?;
+        self.bodies.traverse(order, f)?;
+        self.foot.traverse(order, f)
+    }
+
+    fn traverse_mut<T, B, F>(&mut self, f: &mut F) -> ControlFlow<B>
+    where
+        T: WalkTarget,
+        F: FnMut(&mut T) -> ControlFlow<B>,
+    {
+        self.caption.traverse_mut(f)?;
+        self.head.traverse_mut(f)?;
+        self.bodies.traverse_mut(f)?;
+        self.foot.traverse_mut(f)
+    }
+}
+
+impl Traverse for Caption {
+    fn traverse<T, B, F>(&self, order: Order, f: &mut F) -> ControlFlow<B>
+    where
+        T: WalkTarget,
+        F: FnMut(&T) -> ControlFlow<B>,
+    {
+        if let Some(short) = &self.short {
+            short.traverse(order, f)?;
+        }
+        self.long.traverse(order, f)
+    }
+
+    fn traverse_mut<T, B, F>(&mut self, f: &mut F) -> ControlFlow<B>
+    where
+        T: WalkTarget,
+        F: FnMut(&mut T) -> ControlFlow<B>,
+    {
+        if let Some(short) = &mut self.short {
+            short.traverse_mut(f)?;
+        }
+        self.long.traverse_mut(f)
+    }
+}
+
+impl Traverse for TableHead {
+    fn traverse<T, B, F>(&self, order: Order, f: &mut F) -> ControlFlow<B>
+    where
+        T: WalkTarget,
+        F: FnMut(&T) -> ControlFlow<B>,
+    {
+        self.rows.traverse(order, f)
+    }
+
+    fn traverse_mut<T, B, F>(&mut self, f: &mut F) -> ControlFlow<B>
+    where
+        T: WalkTarget,
+        F: FnMut(&mut T) -> ControlFlow<B>,
+    {
+        self.rows.traverse_mut(f)
+    }
+}
+
+impl Traverse for TableBody {
+    fn traverse<T, B, F>(&self, order: Order, f: &mut F) -> ControlFlow<B>
+    where
+        T: WalkTarget,
+        F: FnMut(&T) -> ControlFlow<B>,
+    {
+        self.head.traverse(order, f)?;
+        self.body.traverse(order, f)
+    }
+
+    fn traverse_mut<T, B, F>(&mut self, f: &mut F) -> ControlFlow<B>
+    where
+        T: WalkTarget,
+        F: FnMut(&mut T) -> ControlFlow<B>,
+    {
+        self.head.traverse_mut(f)?;
+        self.body.traverse_mut(f)
+    }
+}
+
+impl Traverse for TableFoot {
+    fn traverse<T, B, F>(&self, order: Order, f: &mut F) -> ControlFlow<B>
+    where
+        T: WalkTarget,
+        F: FnMut(&T) -> ControlFlow<B>,
+    {
+        self.rows.traverse(order, f)
+    }
+
+    fn traverse_mut<T, B, F>(&mut self, f: &mut F) -> ControlFlow<B>
+    where
+        T: WalkTarget,
+        F: FnMut(&mut T) -> ControlFlow<B>,
+    {
+        self.rows.traverse_mut(f)
+    }
+}
+
+impl Traverse for Row {
+    fn traverse<T, B, F>(&self, order: Order, f: &mut F) -> ControlFlow<B>
+    where
+        T: WalkTarget,
+        F: FnMut(&T) -> ControlFlow<B>,
+    {
+        self.cells.traverse(order, f)
+    }
+
+    fn traverse_mut<T, B, F>(&mut self, f: &mut F) -> ControlFlow<B>
+    where
+        T: WalkTarget,
+        F: FnMut(&mut T) -> ControlFlow<B>,
+    {
+        self.cells.traverse_mut(f)
+    }
+}
+
+impl Traverse for Cell {
+    fn traverse<T, B, F>(&self, order: Order, f: &mut F) -> ControlFlow<B>
+    where
+        T: WalkTarget,
+        F: FnMut(&T) -> ControlFlow<B>,
+    {
+        self.content.traverse(order, f)
+    }
+
+    fn traverse_mut<T, B, F>(&mut self, f: &mut F) -> ControlFlow<B>
+    where
+        T: WalkTarget,
+        F: FnMut(&mut T) -> ControlFlow<B>,
+    {
+        self.content.traverse_mut(f)
+    }
+}
+
+impl Traverse for Vec<Inline> {
+    fn traverse<T, B, F>(&self, order: Order, f: &mut F) -> ControlFlow<B>
+    where
+        T: WalkTarget,
+        F: FnMut(&T) -> ControlFlow<B>,
+    {
+        if order == Order::TopDown {
+            visit(self, T::from_inlines, f)?;
+        }
+        for inline in self {
+            inline.traverse(order, f)?;
+        }
+        match order {
+            Order::TopDown => ControlFlow::Continue(()),
+            Order::BottomUp => visit(self, T::from_inlines, f),
+        }
+    }
+
+    fn traverse_mut<T, B, F>(&mut self, f: &mut F) -> ControlFlow<B>
+    where
+        T: WalkTarget,
+        F: FnMut(&mut T) -> ControlFlow<B>,
+    {
+        for inline in self.iter_mut() {
+            inline.traverse_mut(f)?;
+        }
+        visit_mut(self, T::from_inlines_mut, f)
+    }
+}
+
+impl Traverse for Vec<Block> {
+    fn traverse<T, B, F>(&self, order: Order, f: &mut F) -> ControlFlow<B>
+    where
+        T: WalkTarget,
+        F: FnMut(&T) -> ControlFlow<B>,
+    {
+        if order == Order::TopDown {
+            visit(self, T::from_blocks, f)?;
+        }
+        for block in self {
+            block.traverse(order, f)?;
+        }
+        match order {
+            Order::TopDown => ControlFlow::Continue(()),
+            Order::BottomUp => visit(self, T::from_blocks, f),
+        }
+    }
+
+    fn traverse_mut<T, B, F>(&mut self, f: &mut F) -> ControlFlow<B>
+    where
+        T: WalkTarget,
+        F: FnMut(&mut T) -> ControlFlow<B>,
+    {
+        for block in self.iter_mut() {
+            block.traverse_mut(f)?;
+        }
+        visit_mut(self, T::from_blocks_mut, f)
+    }
+}
+
+/// Implements `Traverse` for vectors of types that aren't themselves targets
+/// at the level of the whole vector.
+macro_rules! traverse_vec {
+    ($($ty:ty),*) => {
+        $(
+            impl Sealed for Vec<$ty> {}
+
+            impl Traverse for Vec<$ty> {
+                fn traverse<T, B, F>(&self, order: Order, f: &mut F) -> ControlFlow<B>
+                where
+                    T: WalkTarget,
+                    F: FnMut(&T) -> ControlFlow<B>,
+                {
+                    for x in self {
+                        x.traverse(order, f)?;
+                    }
+                    ControlFlow::Continue(())
+                }
+
+                fn traverse_mut<T, B, F>(&mut self, f: &mut F) -> ControlFlow<B>
+                where
+                    T: WalkTarget,
+                    F: FnMut(&mut T) -> ControlFlow<B>,
+                {
+                    for x in self {
+                        x.traverse_mut(f)?;
+                    }
+                    ControlFlow::Continue(())
+                }
+            }
+        )*
+    };
+}
+
+traverse_vec!(Pandoc, MetaValue, Citation, Table, Caption, TableBody, Row, Cell);
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::definition::extra::InlineType;
+    use crate::definition::{Attr, ColSpec, Format, IterInlines, MathType, Meta};
+
+    fn str(s: &str) -> Inline {
+        Inline::Str(s.into())
+    }
+
+    fn citation(id: &str, prefix: Vec<Inline>) -> Citation {
+        Citation {
+            citation_id: id.into(),
+            citation_prefix: prefix,
+            ..Citation::placeholder()
+        }
+    }
+
+    fn cell(blocks: Vec<Block>) -> Cell {
+        Cell {
+            content: blocks,
+            ..Default::default()
+        }
+    }
+
+    fn sample() -> Pandoc {
+        let mut meta = Meta::default();
+        meta.insert(
+            "title".to_owned(),
+            MetaValue::MetaInlines(vec![str("title")]),
+        );
+        Pandoc {
+            meta,
+            blocks: vec![
+                Block::Para(vec![
+                    str("a"),
+                    Inline::Space,
+                    Inline::Cite(
+                        vec![citation("key", vec![str("prefix")])],
+                        vec![str("cite")],
+                    ),
+                    Inline::Note(vec![Block::Plain(vec![str("note")])]),
+                ]),
+                Block::Figure(
+                    Attr::default(),
+                    Caption {
+                        short: Some(vec![str("short")]),
+                        long: vec![Block::Plain(vec![str("long")])],
+                    },
+                    vec![Block::Plain(vec![Inline::Math(
+                        MathType::InlineMath,
+                        "x".into(),
+                    )])],
+                ),
+                Block::Table(Table {
+                    colspecs: vec![ColSpec::default()],
+                    bodies: vec![TableBody {
+                        body: vec![Row {
+                            attr: Attr::default(),
+                            cells: vec![cell(vec![Block::Plain(vec![str("cell")])])],
+                        }],
+                        ..Default::default()
+                    }],
+                    ..Default::default()
+                }),
+            ],
+        }
+    }
+
+    #[test]
+    fn walk_inlines_everywhere() {
+        let doc = sample().walk(|inline: Inline| match inline {
+            Inline::Str(s) => Inline::Str(s.to_uppercase()),
+            other => other,
+        });
+        let mut strs: Vec<String> = doc.query(|inline: &Inline| match inline {
+            Inline::Str(s) => Some(s.clone()),
+            _ => None,
+        });
+        strs.sort();
+        assert_eq!(
+            strs,
+            vec!["A", "CELL", "CITE", "LONG", "NOTE", "PREFIX", "SHORT", "TITLE"]
+        );
+    }
+
+    #[test]
+    fn walk_is_bottom_up() {
+        let para = Block::Para(vec![Inline::Emph(vec![str("a")])]);
+        let mut order = Vec::new();
+        let _ = para.walk(|inline: Inline| {
+            order.push(InlineType::from(&inline));
+            inline
+        });
+        assert_eq!(order, vec![InlineType::Str, InlineType::Emph]);
+    }
+
+    #[test]
+    fn query_is_top_down() {
+        let para = Block::Para(vec![Inline::Emph(vec![Inline::Strong(vec![str("a")])])]);
+        let order: Vec<InlineType> = para.query(|inline: &Inline| Some(InlineType::from(inline)));
+        assert_eq!(
+            order,
+            vec![InlineType::Emph, InlineType::Strong, InlineType::Str]
+        );
+    }
+
+    #[test]
+    fn walk_inline_lists() {
+        let para = Block::Para(vec![str("a"), str("b"), Inline::Space, str("c")]);
+        let para = para.walk(|inlines: Vec<Inline>| {
+            let mut result: Vec<Inline> = Vec::new();
+            for inline in inlines {
+                match (result.last_mut(), inline) {
+                    (Some(Inline::Str(last)), Inline::Str(s)) => last.push_str(&s),
+                    (_, inline) => result.push(inline),
+                }
+            }
+            result
+        });
+        assert_eq!(para, Block::Para(vec![str("ab"), Inline::Space, str("c")]));
+    }
+
+    #[test]
+    fn walk_blocks() {
+        let doc = sample().walk(|block: Block| match block {
+            Block::Plain(inlines) => Block::Para(inlines),
+            other => other,
+        });
+        let plains: Vec<()> = doc.query(|block: &Block| match block {
+            Block::Plain(_) => Some(()),
+            _ => None,
+        });
+        assert!(plains.is_empty());
+        let paras: Vec<()> = doc.query(|block: &Block| match block {
+            Block::Para(_) => Some(()),
+            _ => None,
+        });
+        assert_eq!(paras.len(), 5);
+    }
+
+    #[test]
+    fn walk_m_stops_at_first_error() {
+        let mut seen = 0;
+        let result = sample().blocks.walk_m(|inline: Inline| match inline {
+            Inline::RawInline(Format(f), _) => Err(f),
+            Inline::Math(_, m) => Err(m),
+            other => {
+                seen += 1;
+                Ok(other)
+            }
+        });
+        assert_eq!(result, Err("x".to_owned()));
+        assert!(seen > 0);
+    }
+
+    #[test]
+    fn query_meta_and_citations() {
+        let doc = sample();
+        let ids: Vec<String> = doc.query(|c: &Citation| Some(c.citation_id.clone()));
+        assert_eq!(ids, vec!["key".to_owned()]);
+        let metas: Vec<()> = doc.query(|_: &MetaValue| Some(()));
+        assert_eq!(metas.len(), 1);
+    }
+
+    #[test]
+    fn walk_mut_stops_early() {
+        let mut doc = sample();
+        let mut visited = Vec::new();
+        let flow = doc.blocks.walk_mut(|inline: &mut Inline| match inline {
+            Inline::Str(s) if s == "cite" => ControlFlow::Break(s.clone()),
+            Inline::Str(s) => {
+                visited.push(s.clone());
+                s.push('!');
+                ControlFlow::Continue(())
+            }
+            _ => ControlFlow::Continue(()),
+        });
+        assert_eq!(flow, ControlFlow::Break("cite".to_owned()));
+        assert_eq!(visited, vec!["a", "prefix"]);
+        assert_eq!(
+            doc.blocks[0].iter_inlines().next(),
+            Some(&Inline::Str("a!".into()))
+        );
+    }
+
+    #[test]
+    fn walk_ref_reaches_meta() {
+        let mut doc = sample();
+        doc.blocks.clear();
+        let found = doc.walk_ref(|value: &MetaValue| match value {
+            MetaValue::MetaInlines(inlines) => ControlFlow::Break(inlines.len()),
+            _ => ControlFlow::Continue(()),
+        });
+        assert_eq!(found, ControlFlow::Break(1));
+    }
+
+    #[test]
+    fn try_walk_propagates_errors() {
+        let mut doc = sample();
+        let result = doc.try_walk(|block: &mut Block| match block {
+            Block::Table(_) => Err("table"),
+            Block::Plain(inlines) => {
+                *block = Block::Para(std::mem::take(inlines));
+                Ok(())
+            }
+            _ => Ok(()),
+        });
+        assert_eq!(result, Err("table"));
+        assert!(matches!(
+            &doc.blocks[1],
+            Block::Figure(_, _, blocks) if matches!(blocks[..], [Block::Para(_)])
+        ));
+
+        let mut inline = Inline::Note(vec![Block::Null]);
+        assert_eq!(inline.try_walk(|_: &mut Block| Ok::<_, ()>(())), Ok(()));
+    }
+}
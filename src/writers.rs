@@ -0,0 +1,443 @@
+//! This module contains writers that render documents in other formats
+//! without calling the Pandoc executable.
+//!
+//! The writers follow the output of the corresponding Pandoc writers where
+//! that is practical, but they don't try to reproduce it exactly.
+//!
+//! ```
+//! use pandoc_types::builder::{doc, para, text};
+//! use pandoc_types::writers::{plain::write_plain, WriterOptions};
+//!
+//! let doc = doc(para(text("Hello world")));
+//! assert_eq!(write_plain(&doc, &WriterOptions::default()), "Hello world\n");
+//! ```
+use crate::definition::{
+    Alignment, Block, Inline, ListAttributes, ListNumberDelim, ListNumberStyle,
+};
+
+#[cfg(feature = "comrak")]
+pub mod comrak;
+pub mod djot;
+pub mod html;
+pub mod latex;
+pub mod markdown;
+pub mod native;
+pub mod plain;
+#[cfg(feature = "pulldown-cmark")]
+pub mod pulldown_cmark;
+
+/// How to wrap the lines of the output, like Pandoc's `--wrap` option.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum WrapOption {
+    /// Wrap lines to the column width.
+    #[default]
+    Auto,
+    /// Don't wrap lines.
+    None,
+    /// Don't wrap lines, but keep the soft line breaks of the source.
+    Preserve,
+}
+
+/// How the HTML writer renders math, like Pandoc's `HTMLMathMethod`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum HtmlMathMethod {
+    /// Write the TeX source as text.
+    #[default]
+    Plain,
+    /// Write the TeX source between `\(` and `\)`, or `\[` and `\]` for
+    /// display math, to be rendered by MathJax or KaTeX.
+    MathJax,
+}
+
+/// Which LaTeX commands the LaTeX writer uses for citations, like Pandoc's
+/// `--natbib` and `--biblatex` options.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum CiteMethod {
+    /// `\citep` for normal citations, `\textcite` for author-in-text
+    /// citations and `\cite` for citations with the author suppressed, as
+    /// provided by `biblatex` with its `natbib` option.
+    #[default]
+    Cite,
+    /// The `natbib` commands `\citep`, `\citet` and `\citeyearpar`.
+    Natbib,
+    /// The `biblatex` commands `\autocite`, `\textcite` and `\autocite*`.
+    Biblatex,
+}
+
+/// Options for the writers, like Pandoc's `WriterOptions`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct WriterOptions {
+    /// The column width to wrap lines to.
+    pub columns: usize,
+    /// How to wrap lines.
+    pub wrap: WrapOption,
+    /// How to render math in HTML.
+    pub html_math_method: HtmlMathMethod,
+    /// How to write citations in LaTeX.
+    pub cite_method: CiteMethod,
+}
+
+impl Default for WriterOptions {
+    fn default() -> Self {
+        Self {
+            columns: 72,
+            wrap: WrapOption::default(),
+            html_math_method: HtmlMathMethod::default(),
+            cite_method: CiteMethod::default(),
+        }
+    }
+}
+
+impl WriterOptions {
+    /// Returns the width to wrap lines to when `indent` columns are taken
+    /// up by a prefix, or `None` if lines aren't wrapped.
+    pub(crate) fn width(&self, indent: usize) -> Option<usize> {
+        match self.wrap {
+            WrapOption::Auto => Some(self.columns.saturating_sub(indent).max(1)),
+            WrapOption::None | WrapOption::Preserve => None,
+        }
+    }
+}
+
+/// A piece of text to be laid out by [`wrap`].
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) enum Chunk {
+    /// Text that must not be broken.
+    Text(String),
+    /// A space where the line may be broken.
+    Space,
+    /// A soft line break, which is a space unless line breaks are preserved.
+    SoftBreak,
+    /// A line break.
+    Newline,
+}
+
+/// Lays out chunks of text into lines no wider than `width` where possible.
+/// Spaces at the start and end of lines are dropped.
+pub(crate) fn wrap(chunks: &[Chunk], width: Option<usize>, option: WrapOption) -> Vec<String> {
+    // Split the chunks into words, each remembering whether it follows a
+    // space, and forced line breaks.
+    let mut words: Vec<Option<(bool, String)>> = Vec::new();
+    let mut space = false;
+    for chunk in chunks {
+        match chunk {
+            Chunk::Text(text) if text.is_empty() => {}
+            Chunk::Text(text) => match words.last_mut() {
+                Some(Some((_, word))) if !space => word.push_str(text),
+                _ => {
+                    words.push(Some((space, text.clone())));
+                    space = false;
+                }
+            },
+            Chunk::SoftBreak if option == WrapOption::Preserve => {
+                words.push(None);
+                space = false;
+            }
+            Chunk::Space | Chunk::SoftBreak => space = true,
+            Chunk::Newline => {
+                words.push(None);
+                space = false;
+            }
+        }
+    }
+
+    let mut lines = Vec::new();
+    let mut line = String::new();
+    let mut line_width = 0;
+    for word in words {
+        let Some((space, word)) = word else {
+            lines.push(std::mem::take(&mut line));
+            line_width = 0;
+            continue;
+        };
+        let word_width = word.chars().count();
+        if line_width > 0 && space {
+            match width {
+                Some(width) if line_width + 1 + word_width > width => {
+                    lines.push(std::mem::take(&mut line));
+                    line_width = 0;
+                }
+                _ => {
+                    line.push(' ');
+                    line_width += 1;
+                }
+            }
+        }
+        line.push_str(&word);
+        line_width += word_width;
+    }
+    if !line.is_empty() || lines.is_empty() {
+        lines.push(line);
+    }
+    lines
+}
+
+/// Prefixes the first line with `first` and the others with `rest`, leaving
+/// blank lines empty.
+pub(crate) fn indent(lines: Vec<String>, first: &str, rest: &str) -> Vec<String> {
+    lines
+        .into_iter()
+        .enumerate()
+        .map(|(i, line)| {
+            let prefix = if i == 0 { first } else { rest };
+            if line.is_empty() {
+                prefix.trim_end().to_owned()
+            } else {
+                format!("{}{}", prefix, line)
+            }
+        })
+        .collect()
+}
+
+/// Returns the marker of the list item with the given number, such as `3.`,
+/// `(c)` or `iv)`.
+pub(crate) fn list_marker(attrs: &ListAttributes, number: i32) -> String {
+    let number = match attrs.style {
+        ListNumberStyle::DefaultStyle | ListNumberStyle::Decimal | ListNumberStyle::Example => {
+            number.to_string()
+        }
+        ListNumberStyle::LowerRoman => roman(number).to_lowercase(),
+        ListNumberStyle::UpperRoman => roman(number),
+        ListNumberStyle::LowerAlpha => alpha(number).to_lowercase(),
+        ListNumberStyle::UpperAlpha => alpha(number),
+    };
+    match attrs.delim {
+        ListNumberDelim::DefaultDelim | ListNumberDelim::Period => format!("{}.", number),
+        ListNumberDelim::OneParen => format!("{})", number),
+        ListNumberDelim::TwoParens => format!("({})", number),
+    }
+}
+
+/// Formats a number as an uppercase roman numeral, falling back to decimal
+/// for numbers that have none.
+fn roman(mut number: i32) -> String {
+    const NUMERALS: [(i32, &str); 13] = [
+        (1000, "M"),
+        (900, "CM"),
+        (500, "D"),
+        (400, "CD"),
+        (100, "C"),
+        (90, "XC"),
+        (50, "L"),
+        (40, "XL"),
+        (10, "X"),
+        (9, "IX"),
+        (5, "V"),
+        (4, "IV"),
+        (1, "I"),
+    ];
+    if !(1..4000).contains(&number) {
+        return number.to_string();
+    }
+    let mut s = String::new();
+    for (value, numeral) in NUMERALS {
+        while number >= value {
+            s.push_str(numeral);
+            number -= value;
+        }
+    }
+    s
+}
+
+/// Formats a number as uppercase letters: A to Z, then AA, AB and so on.
+fn alpha(number: i32) -> String {
+    if number < 1 {
+        return number.to_string();
+    }
+    let mut s = Vec::new();
+    let mut n = number;
+    while n > 0 {
+        n -= 1;
+        s.push(b'A' + (n % 26) as u8);
+        n /= 26;
+    }
+    s.reverse();
+    String::from_utf8(s).unwrap()
+}
+
+/// Returns whether the first block of a list item is a paragraph starting
+/// with a checkbox, and the text after it.
+pub(crate) fn task(item: &[Block]) -> Option<(bool, &[Inline])> {
+    let (Block::Plain(inlines) | Block::Para(inlines)) = item.first()? else {
+        return None;
+    };
+    let (checked, rest) = match inlines.as_slice() {
+        [Inline::Str(checkbox), rest @ ..] => match checkbox.as_str() {
+            "☐" => (false, rest),
+            "☒" => (true, rest),
+            _ => return None,
+        },
+        _ => return None,
+    };
+    match rest {
+        [] => Some((checked, rest)),
+        [Inline::Space, rest @ ..] => Some((checked, rest)),
+        _ => None,
+    }
+}
+
+/// Merges the text between spaces and line breaks into words, and makes
+/// sure that no word at the start of a line would start a block. The space
+/// before such a word is made non-breaking if there is text before it on the
+/// same line, and otherwise the word is replaced by `escape`, which returns
+/// the escaped word for words that would start a block.
+pub(crate) fn protect(chunks: Vec<Chunk>, escape: impl Fn(&str) -> Option<String>) -> Vec<Chunk> {
+    let mut protected: Vec<Chunk> = Vec::new();
+    for chunk in chunks {
+        match chunk {
+            Chunk::Text(text) => match protected.last_mut() {
+                Some(Chunk::Text(word)) => word.push_str(&text),
+                _ => protected.push(Chunk::Text(text)),
+            },
+            chunk => {
+                protect_last(&mut protected, &escape);
+                protected.push(chunk);
+            }
+        }
+    }
+    protect_last(&mut protected, &escape);
+    protected
+}
+
+fn protect_last(chunks: &mut [Chunk], escape: impl Fn(&str) -> Option<String>) {
+    let Some((Chunk::Text(word), before)) = chunks.split_last_mut() else {
+        return;
+    };
+    let Some(escaped) = escape(word) else {
+        return;
+    };
+    let follows_text = before
+        .iter()
+        .rev()
+        .take_while(|chunk| **chunk != Chunk::Newline)
+        .any(|chunk| matches!(chunk, Chunk::Text(_)));
+    match before.last_mut() {
+        Some(space @ (Chunk::Space | Chunk::SoftBreak)) if follows_text => {
+            *space = Chunk::Text(" ".into())
+        }
+        _ => *word = escaped,
+    }
+}
+
+/// Splits a word that may be an ordered list marker, such as `1.`, `a)` or
+/// `(iv)`, into whether it opens with a parenthesis and the number between
+/// the delimiters.
+pub(crate) fn ordered_marker(word: &str) -> Option<(bool, &str)> {
+    let body = word.strip_suffix(['.', ')'])?;
+    match body.strip_prefix('(') {
+        Some(body) if word.ends_with(')') => Some((true, body)),
+        Some(_) => None,
+        None => Some((false, body)),
+    }
+}
+
+/// Returns true if the number of an ordered list marker is decimal, with at
+/// most 9 digits.
+pub(crate) fn is_decimal(number: &str) -> bool {
+    !number.is_empty() && number.len() <= 9 && number.bytes().all(|b| b.is_ascii_digit())
+}
+
+/// Returns true if the number of an ordered list marker is a letter or a
+/// roman numeral.
+pub(crate) fn is_fancy(number: &str) -> bool {
+    number.len() == 1 && number.bytes().all(|b| b.is_ascii_alphabetic())
+        || !number.is_empty() && number.bytes().all(|b| b"ivxlcdmIVXLCDM".contains(&b))
+}
+
+/// Turns the line breaks in chunks into spaces.
+pub(crate) fn join_lines(chunks: Vec<Chunk>) -> Vec<Chunk> {
+    let mut out: Vec<Chunk> = Vec::new();
+    for chunk in chunks {
+        if chunk == Chunk::Newline {
+            if out.last() == Some(&Chunk::Text("\\".into())) {
+                out.pop();
+            }
+            out.push(Chunk::Space);
+        } else {
+            out.push(chunk);
+        }
+    }
+    out
+}
+
+/// Returns true if list items should be separated by blank lines, which is
+/// the case when they contain paragraphs rather than plain text.
+pub(crate) fn is_loose(blocks: &[Block]) -> bool {
+    blocks.iter().any(|block| matches!(block, Block::Para(_)))
+}
+
+/// Pads text to the given width according to the alignment.
+pub(crate) fn pad(text: &str, width: usize, align: &Alignment) -> String {
+    let fill = width.saturating_sub(text.chars().count());
+    let (left, right) = match align {
+        Alignment::AlignLeft | Alignment::AlignDefault => (0, fill),
+        Alignment::AlignRight => (fill, 0),
+        Alignment::AlignCenter => (fill / 2, fill - fill / 2),
+    };
+    format!("{}{}{}", " ".repeat(left), text, " ".repeat(right))
+}
+
+/// Returns the length of the longest run of `c` in `s`.
+pub(crate) fn longest_run(s: &str, c: char) -> usize {
+    let mut longest = 0;
+    let mut run = 0;
+    for ch in s.chars() {
+        run = if ch == c { run + 1 } else { 0 };
+        longest = longest.max(run);
+    }
+    longest
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn wrapping() {
+        let chunks: Vec<_> = "one two three four"
+            .split(' ')
+            .flat_map(|w| [Chunk::Text(w.into()), Chunk::Space])
+            .collect();
+        assert_eq!(
+            wrap(&chunks, Some(9), WrapOption::Auto),
+            ["one two", "three", "four"]
+        );
+        assert_eq!(
+            wrap(&chunks, None, WrapOption::None),
+            ["one two three four"]
+        );
+
+        let chunks = [
+            Chunk::Space,
+            Chunk::Text("a".into()),
+            Chunk::Text("b".into()),
+            Chunk::SoftBreak,
+            Chunk::Text("c".into()),
+            Chunk::Newline,
+            Chunk::Text("d".into()),
+        ];
+        assert_eq!(wrap(&chunks, Some(72), WrapOption::Auto), ["ab c", "d"]);
+        assert_eq!(wrap(&chunks, None, WrapOption::Preserve), ["ab", "c", "d"]);
+    }
+
+    #[test]
+    fn markers() {
+        let marker = |style, delim, n| {
+            list_marker(
+                &ListAttributes {
+                    start_number: 1,
+                    style,
+                    delim,
+                },
+                n,
+            )
+        };
+        use ListNumberDelim::*;
+        use ListNumberStyle::*;
+        assert_eq!(marker(DefaultStyle, DefaultDelim, 3), "3.");
+        assert_eq!(marker(LowerRoman, OneParen, 14), "xiv)");
+        assert_eq!(marker(UpperRoman, Period, 1994), "MCMXCIV.");
+        assert_eq!(marker(LowerAlpha, TwoParens, 28), "(ab)");
+        assert_eq!(marker(UpperAlpha, Period, 26), "Z.");
+    }
+}
@@ -0,0 +1,817 @@
+//! A writer for the AST of [comrak], a GitHub-Flavored Markdown parser. It
+//! is only available with the `comrak` feature.
+//!
+//! The writer builds a tree of comrak nodes in an arena, which can be
+//! rendered with comrak's `format_html` or `format_commonmark`. It follows
+//! the mapping of [`readers::comrak`](crate::readers::comrak) in reverse, so
+//! that documents read from comrak are written back the same way: bullet
+//! list items starting with `☐` or `☒` become task items, definition lists
+//! become description lists, divs with the class of a GitHub alert become
+//! alerts, links with the class `wikilink` become wiki links and spans with
+//! the class `spoiler` become spoilers. `data-pos` attributes are turned
+//! back into source positions, and the metadata is written as YAML front
+//! matter. Footnotes are defined after the blocks.
+//!
+//! Elements that have no node are written as raw HTML: spans and divs with
+//! attributes as `<span>` and `<div>` tags around their content, small caps
+//! as a `<span>` tag, and figures and tables that don't fit a pipe table as
+//! a whole. Raw blocks and inlines are only kept if their format is `html`,
+//! and the other attributes of headings, code, links and images are
+//! dropped.
+//!
+//! ```
+//! use comrak::{format_html, Arena, Options};
+//! use pandoc_types::builder::*;
+//! use pandoc_types::writers::comrak::write_comrak;
+//!
+//! let doc = doc(definition_list([(text("Term"), vec![plain(text("Details"))])]));
+//! let arena = Arena::new();
+//! let root = write_comrak(&doc, &arena);
+//!
+//! let mut options = Options::default();
+//! options.extension.description_lists = true;
+//! let mut html = Vec::new();
+//! format_html(root, &options, &mut html).unwrap();
+//! assert_eq!(
+//!     String::from_utf8(html).unwrap(),
+//!     "<dl>\n<dt>Term</dt>\n<dd>Details</dd>\n</dl>\n"
+//! );
+//! ```
+//!
+//! [comrak]: https://docs.rs/comrak
+use std::slice;
+
+use comrak::nodes::{
+    AlertType, AstNode, ListDelimType, ListType, NodeAlert, NodeCode, NodeCodeBlock,
+    NodeDescriptionItem, NodeFootnoteDefinition, NodeFootnoteReference, NodeHeading, NodeHtmlBlock,
+    NodeLink, NodeList, NodeMath, NodeTable, NodeValue, NodeWikiLink, Sourcepos, TableAlignment,
+};
+use comrak::Arena;
+use yaml_rust2::yaml::Hash;
+use yaml_rust2::{Yaml, YamlEmitter};
+
+use super::markdown::{blocks_to_markdown, MarkdownFlavor};
+use super::{html, task, WrapOption, WriterOptions};
+use crate::definition::{
+    Alignment, Attr, Block, Format, GridSlot, Inline, ListNumberDelim, MathType, MetaValue, Pandoc,
+    QuoteType, Table, Target,
+};
+
+/// Renders a document as a tree of comrak nodes allocated in `arena`, and
+/// returns its root.
+pub fn write_comrak<'a>(doc: &Pandoc, arena: &'a Arena<AstNode<'a>>) -> &'a AstNode<'a> {
+    let root = blocks_to_comrak(&doc.blocks, arena);
+    if !doc.meta.is_empty() {
+        let hash = doc
+            .meta
+            .iter()
+            .map(|(key, value)| (Yaml::String(key.clone()), yaml(value)))
+            .collect();
+        let mut front_matter = String::new();
+        // Writing to a string can't fail.
+        let _ = YamlEmitter::new(&mut front_matter).dump(&Yaml::Hash(hash));
+        front_matter.push_str("\n---\n\n");
+        root.prepend(arena.alloc(NodeValue::FrontMatter(front_matter).into()));
+    }
+    root
+}
+
+/// Renders a list of blocks as a tree of comrak nodes allocated in `arena`,
+/// followed by the definitions of their footnotes, and returns its root.
+pub fn blocks_to_comrak<'a>(blocks: &[Block], arena: &'a Arena<AstNode<'a>>) -> &'a AstNode<'a> {
+    let mut writer = Writer {
+        arena,
+        notes: Vec::new(),
+    };
+    let root = writer.node(NodeValue::Document);
+    writer.blocks(root, blocks);
+    // Footnotes may contain more footnotes, which are numbered after them.
+    let mut i = 0;
+    while i < writer.notes.len() {
+        let definition = writer.append(
+            root,
+            NodeValue::FootnoteDefinition(NodeFootnoteDefinition {
+                name: (i + 1).to_string(),
+                total_references: 1,
+            }),
+        );
+        let blocks = writer.notes[i];
+        writer.blocks(definition, blocks);
+        i += 1;
+    }
+    root
+}
+
+struct Writer<'a, 'd> {
+    arena: &'a Arena<AstNode<'a>>,
+    notes: Vec<&'d [Block]>,
+}
+
+impl<'a, 'd> Writer<'a, 'd> {
+    fn node(&self, value: NodeValue) -> &'a AstNode<'a> {
+        self.arena.alloc(value.into())
+    }
+
+    fn append(&self, parent: &'a AstNode<'a>, value: NodeValue) -> &'a AstNode<'a> {
+        let node = self.node(value);
+        parent.append(node);
+        node
+    }
+
+    /// Appends text, joining it to the text right before it unless that has
+    /// a source position of its own.
+    fn text(&self, parent: &'a AstNode<'a>, text: &str) {
+        if let Some(last) = parent.last_child() {
+            let mut ast = last.data.borrow_mut();
+            if ast.sourcepos == Sourcepos::from((0, 0, 0, 0)) {
+                if let NodeValue::Text(last) = &mut ast.value {
+                    last.push_str(text);
+                    return;
+                }
+            }
+        }
+        self.append(parent, NodeValue::Text(text.to_owned()));
+    }
+
+    fn html_block(&self, parent: &'a AstNode<'a>, html: String) {
+        let html = NodeHtmlBlock {
+            block_type: 6,
+            literal: html,
+        };
+        self.append(parent, NodeValue::HtmlBlock(html));
+    }
+
+    /// Appends a block as raw HTML.
+    fn html(&self, parent: &'a AstNode<'a>, block: &Block) {
+        let html = html::blocks_to_html(slice::from_ref(block), &WriterOptions::default());
+        self.html_block(parent, html);
+    }
+
+    fn blocks(&mut self, parent: &'a AstNode<'a>, blocks: &'d [Block]) {
+        for block in blocks {
+            self.block(parent, block);
+        }
+    }
+
+    fn block(&mut self, parent: &'a AstNode<'a>, block: &'d Block) {
+        match block {
+            Block::Plain(inlines) | Block::Para(inlines) => {
+                let node = self.append(parent, NodeValue::Paragraph);
+                self.inlines(node, inlines);
+            }
+            Block::LineBlock(lines) => {
+                let node = self.append(parent, NodeValue::Paragraph);
+                for (i, line) in lines.iter().enumerate() {
+                    if i > 0 {
+                        self.append(node, NodeValue::LineBreak);
+                    }
+                    self.inlines(node, line);
+                }
+            }
+            Block::CodeBlock(attr, code) => {
+                let code = NodeCodeBlock {
+                    fenced: true,
+                    fence_char: b'`',
+                    fence_length: 3,
+                    fence_offset: 0,
+                    info: attr.classes.first().cloned().unwrap_or_default(),
+                    literal: match code.is_empty() {
+                        true => String::new(),
+                        false => format!("{}\n", code),
+                    },
+                };
+                let node = self.append(parent, NodeValue::CodeBlock(code));
+                position(node, attr);
+            }
+            Block::RawBlock(Format(format), raw) if format == "html" => {
+                self.html_block(parent, format!("{}\n", raw));
+            }
+            Block::RawBlock(..) | Block::Null => {}
+            Block::BlockQuote(blocks) => {
+                let node = self.append(parent, NodeValue::BlockQuote);
+                self.blocks(node, blocks);
+            }
+            Block::OrderedList(attrs, items) => {
+                let list = NodeList {
+                    list_type: ListType::Ordered,
+                    start: attrs.start_number.max(0) as usize,
+                    delimiter: match attrs.delim {
+                        ListNumberDelim::OneParen | ListNumberDelim::TwoParens => {
+                            ListDelimType::Paren
+                        }
+                        _ => ListDelimType::Period,
+                    },
+                    ..NodeList::default()
+                };
+                self.list(parent, list, items);
+            }
+            Block::BulletList(items) => {
+                let list = NodeList {
+                    list_type: ListType::Bullet,
+                    bullet_char: b'-',
+                    ..NodeList::default()
+                };
+                self.list(parent, list, items);
+            }
+            Block::DefinitionList(items) => {
+                let node = self.append(parent, NodeValue::DescriptionList);
+                for (term, definitions) in items {
+                    let tight = definitions
+                        .iter()
+                        .flatten()
+                        .all(|block| !matches!(block, Block::Para(_)));
+                    let description = NodeDescriptionItem {
+                        tight,
+                        ..NodeDescriptionItem::default()
+                    };
+                    let item = self.append(node, NodeValue::DescriptionItem(description));
+                    let term_node = self.append(item, NodeValue::DescriptionTerm);
+                    let paragraph = self.append(term_node, NodeValue::Paragraph);
+                    self.inlines(paragraph, term);
+                    for blocks in definitions {
+                        let details = self.append(item, NodeValue::DescriptionDetails);
+                        self.blocks(details, blocks);
+                    }
+                }
+            }
+            Block::Header(level, attr, inlines) => {
+                let heading = NodeHeading {
+                    level: (*level).clamp(1, 6) as u8,
+                    setext: false,
+                };
+                let node = self.append(parent, NodeValue::Heading(heading));
+                position(node, attr);
+                self.inlines(node, inlines);
+            }
+            Block::HorizontalRule => {
+                self.append(parent, NodeValue::ThematicBreak);
+            }
+            Block::Table(table) => {
+                if !self.table(parent, table) {
+                    self.html(parent, block);
+                }
+            }
+            Block::Figure(..) => self.html(parent, block),
+            Block::Div(attr, blocks) => match alert(attr) {
+                Some((alert_type, default_title)) => {
+                    let (title, blocks) = match blocks.split_first() {
+                        Some((Block::Div(title_attr, title), rest))
+                            if title_attr.classes == ["title"] =>
+                        {
+                            let title = blocks_to_markdown(
+                                title,
+                                MarkdownFlavor::Gfm,
+                                &WriterOptions {
+                                    wrap: WrapOption::None,
+                                    ..WriterOptions::default()
+                                },
+                            );
+                            (Some(title.trim().to_owned()), rest)
+                        }
+                        _ => (None, blocks.as_slice()),
+                    };
+                    let alert = NodeAlert {
+                        alert_type,
+                        title: title.filter(|title| title != default_title),
+                        multiline: false,
+                        fence_length: 0,
+                        fence_offset: 0,
+                    };
+                    let node = self.append(parent, NodeValue::Alert(alert));
+                    position(node, attr);
+                    self.blocks(node, blocks);
+                }
+                None if is_position(attr) => {
+                    let count = parent.children().count();
+                    self.blocks(parent, blocks);
+                    if let (Some(last), true) =
+                        (parent.last_child(), parent.children().count() == count + 1)
+                    {
+                        position(last, attr);
+                    }
+                }
+                None if attr == &Attr::default() => self.blocks(parent, blocks),
+                None => {
+                    let mut open = "<div".to_owned();
+                    html::attr_to(&mut open, attr);
+                    open.push_str(">\n");
+                    self.html_block(parent, open);
+                    self.blocks(parent, blocks);
+                    self.html_block(parent, "</div>\n".to_owned());
+                }
+            },
+        }
+    }
+
+    fn list(&mut self, parent: &'a AstNode<'a>, mut list: NodeList, items: &'d [Vec<Block>]) {
+        list.tight = items
+            .iter()
+            .flatten()
+            .all(|block| !matches!(block, Block::Para(_)));
+        list.is_task_list = list.list_type == ListType::Bullet
+            && items.iter().any(|item| positioned_task(item).is_some());
+        let node = self.append(parent, NodeValue::List(list));
+        for item in items {
+            match positioned_task(item).filter(|_| list.is_task_list) {
+                Some((checked, rest, attr)) => {
+                    let symbol = checked.then_some('x');
+                    let item_node = self.append(node, NodeValue::TaskItem(symbol));
+                    let paragraph = self.append(item_node, NodeValue::Paragraph);
+                    if let Some(attr) = attr {
+                        position(item_node, attr);
+                        position(paragraph, attr);
+                    }
+                    self.inlines(paragraph, rest);
+                    self.blocks(item_node, &item[1..]);
+                }
+                None => {
+                    let item_node = self.append(node, NodeValue::Item(list));
+                    self.blocks(item_node, item);
+                }
+            }
+        }
+    }
+
+    /// Appends a table with at most one head row and cells holding a single
+    /// paragraph, or returns false if the table has anything else.
+    fn table(&mut self, parent: &'a AstNode<'a>, table: &'d Table) -> bool {
+        let grid = table.grid();
+        if grid.width == 0
+            || !grid.is_valid()
+            || table.head.rows.len() > 1
+            || table.bodies.iter().any(|body| !body.head.is_empty())
+            || !table.foot.rows.is_empty()
+            || !table.caption.long.is_empty()
+        {
+            return false;
+        }
+        let mut rows = Vec::new();
+        for row in &grid.rows {
+            let mut cells = Vec::new();
+            for slot in &row.slots {
+                let inlines: &'d [Inline] = match slot {
+                    GridSlot::Origin(cell) if cell.row_span <= 1 && cell.col_span <= 1 => {
+                        match cell.content.as_slice() {
+                            [] => &[],
+                            [Block::Plain(inlines) | Block::Para(inlines)] => inlines,
+                            _ => return false,
+                        }
+                    }
+                    _ => return false,
+                };
+                cells.push(inlines);
+            }
+            rows.push(cells);
+        }
+        if table.head.rows.is_empty() {
+            rows.insert(0, vec![&[]; grid.width]);
+        }
+
+        let alignments = table
+            .colspecs
+            .iter()
+            .map(|spec| match spec.0 {
+                Alignment::AlignLeft => TableAlignment::Left,
+                Alignment::AlignRight => TableAlignment::Right,
+                Alignment::AlignCenter => TableAlignment::Center,
+                Alignment::AlignDefault => TableAlignment::None,
+            })
+            .collect();
+        let node_table = NodeTable {
+            alignments,
+            num_columns: grid.width,
+            num_rows: rows.len(),
+            num_nonempty_cells: rows
+                .iter()
+                .flatten()
+                .filter(|cell| !cell.is_empty())
+                .count(),
+        };
+        let node = self.append(parent, NodeValue::Table(node_table));
+        position(node, &table.attr);
+        for (i, cells) in rows.into_iter().enumerate() {
+            let row = self.append(node, NodeValue::TableRow(i == 0));
+            for inlines in cells {
+                let cell = self.append(row, NodeValue::TableCell);
+                self.inlines(cell, inlines);
+            }
+        }
+        true
+    }
+
+    fn inlines(&mut self, parent: &'a AstNode<'a>, inlines: &'d [Inline]) {
+        for inline in inlines {
+            self.inline(parent, inline);
+        }
+    }
+
+    fn inline(&mut self, parent: &'a AstNode<'a>, inline: &'d Inline) {
+        let (value, inlines, attr) = match inline {
+            Inline::Str(s) => return self.text(parent, s),
+            Inline::Space => return self.text(parent, " "),
+            Inline::SoftBreak => (NodeValue::SoftBreak, None, None),
+            Inline::LineBreak => (NodeValue::LineBreak, None, None),
+            Inline::Emph(inlines) => (NodeValue::Emph, Some(inlines), None),
+            Inline::Strong(inlines) => (NodeValue::Strong, Some(inlines), None),
+            Inline::Strikeout(inlines) => (NodeValue::Strikethrough, Some(inlines), None),
+            Inline::Superscript(inlines) => (NodeValue::Superscript, Some(inlines), None),
+            Inline::Subscript(inlines) => (NodeValue::Subscript, Some(inlines), None),
+            Inline::Underline(inlines) => (NodeValue::Underline, Some(inlines), None),
+            Inline::SmallCaps(inlines) => {
+                return self.html_inline(parent, "<span class=\"smallcaps\">", inlines, "</span>");
+            }
+            Inline::Quoted(QuoteType::SingleQuote, inlines) => {
+                self.text(parent, "‘");
+                self.inlines(parent, inlines);
+                return self.text(parent, "’");
+            }
+            Inline::Quoted(QuoteType::DoubleQuote, inlines) => {
+                self.text(parent, "“");
+                self.inlines(parent, inlines);
+                return self.text(parent, "”");
+            }
+            Inline::Cite(_, inlines) => return self.inlines(parent, inlines),
+            // comrak never parses an empty code span, and can't write one.
+            Inline::Code(_, code) if code.is_empty() => {
+                (NodeValue::HtmlInline("<code></code>".into()), None, None)
+            }
+            Inline::Code(attr, code) => {
+                let code = NodeCode {
+                    num_backticks: 1,
+                    literal: code.clone(),
+                };
+                (NodeValue::Code(code), None, Some(attr))
+            }
+            Inline::Math(math_type, tex) => {
+                let math = NodeMath {
+                    dollar_math: true,
+                    display_math: math_type == &MathType::DisplayMath,
+                    literal: tex.clone(),
+                };
+                (NodeValue::Math(math), None, None)
+            }
+            Inline::RawInline(Format(format), raw) if format == "html" => {
+                (NodeValue::HtmlInline(raw.clone()), None, None)
+            }
+            Inline::RawInline(..) => return,
+            Inline::Link(attr, inlines, Target { url, title }) => {
+                let value = match attr.classes.iter().any(|class| class == "wikilink") {
+                    true => NodeValue::WikiLink(NodeWikiLink { url: url.clone() }),
+                    false => NodeValue::Link(NodeLink {
+                        url: url.clone(),
+                        title: title.clone(),
+                    }),
+                };
+                (value, Some(inlines), Some(attr))
+            }
+            Inline::Image(attr, inlines, Target { url, title }) => {
+                let link = NodeLink {
+                    url: url.clone(),
+                    title: title.clone(),
+                };
+                (NodeValue::Image(link), Some(inlines), Some(attr))
+            }
+            Inline::Note(blocks) => {
+                self.notes.push(blocks);
+                let name = self.notes.len().to_string();
+                let reference = NodeFootnoteReference {
+                    name,
+                    ref_num: 1,
+                    ix: self.notes.len() as u32,
+                };
+                (NodeValue::FootnoteReference(reference), None, None)
+            }
+            Inline::Span(attr, inlines) if attr.classes == ["spoiler"] => {
+                (NodeValue::SpoileredText, Some(inlines), Some(attr))
+            }
+            Inline::Span(attr, inlines) if is_position(attr) => {
+                let count = parent.children().count();
+                self.inlines(parent, inlines);
+                if let (Some(last), true) =
+                    (parent.last_child(), parent.children().count() == count + 1)
+                {
+                    position(last, attr);
+                }
+                return;
+            }
+            Inline::Span(attr, inlines) if attr == &Attr::default() => {
+                return self.inlines(parent, inlines);
+            }
+            Inline::Span(attr, inlines) => {
+                let mut open = "<span".to_owned();
+                html::attr_to(&mut open, attr);
+                open.push('>');
+                self.append(parent, NodeValue::HtmlInline(open));
+                self.inlines(parent, inlines);
+                self.append(parent, NodeValue::HtmlInline("</span>".into()));
+                return;
+            }
+        };
+        let node = self.append(parent, value);
+        if let Some(attr) = attr {
+            position(node, attr);
+        }
+        if let Some(inlines) = inlines {
+            self.inlines(node, inlines);
+        }
+    }
+
+    fn html_inline(
+        &mut self,
+        parent: &'a AstNode<'a>,
+        open: &str,
+        inlines: &'d [Inline],
+        close: &str,
+    ) {
+        self.append(parent, NodeValue::HtmlInline(open.to_owned()));
+        self.inlines(parent, inlines);
+        self.append(parent, NodeValue::HtmlInline(close.to_owned()));
+    }
+}
+
+/// Returns whether the only attribute of an element is its source position.
+/// Like [`task`], but also looks inside the div holding the source position
+/// of the first paragraph, returning its attributes.
+fn positioned_task(item: &[Block]) -> Option<(bool, &[Inline], Option<&Attr>)> {
+    match item.first()? {
+        Block::Div(attr, blocks) if is_position(attr) && blocks.len() == 1 => {
+            let (checked, rest) = task(blocks)?;
+            Some((checked, rest, Some(attr)))
+        }
+        _ => {
+            let (checked, rest) = task(item)?;
+            Some((checked, rest, None))
+        }
+    }
+}
+
+fn is_position(attr: &Attr) -> bool {
+    attr.identifier.is_empty()
+        && attr.classes.is_empty()
+        && matches!(attr.attributes.as_slice(), [(key, _)] if key == "data-pos")
+}
+
+/// Sets the source position of a node from the `data-pos` attribute, which
+/// has the form `line:column-line:column`, optionally after the name of the
+/// source and `@`.
+fn position(node: &AstNode<'_>, attr: &Attr) {
+    let Some((_, pos)) = attr.attributes.iter().find(|(key, _)| key == "data-pos") else {
+        return;
+    };
+    let pos = pos.rsplit_once('@').map_or(pos.as_str(), |(_, pos)| pos);
+    let parse = |pos: &str| {
+        let (line, column) = pos.split_once(':')?;
+        Some((line.parse().ok()?, column.parse().ok()?))
+    };
+    let Some((start, end)) = pos.split_once('-') else {
+        return;
+    };
+    if let (Some(start), Some(end)) = (parse(start), parse(end)) {
+        node.data.borrow_mut().sourcepos = Sourcepos {
+            start: start.into(),
+            end: end.into(),
+        };
+    }
+}
+
+/// Returns the kind of GitHub alert that a div with the given attributes
+/// stands for, if any, and its default title.
+fn alert(attr: &Attr) -> Option<(AlertType, &'static str)> {
+    if !attr.identifier.is_empty() || attr.attributes.iter().any(|(key, _)| key != "data-pos") {
+        return None;
+    }
+    match attr.classes.as_slice() {
+        [class] => match class.as_str() {
+            "note" => Some((AlertType::Note, "Note")),
+            "tip" => Some((AlertType::Tip, "Tip")),
+            "important" => Some((AlertType::Important, "Important")),
+            "warning" => Some((AlertType::Warning, "Warning")),
+            "caution" => Some((AlertType::Caution, "Caution")),
+            _ => None,
+        },
+        _ => None,
+    }
+}
+
+/// Converts a metadata value to YAML, writing inlines and blocks as
+/// Markdown.
+fn yaml(value: &MetaValue) -> Yaml {
+    let markdown = |blocks: &[Block]| {
+        let options = WriterOptions {
+            wrap: WrapOption::None,
+            ..WriterOptions::default()
+        };
+        let markdown = blocks_to_markdown(blocks, MarkdownFlavor::Gfm, &options);
+        Yaml::String(markdown.trim_end().to_owned())
+    };
+    match value {
+        MetaValue::MetaMap(map) => {
+            let mut hash = Hash::new();
+            for (key, value) in map {
+                hash.insert(Yaml::String(key.clone()), yaml(value));
+            }
+            Yaml::Hash(hash)
+        }
+        MetaValue::MetaList(values) => Yaml::Array(values.iter().map(yaml).collect()),
+        MetaValue::MetaBool(b) => Yaml::Boolean(*b),
+        MetaValue::MetaString(s) => Yaml::String(s.clone()),
+        MetaValue::MetaInlines(inlines) => markdown(&[Block::Plain(inlines.clone())]),
+        MetaValue::MetaBlocks(blocks) => markdown(blocks),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::builder::*;
+    use crate::readers::comrak::read_comrak;
+    use comrak::{format_commonmark, format_html, parse_document, Options};
+
+    fn options() -> Options<'static> {
+        let mut options = Options::default();
+        options.extension.strikethrough = true;
+        options.extension.table = true;
+        options.extension.autolink = true;
+        options.extension.tasklist = true;
+        options.extension.footnotes = true;
+        options.extension.description_lists = true;
+        options.extension.front_matter_delimiter = Some("---".into());
+        options.extension.alerts = true;
+        options.extension.wikilinks_title_after_pipe = true;
+        options.extension.spoiler = true;
+        options.extension.underline = true;
+        options
+    }
+
+    fn html(doc: &Pandoc) -> String {
+        let arena = Arena::new();
+        let mut options = options();
+        options.render.unsafe_ = true;
+        let mut html = Vec::new();
+        format_html(write_comrak(doc, &arena), &options, &mut html).unwrap();
+        String::from_utf8(html).unwrap()
+    }
+
+    fn attr(id: &str, classes: &[&str], attributes: &[(&str, &str)]) -> Attr {
+        Attr {
+            identifier: id.into(),
+            classes: classes.iter().map(|&class| class.into()).collect(),
+            attributes: attributes
+                .iter()
+                .map(|&(key, value)| (key.into(), value.into()))
+                .collect(),
+        }
+    }
+
+    #[test]
+    fn blocks() {
+        let doc = doc(header(2, "Title")
+            + bullet_list([plain(text("☒ done")), plain(text("☐ todo"))])
+            + code_block_with(attr("", &["rust"], &[]), "let x;")
+            + div_with(attr("x", &[], &[]), para(underline("under")))
+            + raw_block("latex", "\\relax"));
+        assert_eq!(
+            html(&doc),
+            "<h2>Title</h2>\n\
+             <ul>\n\
+             <li><input type=\"checkbox\" checked=\"\" disabled=\"\" /> done</li>\n\
+             <li><input type=\"checkbox\" disabled=\"\" /> todo</li>\n\
+             </ul>\n\
+             <pre><code class=\"language-rust\">let x;\n</code></pre>\n\
+             <div id=\"x\">\n\
+             <p><u>under</u></p>\n\
+             </div>\n"
+        );
+    }
+
+    #[test]
+    fn alerts() {
+        let doc = doc(div_with(
+            attr("", &["warning"], &[]),
+            div_with(attr("", &["title"], &[]), para("Warning")) + para("Careful."),
+        ) + div_with(
+            attr("", &["tip"], &[]),
+            div_with(attr("", &["title"], &[]), para(text("Pro tip"))) + para("Hi."),
+        ));
+        assert_eq!(
+            html(&doc),
+            "<div class=\"markdown-alert markdown-alert-warning\">\n\
+             <p class=\"markdown-alert-title\">Warning</p>\n\
+             <p>Careful.</p>\n\
+             </div>\n\
+             <div class=\"markdown-alert markdown-alert-tip\">\n\
+             <p class=\"markdown-alert-title\">Pro tip</p>\n\
+             <p>Hi.</p>\n\
+             </div>\n"
+        );
+    }
+
+    #[test]
+    fn front_matter() {
+        let mut doc = doc(para("Text"));
+        doc.meta.insert(
+            "title".into(),
+            MetaValue::MetaInlines((text("A ") + emph("title")).into_vec()),
+        );
+        doc.meta.insert(
+            "tags".into(),
+            MetaValue::MetaList(vec![
+                MetaValue::MetaString("a".into()),
+                MetaValue::MetaBool(true),
+            ]),
+        );
+        let arena = Arena::new();
+        let mut markdown = Vec::new();
+        format_commonmark(write_comrak(&doc, &arena), &options(), &mut markdown).unwrap();
+        assert_eq!(
+            String::from_utf8(markdown).unwrap(),
+            "---\ntitle: A *title*\ntags:\n  - a\n  - true\n---\n\nText\n"
+        );
+    }
+
+    #[test]
+    fn empty_code() {
+        let doc = doc(para(text("a ") + code("") + text(" b")));
+        let arena = Arena::new();
+        let mut markdown = Vec::new();
+        format_commonmark(write_comrak(&doc, &arena), &options(), &mut markdown).unwrap();
+        assert_eq!(String::from_utf8(markdown).unwrap(), "a <code></code> b\n");
+    }
+
+    #[test]
+    fn sourcepos() {
+        let pos = |pos| attr("", &[], &[("data-pos", pos)]);
+        let doc = doc(
+            header_with(pos("1:1-1:4"), 1, span_with(pos("1:3-1:4"), "Hi"))
+                + div_with(
+                    pos("3:1-3:5"),
+                    para(text("Some ") + code_with(pos("3:6-3:11"), "x")),
+                ),
+        );
+        let arena = Arena::new();
+        let root = write_comrak(&doc, &arena);
+        let positions: Vec<String> = root
+            .descendants()
+            .map(|node| node.data.borrow().sourcepos.to_string())
+            .collect();
+        assert_eq!(
+            positions,
+            ["0:0-0:0", "1:1-1:4", "1:3-1:4", "3:1-3:5", "0:0-0:0", "3:6-3:11"]
+        );
+    }
+
+    #[test]
+    fn notes() {
+        let doc = doc(para(str("A") + note(para(str("B") + note(para("C"))))));
+        assert_eq!(
+            html(&doc),
+            "<p>A<sup class=\"footnote-ref\"><a href=\"#fn-1\" id=\"fnref-1\" data-footnote-ref>1</a></sup></p>\n\
+             <section class=\"footnotes\" data-footnotes>\n\
+             <ol>\n\
+             <li id=\"fn-1\">\n\
+             <p>B<sup class=\"footnote-ref\"><a href=\"#fn-2\" id=\"fnref-2\" data-footnote-ref>2</a></sup> <a href=\"#fnref-1\" class=\"footnote-backref\" data-footnote-backref data-footnote-backref-idx=\"1\" aria-label=\"Back to reference 1\">↩</a></p>\n\
+             </li>\n\
+             <li id=\"fn-2\">\n\
+             <p>C <a href=\"#fnref-2\" class=\"footnote-backref\" data-footnote-backref data-footnote-backref-idx=\"2\" aria-label=\"Back to reference 2\">↩</a></p>\n\
+             </li>\n\
+             </ol>\n\
+             </section>\n"
+        );
+    }
+
+    #[test]
+    fn round_trip() {
+        let input = "---\ntitle: Notes\n---\n\n# Wiki\n\n> [!IMPORTANT]\n> Read [[Home|the home page]] and <https://x.org>.\n\nTerm\n\n: Details\n\n- [x] done\n- item\n\n| a | b |\n|:--|--:|\n| 1 | *2* |\n\nText[^n] ||hidden||.\n\n[^n]: A note.\n";
+        let arena = Arena::new();
+        let doc = read_comrak(parse_document(&arena, input, &options()), &options());
+        assert_eq!(doc.meta.len(), 1);
+
+        let arena = Arena::new();
+        let root = write_comrak(&doc, &arena);
+        assert_eq!(read_comrak(root, &options()), doc);
+
+        // The written tree renders back to a document that reads the same.
+        let mut markdown = Vec::new();
+        format_commonmark(root, &options(), &mut markdown).unwrap();
+        let markdown = String::from_utf8(markdown).unwrap();
+        let arena = Arena::new();
+        let reread = read_comrak(parse_document(&arena, &markdown, &options()), &options());
+        assert_eq!(reread, doc, "{}", markdown);
+    }
+
+    #[test]
+    fn sourcepos_task_list() {
+        let mut options = options();
+        options.render.sourcepos = true;
+        let input = "- [ ] a\n- [x] b\n";
+        let arena = Arena::new();
+        let doc = read_comrak(parse_document(&arena, input, &options), &options);
+
+        let arena = Arena::new();
+        let root = write_comrak(&doc, &arena);
+        assert_eq!(read_comrak(root, &options), doc);
+        let mut markdown = Vec::new();
+        format_commonmark(root, &options, &mut markdown).unwrap();
+        assert_eq!(String::from_utf8(markdown).unwrap(), input);
+    }
+}
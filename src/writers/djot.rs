@@ -0,0 +1,966 @@
+//! A writer for [djot](https://djot.net), like `pandoc -t djot`.
+//!
+//! Djot can express nearly everything in the AST, and the writer follows
+//! the mapping of [`readers::djot`](crate::readers::djot) in reverse, so
+//! that documents read from djot are written back the same way: divs with
+//! the class `section` become headings, spans with the classes `mark`,
+//! `inserted` and `symbol` become highlighted text, inserted text and
+//! symbols, and bullet list items starting with `☐` or `☒` become task list
+//! items. Citations are written as their text, figures as divs with the
+//! class `figure`, and table cells hold text only.
+//!
+//! ```
+//! use pandoc_types::builder::*;
+//! use pandoc_types::readers::djot::DjotOptions;
+//! use pandoc_types::writers::djot::write_djot;
+//! use pandoc_types::writers::WriterOptions;
+//!
+//! let doc = doc(para(text("Use ") + code("a*b") + text(" or ") + emph("a*b") + text("…")));
+//! assert_eq!(
+//!     write_djot(&doc, &DjotOptions::default(), &WriterOptions::default()),
+//!     "Use `a*b` or _a\\*b_...\n"
+//! );
+//! ```
+use super::{
+    indent, is_decimal, is_fancy, is_loose, join_lines, list_marker, longest_run, ordered_marker,
+    pad, protect, task, wrap, Chunk, WriterOptions,
+};
+use crate::definition::{
+    Alignment, Attr, Block, Format, Inline, IterBlocks, MathType, Pandoc, QuoteType, Stringify,
+    Table, Target,
+};
+use crate::readers::djot::{auto_identifier, DjotOptions};
+
+/// Renders a document as djot.
+pub fn write_djot(doc: &Pandoc, djot: &DjotOptions, options: &WriterOptions) -> String {
+    blocks_to_djot(&doc.blocks, djot, options)
+}
+
+/// Renders a list of blocks as djot, followed by their footnotes.
+pub fn blocks_to_djot(blocks: &[Block], djot: &DjotOptions, options: &WriterOptions) -> String {
+    let mut writer = Writer {
+        options,
+        djot,
+        notes: Vec::new(),
+        unwrapped: false,
+    };
+    let mut lines = writer.blocks(blocks, 0);
+
+    // Footnotes may contain more footnotes, which are numbered after them.
+    let mut i = 0;
+    while i < writer.notes.len() {
+        let mut note = writer.blocks(writer.notes[i], 4);
+        if note.is_empty() {
+            note.push(String::new());
+        }
+        if !lines.is_empty() {
+            lines.push(String::new());
+        }
+        lines.extend(indent(note, &format!("[^{}]: ", i + 1), "    "));
+        i += 1;
+    }
+
+    let mut output = String::new();
+    for line in lines {
+        output.push_str(line.trim_end());
+        output.push('\n');
+    }
+    output
+}
+
+struct Writer<'a> {
+    options: &'a WriterOptions,
+    djot: &'a DjotOptions,
+    notes: Vec<&'a [Block]>,
+    /// Whether lines are laid out without wrapping, for table cells and
+    /// terms.
+    unwrapped: bool,
+}
+
+impl<'a> Writer<'a> {
+    /// Renders blocks indented by `indent` columns, separated by blank lines.
+    fn blocks(&mut self, blocks: &'a [Block], indent: usize) -> Vec<String> {
+        let mut lines = Vec::new();
+        let mut previous: Option<&Block> = None;
+        for block in blocks {
+            let Some(block_lines) = self.block(block, indent) else {
+                continue;
+            };
+            if let Some(previous) = previous {
+                lines.push(String::new());
+                // Separate consecutive lists with an empty attribute, as
+                // they would otherwise merge.
+                let list = matches!(
+                    block,
+                    Block::BulletList(_) | Block::OrderedList(..) | Block::DefinitionList(_)
+                );
+                if list && std::mem::discriminant(previous) == std::mem::discriminant(block) {
+                    lines.extend(["{% %}".to_owned(), String::new()]);
+                }
+            }
+            lines.extend(block_lines);
+            previous = Some(block);
+        }
+        lines
+    }
+
+    /// Renders a block, returning `None` if it produces no output at all.
+    fn block(&mut self, block: &'a Block, indent: usize) -> Option<Vec<String>> {
+        let lines = match block {
+            Block::Plain(inlines) | Block::Para(inlines) => self.paragraph(inlines, indent),
+            Block::LineBlock(lines) => {
+                let mut chunks = Vec::new();
+                for (i, line) in lines.iter().enumerate() {
+                    if i > 0 {
+                        chunks.extend([Chunk::Text("\\".into()), Chunk::Newline]);
+                    }
+                    self.inlines(line, &mut chunks);
+                }
+                self.layout(chunks, indent)
+            }
+            Block::CodeBlock(attr, code) => {
+                let fence = "`".repeat(longest_run(code, '`').max(2) + 1);
+                let (lang, attr) = split_class(attr);
+                let mut lines = attr_line(&attr);
+                lines.push(format!("{} {}", fence, lang).trim_end().to_owned());
+                lines.extend(code.lines().map(str::to_owned));
+                lines.push(fence);
+                lines
+            }
+            Block::RawBlock(Format(format), raw) => {
+                let fence = "`".repeat(longest_run(raw, '`').max(2) + 1);
+                let mut lines = vec![format!("{} ={}", fence, format)];
+                lines.extend(raw.lines().map(str::to_owned));
+                lines.push(fence);
+                lines
+            }
+            Block::BlockQuote(blocks) => {
+                let lines = self.blocks(blocks, indent + 2);
+                if lines.is_empty() {
+                    // An empty quote still needs its marker to be read back.
+                    vec![">".to_owned()]
+                } else {
+                    super::indent(lines, "> ", "> ")
+                }
+            }
+            Block::OrderedList(attrs, items) => {
+                let markers = (0..items.len() as i32)
+                    .map(|i| list_marker(attrs, attrs.start_number.saturating_add(i)))
+                    .collect();
+                self.list(markers, items, indent)
+            }
+            Block::BulletList(items) => match items.iter().map(|item| task(item)).collect() {
+                Some(tasks) => self.task_list(tasks, items, indent),
+                None => self.list(vec!["-".to_owned(); items.len()], items, indent),
+            },
+            Block::DefinitionList(items) => {
+                let loose = items
+                    .iter()
+                    .flat_map(|(_, definitions)| definitions)
+                    .any(|blocks| is_loose(blocks));
+                let mut lines = Vec::new();
+                for (term, definitions) in items {
+                    if loose && !lines.is_empty() {
+                        lines.push(String::new());
+                    }
+                    // The term must fit on the line of the marker.
+                    let term = self.unwrapped(|writer| writer.paragraph(term, 0));
+                    lines.push(format!(": {}", term.join(" ")));
+                    for definition in definitions {
+                        let definition = self.blocks(definition, indent + 2);
+                        lines.push(String::new());
+                        lines.extend(super::indent(definition, "  ", "  "));
+                    }
+                }
+                lines
+            }
+            Block::Header(level, attr, inlines) => self.heading(*level, attr.clone(), inlines),
+            Block::HorizontalRule => vec!["* * *".to_owned()],
+            Block::Table(table) => self.table(table, indent),
+            Block::Figure(attr, caption, blocks) => {
+                let fence = ":".repeat(3 + div_depth(blocks.iter().chain(&caption.long)));
+                let mut lines = attr_line(attr);
+                lines.push(format!("{} figure", fence));
+                lines.extend(self.blocks(blocks, indent));
+                if !caption.long.is_empty() {
+                    lines.push(String::new());
+                    lines.extend(self.blocks(&caption.long, indent));
+                }
+                lines.push(fence);
+                lines
+            }
+            Block::Div(attr, blocks) => self.div(attr, blocks, indent),
+            Block::Null => return None,
+        };
+        Some(lines)
+    }
+
+    /// Renders a heading, with its attributes on the line before it. The
+    /// identifier is left out if the reader would generate the same one.
+    fn heading(&mut self, level: i32, mut attr: Attr, inlines: &'a [Inline]) -> Vec<String> {
+        let mut text = String::new();
+        for inline in inlines {
+            inline.stringify_to(&mut text);
+        }
+        if attr.identifier == auto_identifier(&text) {
+            attr.identifier.clear();
+        }
+        let mut chunks = Vec::new();
+        self.inlines(inlines, &mut chunks);
+        // Headings are written on one line.
+        let chunks = join_lines(chunks);
+        let text = wrap(&chunks, None, self.options.wrap).join(" ");
+        let mut lines = attr_line(&attr);
+        let line = format!("{} {}", "#".repeat(level.clamp(1, 6) as usize), text);
+        lines.push(line.trim_end().to_owned());
+        lines
+    }
+
+    fn div(&mut self, attr: &Attr, blocks: &'a [Block], indent: usize) -> Vec<String> {
+        // A section is written as its heading followed by its content.
+        if let [Block::Header(level, header_attr, inlines), rest @ ..] = blocks {
+            if attr.classes == ["section"]
+                && attr.attributes.is_empty()
+                && header_attr.identifier.is_empty()
+            {
+                let header_attr = Attr {
+                    identifier: attr.identifier.clone(),
+                    ..header_attr.clone()
+                };
+                let mut lines = self.heading(*level, header_attr, inlines);
+                if !rest.is_empty() {
+                    lines.push(String::new());
+                    lines.extend(self.blocks(rest, indent));
+                }
+                return lines;
+            }
+        }
+        // Attributes on a line of their own apply to the next block, and the
+        // reader wraps blocks without attributes of their own in a div, so a
+        // div holding just one such block needs no fences.
+        if let [block] = blocks {
+            let wrapped = matches!(
+                block,
+                Block::Para(_)
+                    | Block::LineBlock(_)
+                    | Block::RawBlock(..)
+                    | Block::BlockQuote(_)
+                    | Block::OrderedList(..)
+                    | Block::BulletList(_)
+                    | Block::DefinitionList(_)
+                    | Block::HorizontalRule
+            );
+            if wrapped && attr != &Attr::default() {
+                let mut lines = attr_line(attr);
+                lines.extend(self.block(block, indent).unwrap_or_default());
+                return lines;
+            }
+        }
+        let fence = ":".repeat(3 + div_depth(blocks));
+        let (class, attr) = split_class(attr);
+        let mut lines = attr_line(&attr);
+        lines.push(format!("{} {}", fence, class).trim_end().to_owned());
+        lines.extend(self.blocks(blocks, indent));
+        lines.push(fence);
+        lines
+    }
+
+    /// Renders list items, putting each marker in front of the first line of
+    /// its item and indenting the rest to line up with the text after it.
+    fn list(
+        &mut self,
+        markers: Vec<String>,
+        items: &'a [Vec<Block>],
+        indent: usize,
+    ) -> Vec<String> {
+        let loose = items.iter().any(|item| is_loose(item));
+        let mut lines = Vec::new();
+        for (marker, item) in markers.into_iter().zip(items) {
+            if loose && !lines.is_empty() {
+                lines.push(String::new());
+            }
+            let width = marker.chars().count() + 1;
+            let item = self.blocks(item, indent + width);
+            lines.extend(list_item(marker, item));
+        }
+        lines
+    }
+
+    /// Renders the items of a task list, whose text follows their checkbox.
+    fn task_list(
+        &mut self,
+        tasks: Vec<(bool, &'a [Inline])>,
+        items: &'a [Vec<Block>],
+        indent: usize,
+    ) -> Vec<String> {
+        let loose = items.iter().any(|item| is_loose(item));
+        let mut lines = Vec::new();
+        for ((checked, text), item) in tasks.into_iter().zip(items) {
+            if loose && !lines.is_empty() {
+                lines.push(String::new());
+            }
+            let marker = if checked { "- [x]" } else { "- [ ]" };
+            let width = marker.len() + 1;
+            let mut item_lines = self.paragraph(text, indent + width);
+            if item_lines == [""] {
+                item_lines.clear();
+            }
+            let rest = self.blocks(&item[1..], indent + width);
+            if !rest.is_empty() {
+                item_lines.push(String::new());
+                item_lines.extend(rest);
+            }
+            lines.extend(list_item(marker.to_owned(), item_lines));
+        }
+        lines
+    }
+
+    /// Renders a table as a pipe table. Rows above the separator line are
+    /// the head, and cells spanning several rows or columns are written as
+    /// single cells.
+    fn table(&mut self, table: &'a Table, indent: usize) -> Vec<String> {
+        let mut rows: Vec<Vec<String>> = Vec::new();
+        let parts = table
+            .bodies
+            .iter()
+            .flat_map(|body| body.head.iter().chain(&body.body))
+            .chain(&table.foot.rows);
+        for row in table.head.rows.iter().chain(parts) {
+            let cells = row.cells.iter().map(|cell| self.cell(&cell.content));
+            rows.push(cells.collect());
+        }
+        let columns = rows
+            .iter()
+            .map(Vec::len)
+            .chain([table.colspecs.len()])
+            .max()
+            .unwrap_or(0);
+        let alignment = |column: usize| {
+            let spec = table.colspecs.get(column);
+            spec.map_or(Alignment::AlignDefault, |spec| spec.0.clone())
+        };
+        let widths: Vec<usize> = (0..columns)
+            .map(|column| {
+                rows.iter()
+                    .filter_map(|row| row.get(column))
+                    .map(|text| text.chars().count())
+                    .max()
+                    .unwrap_or(0)
+                    .max(3)
+            })
+            .collect();
+        let line = |cells: &[String]| {
+            let cells: Vec<_> = (0..columns)
+                .map(|column| {
+                    let text = cells.get(column).map_or("", String::as_str);
+                    pad(text, widths[column], &alignment(column))
+                })
+                .collect();
+            format!("| {} |", cells.join(" | "))
+        };
+        let rule: Vec<_> = widths
+            .iter()
+            .enumerate()
+            .map(|(column, &width)| match alignment(column) {
+                Alignment::AlignLeft => format!(":{}", "-".repeat(width + 1)),
+                Alignment::AlignRight => format!("{}:", "-".repeat(width + 1)),
+                Alignment::AlignCenter => format!(":{}:", "-".repeat(width)),
+                Alignment::AlignDefault => "-".repeat(width + 2),
+            })
+            .collect();
+
+        let mut lines = attr_line(&table.attr);
+        let head = table.head.rows.len();
+        lines.extend(rows[..head].iter().map(|row| line(row)));
+        let aligned = (0..columns).any(|column| alignment(column) != Alignment::AlignDefault);
+        if head > 0 || aligned {
+            lines.push(format!("|{}|", rule.join("|")));
+        }
+        lines.extend(rows[head..].iter().map(|row| line(row)));
+
+        let mut chunks = Vec::new();
+        for block in &table.caption.long {
+            if let Block::Plain(inlines) | Block::Para(inlines) = block {
+                if !chunks.is_empty() {
+                    chunks.push(Chunk::Space);
+                }
+                self.inlines(inlines, &mut chunks);
+            }
+        }
+        if !chunks.is_empty() {
+            let caption = self.layout(chunks, indent + 2);
+            lines.push(String::new());
+            lines.extend(super::indent(caption, "^ ", "  "));
+        }
+        lines
+    }
+
+    /// Renders the text of a table cell on a single line.
+    fn cell(&mut self, blocks: &'a [Block]) -> String {
+        let mut chunks = Vec::new();
+        for block in blocks {
+            if let Block::Plain(inlines) | Block::Para(inlines) = block {
+                if !chunks.is_empty() {
+                    chunks.push(Chunk::Space);
+                }
+                self.inlines(inlines, &mut chunks);
+            }
+        }
+        let chunks = join_lines(chunks);
+        let lines = self.unwrapped(|writer| writer.layout(chunks, 0));
+        lines.join(" ")
+    }
+
+    fn unwrapped(&mut self, f: impl FnOnce(&mut Self) -> Vec<String>) -> Vec<String> {
+        let unwrapped = std::mem::replace(&mut self.unwrapped, true);
+        let lines = f(self);
+        self.unwrapped = unwrapped;
+        lines
+    }
+
+    fn paragraph(&mut self, inlines: &'a [Inline], indent: usize) -> Vec<String> {
+        let mut chunks = Vec::new();
+        self.inlines(inlines, &mut chunks);
+        self.layout(chunks, indent)
+    }
+
+    /// Wraps chunks of text into lines, making sure that no line starts with
+    /// something that would be read as the start of a block.
+    fn layout(&self, chunks: Vec<Chunk>, indent: usize) -> Vec<String> {
+        let protected = protect(chunks, |word| escape_block_start(word, self.djot.smart));
+        let width = match self.unwrapped {
+            true => None,
+            false => self.options.width(indent),
+        };
+        wrap(&protected, width, self.options.wrap)
+    }
+
+    fn inlines(&mut self, inlines: &'a [Inline], chunks: &mut Vec<Chunk>) {
+        for inline in inlines {
+            self.inline(inline, chunks);
+        }
+    }
+
+    /// Renders inlines between delimiters, moving spaces at either end
+    /// outside of them so that the delimiters are recognized.
+    fn delimited(
+        &mut self,
+        open: &str,
+        inlines: &'a [Inline],
+        close: &str,
+        chunks: &mut Vec<Chunk>,
+    ) {
+        let mut inner = Vec::new();
+        self.inlines(inlines, &mut inner);
+        let start = inner.iter().position(|c| matches!(c, Chunk::Text(_)));
+        let end = inner.iter().rposition(|c| matches!(c, Chunk::Text(_)));
+        let (Some(start), Some(end)) = (start, end) else {
+            chunks.extend(inner);
+            return;
+        };
+        let after = inner.split_off(end + 1);
+        let middle = inner.split_off(start);
+        chunks.extend(inner);
+        chunks.push(Chunk::Text(open.to_owned()));
+        chunks.extend(middle);
+        chunks.push(Chunk::Text(close.to_owned()));
+        chunks.extend(after);
+    }
+
+    fn inline(&mut self, inline: &'a Inline, chunks: &mut Vec<Chunk>) {
+        match inline {
+            Inline::Str(s) => chunks.push(Chunk::Text(self.escape(s))),
+            Inline::Emph(inlines) => self.delimited("_", inlines, "_", chunks),
+            Inline::Strong(inlines) => self.delimited("*", inlines, "*", chunks),
+            Inline::Strikeout(inlines) => self.delimited("{-", inlines, "-}", chunks),
+            Inline::Underline(inlines) => self.delimited("[", inlines, "]{.underline}", chunks),
+            Inline::SmallCaps(inlines) => self.delimited("[", inlines, "]{.smallcaps}", chunks),
+            Inline::Superscript(inlines) => self.delimited("^", inlines, "^", chunks),
+            Inline::Subscript(inlines) => self.delimited("~", inlines, "~", chunks),
+            Inline::Quoted(quote, inlines) => {
+                let (open, close) = match (quote, self.djot.smart) {
+                    (QuoteType::SingleQuote, true) => ("'", "'"),
+                    (QuoteType::DoubleQuote, true) => ("\"", "\""),
+                    (QuoteType::SingleQuote, false) => ("‘", "’"),
+                    (QuoteType::DoubleQuote, false) => ("“", "”"),
+                };
+                self.delimited(open, inlines, close, chunks);
+            }
+            Inline::Cite(_, inlines) => self.inlines(inlines, chunks),
+            Inline::Code(attr, code) => {
+                let mut text = verbatim(&code.replace('\n', " "));
+                if attr != &Attr::default() {
+                    text.push_str(&djot_attr(attr));
+                }
+                chunks.push(Chunk::Text(text));
+            }
+            Inline::Space => chunks.push(Chunk::Space),
+            Inline::SoftBreak => chunks.push(Chunk::SoftBreak),
+            Inline::LineBreak => chunks.extend([Chunk::Text("\\".into()), Chunk::Newline]),
+            Inline::Math(kind, tex) => {
+                let dollars = match kind {
+                    MathType::DisplayMath => "$$",
+                    MathType::InlineMath => "$",
+                };
+                chunks.push(Chunk::Text(format!("{}{}", dollars, verbatim(tex))));
+            }
+            Inline::RawInline(Format(format), raw) => {
+                chunks.push(Chunk::Text(format!("{}{{={}}}", verbatim(raw), format)));
+            }
+            Inline::Link(attr, inlines, target) => {
+                let text = inlines.stringify();
+                let autolink = match attr.classes.as_slice() {
+                    [class] if class == "uri" => target.url == text && text.contains(':'),
+                    [class] if class == "email" => {
+                        target.url.strip_prefix("mailto:") == Some(text.as_str())
+                            && text.contains('@')
+                            && !text.contains(':')
+                    }
+                    _ => false,
+                };
+                let autolink = autolink
+                    && attr.identifier.is_empty()
+                    && attr.attributes.is_empty()
+                    && target.title.is_empty()
+                    && matches!(inlines.as_slice(), [Inline::Str(_)])
+                    && !text.contains(|c: char| c.is_whitespace() || c == '<' || c == '>');
+                if autolink {
+                    chunks.push(Chunk::Text(format!("<{}>", text)));
+                } else {
+                    self.link("[", attr, inlines, target, chunks);
+                }
+            }
+            Inline::Image(attr, inlines, target) => self.link("![", attr, inlines, target, chunks),
+            Inline::Note(blocks) => {
+                self.notes.push(blocks);
+                chunks.push(Chunk::Text(format!("[^{}]", self.notes.len())));
+            }
+            Inline::Span(attr, inlines) => {
+                let class = match attr {
+                    Attr {
+                        identifier,
+                        classes,
+                        attributes,
+                    } if identifier.is_empty() && attributes.is_empty() && classes.len() == 1 => {
+                        classes[0].as_str()
+                    }
+                    _ => "",
+                };
+                match (class, inlines.as_slice()) {
+                    _ if attr == &Attr::default() => self.inlines(inlines, chunks),
+                    ("mark", _) => self.delimited("{=", inlines, "=}", chunks),
+                    ("inserted", _) => self.delimited("{+", inlines, "+}", chunks),
+                    ("symbol", [Inline::Str(name)]) if is_symbol_name(name) => {
+                        chunks.push(Chunk::Text(format!(":{}:", name)));
+                    }
+                    _ => {
+                        chunks.push(Chunk::Text("[".into()));
+                        self.inlines(inlines, chunks);
+                        chunks.push(Chunk::Text(format!("]{}", djot_attr(attr))));
+                    }
+                }
+            }
+        }
+    }
+
+    /// Renders a link or image, writing its title as an attribute.
+    fn link(
+        &mut self,
+        open: &str,
+        attr: &Attr,
+        inlines: &'a [Inline],
+        target: &Target,
+        chunks: &mut Vec<Chunk>,
+    ) {
+        chunks.push(Chunk::Text(open.into()));
+        self.inlines(inlines, chunks);
+        let mut url = String::new();
+        for c in target.url.chars() {
+            if matches!(c, '\\' | '(' | ')') {
+                url.push('\\');
+            }
+            url.push(c);
+        }
+        chunks.push(Chunk::Text(format!("]({})", url)));
+        let mut attr = attr.clone();
+        if !target.title.is_empty() {
+            attr.attributes
+                .push(("title".to_owned(), target.title.clone()));
+        }
+        if attr != Attr::default() {
+            chunks.push(Chunk::Text(djot_attr(&attr)));
+        }
+    }
+
+    /// Escapes the characters of text that have a meaning in djot. With
+    /// smart punctuation, straight quotes and runs of hyphens and periods
+    /// are escaped, and dashes and ellipses are written as `---`, `--` and
+    /// `...` where that reads back the same.
+    fn escape(&self, s: &str) -> String {
+        let smart = self.djot.smart;
+        let chars: Vec<char> = s.chars().collect();
+        let mut out = String::with_capacity(s.len());
+        for (i, &c) in chars.iter().enumerate() {
+            let previous = i.checked_sub(1).map(|i| chars[i]);
+            let next = chars.get(i + 1).copied();
+            let dash = |c: Option<char>| matches!(c, Some('-' | '–' | '—'));
+            let escape = match c {
+                '\\' | '`' | '*' | '_' | '[' | ']' | '{' | '}' | '<' | '^' | '~' | '$' | '|' => {
+                    true
+                }
+                '"' | '\'' => smart,
+                '-' => smart && next == Some('-'),
+                '.' => smart && next == Some('.') && chars.get(i + 2) == Some(&'.'),
+                ':' => {
+                    let name = chars[i + 1..]
+                        .iter()
+                        .take_while(|&&c| is_symbol_char(c))
+                        .count();
+                    name > 0 && chars.get(i + 1 + name) == Some(&':')
+                }
+                '\u{a0}' => {
+                    out.push_str("\\ ");
+                    continue;
+                }
+                '—' | '–' if smart && !dash(previous) && !dash(next) => {
+                    out.push_str(if c == '—' { "---" } else { "--" });
+                    continue;
+                }
+                '…' if smart && previous != Some('.') => {
+                    out.push_str("...");
+                    continue;
+                }
+                _ => false,
+            };
+            if escape {
+                out.push('\\');
+            }
+            out.push(c);
+        }
+        out
+    }
+}
+
+/// Puts a list marker in front of the first line of an item and indents the
+/// rest to line up with the text after it.
+fn list_item(marker: String, lines: Vec<String>) -> Vec<String> {
+    if lines.is_empty() {
+        return vec![marker];
+    }
+    let width = marker.chars().count() + 1;
+    indent(lines, &format!("{} ", marker), &" ".repeat(width))
+}
+
+/// Returns a word escaped so that it doesn't start a block at the start of
+/// a line, if it would. Only punctuation can be escaped in djot.
+fn escape_block_start(word: &str, smart: bool) -> Option<String> {
+    // With smart punctuation, hyphens in text are escaped, so `--` and `---`
+    // can only stand for a dash, which is kept as the character itself.
+    match word {
+        "--" if smart => return Some("–".to_owned()),
+        "---" if smart => return Some("—".to_owned()),
+        _ => {}
+    }
+    let i = block_start(word)?;
+    Some(format!("{}\\{}", &word[..i], &word[i..]))
+}
+
+/// Returns where to put a backslash in a word that would start a block if
+/// it was at the start of a line.
+fn block_start(word: &str) -> Option<usize> {
+    let first = word.chars().next()?;
+    if ["-", "+", "*", ":", ">"].contains(&word) {
+        return Some(0);
+    }
+    if word.chars().all(|c| c == first) && matches!(first, '#' | '-' | '*' | ':') {
+        return Some(0);
+    }
+    // Ordered list markers such as `1.`, `a)` or `(iv)`.
+    let (_, number) = ordered_marker(word)?;
+    (is_decimal(number) || is_fancy(number)).then_some(word.len() - 1)
+}
+
+fn is_symbol_char(c: char) -> bool {
+    c.is_ascii_alphanumeric() || matches!(c, '_' | '+' | '-')
+}
+
+fn is_symbol_name(name: &str) -> bool {
+    !name.is_empty() && name.chars().all(is_symbol_char)
+}
+
+/// Writes verbatim text between enough backticks, with spaces separating
+/// backticks at either end from the delimiters.
+fn verbatim(text: &str) -> String {
+    let ticks = "`".repeat(longest_run(text, '`') + 1);
+    let start = if text.starts_with('`') { " " } else { "" };
+    let end = if text.ends_with('`') { " " } else { "" };
+    format!("{0}{1}{2}{3}{0}", ticks, start, text, end)
+}
+
+/// Splits off the first class of attributes, which djot writes after the
+/// fence of a code block or div.
+fn split_class(attr: &Attr) -> (String, Attr) {
+    let mut attr = attr.clone();
+    let class = match attr.classes.is_empty() {
+        true => String::new(),
+        false => attr.classes.remove(0),
+    };
+    (class, attr)
+}
+
+/// Returns the line holding the attributes of a block, if it has any.
+fn attr_line(attr: &Attr) -> Vec<String> {
+    match attr == &Attr::default() {
+        true => Vec::new(),
+        false => vec![djot_attr(attr)],
+    }
+}
+
+/// Formats attributes in djot's syntax, such as `{#id .class key="value"}`.
+fn djot_attr(attr: &Attr) -> String {
+    let mut parts = Vec::new();
+    if !attr.identifier.is_empty() {
+        parts.push(format!("#{}", attr.identifier));
+    }
+    parts.extend(attr.classes.iter().map(|class| format!(".{}", class)));
+    for (key, value) in &attr.attributes {
+        let value = value.replace('\\', "\\\\").replace('"', "\\\"");
+        parts.push(format!("{}=\"{}\"", key, value));
+    }
+    format!("{{{}}}", parts.join(" "))
+}
+
+/// Returns the maximum nesting depth of divs in the blocks, so that the
+/// fences of a div can be longer than those of the divs inside it.
+fn div_depth<'a>(blocks: impl IntoIterator<Item = &'a Block>) -> usize {
+    blocks
+        .into_iter()
+        .map(|block| match block {
+            Block::Div(_, blocks) => 1 + div_depth(blocks),
+            Block::Figure(_, caption, blocks) => 1 + div_depth(blocks.iter().chain(&caption.long)),
+            block => div_depth(block.iter_blocks()),
+        })
+        .max()
+        .unwrap_or(0)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::builder::*;
+    use crate::definition::Alignment::*;
+    use crate::definition::{ListAttributes, ListNumberDelim, ListNumberStyle};
+    use crate::readers::djot::read_djot;
+    use crate::writers::WrapOption;
+    use proptest::prelude::*;
+
+    fn attr<const N: usize, const M: usize>(
+        id: &str,
+        classes: [&str; N],
+        attributes: [(&str, &str); M],
+    ) -> Attr {
+        Attr {
+            identifier: id.into(),
+            classes: classes.map(Into::into).into(),
+            attributes: attributes.map(|(k, v)| (k.into(), v.into())).into(),
+        }
+    }
+
+    fn djot(blocks: Blocks) -> String {
+        blocks_to_djot(&blocks, &DjotOptions::default(), &WriterOptions::default())
+    }
+
+    #[test]
+    fn escaping() {
+        let blocks = para("*a* _b_ [c] {d} <e> ^f^ ~g~ $5 a|b :smile: 10:30")
+            + para("\"quoted\" it's a -- b... — – …")
+            + para(text("1. not a list, ") + text("-") + soft_break() + text("B."))
+            + plain("# not a heading");
+        assert_eq!(
+            djot(blocks.clone()),
+            "\\*a\\* \\_b\\_ \\[c\\] \\{d\\} \\<e> \\^f\\^ \\~g\\~ \\$5 a\\|b \\:smile: 10:30\n\n\
+             \\\"quoted\\\" it\\'s a \\-- b\\... --- -- ...\n\n\
+             1\\. not a list, - B.\n\n\\# not a heading\n"
+        );
+        let options = DjotOptions {
+            smart: false,
+            ..DjotOptions::default()
+        };
+        assert_eq!(
+            blocks_to_djot(&blocks, &options, &WriterOptions::default())
+                .lines()
+                .nth(2),
+            Some("\"quoted\" it's a -- b... — – …")
+        );
+    }
+
+    #[test]
+    fn inlines() {
+        let blocks = para(
+            emph(text("a ") + strong("b"))
+                + space()
+                + strikeout("c")
+                + superscript("2")
+                + subscript("i")
+                + space()
+                + underline("u")
+                + space()
+                + span_with(attr("", ["mark"], []), "m")
+                + span_with(attr("", ["symbol"], []), "heart")
+                + space()
+                + quoted(QuoteType::DoubleQuote, "q")
+                + space()
+                + code_with(attr("", ["rust"], []), "a `b`")
+                + space()
+                + math("x^2")
+                + space()
+                + raw_inline("html", "<br>")
+                + line_break()
+                + link_with(attr("", ["uri"], []), "https://x.y", "", "https://x.y")
+                + space()
+                + link("/a(b", "T", "link")
+                + space()
+                + image("i.png", "", "alt"),
+        );
+        assert_eq!(
+            djot(blocks),
+            "_a *b*_ {-c-}^2^~i~ [u]{.underline} {=m=}:heart: \"q\" ``a `b` ``{.rust}\n\
+             $`x^2` `<br>`{=html}\\\n<https://x.y> [link](/a\\(b){title=\"T\"} ![alt](i.png)\n"
+        );
+    }
+
+    #[test]
+    fn sections_and_attributes() {
+        let section = |id: &str, blocks: Blocks| div_with(attr(id, ["section"], []), blocks);
+        let blocks = section(
+            "Hello-world",
+            header(1, "Hello world") + section("intro", header_with(attr("", ["x"], []), 2, "Hi")),
+        ) + code_block_with(attr("code", ["rust", "numberLines"], []), "let x = 1;")
+            + div_with(attr("", ["note"], []), para("a"))
+            + div_with(attr("", ["warning", "big"], []), para("b") + para("c"))
+            + figure_with(
+                Attr::default(),
+                simple_caption(plain("Caption")),
+                plain(image("a.png", "", "A")),
+            );
+        assert_eq!(
+            djot(blocks),
+            "# Hello world\n\n{#intro .x}\n## Hi\n\n\
+             {#code .numberLines}\n``` rust\nlet x = 1;\n```\n\n\
+             {.note}\na\n\n{.big}\n::: warning\nb\n\nc\n:::\n\n\
+             ::: figure\n![A](a.png)\n\nCaption\n:::\n"
+        );
+    }
+
+    #[test]
+    fn lists_and_notes() {
+        let blocks = bullet_list([plain("one"), plain("two") + bullet_list([plain("three")])])
+            + bullet_list([para(text("four") + note(para("A note.") + para("More.")))])
+            + ordered_list_with(
+                ListAttributes {
+                    start_number: 3,
+                    style: ListNumberStyle::LowerRoman,
+                    delim: ListNumberDelim::TwoParens,
+                },
+                [plain("x")],
+            )
+            + bullet_list([plain("☐ to do"), plain("☒ done")])
+            + definition_list([(text("term"), vec![plain("definition")])]);
+        assert_eq!(
+            djot(blocks),
+            "- one\n- two\n\n  - three\n\n{% %}\n\n- four[^1]\n\n(iii) x\n\n\
+             - [ ] to do\n- [x] done\n\n: term\n\n  definition\n\n\
+             [^1]: A note.\n\n    More.\n"
+        );
+    }
+
+    #[test]
+    fn tables() {
+        let table = TableBuilder::new()
+            .caption(simple_caption(plain("Caption")))
+            .alignments([AlignRight, AlignDefault])
+            .header([plain("A"), plain("B")])
+            .body([vec![
+                cell(AlignDefault, 1, 1, plain("1")),
+                cell(AlignDefault, 1, 1, plain("a|b")),
+            ]])
+            .build();
+        assert_eq!(
+            djot(Blocks::from(table)),
+            "|   A | B    |\n|----:|------|\n|   1 | a\\|b |\n\n^ Caption\n"
+        );
+    }
+
+    #[test]
+    fn round_trip() {
+        let input = "# Title\n\nSome _emph_, *strong* and {=marked=} text with a [link][] and\n\
+                     a note.[^1] It’s “quoted” --- or 'not'...\n\n\
+                     {#q}\n> - [x] done\n> - [ ] to do\n\n\
+                     1. one\n\n   two\n\n2. three\n\n\
+                     | a | b |\n|:-:|---|\n| 1 | 2 |\n\n^ Numbers\n\n\
+                     ## Code {.c}\n\n``` rust\nfn main() {}\n```\n\n\
+                     [link]: /url\n\n[^1]: The note.\n";
+        let options = DjotOptions::default();
+        let doc = read_djot(input, &options);
+        let writer_options = WriterOptions {
+            wrap: WrapOption::Preserve,
+            ..WriterOptions::default()
+        };
+        let output = write_djot(&doc, &options, &writer_options);
+        assert_eq!(read_djot(&output, &options), doc);
+    }
+
+    #[test]
+    fn empty_block_quote() {
+        let options = DjotOptions::default();
+        let doc = read_djot(">", &options);
+        assert_eq!(doc.blocks, block_quote(Blocks::new()).into_vec());
+        let output = write_djot(&doc, &options, &WriterOptions::default());
+        assert_eq!(output, ">\n");
+        assert_eq!(read_djot(&output, &options), doc);
+    }
+
+    #[test]
+    fn dash_at_line_start() {
+        let options = DjotOptions::default();
+        for dash in ["–", "—"] {
+            let doc = doc(para(str(dash) + space() + str("quoted")));
+            let output = write_djot(&doc, &options, &WriterOptions::default());
+            assert_eq!(output, format!("{} quoted\n", dash));
+            assert_eq!(read_djot(&output, &options), doc);
+        }
+        let options = DjotOptions {
+            smart: false,
+            ..DjotOptions::default()
+        };
+        for hyphens in ["--", "---", "----"] {
+            let doc = doc(para(str(hyphens) + space() + str("x")));
+            let output = write_djot(&doc, &options, &WriterOptions::default());
+            assert_eq!(output, format!("\\{} x\n", hyphens));
+            assert_eq!(read_djot(&output, &options), doc);
+        }
+    }
+
+    #[test]
+    fn empty_note() {
+        let options = DjotOptions::default();
+        let doc = doc(para(text("a") + note(plain(Inlines::new()))));
+        let output = write_djot(&doc, &options, &WriterOptions::default());
+        assert_eq!(output, "a[^1]\n\n[^1]:\n");
+        assert_eq!(
+            read_djot(&output, &options).blocks,
+            para(text("a") + note(Blocks::new())).into_vec()
+        );
+    }
+
+    proptest! {
+        #![proptest_config(ProptestConfig::with_cases(64))]
+
+        #[test]
+        fn reads_written_documents(doc in any::<Pandoc>()) {
+            let options = DjotOptions::default();
+            let output = write_djot(&doc, &options, &WriterOptions::default());
+            read_djot(&output, &options);
+        }
+    }
+}
@@ -0,0 +1,818 @@
+//! A writer for HTML5, like `pandoc -t html5`.
+//!
+//! The writer produces a fragment suitable for including in a page, with
+//! footnotes collected into an endnotes section at the end. Attributes are
+//! written as `id`, `class` and `data-*` attributes, dropping keys that
+//! aren't valid attribute names, and raw blocks and inlines are only kept if
+//! their format is `html`. Lines aren't wrapped.
+//!
+//! ```
+//! use pandoc_types::builder::*;
+//! use pandoc_types::writers::{html::write_html, HtmlMathMethod, WriterOptions};
+//!
+//! let doc = doc(para(text("Euler: ") + math("e^{i\\pi} = -1") + text(" & more")));
+//!
+//! assert_eq!(
+//!     write_html(&doc, &WriterOptions::default()),
+//!     "<p>Euler: <span class=\"math inline\">e^{i\\pi} = -1</span> &amp; more</p>\n"
+//! );
+//!
+//! let options = WriterOptions {
+//!     html_math_method: HtmlMathMethod::MathJax,
+//!     ..Default::default()
+//! };
+//! assert!(write_html(&doc, &options).contains(r#"<span class="math inline">\(e^{i\pi} = -1\)</span>"#));
+//! ```
+use std::fmt::Write;
+
+use super::{HtmlMathMethod, WrapOption, WriterOptions};
+use crate::definition::{
+    Alignment, Attr, Block, Caption, Citation, ColWidth, Format, GridSlot, Inline, ListAttributes,
+    ListNumberStyle, MathType, Pandoc, QuoteType, Stringify, Table, TablePart, Target,
+};
+
+/// Renders a document as an HTML fragment.
+pub fn write_html(doc: &Pandoc, options: &WriterOptions) -> String {
+    blocks_to_html(&doc.blocks, options)
+}
+
+/// Renders a list of blocks as an HTML fragment, followed by a section with
+/// their footnotes.
+pub fn blocks_to_html(blocks: &[Block], options: &WriterOptions) -> String {
+    let mut writer = Writer {
+        options,
+        out: String::new(),
+        notes: Vec::new(),
+    };
+    writer.blocks(blocks);
+
+    if !writer.notes.is_empty() {
+        writer.newline();
+        writer.out.push_str(concat!(
+            "<section id=\"footnotes\" class=\"footnotes footnotes-end-of-document\"",
+            " role=\"doc-endnotes\">\n<hr />\n<ol>\n"
+        ));
+        // Footnotes may contain more footnotes, which are numbered after them.
+        let mut i = 0;
+        while i < writer.notes.len() {
+            writer.note(i + 1, writer.notes[i]);
+            i += 1;
+        }
+        writer.out.push_str("</ol>\n</section>");
+    }
+    if !writer.out.is_empty() {
+        writer.out.push('\n');
+    }
+    writer.out
+}
+
+/// Renders a list of blocks as HTML inside a document written by another
+/// writer. Their footnotes are numbered after `notes` and added to it, to be
+/// written with the notes of that document, rather than in a section of
+/// their own.
+pub(crate) fn blocks_to_html_with_notes<'a>(
+    blocks: &'a [Block],
+    options: &'a WriterOptions,
+    notes: &mut Vec<&'a [Block]>,
+) -> String {
+    let mut writer = Writer {
+        options,
+        out: String::new(),
+        notes: std::mem::take(notes),
+    };
+    writer.blocks(blocks);
+    *notes = writer.notes;
+    writer.out
+}
+
+/// Attributes that are written as they are, rather than as `data-*`
+/// attributes.
+const HTML_ATTRIBUTES: &[&str] = &[
+    "accesskey",
+    "align",
+    "alt",
+    "autofocus",
+    "colspan",
+    "contenteditable",
+    "dir",
+    "download",
+    "draggable",
+    "headers",
+    "height",
+    "hidden",
+    "href",
+    "hreflang",
+    "lang",
+    "loading",
+    "media",
+    "rel",
+    "role",
+    "rowspan",
+    "scope",
+    "spellcheck",
+    "src",
+    "srcset",
+    "start",
+    "style",
+    "tabindex",
+    "target",
+    "title",
+    "translate",
+    "type",
+    "width",
+];
+
+struct Writer<'a> {
+    options: &'a WriterOptions,
+    out: String,
+    notes: Vec<&'a [Block]>,
+}
+
+impl<'a> Writer<'a> {
+    fn newline(&mut self) {
+        if !self.out.is_empty() && !self.out.ends_with('\n') {
+            self.out.push('\n');
+        }
+    }
+
+    fn text(&mut self, s: &str) {
+        escape_to(&mut self.out, s);
+    }
+
+    fn attr(&mut self, attr: &Attr) {
+        attr_to(&mut self.out, attr);
+    }
+
+    fn attr_except(&mut self, attr: &Attr, written: &[&str]) {
+        attr_except_to(&mut self.out, attr, written);
+    }
+
+    fn attribute(&mut self, key: &str, value: &str) {
+        attribute_to(&mut self.out, key, value);
+    }
+
+    fn start_tag(&mut self, tag: &str, attr: &Attr) {
+        write!(self.out, "<{}", tag).unwrap();
+        self.attr(attr);
+        self.out.push('>');
+    }
+
+    fn blocks(&mut self, blocks: &'a [Block]) {
+        for block in blocks {
+            self.block(block);
+        }
+    }
+
+    fn block(&mut self, block: &'a Block) {
+        if matches!(block, Block::Null) || matches!(block, Block::RawBlock(f, _) if !is_html(f)) {
+            return;
+        }
+        self.newline();
+        match block {
+            Block::Plain(inlines) => self.inlines(inlines),
+            Block::Para(inlines) => {
+                self.out.push_str("<p>");
+                self.inlines(inlines);
+                self.out.push_str("</p>");
+            }
+            Block::LineBlock(lines) => {
+                self.out.push_str("<div class=\"line-block\">");
+                for (i, line) in lines.iter().enumerate() {
+                    if i > 0 {
+                        self.out.push_str("<br />\n");
+                    }
+                    self.inlines(line);
+                }
+                self.out.push_str("</div>");
+            }
+            Block::CodeBlock(attr, code) => {
+                self.start_tag("pre", attr);
+                self.out.push_str("<code>");
+                self.text(code);
+                self.out.push_str("</code></pre>");
+            }
+            Block::RawBlock(_, raw) => self.out.push_str(raw),
+            Block::BlockQuote(blocks) => {
+                self.out.push_str("<blockquote>");
+                self.blocks(blocks);
+                self.newline();
+                self.out.push_str("</blockquote>");
+            }
+            Block::OrderedList(attrs, items) => {
+                self.out.push_str("<ol");
+                self.list_attributes(attrs);
+                self.out.push('>');
+                self.list_items(items);
+                self.out.push_str("</ol>");
+            }
+            Block::BulletList(items) => {
+                self.out.push_str("<ul>");
+                self.list_items(items);
+                self.out.push_str("</ul>");
+            }
+            Block::DefinitionList(items) => {
+                self.out.push_str("<dl>");
+                for (term, definitions) in items {
+                    self.newline();
+                    self.out.push_str("<dt>");
+                    self.inlines(term);
+                    self.out.push_str("</dt>");
+                    for definition in definitions {
+                        self.newline();
+                        self.out.push_str("<dd>");
+                        self.item(definition);
+                        self.out.push_str("</dd>");
+                    }
+                }
+                self.newline();
+                self.out.push_str("</dl>");
+            }
+            Block::Header(level, attr, inlines) => {
+                // HTML only has six levels of headings.
+                let tag = match level {
+                    1..=6 => format!("h{}", level),
+                    _ => "p".to_owned(),
+                };
+                let mut attr = attr.clone();
+                if tag == "p" {
+                    attr.classes.insert(0, "heading".to_owned());
+                }
+                self.start_tag(&tag, &attr);
+                self.inlines(inlines);
+                write!(self.out, "</{}>", tag).unwrap();
+            }
+            Block::HorizontalRule => self.out.push_str("<hr />"),
+            Block::Table(table) => self.table(table),
+            Block::Figure(attr, caption, blocks) => {
+                self.start_tag("figure", attr);
+                self.blocks(blocks);
+                if !caption.long.is_empty() {
+                    self.newline();
+                    self.out.push_str("<figcaption>");
+                    self.item(&caption.long);
+                    self.out.push_str("</figcaption>");
+                }
+                self.newline();
+                self.out.push_str("</figure>");
+            }
+            Block::Div(attr, blocks) => {
+                self.start_tag("div", attr);
+                self.blocks(blocks);
+                self.newline();
+                self.out.push_str("</div>");
+            }
+            Block::Null => {}
+        }
+    }
+
+    fn list_attributes(&mut self, attrs: &ListAttributes) {
+        if attrs.start_number != 1 {
+            write!(self.out, " start=\"{}\"", attrs.start_number).unwrap();
+        }
+        let kind = match attrs.style {
+            ListNumberStyle::LowerRoman => Some("i"),
+            ListNumberStyle::UpperRoman => Some("I"),
+            ListNumberStyle::LowerAlpha => Some("a"),
+            ListNumberStyle::UpperAlpha => Some("A"),
+            ListNumberStyle::Example => {
+                self.out.push_str(" class=\"example\"");
+                None
+            }
+            ListNumberStyle::DefaultStyle | ListNumberStyle::Decimal => None,
+        };
+        if let Some(kind) = kind {
+            write!(self.out, " type=\"{}\"", kind).unwrap();
+        }
+    }
+
+    fn list_items(&mut self, items: &'a [Vec<Block>]) {
+        for item in items {
+            self.newline();
+            self.out.push_str("<li>");
+            self.item(item);
+            self.out.push_str("</li>");
+        }
+        self.newline();
+    }
+
+    /// Writes the contents of a list item or caption, which start on the
+    /// same line as the opening tag.
+    fn item(&mut self, blocks: &'a [Block]) {
+        let start = self.out.len();
+        self.blocks(blocks);
+        // Writing the first block added a line break after the opening tag.
+        if self.out[start..].starts_with('\n') {
+            self.out.remove(start);
+        }
+    }
+
+    fn table(&mut self, table: &'a Table) {
+        self.start_tag("table", &table.attr);
+        self.caption(&table.caption);
+
+        if table
+            .colspecs
+            .iter()
+            .any(|spec| spec.1 != Default::default())
+        {
+            self.out.push_str("\n<colgroup>");
+            for spec in &table.colspecs {
+                self.out.push_str("\n<col");
+                if let ColWidth::ColWidth(width) = spec.1 {
+                    write!(self.out, " style=\"width: {}%\"", percent(width)).unwrap();
+                }
+                self.out.push_str(" />");
+            }
+            self.out.push_str("\n</colgroup>");
+        }
+
+        let grid = table.grid();
+        let mut section = None;
+        for (r, row) in grid.rows.iter().enumerate() {
+            let (tag, part) = match row.part {
+                TablePart::Head => ("thead", None),
+                TablePart::BodyHead(i) | TablePart::Body(i) => ("tbody", Some(i)),
+                TablePart::Foot => ("tfoot", None),
+            };
+            if section != Some((tag, part)) {
+                if let Some((tag, _)) = section {
+                    write!(self.out, "\n</{}>", tag).unwrap();
+                }
+                match part {
+                    Some(i) => {
+                        self.out.push('\n');
+                        self.start_tag(tag, &table.bodies[i].attr);
+                    }
+                    None if tag == "thead" => {
+                        self.out.push('\n');
+                        self.start_tag(tag, &table.head.attr);
+                    }
+                    None => {
+                        self.out.push('\n');
+                        self.start_tag(tag, &table.foot.attr);
+                    }
+                }
+                section = Some((tag, part));
+            }
+
+            let row_head_columns = match row.part {
+                TablePart::Body(i) => table.bodies[i].row_head_columns.max(0) as usize,
+                _ => 0,
+            };
+            self.out.push('\n');
+            self.start_tag("tr", &row.row.attr);
+            for (column, slot) in row.slots.iter().enumerate() {
+                let GridSlot::Origin(cell) = slot else {
+                    continue;
+                };
+                let header = matches!(row.part, TablePart::Head | TablePart::BodyHead(_))
+                    || column < row_head_columns;
+                let tag = if header { "th" } else { "td" };
+                self.out.push('\n');
+                write!(self.out, "<{}", tag).unwrap();
+                self.attr_except(&cell.attr, &["style", "rowspan", "colspan"]);
+                let align = match cell.align {
+                    Alignment::AlignDefault => &table.colspecs[column].0,
+                    ref align => align,
+                };
+                // The alignment goes before any style the cell already has.
+                let style = cell.attr.attributes.iter().find(|(key, _)| key == "style");
+                match (align_name(align), style) {
+                    (Some(align), Some((_, style))) => {
+                        self.attribute("style", &format!("text-align: {}; {}", align, style))
+                    }
+                    (Some(align), None) => {
+                        self.attribute("style", &format!("text-align: {};", align))
+                    }
+                    (None, Some((_, style))) => self.attribute("style", style),
+                    (None, None) => {}
+                }
+                // The grid may have truncated spans that don't fit.
+                let row_span = (r..grid.rows.len())
+                    .take_while(|&i| {
+                        grid.cell_at(i, column).map(|(r, c, _)| (r, c)) == Some((r, column))
+                    })
+                    .count();
+                let col_span = (column..grid.width)
+                    .take_while(|&i| {
+                        grid.cell_at(r, i).map(|(r, c, _)| (r, c)) == Some((r, column))
+                    })
+                    .count();
+                if row_span > 1 {
+                    write!(self.out, " rowspan=\"{}\"", row_span).unwrap();
+                }
+                if col_span > 1 {
+                    write!(self.out, " colspan=\"{}\"", col_span).unwrap();
+                }
+                self.out.push('>');
+                self.item(&cell.content);
+                write!(self.out, "</{}>", tag).unwrap();
+            }
+            self.out.push_str("\n</tr>");
+        }
+        if let Some((tag, _)) = section {
+            write!(self.out, "\n</{}>", tag).unwrap();
+        }
+        self.out.push_str("\n</table>");
+    }
+
+    fn caption(&mut self, caption: &'a Caption) {
+        if !caption.long.is_empty() {
+            self.out.push_str("\n<caption>");
+            self.item(&caption.long);
+            self.out.push_str("</caption>");
+        }
+    }
+
+    /// Writes a footnote as an item of the endnotes list, with a link back
+    /// to the reference at the end of its last paragraph.
+    fn note(&mut self, number: usize, blocks: &'a [Block]) {
+        write!(self.out, "<li id=\"fn{}\">", number).unwrap();
+        let backlink = format!(
+            "<a href=\"#fnref{}\" class=\"footnote-back\" role=\"doc-backlink\">↩︎</a>",
+            number
+        );
+        self.item(blocks);
+        match blocks.last() {
+            Some(Block::Para(_)) if self.out.ends_with("</p>") => {
+                let end = self.out.len() - "</p>".len();
+                self.out.insert_str(end, &backlink);
+            }
+            Some(Block::Plain(_)) => self.out.push_str(&backlink),
+            _ => {
+                self.newline();
+                write!(self.out, "<p>{}</p>", backlink).unwrap();
+            }
+        }
+        self.out.push_str("</li>\n");
+    }
+
+    fn inlines(&mut self, inlines: &'a [Inline]) {
+        for inline in inlines {
+            self.inline(inline);
+        }
+    }
+
+    fn tagged(&mut self, tag: &str, inlines: &'a [Inline]) {
+        write!(self.out, "<{}>", tag).unwrap();
+        self.inlines(inlines);
+        write!(self.out, "</{}>", tag).unwrap();
+    }
+
+    fn inline(&mut self, inline: &'a Inline) {
+        match inline {
+            Inline::Str(s) => self.text(s),
+            Inline::Emph(inlines) => self.tagged("em", inlines),
+            Inline::Underline(inlines) => self.tagged("u", inlines),
+            Inline::Strong(inlines) => self.tagged("strong", inlines),
+            Inline::Strikeout(inlines) => self.tagged("del", inlines),
+            Inline::Superscript(inlines) => self.tagged("sup", inlines),
+            Inline::Subscript(inlines) => self.tagged("sub", inlines),
+            Inline::SmallCaps(inlines) => {
+                self.out.push_str("<span class=\"smallcaps\">");
+                self.inlines(inlines);
+                self.out.push_str("</span>");
+            }
+            Inline::Quoted(quote, inlines) => {
+                let (open, close) = match quote {
+                    QuoteType::SingleQuote => ("‘", "’"),
+                    QuoteType::DoubleQuote => ("“", "”"),
+                };
+                self.out.push_str(open);
+                self.inlines(inlines);
+                self.out.push_str(close);
+            }
+            Inline::Cite(citations, inlines) => {
+                self.out.push_str("<span class=\"citation\"");
+                let ids: Vec<_> = citations
+                    .iter()
+                    .map(|c: &Citation| c.citation_id.as_str())
+                    .collect();
+                self.attribute("data-cites", &ids.join(" "));
+                self.out.push('>');
+                self.inlines(inlines);
+                self.out.push_str("</span>");
+            }
+            Inline::Code(attr, code) => {
+                self.start_tag("code", attr);
+                self.text(code);
+                self.out.push_str("</code>");
+            }
+            Inline::Space => self.out.push(' '),
+            Inline::SoftBreak => match self.options.wrap {
+                WrapOption::Preserve => self.out.push('\n'),
+                WrapOption::Auto | WrapOption::None => self.out.push(' '),
+            },
+            Inline::LineBreak => self.out.push_str("<br />\n"),
+            Inline::Math(kind, tex) => {
+                let (class, open, close) = match kind {
+                    MathType::InlineMath => ("inline", "\\(", "\\)"),
+                    MathType::DisplayMath => ("display", "\\[", "\\]"),
+                };
+                write!(self.out, "<span class=\"math {}\">", class).unwrap();
+                match self.options.html_math_method {
+                    HtmlMathMethod::Plain => self.text(tex),
+                    HtmlMathMethod::MathJax => {
+                        self.out.push_str(open);
+                        self.text(tex);
+                        self.out.push_str(close);
+                    }
+                }
+                self.out.push_str("</span>");
+            }
+            Inline::RawInline(format, raw) => {
+                if is_html(format) {
+                    self.out.push_str(raw);
+                }
+            }
+            Inline::Link(attr, inlines, Target { url, title }) => {
+                self.out.push_str("<a");
+                self.attribute("href", url);
+                match title.is_empty() {
+                    true => self.attr_except(attr, &["href"]),
+                    false => self.attr_except(attr, &["href", "title"]),
+                }
+                if !title.is_empty() {
+                    self.attribute("title", title);
+                }
+                self.out.push('>');
+                self.inlines(inlines);
+                self.out.push_str("</a>");
+            }
+            Inline::Image(attr, inlines, Target { url, title }) => {
+                self.out.push_str("<img");
+                self.attribute("src", url);
+                match title.is_empty() {
+                    true => self.attr_except(attr, &["src", "alt"]),
+                    false => self.attr_except(attr, &["src", "alt", "title"]),
+                }
+                if !title.is_empty() {
+                    self.attribute("title", title);
+                }
+                self.attribute("alt", &inlines.stringify());
+                self.out.push_str(" />");
+            }
+            Inline::Note(blocks) => {
+                self.notes.push(blocks);
+                let n = self.notes.len();
+                write!(
+                    self.out,
+                    concat!(
+                        "<a href=\"#fn{0}\" class=\"footnote-ref\" id=\"fnref{0}\"",
+                        " role=\"doc-noteref\"><sup>{0}</sup></a>"
+                    ),
+                    n
+                )
+                .unwrap();
+            }
+            Inline::Span(attr, inlines) => {
+                self.start_tag("span", attr);
+                self.inlines(inlines);
+                self.out.push_str("</span>");
+            }
+        }
+    }
+}
+
+fn is_html(format: &Format) -> bool {
+    matches!(format.0.as_str(), "html" | "html4" | "html5")
+}
+
+fn align_name(align: &Alignment) -> Option<&'static str> {
+    match align {
+        Alignment::AlignLeft => Some("left"),
+        Alignment::AlignRight => Some("right"),
+        Alignment::AlignCenter => Some("center"),
+        Alignment::AlignDefault => None,
+    }
+}
+
+/// Formats a relative column width as a percentage with at most two
+/// decimals, e.g. `15` or `27.78`.
+fn percent(width: f64) -> String {
+    let s = format!("{:.2}", width * 100.0);
+    s.trim_end_matches('0').trim_end_matches('.').to_owned()
+}
+
+/// Writes the `id`, `class` and other attributes of an element, each
+/// preceded by a space.
+pub(crate) fn attr_to(out: &mut String, attr: &Attr) {
+    attr_except_to(out, attr, &[]);
+}
+
+/// Writes the attributes of an element like [`attr_to`], leaving out the
+/// ones in `written`, which the element already has. Keys that aren't valid
+/// attribute names are dropped, so they can't inject other attributes.
+fn attr_except_to(out: &mut String, attr: &Attr, written: &[&str]) {
+    if !attr.identifier.is_empty() {
+        attribute_to(out, "id", &attr.identifier);
+    }
+    if !attr.classes.is_empty() {
+        attribute_to(out, "class", &attr.classes.join(" "));
+    }
+    for (key, value) in &attr.attributes {
+        if !is_attribute_name(key) || written.contains(&key.as_str()) {
+            continue;
+        }
+        if HTML_ATTRIBUTES.contains(&key.as_str())
+            || key.starts_with("data-")
+            || key.starts_with("aria-")
+        {
+            attribute_to(out, key, value);
+        } else {
+            attribute_to(out, &format!("data-{}", key), value);
+        }
+    }
+}
+
+/// Returns whether a key can be written as an attribute name, which can't
+/// contain spaces, quotes, `<`, `>`, `/`, `=` or control characters.
+fn is_attribute_name(key: &str) -> bool {
+    !key.is_empty()
+        && !key.chars().any(|c| {
+            c.is_whitespace() || c.is_control() || matches!(c, '"' | '\'' | '<' | '>' | '/' | '=')
+        })
+}
+
+fn attribute_to(out: &mut String, key: &str, value: &str) {
+    write!(out, " {}=\"", key).unwrap();
+    escape_to(out, value);
+    out.push('"');
+}
+
+/// Escapes the characters that are special in HTML text and attributes.
+pub(crate) fn escape_to(out: &mut String, s: &str) {
+    for c in s.chars() {
+        match c {
+            '&' => out.push_str("&amp;"),
+            '<' => out.push_str("&lt;"),
+            '>' => out.push_str("&gt;"),
+            '"' => out.push_str("&quot;"),
+            c => out.push(c),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::builder::*;
+    use crate::definition::{Alignment::*, ListNumberDelim};
+
+    fn html(blocks: Blocks) -> String {
+        blocks_to_html(&blocks, &WriterOptions::default())
+    }
+
+    #[test]
+    fn blocks() {
+        let attr = Attr {
+            identifier: "intro".into(),
+            classes: vec!["a".into(), "b".into()],
+            attributes: vec![("key".into(), "<v>".into()), ("lang".into(), "en".into())],
+        };
+        let blocks = header_with(attr, 2, text("Hi"))
+            + bullet_list([plain("one"), para("two") + para("three")])
+            + ordered_list_with(
+                ListAttributes {
+                    start_number: 3,
+                    style: ListNumberStyle::LowerRoman,
+                    delim: ListNumberDelim::OneParen,
+                },
+                [plain("x")],
+            )
+            + block_quote(para(strong("q")))
+            + code_block("a < b")
+            + raw_block("html", "<hr>")
+            + raw_block("latex", "\\relax");
+        assert_eq!(
+            html(blocks),
+            "<h2 id=\"intro\" class=\"a b\" data-key=\"&lt;v&gt;\" lang=\"en\">Hi</h2>\n\
+             <ul>\n<li>one</li>\n<li><p>two</p>\n<p>three</p></li>\n</ul>\n\
+             <ol start=\"3\" type=\"i\">\n<li>x</li>\n</ol>\n\
+             <blockquote>\n<p><strong>q</strong></p>\n</blockquote>\n\
+             <pre><code>a &lt; b</code></pre>\n\
+             <hr>\n"
+        );
+    }
+
+    #[test]
+    fn notes() {
+        let blocks = para(text("a") + note(para("one")))
+            + para(text("b") + note(plain("two") + code_block("c")));
+        assert_eq!(
+            html(blocks),
+            "<p>a<a href=\"#fn1\" class=\"footnote-ref\" id=\"fnref1\" role=\"doc-noteref\"><sup>1</sup></a></p>\n\
+             <p>b<a href=\"#fn2\" class=\"footnote-ref\" id=\"fnref2\" role=\"doc-noteref\"><sup>2</sup></a></p>\n\
+             <section id=\"footnotes\" class=\"footnotes footnotes-end-of-document\" role=\"doc-endnotes\">\n\
+             <hr />\n<ol>\n\
+             <li id=\"fn1\"><p>one<a href=\"#fnref1\" class=\"footnote-back\" role=\"doc-backlink\">↩︎</a></p></li>\n\
+             <li id=\"fn2\">two\n<pre><code>c</code></pre>\n\
+             <p><a href=\"#fnref2\" class=\"footnote-back\" role=\"doc-backlink\">↩︎</a></p></li>\n\
+             </ol>\n</section>\n"
+        );
+    }
+
+    #[test]
+    fn tables() {
+        let table = TableBuilder::new()
+            .caption(simple_caption(plain("Caption")))
+            .alignments([AlignRight, AlignDefault])
+            .widths([0.25, 0.75])
+            .header([plain("A"), plain("B")])
+            .body([
+                vec![
+                    cell(AlignDefault, 2, 1, plain("1")),
+                    cell(AlignCenter, 1, 1, plain("2")),
+                ],
+                vec![cell(AlignDefault, 1, 1, plain("3"))],
+            ])
+            .footer([cell(AlignDefault, 1, 2, plain("total"))])
+            .build();
+        assert_eq!(
+            html(Blocks::from(table)),
+            "<table>\n<caption>Caption</caption>\n\
+             <colgroup>\n<col style=\"width: 25%\" />\n<col style=\"width: 75%\" />\n</colgroup>\n\
+             <thead>\n<tr>\n<th style=\"text-align: right;\">A</th>\n<th>B</th>\n</tr>\n</thead>\n\
+             <tbody>\n<tr>\n<td style=\"text-align: right;\" rowspan=\"2\">1</td>\n\
+             <td style=\"text-align: center;\">2</td>\n</tr>\n\
+             <tr>\n<td>3</td>\n</tr>\n</tbody>\n\
+             <tfoot>\n<tr>\n<td style=\"text-align: right;\" colspan=\"2\">total</td>\n</tr>\n</tfoot>\n\
+             </table>\n"
+        );
+    }
+
+    #[test]
+    fn table_cell_attributes() {
+        let mut styled = cell(AlignCenter, 1, 1, plain("a"));
+        styled.attr.attributes = vec![
+            ("style".into(), "color: red;".into()),
+            ("colspan".into(), "3".into()),
+        ];
+        let mut plain_style = cell(AlignDefault, 1, 1, plain("b"));
+        plain_style.attr.attributes = vec![("style".into(), "color: blue;".into())];
+        let table = TableBuilder::new()
+            .alignments([AlignDefault, AlignDefault])
+            .body([vec![styled, plain_style]])
+            .build();
+        assert_eq!(
+            html(Blocks::from(table)),
+            "<table>\n<tbody>\n<tr>\n\
+             <td style=\"text-align: center; color: red;\">a</td>\n\
+             <td style=\"color: blue;\">b</td>\n\
+             </tr>\n</tbody>\n</table>\n"
+        );
+    }
+
+    #[test]
+    fn inlines() {
+        let image = image("a.png", "", text("An ") + emph("image"));
+        let blocks = figure(simple_caption(plain("A figure")), plain(image))
+            + plain(
+                link("https://example.com", "Title", "a & b")
+                    + line_break()
+                    + display_math("x")
+                    + single_quoted("q")
+                    + raw_inline("tex", "\\LaTeX"),
+            );
+        let options = WriterOptions {
+            html_math_method: HtmlMathMethod::MathJax,
+            ..Default::default()
+        };
+        assert_eq!(
+            blocks_to_html(&blocks, &options),
+            "<figure>\n<img src=\"a.png\" alt=\"An image\" />\n\
+             <figcaption>A figure</figcaption>\n</figure>\n\
+             <a href=\"https://example.com\" title=\"Title\">a &amp; b</a><br />\n\
+             <span class=\"math display\">\\[x\\]</span>‘q’\n"
+        );
+    }
+
+    #[test]
+    fn attributes() {
+        let attr = |attributes: &[(&str, &str)]| Attr {
+            attributes: attributes
+                .iter()
+                .map(|&(key, value)| (key.into(), value.into()))
+                .collect(),
+            ..Attr::default()
+        };
+        assert_eq!(
+            html(plain(
+                span_with(
+                    attr(&[("x onclick=alert(1) y", "v"), ("", "v"), ("lang", "en")]),
+                    "a"
+                ) + link_with(
+                    attr(&[("href", "evil"), ("title", "t"), ("rel", "me")]),
+                    "u",
+                    "Title",
+                    "b"
+                ) + link_with(attr(&[("title", "t")]), "u", "", "c")
+                    + image_with(attr(&[("src", "evil"), ("alt", "x")]), "a.png", "", "d")
+            )),
+            "<span lang=\"en\">a</span>\
+             <a href=\"u\" rel=\"me\" title=\"Title\">b</a>\
+             <a href=\"u\" title=\"t\">c</a>\
+             <img src=\"a.png\" alt=\"d\" />\n"
+        );
+    }
+}
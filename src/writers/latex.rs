@@ -0,0 +1,1022 @@
+//! A writer for LaTeX, like `pandoc -t latex`.
+//!
+//! Only the body of the document is written, without a preamble, so that it
+//! can be included in a template. Some of the commands in the output need
+//! packages: `hyperref` for links, `graphicx` for images, `longtable` and
+//! `booktabs` for tables, `multirow` for cells spanning several rows,
+//! `soul` for underlined and struck out text, and `biblatex` with its
+//! `natbib` option for citations, or `natbib` or plain `biblatex` depending
+//! on [`CiteMethod`].
+//!
+//! The `verbatim` environment cannot be used in the argument of a command,
+//! so code blocks in notes are written with the `Verbatim` environment of
+//! `fancyvrb` instead, which needs `\VerbatimFootnotes` in the preamble, and
+//! code blocks in cells written with `\multirow` or `\multicolumn` are
+//! written as `\texttt` lines.
+//!
+//! ```
+//! use pandoc_types::builder::*;
+//! use pandoc_types::writers::{latex::write_latex, WriterOptions};
+//!
+//! let doc = doc(
+//!     header(1, "Costs")
+//!         + para(text("We spent 50% of the $10 budget") + note(plain("Roughly."))),
+//! );
+//!
+//! assert_eq!(
+//!     write_latex(&doc, &WriterOptions::default()),
+//!     "\\section{Costs}\n\n\
+//!      We spent 50\\% of the \\$10 budget\\footnote{Roughly.}\n"
+//! );
+//! ```
+use super::{wrap, Chunk, CiteMethod, WrapOption, WriterOptions};
+use crate::definition::{
+    Alignment, Attr, Block, Caption, Citation, CitationMode, ColSpec, ColWidth, GridSlot, Inline,
+    ListAttributes, ListNumberDelim, ListNumberStyle, MathType, Pandoc, QuoteType, Table,
+    TablePart, Target,
+};
+
+/// Renders a document as LaTeX.
+pub fn write_latex(doc: &Pandoc, options: &WriterOptions) -> String {
+    blocks_to_latex(&doc.blocks, options)
+}
+
+/// Renders a list of blocks as LaTeX.
+pub fn blocks_to_latex(blocks: &[Block], options: &WriterOptions) -> String {
+    let mut writer = Writer {
+        options,
+        enumerate_depth: 0,
+        verbatim: Verbatim::Environment,
+    };
+    let mut output = String::new();
+    for line in writer.blocks(blocks, 0) {
+        output.push_str(&line);
+        output.push('\n');
+    }
+    output
+}
+
+struct Writer<'a> {
+    options: &'a WriterOptions,
+    /// The number of enclosing `enumerate` environments, which determines
+    /// the counter of an ordered list.
+    enumerate_depth: usize,
+    /// How code blocks are written where the writer currently is.
+    verbatim: Verbatim,
+}
+
+/// The ways of writing a code block.
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum Verbatim {
+    /// A `verbatim` environment, outside of command arguments.
+    Environment,
+    /// A `Verbatim` environment of `fancyvrb`, inside `\footnote`.
+    Footnote,
+    /// A `\texttt` command per line, inside other command arguments.
+    Texttt,
+}
+
+impl<'a> Writer<'a> {
+    /// Renders blocks indented by `indent` columns, separated by blank lines.
+    fn blocks(&mut self, blocks: &'a [Block], indent: usize) -> Vec<String> {
+        let mut lines = Vec::new();
+        for block in blocks {
+            let Some(block_lines) = self.block(block, indent) else {
+                continue;
+            };
+            if !lines.is_empty() {
+                lines.push(String::new());
+            }
+            lines.extend(block_lines);
+        }
+        lines
+    }
+
+    /// Renders a block, returning `None` if it produces no output at all.
+    fn block(&mut self, block: &'a Block, indent: usize) -> Option<Vec<String>> {
+        let lines = match block {
+            Block::Plain(inlines) | Block::Para(inlines) => self.paragraph(inlines, indent),
+            Block::LineBlock(lines) => {
+                let mut chunks = Vec::new();
+                for (i, line) in lines.iter().enumerate() {
+                    if i > 0 {
+                        chunks.extend([Chunk::Text("\\\\".into()), Chunk::Newline]);
+                    }
+                    self.inlines(line, &mut chunks);
+                }
+                self.layout(&chunks, indent)
+            }
+            Block::CodeBlock(_, code) => match self.verbatim {
+                Verbatim::Environment | Verbatim::Footnote => {
+                    let name = match self.verbatim {
+                        Verbatim::Footnote => "Verbatim",
+                        _ => "verbatim",
+                    };
+                    let mut lines = vec![format!("\\begin{{{}}}", name)];
+                    lines.extend(code.lines().map(str::to_owned));
+                    lines.push(format!("\\end{{{}}}", name));
+                    lines
+                }
+                Verbatim::Texttt => {
+                    let count = code.lines().count();
+                    let lines = code.lines().enumerate().map(|(i, line)| {
+                        let end = if i + 1 < count { "\\\\" } else { "" };
+                        let line = escape(line, true).replace(' ', "~");
+                        format!("\\texttt{{{}}}{}", line, end)
+                    });
+                    lines.collect()
+                }
+            },
+            Block::RawBlock(format, raw) if is_latex(&format.0) => {
+                raw.lines().map(str::to_owned).collect()
+            }
+            Block::RawBlock(..) | Block::Null => return None,
+            Block::BlockQuote(blocks) => self.environment("quote", blocks, indent),
+            Block::OrderedList(attrs, items) => self.ordered_list(attrs, items, indent),
+            Block::BulletList(items) => {
+                let mut lines = vec!["\\begin{itemize}".to_owned()];
+                for item in items {
+                    lines.push("\\item".to_owned());
+                    lines.extend(nest(self.blocks(item, indent + 2)));
+                }
+                lines.push("\\end{itemize}".to_owned());
+                lines
+            }
+            Block::DefinitionList(items) => {
+                let mut lines = vec!["\\begin{description}".to_owned()];
+                for (term, definitions) in items {
+                    lines.push(format!("\\item[{{{}}}]", self.inline_text(term)));
+                    for (i, definition) in definitions.iter().enumerate() {
+                        if i > 0 {
+                            lines.push(String::new());
+                        }
+                        lines.extend(nest(self.blocks(definition, indent + 2)));
+                    }
+                }
+                lines.push("\\end{description}".to_owned());
+                lines
+            }
+            Block::Header(level, attr, inlines) => {
+                let command = match level {
+                    1 => "section",
+                    2 => "subsection",
+                    3 => "subsubsection",
+                    4 => "paragraph",
+                    5 => "subparagraph",
+                    _ => return Some(self.paragraph(inlines, indent)),
+                };
+                let star = if attr.classes.iter().any(|c| c == "unnumbered") {
+                    "*"
+                } else {
+                    ""
+                };
+                vec![format!(
+                    "\\{}{}{{{}}}{}",
+                    command,
+                    star,
+                    self.inline_text(inlines),
+                    label(attr)
+                )]
+            }
+            Block::HorizontalRule => {
+                vec!["\\begin{center}\\rule{0.5\\linewidth}{0.5pt}\\end{center}".to_owned()]
+            }
+            Block::Table(table) => self.table(table, indent),
+            Block::Figure(attr, caption, blocks) => {
+                let mut lines = vec!["\\begin{figure}".to_owned(), "\\centering".to_owned()];
+                lines.extend(self.blocks(blocks, indent));
+                if !caption.long.is_empty() {
+                    lines.push(format!("{}{}", self.caption(caption), label(attr)));
+                }
+                lines.push("\\end{figure}".to_owned());
+                lines
+            }
+            Block::Div(_, blocks) => self.blocks(blocks, indent),
+        };
+        Some(lines)
+    }
+
+    fn environment(&mut self, name: &str, blocks: &'a [Block], indent: usize) -> Vec<String> {
+        let mut lines = vec![format!("\\begin{{{}}}", name)];
+        lines.extend(self.blocks(blocks, indent));
+        lines.push(format!("\\end{{{}}}", name));
+        lines
+    }
+
+    /// Renders an ordered list, redefining the label and setting the start
+    /// of the counter of its `enumerate` environment as needed. LaTeX only
+    /// has counters for four levels of nesting, so deeper lists are
+    /// numbered by their environment.
+    fn ordered_list(
+        &mut self,
+        attrs: &ListAttributes,
+        items: &'a [Vec<Block>],
+        indent: usize,
+    ) -> Vec<String> {
+        let mut lines = vec!["\\begin{enumerate}".to_owned()];
+        if let Some(counter) = ["enumi", "enumii", "enumiii", "enumiv"].get(self.enumerate_depth) {
+            let number = match attrs.style {
+                ListNumberStyle::DefaultStyle
+                | ListNumberStyle::Decimal
+                | ListNumberStyle::Example => "arabic",
+                ListNumberStyle::LowerRoman => "roman",
+                ListNumberStyle::UpperRoman => "Roman",
+                ListNumberStyle::LowerAlpha => "alph",
+                ListNumberStyle::UpperAlpha => "Alph",
+            };
+            let number = format!("\\{}{{{}}}", number, counter);
+            let label = match attrs.delim {
+                ListNumberDelim::DefaultDelim if attrs.style == ListNumberStyle::DefaultStyle => {
+                    None
+                }
+                ListNumberDelim::DefaultDelim | ListNumberDelim::Period => {
+                    Some(format!("{}.", number))
+                }
+                ListNumberDelim::OneParen => Some(format!("{})", number)),
+                ListNumberDelim::TwoParens => Some(format!("({})", number)),
+            };
+            if let Some(label) = label {
+                lines.push(format!("\\def\\label{}{{{}}}", counter, label));
+            }
+            if attrs.start_number != 1 {
+                lines.push(format!(
+                    "\\setcounter{{{}}}{{{}}}",
+                    counter,
+                    attrs.start_number - 1
+                ));
+            }
+        }
+        self.enumerate_depth += 1;
+        for item in items {
+            lines.push("\\item".to_owned());
+            lines.extend(nest(self.blocks(item, indent + 2)));
+        }
+        self.enumerate_depth -= 1;
+        lines.push("\\end{enumerate}".to_owned());
+        lines
+    }
+
+    fn caption(&mut self, caption: &'a Caption) -> String {
+        let mut long = Vec::new();
+        for block in &caption.long {
+            if let Block::Plain(inlines) | Block::Para(inlines) = block {
+                if !long.is_empty() {
+                    long.push(Chunk::Space);
+                }
+                self.inlines(inlines, &mut long);
+            }
+        }
+        let long = wrap(&long, None, WrapOption::None).join("\n");
+        match &caption.short {
+            Some(short) => format!("\\caption[{}]{{{}}}", self.inline_text(short), long),
+            None => format!("\\caption{{{}}}", long),
+        }
+    }
+
+    /// Renders a table as a `longtable`. Columns with relative widths, or
+    /// all columns if a cell holds more than a single paragraph, become
+    /// paragraph columns. Cells spanning several columns or rows are
+    /// written with `\multicolumn` and `\multirow`.
+    fn table(&mut self, table: &'a Table, indent: usize) -> Vec<String> {
+        let grid = table.grid();
+        let n = grid.width;
+        let simple = grid.rows.iter().all(|row| {
+            row.slots.iter().all(|slot| match slot {
+                GridSlot::Origin(cell) => matches!(
+                    cell.content.as_slice(),
+                    [] | [Block::Plain(_) | Block::Para(_)]
+                ),
+                _ => true,
+            })
+        });
+        let widths = if simple
+            && table
+                .colspecs
+                .iter()
+                .all(|ColSpec(_, width)| width == &ColWidth::ColWidthDefault)
+        {
+            None
+        } else {
+            let given: f64 = table
+                .colspecs
+                .iter()
+                .map(|ColSpec(_, width)| match width {
+                    ColWidth::ColWidth(w) => *w,
+                    ColWidth::ColWidthDefault => 0.0,
+                })
+                .sum();
+            let defaults = n - table
+                .colspecs
+                .iter()
+                .filter(|ColSpec(_, width)| matches!(width, ColWidth::ColWidth(_)))
+                .count();
+            let default = match (1.0 - given) / defaults as f64 {
+                w if w > 0.0 => w,
+                _ => 1.0 / n as f64,
+            };
+            Some(
+                (0..n)
+                    .map(|c| match table.colspecs.get(c) {
+                        Some(ColSpec(_, ColWidth::ColWidth(w))) => *w,
+                        _ => default,
+                    })
+                    .collect::<Vec<_>>(),
+            )
+        };
+        let align = |c: usize| {
+            table
+                .colspecs
+                .get(c)
+                .map_or(&Alignment::AlignDefault, |ColSpec(align, _)| align)
+        };
+        let spec = |align: &Alignment, columns: std::ops::Range<usize>| {
+            column_spec(
+                align,
+                widths
+                    .as_ref()
+                    .map(|widths| widths[columns.clone()].iter().sum()),
+                n,
+                columns.len(),
+            )
+        };
+
+        let mut lines = Vec::new();
+        match &widths {
+            None => lines.push(format!(
+                "\\begin{{longtable}}[]{{@{{}}{}@{{}}}}",
+                (0..n).map(|c| spec(align(c), c..c + 1)).collect::<String>()
+            )),
+            Some(_) => {
+                lines.push("\\begin{longtable}[]{@{}".to_owned());
+                for c in 0..n {
+                    lines.push(format!("  {}", spec(align(c), c..c + 1)));
+                }
+                if let Some(last) = lines.last_mut() {
+                    last.push_str("@{}}");
+                }
+            }
+        }
+        if !table.caption.long.is_empty() {
+            let caption = self.caption(&table.caption);
+            lines.push(format!("{}{}\\tabularnewline", caption, label(&table.attr)));
+        }
+
+        let (mut head, mut body, mut foot) = (Vec::new(), Vec::new(), Vec::new());
+        for (r, row) in grid.rows.iter().enumerate() {
+            let mut cells = Vec::new();
+            let mut c = 0;
+            while c < n {
+                let origin = grid.cell_at(r, c).map(|(r, c, _)| (r, c));
+                // The grid may have truncated spans that don't fit.
+                let row_span = (r..grid.rows.len())
+                    .take_while(|&i| grid.cell_at(i, c).map(|(r, c, _)| (r, c)) == origin)
+                    .count();
+                let col_span = match origin {
+                    Some(_) => (c..n)
+                        .take_while(|&i| grid.cell_at(r, i).map(|(r, c, _)| (r, c)) == origin)
+                        .count(),
+                    None => 1,
+                };
+                let content = match grid.get(r, c) {
+                    Some(GridSlot::Origin(cell)) => {
+                        let head = row.part == TablePart::Head;
+                        let column_align = align(c);
+                        let cell_align = match &cell.align {
+                            Alignment::AlignDefault => column_align,
+                            align => align,
+                        };
+                        let multicolumn = col_span > 1 || cell_align != column_align;
+                        let mut content = if row_span > 1 || multicolumn {
+                            let verbatim = std::mem::replace(&mut self.verbatim, Verbatim::Texttt);
+                            let content = self.cell(&cell.content, head, indent);
+                            self.verbatim = verbatim;
+                            content
+                        } else {
+                            self.cell(&cell.content, head, indent)
+                        };
+                        if row_span > 1 {
+                            content = format!("\\multirow{{{}}}{{*}}{{{}}}", row_span, content);
+                        }
+                        if multicolumn {
+                            content = format!(
+                                "\\multicolumn{{{}}}{{{}}}{{{}}}",
+                                col_span,
+                                spec(cell_align, c..c + col_span),
+                                content
+                            );
+                        }
+                        content
+                    }
+                    // The rest of a cell spanning down from a previous row.
+                    Some(GridSlot::Covered { .. }) if col_span > 1 => format!(
+                        "\\multicolumn{{{}}}{{{}}}{{}}",
+                        col_span,
+                        spec(align(c), c..c + col_span)
+                    ),
+                    _ => String::new(),
+                };
+                cells.push(content);
+                c += col_span;
+            }
+            let line = format!("{} \\\\", cells.join(" & "));
+            match row.part {
+                TablePart::Head => head.push(line),
+                TablePart::Foot => foot.push(line),
+                TablePart::BodyHead(_) | TablePart::Body(_) => body.push(line),
+            }
+        }
+
+        // The head is repeated on every page, with the caption only on the
+        // first one.
+        let mut top = vec!["\\toprule\\noalign{}".to_owned()];
+        if !head.is_empty() {
+            top.extend(head);
+            top.push("\\midrule\\noalign{}".to_owned());
+        }
+        if !table.caption.long.is_empty() {
+            lines.extend(top.iter().cloned());
+            lines.push("\\endfirsthead".to_owned());
+        }
+        lines.extend(top);
+        lines.push("\\endhead".to_owned());
+        if !foot.is_empty() {
+            lines.push("\\midrule\\noalign{}".to_owned());
+            lines.extend(foot);
+        }
+        lines.push("\\bottomrule\\noalign{}".to_owned());
+        lines.push("\\endlastfoot".to_owned());
+        lines.extend(body);
+        lines.push("\\end{longtable}".to_owned());
+        lines
+    }
+
+    /// Renders the contents of a table cell, putting anything but a single
+    /// paragraph into a `minipage`.
+    fn cell(&mut self, blocks: &'a [Block], head: bool, indent: usize) -> String {
+        match blocks {
+            [] => String::new(),
+            [Block::Plain(inlines) | Block::Para(inlines)] => self.inline_text(inlines),
+            _ => {
+                let position = if head { "b" } else { "t" };
+                let mut lines = vec![format!("\\begin{{minipage}}[{}]{{\\linewidth}}", position)];
+                lines.extend(self.blocks(blocks, indent));
+                lines.push("\\end{minipage}".to_owned());
+                lines.join("\n")
+            }
+        }
+    }
+
+    fn paragraph(&mut self, inlines: &'a [Inline], indent: usize) -> Vec<String> {
+        let mut chunks = Vec::new();
+        self.inlines(inlines, &mut chunks);
+        self.layout(&chunks, indent)
+    }
+
+    fn layout(&self, chunks: &[Chunk], indent: usize) -> Vec<String> {
+        let mut lines = wrap(chunks, self.options.width(indent), self.options.wrap);
+        for line in &mut lines {
+            line.truncate(line.trim_end().len());
+        }
+        lines
+    }
+
+    /// Renders inlines without wrapping, for use as the argument of a
+    /// command.
+    fn inline_text(&mut self, inlines: &'a [Inline]) -> String {
+        let mut chunks = Vec::new();
+        self.inlines(inlines, &mut chunks);
+        wrap(&chunks, None, WrapOption::None).join("\n")
+    }
+
+    fn inlines(&mut self, inlines: &'a [Inline], chunks: &mut Vec<Chunk>) {
+        for inline in inlines {
+            self.inline(inline, chunks);
+        }
+    }
+
+    /// Renders inlines as the argument of a command, such as `\emph{...}`.
+    fn command(&mut self, command: &str, inlines: &'a [Inline], chunks: &mut Vec<Chunk>) {
+        chunks.push(Chunk::Text(format!("\\{}{{", command)));
+        self.inlines(inlines, chunks);
+        chunks.push(Chunk::Text("}".into()));
+    }
+
+    fn inline(&mut self, inline: &'a Inline, chunks: &mut Vec<Chunk>) {
+        match inline {
+            Inline::Str(s) => chunks.push(Chunk::Text(escape(s, false))),
+            Inline::Emph(inlines) => self.command("emph", inlines, chunks),
+            Inline::Underline(inlines) => self.command("ul", inlines, chunks),
+            Inline::Strong(inlines) => self.command("textbf", inlines, chunks),
+            Inline::Strikeout(inlines) => self.command("st", inlines, chunks),
+            Inline::Superscript(inlines) => self.command("textsuperscript", inlines, chunks),
+            Inline::Subscript(inlines) => self.command("textsubscript", inlines, chunks),
+            Inline::SmallCaps(inlines) => self.command("textsc", inlines, chunks),
+            Inline::Quoted(quote, inlines) => {
+                let (open, close) = match quote {
+                    QuoteType::SingleQuote => ("`", "'"),
+                    QuoteType::DoubleQuote => ("``", "''"),
+                };
+                // Separate nested quotes, which would otherwise form a
+                // ligature with these.
+                chunks.push(Chunk::Text(open.into()));
+                if let Some(Inline::Quoted(..)) = inlines.first() {
+                    chunks.push(Chunk::Text("\\,".into()));
+                }
+                self.inlines(inlines, chunks);
+                if let Some(Inline::Quoted(..)) = inlines.last() {
+                    chunks.push(Chunk::Text("\\,".into()));
+                }
+                chunks.push(Chunk::Text(close.into()));
+            }
+            Inline::Cite(citations, _) => self.cite(citations, chunks),
+            Inline::Code(_, code) => {
+                chunks.push(Chunk::Text(format!("\\texttt{{{}}}", escape(code, true))))
+            }
+            Inline::Space => chunks.push(Chunk::Space),
+            Inline::SoftBreak => chunks.push(Chunk::SoftBreak),
+            Inline::LineBreak => chunks.extend([Chunk::Text("\\\\".into()), Chunk::Newline]),
+            Inline::Math(MathType::InlineMath, tex) => {
+                chunks.push(Chunk::Text(format!("\\({}\\)", tex)))
+            }
+            Inline::Math(MathType::DisplayMath, tex) => {
+                chunks.push(Chunk::Text(format!("\\[{}\\]", tex)))
+            }
+            Inline::RawInline(format, raw) if is_latex(&format.0) => {
+                chunks.push(Chunk::Text(raw.clone()))
+            }
+            Inline::RawInline(..) => {}
+            Inline::Link(_, inlines, target) => self.link(inlines, target, chunks),
+            Inline::Image(attr, _, target) => {
+                let options: Vec<_> = ["width", "height"]
+                    .into_iter()
+                    .filter_map(|key| {
+                        let (_, value) = attr.attributes.iter().find(|(k, _)| k == key)?;
+                        Some(format!("{}={}", key, dimension(value)))
+                    })
+                    .collect();
+                let options = match options.is_empty() {
+                    true => String::new(),
+                    false => format!("[{}]", options.join(",")),
+                };
+                chunks.push(Chunk::Text(format!(
+                    "\\includegraphics{}{{{}}}",
+                    options,
+                    escape_url(&target.url)
+                )));
+            }
+            Inline::Note(blocks) => match blocks.as_slice() {
+                [Block::Plain(inlines) | Block::Para(inlines)] => {
+                    self.command("footnote", inlines, chunks)
+                }
+                _ => {
+                    let verbatim = self.verbatim;
+                    if verbatim == Verbatim::Environment {
+                        self.verbatim = Verbatim::Footnote;
+                    }
+                    let lines = self.blocks(blocks, 0);
+                    self.verbatim = verbatim;
+                    // `fancyvrb` wants its environments on lines of their own.
+                    let starts_verbatim = lines.first().is_some_and(|l| l == "\\begin{Verbatim}");
+                    let ends_verbatim = lines.last().is_some_and(|l| l == "\\end{Verbatim}");
+                    chunks.push(Chunk::Text("\\footnote{".into()));
+                    if starts_verbatim {
+                        chunks.push(Chunk::Newline);
+                    }
+                    for (i, line) in lines.into_iter().enumerate() {
+                        if i > 0 {
+                            chunks.push(Chunk::Newline);
+                        }
+                        chunks.push(Chunk::Text(line));
+                    }
+                    if ends_verbatim {
+                        chunks.push(Chunk::Newline);
+                    }
+                    chunks.push(Chunk::Text("}".into()));
+                }
+            },
+            Inline::Span(_, inlines) => self.inlines(inlines, chunks),
+        }
+    }
+
+    fn link(&mut self, inlines: &'a [Inline], target: &Target, chunks: &mut Vec<Chunk>) {
+        if let Some(id) = target.url.strip_prefix('#') {
+            chunks.push(Chunk::Text(format!("\\hyperref[{}]{{", id)));
+        } else {
+            let url = escape_url(&target.url);
+            if let [Inline::Str(text)] = inlines {
+                if text == &target.url {
+                    chunks.push(Chunk::Text(format!("\\url{{{}}}", url)));
+                    return;
+                }
+            }
+            chunks.push(Chunk::Text(format!("\\href{{{}}}{{", url)));
+        }
+        self.inlines(inlines, chunks);
+        chunks.push(Chunk::Text("}".into()));
+    }
+
+    /// Renders citations with the commands of the [`CiteMethod`] for their
+    /// [`CitationMode`]. Consecutive citations with the same mode share a
+    /// command unless a prefix or suffix comes between them.
+    fn cite(&mut self, citations: &'a [Citation], chunks: &mut Vec<Chunk>) {
+        let mut start = 0;
+        for end in 1..=citations.len() {
+            if let (Some(previous), Some(next)) = (citations.get(end - 1), citations.get(end)) {
+                if previous.citation_mode == next.citation_mode
+                    && previous.citation_suffix.is_empty()
+                    && next.citation_prefix.is_empty()
+                {
+                    continue;
+                }
+            }
+            let group = &citations[start..end];
+            if start > 0 {
+                chunks.extend([Chunk::Text(";".into()), Chunk::Space]);
+            }
+            let command = match (self.options.cite_method, &group[0].citation_mode) {
+                (CiteMethod::Cite | CiteMethod::Natbib, CitationMode::NormalCitation) => "citep",
+                (CiteMethod::Cite | CiteMethod::Biblatex, CitationMode::AuthorInText) => "textcite",
+                (CiteMethod::Cite, CitationMode::SuppressAuthor) => "cite",
+                (CiteMethod::Natbib, CitationMode::AuthorInText) => "citet",
+                (CiteMethod::Natbib, CitationMode::SuppressAuthor) => "citeyearpar",
+                (CiteMethod::Biblatex, CitationMode::NormalCitation) => "autocite",
+                (CiteMethod::Biblatex, CitationMode::SuppressAuthor) => "autocite*",
+            };
+            let prefix = self.inline_text(&group[0].citation_prefix);
+            let suffix = self.inline_text(&group[group.len() - 1].citation_suffix);
+            let suffix = suffix.trim_start_matches([',', ' ']);
+            let options = match (prefix.is_empty(), suffix.is_empty()) {
+                (true, true) => String::new(),
+                (true, false) => format!("[{}]", suffix),
+                (false, _) => format!("[{}][{}]", prefix, suffix),
+            };
+            let keys: Vec<_> = group.iter().map(|c| c.citation_id.as_str()).collect();
+            chunks.push(Chunk::Text(format!(
+                "\\{}{}{{{}}}",
+                command,
+                options,
+                keys.join(",")
+            )));
+            start = end;
+        }
+    }
+}
+
+/// Indents the contents of a list item by two spaces, except for the
+/// contents of verbatim environments, which must be kept as they are.
+fn nest(lines: Vec<String>) -> Vec<String> {
+    let mut verbatim = false;
+    lines
+        .into_iter()
+        .map(|line| {
+            let nested = if verbatim || line.is_empty() {
+                line
+            } else {
+                format!("  {}", line)
+            };
+            match nested.trim_start() {
+                "\\begin{verbatim}" | "\\begin{Verbatim}" => verbatim = true,
+                "\\end{verbatim}" | "\\end{Verbatim}" => verbatim = false,
+                _ => {}
+            }
+            nested
+        })
+        .collect()
+}
+
+fn is_latex(format: &str) -> bool {
+    matches!(format, "latex" | "tex")
+}
+
+/// Returns a `\label` for the identifier of the attributes, if it has one.
+fn label(attr: &Attr) -> String {
+    match attr.identifier.as_str() {
+        "" => String::new(),
+        id => format!("\\label{{{}}}", id),
+    }
+}
+
+/// Returns the column type for `span` columns with the given alignment and
+/// relative width, out of the `columns` columns of the table.
+fn column_spec(align: &Alignment, width: Option<f64>, columns: usize, span: usize) -> String {
+    match width {
+        None => match align {
+            Alignment::AlignLeft | Alignment::AlignDefault => "l",
+            Alignment::AlignRight => "r",
+            Alignment::AlignCenter => "c",
+        }
+        .to_owned(),
+        Some(width) => {
+            let align = match align {
+                Alignment::AlignLeft | Alignment::AlignDefault => "\\raggedright",
+                Alignment::AlignRight => "\\raggedleft",
+                Alignment::AlignCenter => "\\centering",
+            };
+            // A cell spanning several columns also takes up the space
+            // between them.
+            let between = match span {
+                0 | 1 => String::new(),
+                _ => format!(" + {}\\tabcolsep", 2 * (span - 1)),
+            };
+            format!(
+                ">{{{}\\arraybackslash}}p{{(\\linewidth - {}\\tabcolsep) * \\real{{{:.4}}}{}}}",
+                align,
+                2 * columns,
+                width,
+                between
+            )
+        }
+    }
+}
+
+/// Converts an image dimension to LaTeX, taking percentages relative to the
+/// line width.
+fn dimension(value: &str) -> String {
+    match value.strip_suffix('%').and_then(|n| n.parse::<f64>().ok()) {
+        Some(percent) => format!("{}\\linewidth", percent / 100.0),
+        None => value.to_owned(),
+    }
+}
+
+/// Escapes text for LaTeX. Typographic punctuation is written with the
+/// ligatures TeX uses for it, except in `code`, where characters that would
+/// form ligatures are kept apart instead.
+fn escape(s: &str, code: bool) -> String {
+    let mut out = String::with_capacity(s.len());
+    let mut chars = s.chars().peekable();
+    while let Some(c) = chars.next() {
+        match c {
+            '{' | '}' | '$' | '%' | '&' | '_' | '#' => {
+                out.push('\\');
+                out.push(c);
+            }
+            '^' => out.push_str("\\^{}"),
+            '~' => out.push_str("\\textasciitilde{}"),
+            '\\' => out.push_str("\\textbackslash{}"),
+            '[' => out.push_str("{[}"),
+            ']' => out.push_str("{]}"),
+            '<' => out.push_str("\\textless{}"),
+            '>' => out.push_str("\\textgreater{}"),
+            '|' => out.push_str("\\textbar{}"),
+            '-' if chars.peek() == Some(&'-') => out.push_str("-{}"),
+            '\'' if code => out.push_str("\\textquotesingle{}"),
+            '`' if code => out.push_str("\\textasciigrave{}"),
+            '\u{a0}' if !code => out.push('~'),
+            '–' if !code => out.push_str("--"),
+            '—' if !code => out.push_str("---"),
+            '…' if !code => out.push_str("\\ldots{}"),
+            '‘' if !code => out.push('`'),
+            '’' if !code => out.push('\''),
+            '“' if !code => out.push_str("``"),
+            '”' if !code => out.push_str("''"),
+            _ => out.push(c),
+        }
+    }
+    out
+}
+
+/// Escapes a URL for `\url`, `\href` and `\includegraphics`.
+fn escape_url(url: &str) -> String {
+    let mut out = String::with_capacity(url.len());
+    for c in url.chars() {
+        if matches!(c, '%' | '#' | '\\' | '{' | '}') {
+            out.push('\\');
+        }
+        out.push(c);
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::builder::*;
+    use crate::definition::Alignment::*;
+
+    fn latex(blocks: Blocks) -> String {
+        blocks_to_latex(&blocks, &WriterOptions::default())
+    }
+
+    fn citation(id: &str, mode: CitationMode, prefix: &str, suffix: &str) -> Citation {
+        Citation {
+            citation_id: id.into(),
+            citation_prefix: text(prefix).into_vec(),
+            citation_suffix: text(suffix).into_vec(),
+            citation_mode: mode,
+            citation_note_num: 1,
+            citation_hash: 0,
+        }
+    }
+
+    #[test]
+    fn escaping() {
+        let blocks = para("{a} $1 50% & _b_ #c ^d ~e \\f [g] <h> |i| --")
+            + para("“Quote” ‘single’ – — … a\u{a0}b")
+            + para(code("'--`\\"));
+        assert_eq!(
+            latex(blocks),
+            "\\{a\\} \\$1 50\\% \\& \\_b\\_ \\#c \\^{}d \\textasciitilde{}e \\textbackslash{}f\n\
+             {[}g{]} \\textless{}h\\textgreater{} \\textbar{}i\\textbar{} -{}-\n\n\
+             ``Quote'' `single' -- --- \\ldots{} a~b\n\n\
+             \\texttt{\\textquotesingle{}-{}-\\textasciigrave{}\\textbackslash{}}\n"
+        );
+    }
+
+    #[test]
+    fn images() {
+        let blocks = plain(image("a_b%c#d.png", "", "alt") + image("{e}.png", "", "alt"));
+        assert_eq!(
+            latex(blocks),
+            "\\includegraphics{a_b\\%c\\#d.png}\\includegraphics{\\{e\\}.png}\n"
+        );
+    }
+
+    #[test]
+    fn blocks() {
+        let attr = Attr {
+            identifier: "intro".into(),
+            classes: vec!["unnumbered".into()],
+            attributes: Vec::new(),
+        };
+        let blocks = header_with(attr, 2, emph("Intro"))
+            + header(6, "Deep")
+            + block_quote(para("q"))
+            + bullet_list([plain("one") + code_block("code"), para("two")])
+            + ordered_list_with(
+                ListAttributes {
+                    start_number: 3,
+                    style: ListNumberStyle::LowerAlpha,
+                    delim: ListNumberDelim::TwoParens,
+                },
+                [plain("x")],
+            )
+            + raw_block("tex", "\\relax")
+            + raw_block("html", "<hr>")
+            + horizontal_rule();
+        assert_eq!(
+            latex(blocks),
+            "\\subsection*{\\emph{Intro}}\\label{intro}\n\nDeep\n\n\
+             \\begin{quote}\nq\n\\end{quote}\n\n\
+             \\begin{itemize}\n\\item\n  one\n\n  \\begin{verbatim}\ncode\n\\end{verbatim}\n\
+             \\item\n  two\n\\end{itemize}\n\n\
+             \\begin{enumerate}\n\\def\\labelenumi{(\\alph{enumi})}\n\\setcounter{enumi}{2}\n\
+             \\item\n  x\n\\end{enumerate}\n\n\
+             \\relax\n\n\
+             \\begin{center}\\rule{0.5\\linewidth}{0.5pt}\\end{center}\n"
+        );
+
+        // Trailing whitespace is part of the code.
+        assert_eq!(
+            latex(code_block("a  \nb\t")),
+            "\\begin{verbatim}\na  \nb\t\n\\end{verbatim}\n"
+        );
+    }
+
+    #[test]
+    fn inlines() {
+        let citations = vec![
+            citation("a", CitationMode::NormalCitation, "see", ", p. 1"),
+            citation("b", CitationMode::NormalCitation, "", ""),
+            citation("c", CitationMode::NormalCitation, "", ""),
+            citation("d", CitationMode::AuthorInText, "", ""),
+            citation("e", CitationMode::SuppressAuthor, "", ""),
+        ];
+        let blocks = plain(
+            cite(citations, "[@a]")
+                + line_break()
+                + link("https://example.com", "", "https://example.com")
+                + text(" ")
+                + link("#intro", "", "intro")
+                + text(" ")
+                + link("https://example.com/#a%20b", "", "x")
+                + math("x^2")
+                + display_math("y")
+                + raw_inline("latex", "\\LaTeX")
+                + raw_inline("html", "<br>")
+                + note(para("A note.") + para("More."))
+                + double_quoted(single_quoted("q")),
+        );
+        assert_eq!(
+            latex(blocks),
+            "\\citep[see][p. 1]{a}; \\citep{b,c}; \\textcite{d}; \\cite{e}\\\\\n\
+             \\url{https://example.com} \\hyperref[intro]{intro}\n\
+             \\href{https://example.com/\\#a\\%20b}{x}\\(x^2\\)\\[y\\]\\LaTeX\\footnote{A note.\n\n\
+             More.}``\\,`q'\\,''\n"
+        );
+    }
+
+    #[test]
+    fn tables() {
+        let table = TableBuilder::new()
+            .alignments([AlignRight, AlignDefault])
+            .header([plain("A"), plain("B")])
+            .body([vec![
+                cell(AlignDefault, 1, 1, plain("1")),
+                cell(AlignCenter, 1, 1, plain("2")),
+            ]])
+            .build();
+        assert_eq!(
+            latex(Blocks::from(table)),
+            "\\begin{longtable}[]{@{}rl@{}}\n\\toprule\\noalign{}\nA & B \\\\\n\
+             \\midrule\\noalign{}\n\\endhead\n\\bottomrule\\noalign{}\n\\endlastfoot\n\
+             1 & \\multicolumn{1}{c}{2} \\\\\n\\end{longtable}\n"
+        );
+
+        let table = TableBuilder::new()
+            .caption(simple_caption(plain("Caption")))
+            .alignments([AlignLeft, AlignDefault])
+            .widths([0.25, 0.75])
+            .header([plain("A"), plain("B")])
+            .body([
+                vec![
+                    cell(AlignDefault, 2, 1, plain("1")),
+                    cell(AlignDefault, 1, 1, para("2") + para("3")),
+                ],
+                vec![cell(AlignDefault, 1, 1, plain("4"))],
+            ])
+            .footer([cell(AlignDefault, 1, 2, plain("total"))])
+            .build();
+        assert_eq!(latex(Blocks::from(table)),
+            "\\begin{longtable}[]{@{}\n\
+             \x20 >{\\raggedright\\arraybackslash}p{(\\linewidth - 4\\tabcolsep) * \\real{0.2500}}\n\
+             \x20 >{\\raggedright\\arraybackslash}p{(\\linewidth - 4\\tabcolsep) * \\real{0.7500}}@{}}\n\
+             \\caption{Caption}\\tabularnewline\n\
+             \\toprule\\noalign{}\nA & B \\\\\n\\midrule\\noalign{}\n\\endfirsthead\n\
+             \\toprule\\noalign{}\nA & B \\\\\n\\midrule\\noalign{}\n\\endhead\n\
+             \\midrule\\noalign{}\n\
+             \\multicolumn{2}{>{\\raggedright\\arraybackslash}p{(\\linewidth - 4\\tabcolsep) * \\real{1.0000} + 2\\tabcolsep}}{total} \\\\\n\
+             \\bottomrule\\noalign{}\n\\endlastfoot\n\
+             \\multirow{2}{*}{1} & \\begin{minipage}[t]{\\linewidth}\n2\n\n3\n\\end{minipage} \\\\\n\
+             \x20& 4 \\\\\n\\end{longtable}\n"
+        );
+    }
+
+    #[test]
+    fn code_in_arguments() {
+        let blocks = plain(text("a") + note(code_block("x  y")))
+            + bullet_list([plain(note(para("b") + code_block("z")))]);
+        assert_eq!(
+            latex(blocks),
+            "a\\footnote{\n\\begin{Verbatim}\nx  y\n\\end{Verbatim}\n}\n\n\
+             \\begin{itemize}\n\\item\n  \\footnote{b\n\n  \\begin{Verbatim}\nz\n\\end{Verbatim}\n  }\n\
+             \\end{itemize}\n"
+        );
+
+        let table = TableBuilder::new()
+            .alignments([AlignDefault, AlignDefault])
+            .body([
+                vec![
+                    cell(AlignDefault, 2, 1, plain("a") + code_block("{ x }\n  y")),
+                    cell(AlignDefault, 1, 1, plain("1")),
+                ],
+                vec![cell(AlignDefault, 1, 1, plain("2"))],
+            ])
+            .build();
+        let output = latex(Blocks::from(table));
+        assert!(output.contains(
+            "\\multirow{2}{*}{\\begin{minipage}[t]{\\linewidth}\na\n\n\
+             \\texttt{\\{~x~\\}}\\\\\n\\texttt{~~y}\n\\end{minipage}} & 1 \\\\\n"
+        ));
+        assert!(!output.contains("verbatim"));
+
+        let table = TableBuilder::new()
+            .alignments([AlignDefault, AlignDefault])
+            .body([
+                vec![cell(AlignDefault, 1, 2, plain("a") + code_block("x"))],
+                vec![
+                    cell(AlignCenter, 1, 1, code_block("y")),
+                    cell(AlignDefault, 1, 1, plain("z")),
+                ],
+            ])
+            .build();
+        let output = latex(Blocks::from(table));
+        assert!(output.contains("a\n\n\\texttt{x}\n\\end{minipage}} \\\\\n"));
+        assert!(output.contains("\\texttt{y}\n\\end{minipage}} & z \\\\\n"));
+        assert!(!output.contains("verbatim"));
+    }
+
+    #[test]
+    fn cite_methods() {
+        let citations = vec![
+            citation("a", CitationMode::NormalCitation, "", ""),
+            citation("b", CitationMode::AuthorInText, "", ""),
+            citation("c", CitationMode::SuppressAuthor, "", ""),
+        ];
+        let blocks = plain(cite(citations, "[@a]")).into_vec();
+        let latex = |cite_method| {
+            let options = WriterOptions {
+                cite_method,
+                ..Default::default()
+            };
+            blocks_to_latex(&blocks, &options)
+        };
+        assert_eq!(
+            latex(CiteMethod::Natbib),
+            "\\citep{a}; \\citet{b}; \\citeyearpar{c}\n"
+        );
+        assert_eq!(
+            latex(CiteMethod::Biblatex),
+            "\\autocite{a}; \\textcite{b}; \\autocite*{c}\n"
+        );
+    }
+}
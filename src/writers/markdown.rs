@@ -0,0 +1,1321 @@
+//! A writer for Markdown, like `pandoc -t markdown`, `-t gfm` or
+//! `-t commonmark`.
+//!
+//! Each [`MarkdownFlavor`] uses the syntax its readers understand and falls
+//! back to raw HTML for elements it can't express: strict CommonMark has no
+//! tables, strikeout, footnotes or attributes, GitHub-Flavored Markdown adds
+//! pipe tables, strikeout, footnotes and math, and Pandoc's Markdown can
+//! express nearly everything, including attributes, pipe and grid tables
+//! and fenced divs.
+//!
+//! ```
+//! use pandoc_types::builder::*;
+//! use pandoc_types::definition::Attr;
+//! use pandoc_types::writers::markdown::{write_markdown, MarkdownFlavor};
+//! use pandoc_types::writers::WriterOptions;
+//!
+//! let doc = doc(
+//!     para(text("Use ") + code("a*b") + text(" or ") + emph("a*b") + text("."))
+//!         + code_block_with(
+//!             Attr {
+//!                 classes: vec!["rust".into()],
+//!                 ..Attr::default()
+//!             },
+//!             "let x = 1;",
+//!         ),
+//! );
+//!
+//! assert_eq!(
+//!     write_markdown(&doc, MarkdownFlavor::CommonMark, &WriterOptions::default()),
+//!     "Use `a*b` or *a\\*b*.\n\n```rust\nlet x = 1;\n```\n"
+//! );
+//! ```
+use std::slice;
+
+use super::{
+    html, indent, is_decimal, is_fancy, is_loose, join_lines, list_marker, longest_run,
+    ordered_marker, pad, protect, wrap, Chunk, WriterOptions,
+};
+use crate::definition::{
+    Alignment, Attr, Block, Citation, CitationMode, ColWidth, Format, GridSlot, Inline, IterBlocks,
+    ListAttributes, ListNumberDelim, ListNumberStyle, MathType, Pandoc, QuoteType, Stringify,
+    Table, TableGrid, TablePart, Target,
+};
+
+/// The flavor of Markdown to write.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum MarkdownFlavor {
+    /// Strict CommonMark.
+    CommonMark,
+    /// GitHub-Flavored Markdown.
+    Gfm,
+    /// Pandoc's Markdown.
+    #[default]
+    Pandoc,
+}
+
+/// Renders a document as Markdown.
+pub fn write_markdown(doc: &Pandoc, flavor: MarkdownFlavor, options: &WriterOptions) -> String {
+    blocks_to_markdown(&doc.blocks, flavor, options)
+}
+
+/// Renders a list of blocks as Markdown, followed by their footnotes.
+pub fn blocks_to_markdown(
+    blocks: &[Block],
+    flavor: MarkdownFlavor,
+    options: &WriterOptions,
+) -> String {
+    let mut writer = Writer {
+        options,
+        flavor,
+        notes: Vec::new(),
+        unwrapped: false,
+        pipe_cell: false,
+    };
+    let mut lines = writer.blocks(blocks, 0);
+
+    // Footnotes may contain more footnotes, which are numbered after them.
+    let mut i = 0;
+    while i < writer.notes.len() {
+        let marker = match flavor {
+            MarkdownFlavor::CommonMark => format!("[{}] ", i + 1),
+            MarkdownFlavor::Gfm | MarkdownFlavor::Pandoc => format!("[^{}]: ", i + 1),
+        };
+        let note = writer.blocks(writer.notes[i], 4);
+        if !lines.is_empty() {
+            lines.push(String::new());
+        }
+        lines.extend(indent(note, &marker, "    "));
+        i += 1;
+    }
+
+    let mut output = String::new();
+    for line in lines {
+        output.push_str(&line);
+        output.push('\n');
+    }
+    output
+}
+
+struct Writer<'a> {
+    options: &'a WriterOptions,
+    flavor: MarkdownFlavor,
+    notes: Vec<&'a [Block]>,
+    /// Whether lines are laid out without wrapping, to measure table cells.
+    unwrapped: bool,
+    /// Whether a cell of a pipe table is being written, in which GFM reads
+    /// every `|` as the end of the cell unless it is escaped, even in code.
+    pipe_cell: bool,
+}
+
+impl<'a> Writer<'a> {
+    fn pandoc(&self) -> bool {
+        self.flavor == MarkdownFlavor::Pandoc
+    }
+
+    /// Renders blocks indented by `indent` columns, separated by blank lines.
+    fn blocks(&mut self, blocks: &'a [Block], indent: usize) -> Vec<String> {
+        let mut lines = Vec::new();
+        let mut previous: Option<&Block> = None;
+        for block in blocks {
+            let Some(block_lines) = self.block(block, indent) else {
+                continue;
+            };
+            match (previous, block) {
+                (None, _) => {}
+                // Keep lists tight: these blocks can follow a line of text
+                // without a blank line in between.
+                (
+                    Some(Block::Plain(_)),
+                    Block::BulletList(_)
+                    | Block::OrderedList(..)
+                    | Block::CodeBlock(..)
+                    | Block::BlockQuote(_),
+                ) => {}
+                // Separate consecutive lists, which would otherwise merge.
+                (Some(Block::BulletList(_)), Block::BulletList(_))
+                | (Some(Block::OrderedList(..)), Block::OrderedList(..)) => {
+                    lines.extend(["".to_owned(), "<!-- -->".to_owned(), "".to_owned()]);
+                }
+                _ => lines.push(String::new()),
+            }
+            lines.extend(block_lines);
+            previous = Some(block);
+        }
+        lines
+    }
+
+    /// Renders a block, returning `None` if it produces no output at all.
+    fn block(&mut self, block: &'a Block, indent: usize) -> Option<Vec<String>> {
+        let lines = match block {
+            Block::Plain(inlines) | Block::Para(inlines) => self.paragraph(inlines, indent),
+            Block::LineBlock(lines) if self.pandoc() => {
+                let mut out = Vec::new();
+                for line in lines {
+                    let text = self.unwrapped(|writer| writer.paragraph(line, 0));
+                    out.push(format!("| {}", text.join(" ")).trim_end().to_owned());
+                }
+                out
+            }
+            Block::LineBlock(lines) => {
+                let mut chunks = Vec::new();
+                for (i, line) in lines.iter().enumerate() {
+                    if i > 0 {
+                        chunks.extend([Chunk::Text("\\".into()), Chunk::Newline]);
+                    }
+                    self.inlines(line, &mut chunks);
+                }
+                self.layout(chunks, indent)
+            }
+            Block::CodeBlock(attr, code) => {
+                let fence = "`".repeat(longest_run(code, '`').max(2) + 1);
+                let info = match self.flavor {
+                    _ if attr == &Attr::default() => String::new(),
+                    MarkdownFlavor::Pandoc => match attr {
+                        Attr {
+                            identifier,
+                            classes,
+                            attributes,
+                        } if identifier.is_empty()
+                            && attributes.is_empty()
+                            && classes.len() == 1 =>
+                        {
+                            classes[0].clone()
+                        }
+                        _ => format!(" {}", pandoc_attr(attr)),
+                    },
+                    _ => attr.classes.first().cloned().unwrap_or_default(),
+                };
+                let mut lines = vec![format!("{}{}", fence, info)];
+                lines.extend(code.lines().map(str::to_owned));
+                lines.push(fence);
+                lines
+            }
+            Block::RawBlock(format, raw) => match format.0.as_str() {
+                "markdown" | "html" | "html4" | "html5" => raw.lines().map(str::to_owned).collect(),
+                _ if self.pandoc() => {
+                    let fence = "`".repeat(longest_run(raw, '`').max(2) + 1);
+                    let mut lines = vec![format!("{}{{={}}}", fence, format.0)];
+                    lines.extend(raw.lines().map(str::to_owned));
+                    lines.push(fence);
+                    lines
+                }
+                _ => return None,
+            },
+            Block::BlockQuote(blocks) => {
+                let lines = self.blocks(blocks, indent + 2);
+                super::indent(lines, "> ", "> ")
+            }
+            Block::OrderedList(attrs, items) => {
+                let markers = (0..items.len() as i32)
+                    .map(|i| self.ordered_marker(attrs, attrs.start_number.saturating_add(i)))
+                    .collect();
+                self.list(markers, items, indent)
+            }
+            Block::BulletList(items) => self.list(vec!["-".to_owned(); items.len()], items, indent),
+            Block::DefinitionList(items) if self.pandoc() => {
+                let loose = items
+                    .iter()
+                    .flat_map(|(_, definitions)| definitions)
+                    .any(|blocks| is_loose(blocks));
+                let mut lines = Vec::new();
+                for (term, definitions) in items {
+                    if !lines.is_empty() {
+                        lines.push(String::new());
+                    }
+                    lines.extend(self.unwrapped(|writer| writer.paragraph(term, 0)));
+                    for definition in definitions {
+                        if loose {
+                            lines.push(String::new());
+                        }
+                        let definition = self.blocks(definition, indent + 4);
+                        lines.extend(super::indent(definition, ":   ", "    "));
+                    }
+                }
+                lines
+            }
+            Block::DefinitionList(_) => self.html(block),
+            Block::Header(level @ 1..=6, attr, inlines) => {
+                let mut chunks = Vec::new();
+                self.inlines(inlines, &mut chunks);
+                // Headers must fit on one line.
+                let chunks = join_lines(chunks);
+                let mut text = wrap(&chunks, None, self.options.wrap).join(" ");
+                if text.ends_with('#') {
+                    text.insert(text.len() - 1, '\\');
+                }
+                let mut line = format!("{} {}", "#".repeat(*level as usize), text);
+                if self.pandoc() {
+                    let mut attr = attr.clone();
+                    if attr.identifier == auto_identifier(inlines) {
+                        attr.identifier.clear();
+                    }
+                    if attr != Attr::default() {
+                        line.push(' ');
+                        line.push_str(&pandoc_attr(&attr));
+                    }
+                }
+                vec![line.trim_end().to_owned()]
+            }
+            Block::Header(_, _, inlines) => self.paragraph(inlines, indent),
+            Block::HorizontalRule => {
+                vec!["-".repeat(self.options.columns.saturating_sub(indent).max(3))]
+            }
+            Block::Table(table) => match self.table(table, indent) {
+                Some(lines) => lines,
+                None => self.html(block),
+            },
+            Block::Figure(..) => match self.implicit_figure(block, indent) {
+                Some(lines) => lines,
+                None => self.html(block),
+            },
+            Block::Div(attr, blocks) if attr == &Attr::default() => self.blocks(blocks, indent),
+            Block::Div(attr, blocks) if self.pandoc() => {
+                let fence = ":".repeat(3 + div_depth(blocks));
+                let mut lines = vec![format!("{} {}", fence, pandoc_attr(attr))];
+                lines.extend(self.blocks(blocks, indent));
+                lines.push(fence);
+                lines
+            }
+            Block::Div(attr, blocks) => {
+                let mut open = "<div".to_owned();
+                html::attr_to(&mut open, attr);
+                open.push('>');
+                let mut lines = vec![open, String::new()];
+                lines.extend(self.blocks(blocks, indent));
+                lines.extend([String::new(), "</div>".to_owned()]);
+                lines
+            }
+            Block::Null => return None,
+        };
+        Some(lines)
+    }
+
+    /// Renders a figure holding just an image as an image alone in a
+    /// paragraph, which Pandoc's Markdown reads as a figure.
+    fn implicit_figure(&mut self, block: &'a Block, indent: usize) -> Option<Vec<String>> {
+        let Block::Figure(attr, caption, blocks) = block else {
+            return None;
+        };
+        let caption = match caption.long.as_slice() {
+            [] => &[],
+            [Block::Plain(inlines) | Block::Para(inlines)] => inlines.as_slice(),
+            _ => return None,
+        };
+        match blocks.as_slice() {
+            [Block::Plain(image)] if self.pandoc() && attr == &Attr::default() => {
+                let [Inline::Image(attr, _, target)] = image.as_slice() else {
+                    return None;
+                };
+                let mut chunks = Vec::new();
+                self.image(attr, caption, target, &mut chunks);
+                Some(self.layout(chunks, indent))
+            }
+            _ => None,
+        }
+    }
+
+    /// Renders a block as raw HTML. Its footnotes are numbered along with
+    /// the others and written at the end of the document.
+    fn html(&mut self, block: &'a Block) -> Vec<String> {
+        let html =
+            html::blocks_to_html_with_notes(slice::from_ref(block), self.options, &mut self.notes);
+        html.lines().map(str::to_owned).collect()
+    }
+
+    fn ordered_marker(&self, attrs: &ListAttributes, number: i32) -> String {
+        match self.flavor {
+            MarkdownFlavor::Pandoc if attrs.style == ListNumberStyle::Example => "(@)".to_owned(),
+            MarkdownFlavor::Pandoc => list_marker(attrs, number),
+            // CommonMark only has decimal numbers followed by a period or a
+            // parenthesis.
+            _ => match attrs.delim {
+                ListNumberDelim::OneParen | ListNumberDelim::TwoParens => format!("{})", number),
+                _ => format!("{}.", number),
+            },
+        }
+    }
+
+    /// Renders list items, putting each marker in front of the first line of
+    /// its item and indenting the rest to line up with the text after it.
+    fn list(
+        &mut self,
+        markers: Vec<String>,
+        items: &'a [Vec<Block>],
+        indent: usize,
+    ) -> Vec<String> {
+        let loose = items.iter().any(|item| is_loose(item));
+        let mut lines = Vec::new();
+        for (marker, item) in markers.into_iter().zip(items) {
+            if loose && !lines.is_empty() {
+                lines.push(String::new());
+            }
+            let mut width = marker.chars().count() + 1;
+            if self.pandoc() {
+                // Pandoc pads markers to four columns.
+                width = width.max(4);
+            }
+            let first = format!("{:width$}", marker, width = width);
+            let item = self.blocks(item, indent + width);
+            if item.is_empty() {
+                lines.push(marker);
+            } else {
+                lines.extend(super::indent(item, &first, &" ".repeat(width)));
+            }
+        }
+        lines
+    }
+
+    /// Renders a table as a pipe or grid table, or returns `None` if the
+    /// flavor has no table syntax that can express it.
+    fn table(&mut self, table: &'a Table, indent: usize) -> Option<Vec<String>> {
+        let grid = table.grid();
+        if self.flavor == MarkdownFlavor::CommonMark
+            || grid.width == 0
+            || !grid.is_valid()
+            || table.bodies.len() > 1
+            || table.bodies.iter().any(|body| !body.head.is_empty())
+        {
+            return None;
+        }
+        let head_rows = table.head.rows.len();
+        let has_foot = !table.foot.rows.is_empty();
+        let has_caption = !table.caption.long.is_empty();
+        let spans = grid.rows.iter().flat_map(|row| &row.slots).any(|slot| {
+            !matches!(slot, GridSlot::Origin(cell) if cell.row_span <= 1 && cell.col_span <= 1)
+        });
+        let default_widths = table
+            .colspecs
+            .iter()
+            .all(|spec| spec.1 == ColWidth::ColWidthDefault);
+
+        let mut lines = match self.flavor {
+            MarkdownFlavor::Gfm if !spans && !has_foot && !has_caption && head_rows <= 1 => {
+                self.pipe_table(table, &grid)?
+            }
+            MarkdownFlavor::Pandoc if !spans && !has_foot && head_rows == 1 && default_widths => {
+                let notes = self.notes.len();
+                match self.pipe_table(table, &grid) {
+                    Some(lines) => lines,
+                    None => {
+                        self.notes.truncate(notes);
+                        self.grid_table(table, &grid, indent)
+                    }
+                }
+            }
+            MarkdownFlavor::Pandoc => self.grid_table(table, &grid, indent),
+            _ => return None,
+        };
+
+        if has_caption {
+            let mut chunks = Vec::new();
+            for block in &table.caption.long {
+                if let Block::Plain(inlines) | Block::Para(inlines) = block {
+                    if !chunks.is_empty() {
+                        chunks.push(Chunk::Space);
+                    }
+                    self.inlines(inlines, &mut chunks);
+                }
+            }
+            let caption = self.layout(chunks, indent + 2);
+            lines.push(String::new());
+            lines.extend(super::indent(caption, ": ", "  "));
+        }
+        Some(lines)
+    }
+
+    /// Renders a pipe table, or returns `None` if a cell doesn't fit on a
+    /// single line.
+    fn pipe_table(&mut self, table: &'a Table, grid: &TableGrid<'a>) -> Option<Vec<String>> {
+        let mut rows = Vec::new();
+        if table.head.rows.is_empty() {
+            rows.push(vec![String::new(); grid.width]);
+        }
+        for row in &grid.rows {
+            let mut cells = Vec::new();
+            for slot in &row.slots {
+                let GridSlot::Origin(cell) = slot else {
+                    return None;
+                };
+                let text = match cell.content.as_slice() {
+                    [] => String::new(),
+                    [Block::Plain(inlines) | Block::Para(inlines)] => {
+                        self.pipe_cell = true;
+                        let mut lines = self.unwrapped(|writer| writer.paragraph(inlines, 0));
+                        self.pipe_cell = false;
+                        if lines.len() > 1 {
+                            return None;
+                        }
+                        lines.pop().unwrap_or_default()
+                    }
+                    _ => return None,
+                };
+                cells.push(text);
+            }
+            rows.push(cells);
+        }
+
+        let widths: Vec<usize> = (0..grid.width)
+            .map(|column| {
+                rows.iter()
+                    .map(|row| row[column].chars().count())
+                    .max()
+                    .unwrap_or(0)
+                    .max(3)
+            })
+            .collect();
+        let line = |cells: &[String]| {
+            let cells: Vec<_> = cells
+                .iter()
+                .enumerate()
+                .map(|(column, text)| pad(text, widths[column], &table.colspecs[column].0))
+                .collect();
+            format!("| {} |", cells.join(" | "))
+        };
+        let rule: Vec<_> = table
+            .colspecs
+            .iter()
+            .zip(&widths)
+            .map(|(spec, &width)| match spec.0 {
+                Alignment::AlignLeft => format!(":{}", "-".repeat(width + 1)),
+                Alignment::AlignRight => format!("{}:", "-".repeat(width + 1)),
+                Alignment::AlignCenter => format!(":{}:", "-".repeat(width)),
+                Alignment::AlignDefault => "-".repeat(width + 2),
+            })
+            .collect();
+
+        let mut lines = vec![line(&rows[0]), format!("|{}|", rule.join("|"))];
+        lines.extend(rows[1..].iter().map(|row| line(row)));
+        Some(lines)
+    }
+
+    /// Renders a grid table, which can hold any blocks and cells spanning
+    /// several rows and columns.
+    fn grid_table(&mut self, table: &'a Table, grid: &TableGrid<'a>, indent: usize) -> Vec<String> {
+        let n = grid.width;
+        // Each column takes three characters for its left border and padding,
+        // plus one for the right border of the table.
+        let available = self
+            .options
+            .columns
+            .saturating_sub(indent + 3 * n + 1)
+            .max(n);
+
+        // The origin, spans and rendered content of each cell.
+        let mut cells = Vec::new();
+        for (r, row) in grid.rows.iter().enumerate() {
+            for (c, slot) in row.slots.iter().enumerate() {
+                if let GridSlot::Origin(cell) = slot {
+                    let row_span = (r..grid.rows.len())
+                        .take_while(|&i| grid.cell_at(i, c).map(|(r, c, _)| (r, c)) == Some((r, c)))
+                        .count();
+                    let col_span = (c..n)
+                        .take_while(|&i| grid.cell_at(r, i).map(|(r, c, _)| (r, c)) == Some((r, c)))
+                        .count();
+                    cells.push((r, c, row_span, col_span, &cell.content, Vec::new()));
+                }
+            }
+        }
+
+        // Columns are sized by their relative widths, or by their contents
+        // if they have none, shrinking them if the table is too wide.
+        let relative: Vec<f64> = table
+            .colspecs
+            .iter()
+            .map(|spec| match spec.1 {
+                ColWidth::ColWidth(width) => width,
+                ColWidth::ColWidthDefault => 0.0,
+            })
+            .collect();
+        let mut widths: Vec<usize> = if relative.iter().any(|&w| w > 0.0) {
+            let total = self.options.columns.saturating_sub(indent) as f64;
+            relative
+                .iter()
+                .map(|w| ((w * total) as usize).saturating_sub(3).max(1))
+                .collect()
+        } else {
+            let mut natural = vec![1; n];
+            for (_, c, _, col_span, content, _) in &cells {
+                if *col_span == 1 {
+                    let notes = self.notes.len();
+                    let lines = self.unwrapped(|writer| writer.blocks(content, 0));
+                    // The cell is rendered again below.
+                    self.notes.truncate(notes);
+                    let width = lines.iter().map(|l| l.chars().count()).max().unwrap_or(0);
+                    natural[*c] = natural[*c].max(width);
+                }
+            }
+            let total: usize = natural.iter().sum();
+            if total > available {
+                natural
+                    .iter()
+                    .map(|w| (w * available / total).max(1))
+                    .collect()
+            } else {
+                natural
+            }
+        };
+
+        let span_width = |widths: &[usize], c: usize, span: usize| {
+            widths[c..c + span].iter().sum::<usize>() + 3 * (span - 1)
+        };
+        for (_, c, _, col_span, content, lines) in &mut cells {
+            let width = span_width(&widths, *c, *col_span);
+            // Render the cell as if it were indented by the rest of the line.
+            *lines = self.blocks(content, self.options.columns.saturating_sub(width));
+        }
+        // Widen columns whose contents still don't fit, such as long words.
+        cells.sort_by_key(|cell| cell.3);
+        for (_, c, _, col_span, _, lines) in &cells {
+            let needed = lines.iter().map(|l| l.chars().count()).max().unwrap_or(0);
+            let width = span_width(&widths, *c, *col_span);
+            if needed > width {
+                widths[c + col_span - 1] += needed - width;
+            }
+        }
+
+        // Row heights, growing the last row of spanning cells if needed.
+        let mut heights = vec![1; grid.rows.len()];
+        cells.sort_by_key(|cell| cell.2);
+        for (r, _, row_span, _, _, lines) in &cells {
+            let available = heights[*r..r + row_span].iter().sum::<usize>() + row_span - 1;
+            if lines.len() > available {
+                heights[r + row_span - 1] += lines.len() - available;
+            }
+        }
+
+        let mut xs = vec![0];
+        for width in &widths {
+            xs.push(xs.last().unwrap() + width + 3);
+        }
+        let mut ys = vec![0];
+        for height in &heights {
+            ys.push(ys.last().unwrap() + height + 1);
+        }
+
+        // Draw the borders of every cell, then fill in their contents.
+        let (w, h) = (xs[n] + 1, ys[grid.rows.len()] + 1);
+        let mut horizontal = vec![vec![false; w]; h];
+        let mut vertical = vec![vec![false; w]; h];
+        let mut canvas = vec![vec![' '; w]; h];
+        for (r, c, row_span, col_span, _, lines) in &cells {
+            let (x0, x1, y0, y1) = (xs[*c], xs[c + col_span], ys[*r], ys[r + row_span]);
+            for y in [y0, y1] {
+                horizontal[y][x0..=x1].fill(true);
+            }
+            for row in &mut vertical[y0..=y1] {
+                row[x0] = true;
+                row[x1] = true;
+            }
+            for (i, line) in lines.iter().enumerate() {
+                for (j, ch) in line.chars().enumerate() {
+                    canvas[y0 + 1 + i][x0 + 2 + j] = ch;
+                }
+            }
+        }
+        for y in 0..h {
+            for x in 0..w {
+                canvas[y][x] = match (horizontal[y][x], vertical[y][x]) {
+                    (true, true) => '+',
+                    (true, false) => '-',
+                    (false, true) => '|',
+                    (false, false) => canvas[y][x],
+                };
+            }
+        }
+
+        // The line below the head and the lines around the foot use `=`.
+        let foot = grid.rows.iter().position(|row| row.part == TablePart::Foot);
+        let mut double = Vec::new();
+        let head_rows = table.head.rows.len();
+        if head_rows > 0 {
+            double.push(ys[head_rows]);
+        }
+        if let Some(foot) = foot {
+            double.extend([ys[foot], h - 1]);
+        }
+        for &y in &double {
+            for ch in &mut canvas[y] {
+                if *ch == '-' {
+                    *ch = '=';
+                }
+            }
+        }
+        // Alignments are marked on the line below the head, or on the top
+        // line if there is no head.
+        let y = if head_rows > 0 { ys[head_rows] } else { 0 };
+        for (c, spec) in table.colspecs.iter().enumerate() {
+            let (left, right) = match spec.0 {
+                Alignment::AlignLeft => (true, false),
+                Alignment::AlignRight => (false, true),
+                Alignment::AlignCenter => (true, true),
+                Alignment::AlignDefault => (false, false),
+            };
+            for (set, x) in [(left, xs[c] + 1), (right, xs[c + 1] - 1)] {
+                if set && matches!(canvas[y][x], '-' | '=') {
+                    canvas[y][x] = ':';
+                }
+            }
+        }
+
+        canvas
+            .into_iter()
+            .map(|line| line.into_iter().collect::<String>().trim_end().to_owned())
+            .collect()
+    }
+
+    fn unwrapped(&mut self, f: impl FnOnce(&mut Self) -> Vec<String>) -> Vec<String> {
+        let unwrapped = std::mem::replace(&mut self.unwrapped, true);
+        let lines = f(self);
+        self.unwrapped = unwrapped;
+        lines
+    }
+
+    fn paragraph(&mut self, inlines: &'a [Inline], indent: usize) -> Vec<String> {
+        let mut chunks = Vec::new();
+        self.inlines(inlines, &mut chunks);
+        self.layout(chunks, indent)
+    }
+
+    /// Wraps chunks of text into lines, making sure that no line starts with
+    /// something that would be read as the start of a block.
+    fn layout(&self, chunks: Vec<Chunk>, indent: usize) -> Vec<String> {
+        let protected = protect(chunks, |word| {
+            let i = self.block_start(word)?;
+            Some(format!("{}\\{}", &word[..i], &word[i..]))
+        });
+        let width = match self.unwrapped {
+            true => None,
+            false => self.options.width(indent),
+        };
+        let mut lines = wrap(&protected, width, self.options.wrap);
+        // Trailing spaces would make a hard line break in Markdown.
+        for line in &mut lines {
+            line.truncate(line.trim_end().len());
+        }
+        lines
+    }
+
+    /// Returns where to put a backslash in a word that would start a block
+    /// if it was at the start of a line.
+    fn block_start(&self, word: &str) -> Option<usize> {
+        let first = word.chars().next()?;
+        if ["-", "+", "*", ":"].contains(&word) && (word != ":" || self.pandoc()) {
+            return Some(0);
+        }
+        if word.chars().all(|c| c == first) && matches!(first, '#' | '=' | '-') {
+            return Some(0);
+        }
+        if first == '>' || word.starts_with("~~~") {
+            return Some(0);
+        }
+        // Ordered list markers such as `1.` or, in Pandoc's Markdown, `(a)`.
+        let (open, number) = ordered_marker(word)?;
+        let marker = match self.flavor {
+            MarkdownFlavor::Pandoc => {
+                is_decimal(number) || is_fancy(number) || number.starts_with("\\@") || number == "#"
+            }
+            MarkdownFlavor::CommonMark | MarkdownFlavor::Gfm => is_decimal(number) && !open,
+        };
+        marker.then_some(word.len() - 1)
+    }
+
+    fn inlines(&mut self, inlines: &'a [Inline], chunks: &mut Vec<Chunk>) {
+        for inline in inlines {
+            let start = chunks.len();
+            self.inline(inline, chunks);
+            // A `!` right before a link or span would turn it into an image.
+            let opens =
+                matches!(chunks.get(start), Some(Chunk::Text(after)) if after.starts_with('['));
+            if let [.., Chunk::Text(before)] = &mut chunks[..start] {
+                if opens && before.ends_with('!') {
+                    before.insert(before.len() - 1, '\\');
+                }
+            }
+        }
+    }
+
+    /// Renders inlines between delimiters, moving spaces at either end
+    /// outside of them so that the delimiters are recognized.
+    fn delimited(
+        &mut self,
+        open: &str,
+        inlines: &'a [Inline],
+        close: &str,
+        chunks: &mut Vec<Chunk>,
+    ) {
+        let mut inner = Vec::new();
+        self.inlines(inlines, &mut inner);
+        let start = inner.iter().position(|c| matches!(c, Chunk::Text(_)));
+        let end = inner.iter().rposition(|c| matches!(c, Chunk::Text(_)));
+        let (Some(start), Some(end)) = (start, end) else {
+            chunks.extend(inner);
+            return;
+        };
+        let after = inner.split_off(end + 1);
+        let middle = inner.split_off(start);
+        chunks.extend(inner);
+        chunks.push(Chunk::Text(open.to_owned()));
+        chunks.extend(middle);
+        chunks.push(Chunk::Text(close.to_owned()));
+        chunks.extend(after);
+    }
+
+    fn inline(&mut self, inline: &'a Inline, chunks: &mut Vec<Chunk>) {
+        use MarkdownFlavor::*;
+        match inline {
+            Inline::Str(s) => chunks.push(Chunk::Text(self.escape(s))),
+            Inline::Emph(inlines) => self.delimited("*", inlines, "*", chunks),
+            Inline::Strong(inlines) => self.delimited("**", inlines, "**", chunks),
+            Inline::Strikeout(inlines) => match self.flavor {
+                CommonMark => self.delimited("<del>", inlines, "</del>", chunks),
+                Gfm | Pandoc => self.delimited("~~", inlines, "~~", chunks),
+            },
+            Inline::Underline(inlines) => match self.flavor {
+                Pandoc => self.delimited("[", inlines, "]{.underline}", chunks),
+                CommonMark | Gfm => self.delimited("<u>", inlines, "</u>", chunks),
+            },
+            Inline::SmallCaps(inlines) => match self.flavor {
+                Pandoc => self.delimited("[", inlines, "]{.smallcaps}", chunks),
+                CommonMark | Gfm => {
+                    self.delimited("<span class=\"smallcaps\">", inlines, "</span>", chunks)
+                }
+            },
+            Inline::Superscript(inlines) | Inline::Subscript(inlines) => {
+                let superscript = matches!(inline, Inline::Superscript(_));
+                if self.pandoc() {
+                    // Spaces must be escaped in superscripts and subscripts.
+                    let delim = if superscript { "^" } else { "~" };
+                    chunks.push(Chunk::Text(delim.into()));
+                    let mut inner = Vec::new();
+                    self.inlines(inlines, &mut inner);
+                    chunks.extend(inner.into_iter().map(|chunk| match chunk {
+                        Chunk::Space | Chunk::SoftBreak => Chunk::Text("\\ ".into()),
+                        chunk => chunk,
+                    }));
+                    chunks.push(Chunk::Text(delim.into()));
+                } else {
+                    let tag = if superscript { "sup" } else { "sub" };
+                    chunks.push(Chunk::Text(format!("<{}>", tag)));
+                    self.inlines(inlines, chunks);
+                    chunks.push(Chunk::Text(format!("</{}>", tag)));
+                }
+            }
+            Inline::Quoted(quote, inlines) => {
+                let (open, close) = match (quote, self.flavor) {
+                    (QuoteType::SingleQuote, Pandoc) => ("'", "'"),
+                    (QuoteType::DoubleQuote, Pandoc) => ("\"", "\""),
+                    (QuoteType::SingleQuote, _) => ("‘", "’"),
+                    (QuoteType::DoubleQuote, _) => ("“", "”"),
+                };
+                chunks.push(Chunk::Text(open.into()));
+                self.inlines(inlines, chunks);
+                chunks.push(Chunk::Text(close.into()));
+            }
+            Inline::Cite(citations, _) if self.pandoc() && !citations.is_empty() => {
+                self.citations(citations, chunks)
+            }
+            Inline::Cite(_, inlines) => self.inlines(inlines, chunks),
+            Inline::Code(attr, code) => {
+                let code = code.replace('\n', " ");
+                let ticks = "`".repeat(longest_run(&code, '`') + 1);
+                let pad = code.starts_with('`')
+                    || code.ends_with('`')
+                    || code.starts_with(' ') && code.ends_with(' ') && code.trim() != "";
+                let pad = if pad { " " } else { "" };
+                let mut text = format!("{0}{1}{2}{1}{0}", ticks, pad, code);
+                if self.pipe_cell && self.flavor == Gfm {
+                    text = text.replace('|', "\\|");
+                }
+                if self.pandoc() && attr != &Attr::default() {
+                    text.push_str(&pandoc_attr(attr));
+                }
+                chunks.push(Chunk::Text(text));
+            }
+            Inline::Space => chunks.push(Chunk::Space),
+            Inline::SoftBreak => chunks.push(Chunk::SoftBreak),
+            Inline::LineBreak => chunks.extend([Chunk::Text("\\".into()), Chunk::Newline]),
+            Inline::Math(kind, tex) => {
+                let display = kind == &MathType::DisplayMath;
+                let text = match self.flavor {
+                    Pandoc | Gfm if display => format!("$${}$$", tex),
+                    Pandoc | Gfm => format!("${}$", tex),
+                    CommonMark => {
+                        let (class, tex) = match display {
+                            true => ("display", format!("\\[{}\\]", tex)),
+                            false => ("inline", format!("\\({}\\)", tex)),
+                        };
+                        format!(
+                            "<span class=\"math {}\">{}</span>",
+                            class,
+                            self.escape(&tex)
+                        )
+                    }
+                };
+                chunks.push(Chunk::Text(text));
+            }
+            Inline::RawInline(Format(format), raw) => match format.as_str() {
+                "markdown" | "html" | "html4" | "html5" => chunks.push(Chunk::Text(raw.clone())),
+                _ if self.pandoc() => {
+                    let ticks = "`".repeat(longest_run(raw, '`') + 1);
+                    chunks.push(Chunk::Text(format!(
+                        "{0}{1}{0}{{={2}}}",
+                        ticks, raw, format
+                    )));
+                }
+                _ => {}
+            },
+            Inline::Link(attr, inlines, target) => {
+                let text = inlines.stringify();
+                let autolink = (target.url == text && has_scheme(&text)
+                    || target.url.strip_prefix("mailto:") == Some(text.as_str())
+                        && text.contains('@'))
+                    && target.title.is_empty()
+                    && !text.contains(['<', '>', ' '])
+                    && (!self.pandoc()
+                        || attr == &Attr::default()
+                        || attr.identifier.is_empty()
+                            && attr.attributes.is_empty()
+                            && matches!(attr.classes.as_slice(), [c] if c == "uri" || c == "email"));
+                if autolink {
+                    chunks.push(Chunk::Text(format!("<{}>", text)));
+                } else {
+                    chunks.push(Chunk::Text("[".into()));
+                    self.inlines(inlines, chunks);
+                    chunks.push(Chunk::Text(format!("]({})", destination(target))));
+                    if self.pandoc() && attr != &Attr::default() {
+                        chunks.push(Chunk::Text(pandoc_attr(attr)));
+                    }
+                }
+            }
+            Inline::Image(attr, inlines, target) => self.image(attr, inlines, target, chunks),
+            Inline::Note(blocks) => {
+                self.notes.push(blocks);
+                let marker = match self.flavor {
+                    CommonMark => format!("[{}]", self.notes.len()),
+                    Gfm | Pandoc => format!("[^{}]", self.notes.len()),
+                };
+                chunks.push(Chunk::Text(marker));
+            }
+            Inline::Span(attr, inlines) => match self.flavor {
+                _ if attr == &Attr::default() => self.inlines(inlines, chunks),
+                Pandoc => {
+                    chunks.push(Chunk::Text("[".into()));
+                    self.inlines(inlines, chunks);
+                    chunks.push(Chunk::Text(format!("]{}", pandoc_attr(attr))));
+                }
+                CommonMark | Gfm => {
+                    let mut open = "<span".to_owned();
+                    html::attr_to(&mut open, attr);
+                    open.push('>');
+                    chunks.push(Chunk::Text(open));
+                    self.inlines(inlines, chunks);
+                    chunks.push(Chunk::Text("</span>".into()));
+                }
+            },
+        }
+    }
+
+    fn image(
+        &mut self,
+        attr: &Attr,
+        inlines: &'a [Inline],
+        target: &Target,
+        chunks: &mut Vec<Chunk>,
+    ) {
+        chunks.push(Chunk::Text("![".into()));
+        self.inlines(inlines, chunks);
+        chunks.push(Chunk::Text(format!("]({})", destination(target))));
+        if self.pandoc() && attr != &Attr::default() {
+            chunks.push(Chunk::Text(pandoc_attr(attr)));
+        }
+    }
+
+    /// Renders citations in Pandoc's syntax, such as `[see @doe, p. 1]` or
+    /// `@doe [p. 1]`.
+    fn citations(&mut self, citations: &'a [Citation], chunks: &mut Vec<Chunk>) {
+        let mut rest = citations;
+        match citations {
+            [first, tail @ ..] if first.citation_mode == CitationMode::AuthorInText => {
+                chunks.push(Chunk::Text(format!("@{}", first.citation_id)));
+                // The suffix of an in-text citation goes in brackets after it.
+                let suffix = &first.citation_suffix;
+                let start = suffix
+                    .iter()
+                    .position(|i| !matches!(i, Inline::Space | Inline::SoftBreak))
+                    .unwrap_or(suffix.len());
+                if start == suffix.len() && tail.is_empty() {
+                    return;
+                }
+                chunks.extend([Chunk::Space, Chunk::Text("[".into())]);
+                self.inlines(&suffix[start..], chunks);
+                if start < suffix.len() && !tail.is_empty() {
+                    chunks.extend([Chunk::Text(";".into()), Chunk::Space]);
+                }
+                rest = tail;
+            }
+            _ => chunks.push(Chunk::Text("[".into())),
+        }
+        for (i, citation) in rest.iter().enumerate() {
+            if i > 0 {
+                chunks.extend([Chunk::Text(";".into()), Chunk::Space]);
+            }
+            if !citation.citation_prefix.is_empty() {
+                self.inlines(&citation.citation_prefix, chunks);
+                chunks.push(Chunk::Space);
+            }
+            let dash = match citation.citation_mode {
+                CitationMode::SuppressAuthor => "-",
+                _ => "",
+            };
+            chunks.push(Chunk::Text(format!("{}@{}", dash, citation.citation_id)));
+            self.inlines(&citation.citation_suffix, chunks);
+        }
+        chunks.push(Chunk::Text("]".into()));
+    }
+
+    /// Escapes the characters of text that have a meaning in the flavor.
+    fn escape(&self, s: &str) -> String {
+        let mut out = String::with_capacity(s.len());
+        let mut previous = None;
+        let mut chars = s.chars().peekable();
+        while let Some(c) = chars.next() {
+            let escape = match c {
+                '\\' | '`' | '*' | '_' | '[' | ']' | '<' | '>' => true,
+                '&' => chars
+                    .peek()
+                    .is_some_and(|c| c.is_ascii_alphanumeric() || *c == '#'),
+                '~' | '|' | '$' => self.flavor != MarkdownFlavor::CommonMark,
+                '^' => self.pandoc(),
+                // An `@` at the start of a word would start a citation.
+                '@' => self.pandoc() && !previous.is_some_and(char::is_alphanumeric),
+                _ => false,
+            };
+            if escape {
+                out.push('\\');
+            }
+            out.push(c);
+            previous = Some(c);
+        }
+        out
+    }
+}
+
+/// Returns the maximum nesting depth of divs in the blocks, so that the
+/// fences of a div can be longer than those of the divs inside it.
+fn div_depth<'a>(blocks: impl IntoIterator<Item = &'a Block>) -> usize {
+    blocks
+        .into_iter()
+        .map(|block| match block {
+            Block::Div(_, blocks) => 1 + div_depth(blocks),
+            block => div_depth(block.iter_blocks()),
+        })
+        .max()
+        .unwrap_or(0)
+}
+
+/// Checks that a URL starts with a scheme, which CommonMark requires of
+/// the URL of an autolink: a letter, then letters, digits, `+`, `.` or `-`,
+/// 2 to 32 characters in all, followed by a `:`.
+fn has_scheme(url: &str) -> bool {
+    let Some((scheme, _)) = url.split_once(':') else {
+        return false;
+    };
+    (2..=32).contains(&scheme.len())
+        && scheme.starts_with(|c: char| c.is_ascii_alphabetic())
+        && scheme
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || matches!(c, '+' | '.' | '-'))
+}
+
+/// Formats attributes in Pandoc's syntax, such as `{#id .class key="value"}`.
+fn pandoc_attr(attr: &Attr) -> String {
+    let mut parts = Vec::new();
+    if !attr.identifier.is_empty() {
+        parts.push(format!("#{}", attr.identifier));
+    }
+    parts.extend(attr.classes.iter().map(|class| format!(".{}", class)));
+    for (key, value) in &attr.attributes {
+        let value = value.replace('\\', "\\\\").replace('"', "\\\"");
+        parts.push(format!("{}=\"{}\"", key, value));
+    }
+    format!("{{{}}}", parts.join(" "))
+}
+
+/// Formats the destination and title of a link or image.
+fn destination(target: &Target) -> String {
+    let url = &target.url;
+    let mut depth = 0i32;
+    let balanced = url.chars().all(|c| {
+        depth += match c {
+            '(' => 1,
+            ')' => -1,
+            _ => 0,
+        };
+        depth >= 0
+    }) && depth == 0;
+    let mut out = if url.is_empty() || !balanced || url.contains([' ', '<', '>']) {
+        format!("<{}>", url.replace('<', "\\<").replace('>', "\\>"))
+    } else {
+        url.clone()
+    };
+    if !target.title.is_empty() {
+        let title = target.title.replace('\\', "\\\\").replace('"', "\\\"");
+        out.push_str(&format!(" \"{}\"", title));
+    }
+    out
+}
+
+/// Computes the identifier Pandoc's Markdown reader gives a header, which
+/// doesn't need to be written out.
+fn auto_identifier(inlines: &[Inline]) -> String {
+    let mut text = String::new();
+    for inline in inlines {
+        inline.stringify_to(&mut text);
+    }
+    let text: String = text
+        .chars()
+        .filter(|c| c.is_alphanumeric() || c.is_whitespace() || matches!(c, '_' | '-' | '.'))
+        .flat_map(char::to_lowercase)
+        .collect();
+    let id = text.split_whitespace().collect::<Vec<_>>().join("-");
+    let id = id.trim_start_matches(|c: char| !c.is_alphabetic());
+    if id.is_empty() {
+        "section".to_owned()
+    } else {
+        id.to_owned()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::builder::*;
+    use crate::definition::Alignment::*;
+
+    fn attr<const N: usize, const M: usize>(
+        id: &str,
+        classes: [&str; N],
+        attributes: [(&str, &str); M],
+    ) -> Attr {
+        Attr {
+            identifier: id.into(),
+            classes: classes.map(Into::into).into(),
+            attributes: attributes.map(|(k, v)| (k.into(), v.into())).into(),
+        }
+    }
+
+    fn markdown(blocks: Blocks, flavor: MarkdownFlavor) -> String {
+        blocks_to_markdown(&blocks, flavor, &WriterOptions::default())
+    }
+
+    #[test]
+    fn escaping() {
+        let blocks = para("*a* _b_ [c] <d> a&b ~e~ |f| $5 ^g^ @h x@y")
+            + para(text("1. not a list, ") + text("-") + soft_break() + text("#"))
+            + plain("+");
+        assert_eq!(
+            markdown(blocks.clone(), MarkdownFlavor::CommonMark),
+            "\\*a\\* \\_b\\_ \\[c\\] \\<d\\> a\\&b ~e~ |f| $5 ^g^ @h x@y\n\n\
+             1\\. not a list, - #\n\n\\+\n"
+        );
+        assert_eq!(
+            markdown(blocks, MarkdownFlavor::Pandoc),
+            "\\*a\\* \\_b\\_ \\[c\\] \\<d\\> a\\&b \\~e\\~ \\|f\\| \\$5 \\^g\\^ \\@h x@y\n\n\
+             1\\. not a list, - #\n\n\\+\n"
+        );
+    }
+
+    #[test]
+    fn bang_before_link() {
+        let blocks =
+            para(str("Wow!") + link("u", "", "x")) + para(str("Wow!") + emph(link("u", "", "y")));
+        for flavor in [
+            MarkdownFlavor::CommonMark,
+            MarkdownFlavor::Gfm,
+            MarkdownFlavor::Pandoc,
+        ] {
+            assert_eq!(
+                markdown(blocks.clone(), flavor),
+                "Wow\\![x](u)\n\nWow!*[y](u)*\n"
+            );
+        }
+    }
+
+    #[test]
+    fn autolinks() {
+        let blocks = para(link("page", "", "page"))
+            + para(link("mailto:a@b.c", "", "a@b.c"))
+            + para(link("https://b.c", "", "https://b.c"));
+        for flavor in [
+            MarkdownFlavor::CommonMark,
+            MarkdownFlavor::Gfm,
+            MarkdownFlavor::Pandoc,
+        ] {
+            assert_eq!(
+                markdown(blocks.clone(), flavor),
+                "[page](page)\n\n<a@b.c>\n\n<https://b.c>\n"
+            );
+        }
+    }
+
+    #[test]
+    fn code_blocks() {
+        let blocks = code_block_with(
+            attr("main", ["rust", "numberLines"], []),
+            "```\nfn main() {}",
+        ) + code_block_with(attr("", ["python"], []), "pass")
+            + para(code("a ` b") + text(" ") + code("`x"));
+        assert_eq!(
+            markdown(blocks.clone(), MarkdownFlavor::Gfm),
+            "````rust\n```\nfn main() {}\n````\n\n```python\npass\n```\n\n``a ` b`` `` `x ``\n"
+        );
+        assert_eq!(
+            markdown(blocks, MarkdownFlavor::Pandoc),
+            "```` {#main .rust .numberLines}\n```\nfn main() {}\n````\n\n\
+             ```python\npass\n```\n\n``a ` b`` `` `x ``\n"
+        );
+
+        // Trailing whitespace is part of the code.
+        let blocks = code_block("a  \nb\t") + block_quote(code_block("c "));
+        assert_eq!(
+            markdown(blocks, MarkdownFlavor::CommonMark),
+            "```\na  \nb\t\n```\n\n> ```\n> c \n> ```\n"
+        );
+        assert_eq!(
+            markdown(raw_block("html", "<pre>a  \n</pre>"), MarkdownFlavor::Gfm),
+            "<pre>a  \n</pre>\n"
+        );
+    }
+
+    #[test]
+    fn attributes() {
+        let blocks = header_with(attr("hello-world", [], []), 1, text("Hello world"))
+            + header_with(
+                attr("intro", ["unnumbered"], [("lang", "en")]),
+                2,
+                text("Hi"),
+            )
+            + para(link("https://example.com", "", "https://example.com"))
+            + plain(
+                span_with(attr("", ["smallcaps"], []), "x") + image("a.png", "Title", text("alt")),
+            );
+        assert_eq!(
+            markdown(blocks.clone(), MarkdownFlavor::CommonMark),
+            "# Hello world\n\n## Hi\n\n<https://example.com>\n\n\
+             <span class=\"smallcaps\">x</span>![alt](a.png \"Title\")\n"
+        );
+        assert_eq!(
+            markdown(blocks, MarkdownFlavor::Pandoc),
+            "# Hello world\n\n## Hi {#intro .unnumbered lang=\"en\"}\n\n<https://example.com>\n\n\
+             [x]{.smallcaps}![alt](a.png \"Title\")\n"
+        );
+    }
+
+    #[test]
+    fn lists_and_notes() {
+        let blocks = bullet_list([plain("one"), plain("two")])
+            + bullet_list([para(text("three") + note(para("A note.") + para("More.")))])
+            + ordered_list_with(
+                ListAttributes {
+                    start_number: 3,
+                    style: ListNumberStyle::LowerRoman,
+                    delim: ListNumberDelim::TwoParens,
+                },
+                [plain("x")],
+            );
+        assert_eq!(
+            markdown(blocks.clone(), MarkdownFlavor::CommonMark),
+            "- one\n- two\n\n<!-- -->\n\n- three[1]\n\n3) x\n\n[1] A note.\n\n    More.\n"
+        );
+        assert_eq!(markdown(blocks, MarkdownFlavor::Pandoc),
+            "-   one\n-   two\n\n<!-- -->\n\n-   three[^1]\n\n(iii) x\n\n[^1]: A note.\n\n    More.\n"
+        );
+    }
+
+    fn simple_table() -> Table {
+        TableBuilder::new()
+            .alignments([AlignRight, AlignDefault])
+            .header([plain("A"), plain("B")])
+            .body([vec![
+                cell(AlignDefault, 1, 1, plain("1")),
+                cell(AlignDefault, 1, 1, plain("a|b")),
+            ]])
+            .build()
+    }
+
+    #[test]
+    fn pipe_tables() {
+        let table = Blocks::from(simple_table());
+        for flavor in [MarkdownFlavor::Gfm, MarkdownFlavor::Pandoc] {
+            assert_eq!(
+                markdown(table.clone(), flavor),
+                "|   A | B    |\n|----:|------|\n|   1 | a\\|b |\n"
+            );
+        }
+        assert!(markdown(table, MarkdownFlavor::CommonMark).starts_with("<table>\n"));
+    }
+
+    #[test]
+    fn pipe_table_code() {
+        let table = TableBuilder::new()
+            .header([plain("A")])
+            .body([[plain(code("a|b"))]])
+            .build();
+        let table = Blocks::from(table);
+        assert_eq!(
+            markdown(table.clone(), MarkdownFlavor::Gfm),
+            "| A      |\n|--------|\n| `a\\|b` |\n"
+        );
+        assert_eq!(
+            markdown(table, MarkdownFlavor::Pandoc),
+            "| A     |\n|-------|\n| `a|b` |\n"
+        );
+    }
+
+    #[test]
+    fn grid_tables() {
+        let table = TableBuilder::new()
+            .caption(simple_caption(plain("Caption")))
+            .alignments([AlignLeft, AlignDefault])
+            .header([plain("A"), plain("B")])
+            .body([
+                vec![
+                    cell(AlignDefault, 2, 1, plain("1")),
+                    cell(AlignDefault, 1, 1, para("2") + para("3")),
+                ],
+                vec![cell(AlignDefault, 1, 1, plain("4"))],
+            ])
+            .footer([cell(AlignDefault, 1, 2, plain("total"))])
+            .build();
+        let table = Blocks::from(table);
+        assert_eq!(
+            markdown(table.clone(), MarkdownFlavor::Pandoc),
+            "+---+---+\n| A | B |\n+:==+===+\n| 1 | 2 |\n|   |   |\n|   | 3 |\n\
+             |   +---+\n|   | 4 |\n+===+===+\n| total |\n+=======+\n\n: Caption\n"
+        );
+        assert!(markdown(table, MarkdownFlavor::Gfm).starts_with("<table>\n"));
+    }
+
+    #[test]
+    fn notes_in_html_fallback() {
+        let table = TableBuilder::new()
+            .alignments([AlignDefault])
+            .header([plain("A")])
+            .body([vec![cell(
+                AlignDefault,
+                1,
+                1,
+                para(text("b") + note(para("Inner."))) + para("c"),
+            )]])
+            .build();
+        let blocks = para(text("a") + note(para("Outer."))) + Blocks::from(table);
+        let out = markdown(blocks.clone(), MarkdownFlavor::CommonMark);
+        assert!(out.contains("<sup>2</sup>"), "{out}");
+        assert!(!out.contains("footnotes"), "{out}");
+        assert!(out.ends_with("[1] Outer.\n\n[2] Inner.\n"), "{out}");
+        let out = markdown(blocks, MarkdownFlavor::Gfm);
+        assert!(out.contains("href=\"#fn2\""), "{out}");
+        assert!(out.ends_with("[^1]: Outer.\n\n[^2]: Inner.\n"), "{out}");
+    }
+}
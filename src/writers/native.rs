@@ -0,0 +1,929 @@
+//! A writer for Pandoc's native format, like `pandoc -t native`.
+//!
+//! The native format is the Haskell `show` representation of the AST, laid
+//! out after the rules of the `pretty-show` library that Pandoc uses: a
+//! value goes on one line if it fits within [`WriterOptions::columns`], and
+//! otherwise its arguments or elements go on separate lines, indented under
+//! it. Lines aren't limited unless [`WriterOptions::wrap`] is
+//! [`WrapOption::Auto`](super::WrapOption::Auto). [`write_native`] writes a
+//! whole document like `pandoc -s -t native`, with `Pandoc`, the metadata
+//! and the blocks on separate lines. The output can be read back with
+//! [`read_native`](crate::readers::native::read_native).
+//!
+//! ```
+//! use pandoc_types::builder::*;
+//! use pandoc_types::writers::native::{blocks_to_native, write_native};
+//! use pandoc_types::writers::WriterOptions;
+//!
+//! let blocks = para(text("Hello ") + emph("world"));
+//! let options = WriterOptions::default();
+//! assert_eq!(
+//!     blocks_to_native(&blocks, &options),
+//!     "[ Para [ Str \"Hello\" , Space , Emph [ Str \"world\" ] ] ]\n"
+//! );
+//! assert_eq!(
+//!     write_native(&doc(blocks), &options),
+//!     "Pandoc\n  \
+//!        Meta { unMeta = fromList [] }\n  \
+//!        [ Para [ Str \"Hello\" , Space , Emph [ Str \"world\" ] ] ]\n"
+//! );
+//! ```
+use super::WriterOptions;
+use crate::definition::{
+    Alignment, Attr, Block, Caption, Cell, Citation, CitationMode, ColSpec, ColWidth, Format,
+    IndexMap, Inline, ListAttributes, ListNumberDelim, ListNumberStyle, MathType, Meta, MetaValue,
+    Pandoc, QuoteType, Row, Table, TableBody, TableFoot, TableHead, Target,
+};
+
+/// Renders a document in the native format, like `pandoc -s -t native`.
+pub fn write_native(doc: &Pandoc, options: &WriterOptions) -> String {
+    let mut printer = Printer::new(options);
+    printer.text("Pandoc");
+    printer.newline(2);
+    printer.value(&meta(&doc.meta), 0);
+    printer.newline(2);
+    printer.value(&list(&doc.blocks, block), 0);
+    printer.finish()
+}
+
+/// Renders a list of blocks in the native format, like `pandoc -t native`.
+pub fn blocks_to_native(blocks: &[Block], options: &WriterOptions) -> String {
+    render(&list(blocks, block), options)
+}
+
+/// Renders a list of inlines in the native format.
+pub fn inlines_to_native(inlines: &[Inline], options: &WriterOptions) -> String {
+    render(&list(inlines, inline), options)
+}
+
+/// A value in the syntax of Haskell's `show`, as parsed by `pretty-show`.
+enum Value {
+    /// A constructor applied to arguments, such as `Str "a"`.
+    Con(&'static str, Vec<Value>),
+    /// A constructor with record syntax, such as `Meta { unMeta = ... }`.
+    Rec(&'static str, Vec<(&'static str, Value)>),
+    List(Vec<Value>),
+    Tuple(Vec<Value>),
+    /// A negative number.
+    Neg(Box<Value>),
+    /// A number or string literal.
+    Lit(String),
+}
+
+impl Value {
+    /// Returns true if the value doesn't need parentheses as an argument.
+    fn is_atom(&self) -> bool {
+        match self {
+            Value::Con(_, args) => args.is_empty(),
+            Value::Neg(_) => false,
+            _ => true,
+        }
+    }
+
+    /// Returns the width of the value laid out on a single line.
+    fn width(&self) -> usize {
+        fn block(items: impl ExactSizeIterator<Item = usize>) -> usize {
+            match items.len() {
+                0 => 2,
+                n => items.sum::<usize>() + 3 * (n - 1) + 4,
+            }
+        }
+        match self {
+            Value::Con(name, args) => {
+                name.len() + args.iter().map(|arg| 1 + arg.atom_width()).sum::<usize>()
+            }
+            Value::Rec(name, fields) => {
+                name.len()
+                    + 1
+                    + block(
+                        fields
+                            .iter()
+                            .map(|(field, value)| field.len() + 3 + value.width()),
+                    )
+            }
+            Value::List(items) | Value::Tuple(items) => block(items.iter().map(Value::width)),
+            Value::Neg(value) => 1 + value.atom_width(),
+            Value::Lit(s) => s.len(),
+        }
+    }
+
+    fn atom_width(&self) -> usize {
+        match self.is_atom() {
+            true => self.width(),
+            false => self.width() + 2,
+        }
+    }
+}
+
+fn render(value: &Value, options: &WriterOptions) -> String {
+    let mut printer = Printer::new(options);
+    printer.value(value, 0);
+    printer.finish()
+}
+
+/// Lays out values like the `pretty` library, which puts the parts of a
+/// value side by side if they fit on the line and one below the other
+/// otherwise.
+struct Printer {
+    out: String,
+    column: usize,
+    /// The column lines end by, if they are limited.
+    columns: Option<usize>,
+}
+
+impl Printer {
+    fn new(options: &WriterOptions) -> Self {
+        Printer {
+            out: String::new(),
+            column: 0,
+            columns: options.width(0),
+        }
+    }
+
+    fn finish(mut self) -> String {
+        self.out.push('\n');
+        self.out
+    }
+
+    fn text(&mut self, s: &str) {
+        self.out.push_str(s);
+        self.column += s.len();
+    }
+
+    fn newline(&mut self, indent: usize) {
+        self.out.push('\n');
+        self.out.push_str(&" ".repeat(indent));
+        self.column = indent;
+    }
+
+    /// Returns true if `width` characters, followed by `trail` characters
+    /// that must go on the same line, fit on the current line.
+    fn fits(&self, width: usize, trail: usize) -> bool {
+        match self.columns {
+            Some(columns) => self.column + width + trail <= columns,
+            None => true,
+        }
+    }
+
+    /// Writes a value on a single line.
+    fn flat(&mut self, value: &Value) {
+        match value {
+            Value::Con(name, args) => {
+                self.text(name);
+                for arg in args {
+                    self.text(" ");
+                    self.flat_atom(arg);
+                }
+            }
+            Value::Rec(name, fields) => {
+                self.text(name);
+                self.text(" ");
+                self.flat_block('{', '}', fields, |printer, (field, value)| {
+                    printer.text(field);
+                    printer.text(" = ");
+                    printer.flat(value);
+                });
+            }
+            Value::List(items) => self.flat_block('[', ']', items, Self::flat),
+            Value::Tuple(items) => self.flat_block('(', ')', items, Self::flat),
+            Value::Neg(value) => {
+                self.text("-");
+                self.flat_atom(value);
+            }
+            Value::Lit(s) => self.text(s),
+        }
+    }
+
+    fn flat_atom(&mut self, value: &Value) {
+        if value.is_atom() {
+            self.flat(value);
+        } else {
+            self.text("(");
+            self.flat(value);
+            self.text(")");
+        }
+    }
+
+    fn flat_block<T>(&mut self, open: char, close: char, items: &[T], f: fn(&mut Self, &T)) {
+        self.out.push(open);
+        self.column += 1;
+        for (i, item) in items.iter().enumerate() {
+            self.text(if i == 0 { " " } else { " , " });
+            f(self, item);
+        }
+        if !items.is_empty() {
+            self.text(" ");
+        }
+        self.out.push(close);
+        self.column += 1;
+    }
+
+    /// Writes a value followed by `trail` characters on the same line.
+    fn value(&mut self, value: &Value, trail: usize) {
+        if self.fits(value.width(), trail) {
+            return self.flat(value);
+        }
+        match value {
+            // `hang (text name) 2 (sep args)`
+            Value::Con(name, args) if !args.is_empty() => {
+                let margin = self.column;
+                self.text(name);
+                self.newline(margin + 2);
+                let args_width = args.iter().map(Value::atom_width).sum::<usize>() + args.len() - 1;
+                if self.fits(args_width, trail) {
+                    for (i, arg) in args.iter().enumerate() {
+                        if i > 0 {
+                            self.text(" ");
+                        }
+                        self.flat_atom(arg);
+                    }
+                } else {
+                    for (i, arg) in args.iter().enumerate() {
+                        if i > 0 {
+                            self.newline(margin + 2);
+                        }
+                        let last = i + 1 == args.len();
+                        self.atom(arg, if last { trail } else { 0 });
+                    }
+                }
+            }
+            // `hang (text name) 2 (block '{' '}' fields)`
+            Value::Rec(name, fields) => {
+                let margin = self.column;
+                self.text(name);
+                self.newline(margin + 2);
+                let width = value.width() - name.len() - 1;
+                if self.fits(width, trail) {
+                    self.flat_block('{', '}', fields, |printer, (field, value)| {
+                        printer.text(field);
+                        printer.text(" = ");
+                        printer.flat(value);
+                    });
+                } else {
+                    self.block('{', '}', fields, |printer, (field, value)| {
+                        printer.field(field, value)
+                    });
+                }
+            }
+            Value::List(items) => {
+                self.block('[', ']', items, |printer, item| printer.value(item, 0))
+            }
+            Value::Tuple(items) => {
+                self.block('(', ')', items, |printer, item| printer.value(item, 0))
+            }
+            Value::Neg(value) => {
+                self.text("-");
+                self.atom(value, trail);
+            }
+            Value::Con(..) | Value::Lit(_) => self.flat(value),
+        }
+    }
+
+    fn atom(&mut self, value: &Value, trail: usize) {
+        if value.is_atom() {
+            self.value(value, trail);
+        } else {
+            self.text("(");
+            self.value(value, trail + 1);
+            self.text(")");
+        }
+    }
+
+    /// Writes a record field, `hang (text field <+> char '=') 2 value`.
+    fn field(&mut self, field: &str, value: &Value) {
+        let margin = self.column;
+        self.text(field);
+        self.text(" =");
+        if self.fits(value.width() + 1, 0) {
+            self.text(" ");
+            self.flat(value);
+        } else {
+            self.newline(margin + 2);
+            self.value(value, 0);
+        }
+    }
+
+    /// Writes the items of a list, tuple or record one below the other, as
+    /// they don't fit on one line.
+    fn block<T>(&mut self, open: char, close: char, items: &[T], f: fn(&mut Self, &T)) {
+        let margin = self.column;
+        for (i, item) in items.iter().enumerate() {
+            if i > 0 {
+                self.newline(margin);
+                self.text(", ");
+            } else {
+                self.out.push(open);
+                self.text(" ");
+                self.column += 1;
+            }
+            f(self, item);
+        }
+        if items.is_empty() {
+            self.out.push(open);
+            self.column += 1;
+        } else {
+            self.newline(margin);
+        }
+        self.out.push(close);
+        self.column += 1;
+    }
+}
+
+fn list<T>(items: &[T], f: fn(&T) -> Value) -> Value {
+    Value::List(items.iter().map(f).collect())
+}
+
+fn con(name: &'static str, args: impl Into<Vec<Value>>) -> Value {
+    Value::Con(name, args.into())
+}
+
+fn string(s: &str) -> Value {
+    Value::Lit(show_string(s))
+}
+
+fn int(n: i32) -> Value {
+    match n {
+        n if n < 0 => Value::Neg(Box::new(Value::Lit(n.unsigned_abs().to_string()))),
+        n => Value::Lit(n.to_string()),
+    }
+}
+
+fn double(x: f64) -> Value {
+    match x {
+        x if x.is_sign_negative() && !x.is_nan() => {
+            Value::Neg(Box::new(Value::Lit(show_double(-x))))
+        }
+        x => Value::Lit(show_double(x)),
+    }
+}
+
+fn meta(meta: &Meta) -> Value {
+    Value::Rec("Meta", vec![("unMeta", map(&meta.0))])
+}
+
+fn map(map: &IndexMap<String, MetaValue>) -> Value {
+    // Haskell's maps are shown in the order of their keys.
+    let mut entries: Vec<_> = map.iter().collect();
+    entries.sort_by_key(|(key, _)| *key);
+    let entries = entries
+        .into_iter()
+        .map(|(key, value)| Value::Tuple(vec![string(key), meta_value(value)]))
+        .collect();
+    con("fromList", [Value::List(entries)])
+}
+
+fn meta_value(value: &MetaValue) -> Value {
+    match value {
+        MetaValue::MetaMap(m) => con("MetaMap", [map(m)]),
+        MetaValue::MetaList(values) => con("MetaList", [list(values, meta_value)]),
+        MetaValue::MetaBool(b) => con("MetaBool", [con(if *b { "True" } else { "False" }, [])]),
+        MetaValue::MetaString(s) => con("MetaString", [string(s)]),
+        MetaValue::MetaInlines(inlines) => con("MetaInlines", [list(inlines, inline)]),
+        MetaValue::MetaBlocks(blocks) => con("MetaBlocks", [list(blocks, block)]),
+    }
+}
+
+fn blocks(blocks: &[Block]) -> Value {
+    list(blocks, block)
+}
+
+fn inlines(inlines: &[Inline]) -> Value {
+    list(inlines, inline)
+}
+
+fn block(block: &Block) -> Value {
+    match block {
+        Block::Plain(ils) => con("Plain", [inlines(ils)]),
+        Block::Para(ils) => con("Para", [inlines(ils)]),
+        Block::LineBlock(lines) => con("LineBlock", [list(lines, |line| inlines(line))]),
+        Block::CodeBlock(a, code) => con("CodeBlock", [attr(a), string(code)]),
+        Block::RawBlock(f, raw) => con("RawBlock", [format(f), string(raw)]),
+        Block::BlockQuote(bs) => con("BlockQuote", [blocks(bs)]),
+        Block::OrderedList(attrs, items) => con(
+            "OrderedList",
+            [list_attributes(attrs), list(items, |item| blocks(item))],
+        ),
+        Block::BulletList(items) => con("BulletList", [list(items, |item| blocks(item))]),
+        Block::DefinitionList(items) => con(
+            "DefinitionList",
+            [list(items, |(term, definitions)| {
+                Value::Tuple(vec![
+                    inlines(term),
+                    list(definitions, |definition| blocks(definition)),
+                ])
+            })],
+        ),
+        Block::Header(level, a, ils) => con("Header", [int(*level), attr(a), inlines(ils)]),
+        Block::HorizontalRule => con("HorizontalRule", []),
+        Block::Table(t) => table(t),
+        Block::Figure(a, c, bs) => con("Figure", [attr(a), caption(c), blocks(bs)]),
+        Block::Div(a, bs) => con("Div", [attr(a), blocks(bs)]),
+        Block::Null => con("Null", []),
+    }
+}
+
+fn table(table: &Table) -> Value {
+    let Table {
+        attr: a,
+        caption: c,
+        colspecs,
+        head,
+        bodies,
+        foot,
+    } = table;
+    con(
+        "Table",
+        [
+            attr(a),
+            caption(c),
+            list(colspecs, |ColSpec(align, width)| {
+                Value::Tuple(vec![alignment(align), col_width(width)])
+            }),
+            table_head(head),
+            list(bodies, table_body),
+            table_foot(foot),
+        ],
+    )
+}
+
+fn caption(caption: &Caption) -> Value {
+    let short = match &caption.short {
+        Some(short) => con("Just", [inlines(short)]),
+        None => con("Nothing", []),
+    };
+    con("Caption", [short, blocks(&caption.long)])
+}
+
+fn col_width(width: &ColWidth) -> Value {
+    match width {
+        ColWidth::ColWidth(w) => con("ColWidth", [double(*w)]),
+        ColWidth::ColWidthDefault => con("ColWidthDefault", []),
+    }
+}
+
+fn table_head(head: &TableHead) -> Value {
+    con("TableHead", [attr(&head.attr), list(&head.rows, row)])
+}
+
+fn table_body(body: &TableBody) -> Value {
+    con(
+        "TableBody",
+        [
+            attr(&body.attr),
+            con("RowHeadColumns", [int(body.row_head_columns)]),
+            list(&body.head, row),
+            list(&body.body, row),
+        ],
+    )
+}
+
+fn table_foot(foot: &TableFoot) -> Value {
+    con("TableFoot", [attr(&foot.attr), list(&foot.rows, row)])
+}
+
+fn row(row: &Row) -> Value {
+    con("Row", [attr(&row.attr), list(&row.cells, cell)])
+}
+
+fn cell(cell: &Cell) -> Value {
+    con(
+        "Cell",
+        [
+            attr(&cell.attr),
+            alignment(&cell.align),
+            con("RowSpan", [int(cell.row_span)]),
+            con("ColSpan", [int(cell.col_span)]),
+            blocks(&cell.content),
+        ],
+    )
+}
+
+fn alignment(align: &Alignment) -> Value {
+    con(
+        match align {
+            Alignment::AlignLeft => "AlignLeft",
+            Alignment::AlignRight => "AlignRight",
+            Alignment::AlignCenter => "AlignCenter",
+            Alignment::AlignDefault => "AlignDefault",
+        },
+        [],
+    )
+}
+
+fn list_attributes(attrs: &ListAttributes) -> Value {
+    let style = match attrs.style {
+        ListNumberStyle::DefaultStyle => "DefaultStyle",
+        ListNumberStyle::Example => "Example",
+        ListNumberStyle::Decimal => "Decimal",
+        ListNumberStyle::LowerRoman => "LowerRoman",
+        ListNumberStyle::UpperRoman => "UpperRoman",
+        ListNumberStyle::LowerAlpha => "LowerAlpha",
+        ListNumberStyle::UpperAlpha => "UpperAlpha",
+    };
+    let delim = match attrs.delim {
+        ListNumberDelim::DefaultDelim => "DefaultDelim",
+        ListNumberDelim::Period => "Period",
+        ListNumberDelim::OneParen => "OneParen",
+        ListNumberDelim::TwoParens => "TwoParens",
+    };
+    Value::Tuple(vec![
+        int(attrs.start_number),
+        con(style, []),
+        con(delim, []),
+    ])
+}
+
+fn attr(attr: &Attr) -> Value {
+    Value::Tuple(vec![
+        string(&attr.identifier),
+        list(&attr.classes, |class| string(class)),
+        list(&attr.attributes, |(key, value)| {
+            Value::Tuple(vec![string(key), string(value)])
+        }),
+    ])
+}
+
+fn format(format: &Format) -> Value {
+    con("Format", [string(&format.0)])
+}
+
+fn target(target: &Target) -> Value {
+    Value::Tuple(vec![string(&target.url), string(&target.title)])
+}
+
+fn inline(inline: &Inline) -> Value {
+    match inline {
+        Inline::Str(s) => con("Str", [string(s)]),
+        Inline::Emph(ils) => con("Emph", [inlines(ils)]),
+        Inline::Underline(ils) => con("Underline", [inlines(ils)]),
+        Inline::Strong(ils) => con("Strong", [inlines(ils)]),
+        Inline::Strikeout(ils) => con("Strikeout", [inlines(ils)]),
+        Inline::Superscript(ils) => con("Superscript", [inlines(ils)]),
+        Inline::Subscript(ils) => con("Subscript", [inlines(ils)]),
+        Inline::SmallCaps(ils) => con("SmallCaps", [inlines(ils)]),
+        Inline::Quoted(quote, ils) => {
+            let quote = match quote {
+                QuoteType::SingleQuote => "SingleQuote",
+                QuoteType::DoubleQuote => "DoubleQuote",
+            };
+            con("Quoted", [con(quote, []), inlines(ils)])
+        }
+        Inline::Cite(citations, ils) => con("Cite", [list(citations, citation), inlines(ils)]),
+        Inline::Code(a, code) => con("Code", [attr(a), string(code)]),
+        Inline::Space => con("Space", []),
+        Inline::SoftBreak => con("SoftBreak", []),
+        Inline::LineBreak => con("LineBreak", []),
+        Inline::Math(math_type, tex) => {
+            let math_type = match math_type {
+                MathType::DisplayMath => "DisplayMath",
+                MathType::InlineMath => "InlineMath",
+            };
+            con("Math", [con(math_type, []), string(tex)])
+        }
+        Inline::RawInline(f, raw) => con("RawInline", [format(f), string(raw)]),
+        Inline::Link(a, ils, t) => con("Link", [attr(a), inlines(ils), target(t)]),
+        Inline::Image(a, ils, t) => con("Image", [attr(a), inlines(ils), target(t)]),
+        Inline::Note(bs) => con("Note", [blocks(bs)]),
+        Inline::Span(a, ils) => con("Span", [attr(a), inlines(ils)]),
+    }
+}
+
+fn citation(citation: &Citation) -> Value {
+    let mode = match citation.citation_mode {
+        CitationMode::AuthorInText => "AuthorInText",
+        CitationMode::SuppressAuthor => "SuppressAuthor",
+        CitationMode::NormalCitation => "NormalCitation",
+    };
+    Value::Rec(
+        "Citation",
+        vec![
+            ("citationId", string(&citation.citation_id)),
+            ("citationPrefix", inlines(&citation.citation_prefix)),
+            ("citationSuffix", inlines(&citation.citation_suffix)),
+            ("citationMode", con(mode, [])),
+            ("citationNoteNum", int(citation.citation_note_num)),
+            ("citationHash", int(citation.citation_hash)),
+        ],
+    )
+}
+
+/// Formats a string like Haskell's `show`: printable ASCII characters are
+/// kept, and everything else is escaped, using decimal codes for characters
+/// outside of ASCII.
+pub(crate) fn show_string(s: &str) -> String {
+    const ASCII_NAMES: [&str; 32] = [
+        "NUL", "SOH", "STX", "ETX", "EOT", "ENQ", "ACK", "a", "b", "t", "n", "v", "f", "r", "SO",
+        "SI", "DLE", "DC1", "DC2", "DC3", "DC4", "NAK", "SYN", "ETB", "CAN", "EM", "SUB", "ESC",
+        "FS", "GS", "RS", "US",
+    ];
+    let mut out = String::with_capacity(s.len() + 2);
+    out.push('"');
+    let mut chars = s.chars().peekable();
+    while let Some(c) = chars.next() {
+        match c {
+            '"' => out.push_str("\\\""),
+            '\\' => out.push_str("\\\\"),
+            ' '..='~' => out.push(c),
+            '\x7f' => out.push_str("\\DEL"),
+            '\0'..='\x1f' => {
+                let name = ASCII_NAMES[c as usize];
+                out.push('\\');
+                out.push_str(name);
+                // `\SO` followed by `H` would read as `\SOH`.
+                if name == "SO" && chars.peek() == Some(&'H') {
+                    out.push_str("\\&");
+                }
+            }
+            _ => {
+                out.push('\\');
+                out.push_str(&(c as u32).to_string());
+                // Separate the code from a following digit.
+                if chars.peek().is_some_and(|c| c.is_ascii_digit()) {
+                    out.push_str("\\&");
+                }
+            }
+        }
+    }
+    out.push('"');
+    out
+}
+
+/// Formats a non-negative number like Haskell's `show` for `Double`: with
+/// the shortest digits that identify it, in positional notation between
+/// 0.1 and 10^7 and in scientific notation otherwise.
+pub(crate) fn show_double(x: f64) -> String {
+    if x.is_nan() {
+        return "NaN".to_owned();
+    }
+    if x.is_infinite() {
+        return "Infinity".to_owned();
+    }
+    if x == 0.0 {
+        return "0.0".to_owned();
+    }
+    // Rust's `{:e}` also uses the shortest digits, as `d.ddde-n`.
+    let scientific = format!("{:e}", x);
+    let (mantissa, exponent) = scientific.split_once('e').unwrap();
+    let digits: String = mantissa.chars().filter(|c| *c != '.').collect();
+    // The exponent of `0.ddd` rather than `d.ddd`.
+    let exponent = exponent.parse::<i32>().unwrap() + 1;
+    if (0..=7).contains(&exponent) {
+        let point = exponent as usize;
+        let (int, frac) = if digits.len() > point {
+            (digits[..point].to_owned(), digits[point..].to_owned())
+        } else {
+            (format!("{:0<width$}", digits, width = point), String::new())
+        };
+        let int = if int.is_empty() { "0".to_owned() } else { int };
+        let frac = if frac.is_empty() {
+            "0".to_owned()
+        } else {
+            frac
+        };
+        format!("{}.{}", int, frac)
+    } else {
+        let frac = if digits.len() > 1 { &digits[1..] } else { "0" };
+        format!("{}.{}e{}", &digits[..1], frac, exponent - 1)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::builder::*;
+    use crate::definition::Alignment::*;
+    use crate::writers::WrapOption;
+
+    fn native(blocks: Blocks) -> String {
+        blocks_to_native(&blocks, &WriterOptions::default())
+    }
+
+    #[test]
+    fn strings() {
+        assert_eq!(
+            show_string("plain \"quoted\" \\"),
+            r#""plain \"quoted\" \\""#
+        );
+        assert_eq!(show_string("a\nb\tc\x07\x7f"), r#""a\nb\tc\a\DEL""#);
+        assert_eq!(show_string("\x0eH\x0eI\x1b"), r#""\SO\&H\SOI\ESC""#);
+        assert_eq!(
+            show_string("Gruber’s ’9 é"),
+            r#""Gruber\8217s \8217\&9 \233""#
+        );
+    }
+
+    #[test]
+    fn doubles() {
+        let cases = [
+            (0.0, "0.0"),
+            (1.0, "1.0"),
+            (0.5, "0.5"),
+            (0.1, "0.1"),
+            (0.05, "5.0e-2"),
+            (0.05555555555555555, "5.555555555555555e-2"),
+            (0.16666666666666666, "0.16666666666666666"),
+            (1234567.5, "1234567.5"),
+            (1.0e7, "1.0e7"),
+            (12345678.0, "1.2345678e7"),
+        ];
+        for (x, expected) in cases {
+            assert_eq!(show_double(x), expected);
+        }
+    }
+
+    #[test]
+    fn short_values_stay_on_one_line() {
+        let blocks = para(text("Hello ") + emph("pandoc") + str("!"));
+        assert_eq!(
+            native(blocks),
+            "[ Para [ Str \"Hello\" , Space , Emph [ Str \"pandoc\" ] , Str \"!\" ] ]\n"
+        );
+        assert_eq!(native(Blocks::new()), "[]\n");
+        assert_eq!(
+            inlines_to_native(&code("x").into_vec(), &WriterOptions::default()),
+            "[ Code ( \"\" , [] , [] ) \"x\" ]\n"
+        );
+    }
+
+    #[test]
+    fn long_values_are_broken() {
+        let blocks = header(
+            2,
+            text("Level 2 with an ") + link("/url", "", "embedded link"),
+        );
+        assert_eq!(
+            native(blocks),
+            "[ Header\n    \
+               2\n    \
+               ( \"\" , [] , [] )\n    \
+               [ Str \"Level\"\n    \
+               , Space\n    \
+               , Str \"2\"\n    \
+               , Space\n    \
+               , Str \"with\"\n    \
+               , Space\n    \
+               , Str \"an\"\n    \
+               , Space\n    \
+               , Link\n        \
+                   ( \"\" , [] , [] )\n        \
+                   [ Str \"embedded\" , Space , Str \"link\" ]\n        \
+                   ( \"/url\" , \"\" )\n    \
+               ]\n\
+             ]\n"
+        );
+    }
+
+    #[test]
+    fn arguments_in_parentheses() {
+        let blocks = raw_block("html", "<hr>")
+            + header(-1, "x")
+            + ordered_list_with(
+                ListAttributes {
+                    start_number: -2,
+                    style: ListNumberStyle::Decimal,
+                    delim: ListNumberDelim::Period,
+                },
+                [plain("a")],
+            );
+        assert_eq!(
+            native(blocks),
+            "[ RawBlock (Format \"html\") \"<hr>\"\n\
+             , Header (-1) ( \"\" , [] , [] ) [ Str \"x\" ]\n\
+             , OrderedList ( -2 , Decimal , Period ) [ [ Plain [ Str \"a\" ] ] ]\n\
+             ]\n"
+        );
+    }
+
+    #[test]
+    fn tables() {
+        let blocks: Blocks = TableBuilder::new()
+            .alignments([AlignRight])
+            .widths([0.5])
+            .header([plain("H")])
+            .body([[plain("1")]])
+            .build()
+            .into();
+        assert_eq!(
+            native(blocks),
+            "[ Table\n    \
+               ( \"\" , [] , [] )\n    \
+               (Caption Nothing [])\n    \
+               [ ( AlignRight , ColWidth 0.5 ) ]\n    \
+               (TableHead\n       \
+                  ( \"\" , [] , [] )\n       \
+                  [ Row\n           \
+                      ( \"\" , [] , [] )\n           \
+                      [ Cell\n               \
+                          ( \"\" , [] , [] )\n               \
+                          AlignDefault\n               \
+                          (RowSpan 1)\n               \
+                          (ColSpan 1)\n               \
+                          [ Plain [ Str \"H\" ] ]\n           \
+                      ]\n       \
+                  ])\n    \
+               [ TableBody\n        \
+                   ( \"\" , [] , [] )\n        \
+                   (RowHeadColumns 0)\n        \
+                   []\n        \
+                   [ Row\n            \
+                       ( \"\" , [] , [] )\n            \
+                       [ Cell\n                \
+                           ( \"\" , [] , [] )\n                \
+                           AlignDefault\n                \
+                           (RowSpan 1)\n                \
+                           (ColSpan 1)\n                \
+                           [ Plain [ Str \"1\" ] ]\n            \
+                       ]\n        \
+                   ]\n    \
+               ]\n    \
+               (TableFoot ( \"\" , [] , [] ) [])\n\
+             ]\n"
+        );
+    }
+
+    #[test]
+    fn documents() {
+        let mut doc = doc(para("a"));
+        assert_eq!(
+            write_native(&doc, &WriterOptions::default()),
+            "Pandoc\n  Meta { unMeta = fromList [] }\n  [ Para [ Str \"a\" ] ]\n"
+        );
+        doc.meta
+            .0
+            .insert("title".into(), MetaValue::MetaInlines(text("T").into_vec()));
+        doc.meta
+            .0
+            .insert("author".into(), MetaValue::MetaBool(true));
+        assert_eq!(
+            write_native(&doc, &WriterOptions::default()),
+            "Pandoc\n  \
+               Meta\n    \
+                 { unMeta =\n        \
+                     fromList\n          \
+                       [ ( \"author\" , MetaBool True )\n          \
+                       , ( \"title\" , MetaInlines [ Str \"T\" ] )\n          \
+                       ]\n    \
+                 }\n  \
+               [ Para [ Str \"a\" ] ]\n"
+        );
+    }
+
+    #[test]
+    fn columns() {
+        let blocks = para(text("one two three"));
+        let options = WriterOptions {
+            columns: 20,
+            ..WriterOptions::default()
+        };
+        assert_eq!(
+            blocks_to_native(&blocks, &options),
+            "[ Para\n    \
+               [ Str \"one\"\n    \
+               , Space\n    \
+               , Str \"two\"\n    \
+               , Space\n    \
+               , Str \"three\"\n    \
+               ]\n\
+             ]\n"
+        );
+        let options = WriterOptions {
+            columns: 20,
+            wrap: WrapOption::None,
+            ..WriterOptions::default()
+        };
+        assert_eq!(
+            blocks_to_native(&blocks, &options),
+            "[ Para [ Str \"one\" , Space , Str \"two\" , Space , Str \"three\" ] ]\n"
+        );
+    }
+
+    #[test]
+    fn citations() {
+        let citation = Citation {
+            citation_id: "doe".into(),
+            citation_prefix: vec![],
+            citation_suffix: vec![],
+            citation_mode: CitationMode::AuthorInText,
+            citation_note_num: 1,
+            citation_hash: 0,
+        };
+        assert_eq!(
+            inlines_to_native(
+                &cite(vec![citation], "@doe").into_vec(),
+                &WriterOptions::default()
+            ),
+            "[ Cite\n    \
+               [ Citation\n        \
+                   { citationId = \"doe\"\n        \
+                   , citationPrefix = []\n        \
+                   , citationSuffix = []\n        \
+                   , citationMode = AuthorInText\n        \
+                   , citationNoteNum = 1\n        \
+                   , citationHash = 0\n        \
+                   }\n    \
+               ]\n    \
+               [ Str \"@doe\" ]\n\
+             ]\n"
+        );
+    }
+}
@@ -0,0 +1,607 @@
+//! A writer for plain text, like `pandoc -t plain`.
+//!
+//! All formatting is removed except what is needed to make the structure of
+//! the document readable: paragraphs are separated by blank lines and
+//! wrapped, list items get markers following their [`ListAttributes`], block
+//! quotes and code blocks are indented, tables are laid out in columns and
+//! footnotes are collected at the end of the document.
+//!
+//! ```
+//! use pandoc_types::builder::*;
+//! use pandoc_types::definition::{ListAttributes, ListNumberDelim, ListNumberStyle};
+//! use pandoc_types::writers::{plain::write_plain, WriterOptions};
+//!
+//! let attrs = ListAttributes {
+//!     start_number: 3,
+//!     style: ListNumberStyle::LowerRoman,
+//!     delim: ListNumberDelim::OneParen,
+//! };
+//! let doc = doc(
+//!     para(text("Some ") + emph("important") + text(" points:"))
+//!         + ordered_list_with(attrs, [plain("one"), plain("two")]),
+//! );
+//!
+//! assert_eq!(
+//!     write_plain(&doc, &WriterOptions::default()),
+//!     "Some important points:\n\niii) one\niv) two\n"
+//! );
+//! ```
+use super::{indent, is_loose, list_marker, pad, wrap, Chunk, WriterOptions};
+use crate::definition::{
+    Alignment, Block, Caption, ColWidth, GridSlot, Inline, ListAttributes, MathType, Pandoc,
+    QuoteType, Table, TablePart,
+};
+
+/// Renders a document as plain text.
+pub fn write_plain(doc: &Pandoc, options: &WriterOptions) -> String {
+    blocks_to_plain(&doc.blocks, options)
+}
+
+/// Renders a list of blocks as plain text, followed by their footnotes.
+pub fn blocks_to_plain(blocks: &[Block], options: &WriterOptions) -> String {
+    let mut writer = Writer {
+        options,
+        notes: Vec::new(),
+        unwrapped: false,
+    };
+    let mut lines = writer.blocks(blocks, 0);
+
+    // Footnotes may contain more footnotes, which are numbered after them.
+    let mut i = 0;
+    while i < writer.notes.len() {
+        let marker = format!("[{}]", i + 1);
+        let note = writer.blocks(writer.notes[i], 4);
+        if !lines.is_empty() {
+            lines.push(String::new());
+        }
+        lines.extend(indent(note, &format!("{:4}", marker + " "), "    "));
+        i += 1;
+    }
+
+    let mut output = String::new();
+    for line in lines {
+        output.push_str(line.trim_end());
+        output.push('\n');
+    }
+    output
+}
+
+struct Writer<'a> {
+    options: &'a WriterOptions,
+    notes: Vec<&'a [Block]>,
+    /// Whether lines are laid out without wrapping, to measure table cells.
+    unwrapped: bool,
+}
+
+impl<'a> Writer<'a> {
+    /// Renders blocks indented by `indent` columns, separated by blank lines
+    /// except after [`Block::Plain`].
+    fn blocks(&mut self, blocks: &'a [Block], indent: usize) -> Vec<String> {
+        let mut lines = Vec::new();
+        let mut tight = true;
+        for block in blocks {
+            let Some(block_lines) = self.block(block, indent) else {
+                continue;
+            };
+            if !lines.is_empty() && !tight {
+                lines.push(String::new());
+            }
+            lines.extend(block_lines);
+            tight = matches!(block, Block::Plain(_));
+        }
+        lines
+    }
+
+    /// Renders a block, returning `None` if it produces no output at all.
+    fn block(&mut self, block: &'a Block, indent: usize) -> Option<Vec<String>> {
+        let lines = match block {
+            Block::Plain(inlines) | Block::Para(inlines) | Block::Header(_, _, inlines) => {
+                self.wrap(inlines, indent)
+            }
+            Block::LineBlock(lines) => lines
+                .iter()
+                .flat_map(|line| self.wrap(line, indent))
+                .collect(),
+            Block::CodeBlock(_, code) => code.lines().map(|line| format!("    {}", line)).collect(),
+            Block::RawBlock(format, raw) if format.0 == "plain" => {
+                raw.lines().map(str::to_owned).collect()
+            }
+            Block::RawBlock(..) | Block::Null => return None,
+            Block::BlockQuote(blocks) => {
+                let lines = self.blocks(blocks, indent + 2);
+                super::indent(lines, "  ", "  ")
+            }
+            Block::OrderedList(attrs, items) => self.ordered_list(attrs, items, indent),
+            Block::BulletList(items) => {
+                let markers = vec!["-".to_owned(); items.len()];
+                self.list(&markers, items, indent)
+            }
+            Block::DefinitionList(items) => {
+                let loose = items
+                    .iter()
+                    .flat_map(|(_, definitions)| definitions)
+                    .any(|blocks| is_loose(blocks));
+                let mut lines = Vec::new();
+                for (term, definitions) in items {
+                    if !lines.is_empty() {
+                        lines.push(String::new());
+                    }
+                    lines.extend(self.wrap(term, indent));
+                    for definition in definitions {
+                        if loose {
+                            lines.push(String::new());
+                        }
+                        let definition = self.blocks(definition, indent + 4);
+                        lines.extend(super::indent(definition, "    ", "    "));
+                    }
+                }
+                lines
+            }
+            Block::HorizontalRule => {
+                vec!["-".repeat(self.options.columns.saturating_sub(indent).max(1))]
+            }
+            Block::Table(table) => self.table(table, indent),
+            Block::Figure(_, caption, blocks) => {
+                let mut lines = self.blocks(blocks, indent);
+                self.caption(caption, indent, &mut lines);
+                lines
+            }
+            Block::Div(_, blocks) => self.blocks(blocks, indent),
+        };
+        Some(lines)
+    }
+
+    /// Renders list items, putting each marker in front of the first line of
+    /// its item and indenting the rest to line up with the text after it.
+    fn list(&mut self, markers: &[String], items: &'a [Vec<Block>], indent: usize) -> Vec<String> {
+        let loose = items.iter().any(|item| is_loose(item));
+        let mut lines = Vec::new();
+        for (marker, item) in markers.iter().zip(items) {
+            if loose && !lines.is_empty() {
+                lines.push(String::new());
+            }
+            // Markers are padded to four columns, like pandoc does.
+            let width = (marker.chars().count() + 1).max(4);
+            let first = format!("{:width$}", marker, width = width);
+            let item = self.blocks(item, indent + width);
+            if item.is_empty() {
+                lines.push(marker.clone());
+            } else {
+                lines.extend(super::indent(item, &first, &" ".repeat(width)));
+            }
+        }
+        lines
+    }
+
+    fn ordered_list(
+        &mut self,
+        attrs: &ListAttributes,
+        items: &'a [Vec<Block>],
+        indent: usize,
+    ) -> Vec<String> {
+        let markers: Vec<_> = (0..items.len() as i32)
+            .map(|i| list_marker(attrs, attrs.start_number.saturating_add(i)))
+            .collect();
+        self.list(&markers, items, indent)
+    }
+
+    fn caption(&mut self, caption: &'a Caption, indent: usize, lines: &mut Vec<String>) {
+        let caption = self.blocks(&caption.long, indent);
+        if !caption.is_empty() {
+            if !lines.is_empty() {
+                lines.push(String::new());
+            }
+            lines.extend(caption);
+        }
+    }
+
+    /// Lays out a table in columns. Columns with a relative width are sized
+    /// to that share of the line width and their contents wrapped, others
+    /// are as wide as their contents.
+    fn table(&mut self, table: &'a Table, indent: usize) -> Vec<String> {
+        let grid = table.grid();
+        let mut lines = Vec::new();
+        if grid.width > 0 && !grid.rows.is_empty() {
+            let total = self.options.columns.saturating_sub(indent);
+            let fixed: Option<Vec<usize>> = table
+                .colspecs
+                .iter()
+                .map(|spec| match spec.1 {
+                    ColWidth::ColWidth(w) => {
+                        Some(((w * total as f64) as usize).saturating_sub(1).max(1))
+                    }
+                    ColWidth::ColWidthDefault => None,
+                })
+                .collect();
+
+            // The number of consecutive columns from `column` covered by the
+            // same cell as the slot at `column`.
+            let span = |row: usize, column: usize| {
+                let origin = grid.cell_at(row, column).map(|(r, c, _)| (r, c));
+                let mut end = column + 1;
+                while origin.is_some()
+                    && end < grid.width
+                    && grid.cell_at(row, end).map(|(r, c, _)| (r, c)) == origin
+                {
+                    end += 1;
+                }
+                end - column
+            };
+            let span_width = |widths: &[usize], column: usize, span: usize| {
+                widths[column..column + span].iter().sum::<usize>() + span - 1
+            };
+
+            // Render the contents of each cell, unwrapped unless the columns
+            // have fixed widths.
+            let mut widths = fixed.clone().unwrap_or_else(|| vec![1; grid.width]);
+            let mut cells = Vec::new();
+            for r in 0..grid.rows.len() {
+                let mut column = 0;
+                while column < grid.width {
+                    let n = span(r, column);
+                    if let Some(GridSlot::Origin(cell)) = grid.get(r, column) {
+                        let content = match &fixed {
+                            Some(_) => {
+                                let width = span_width(&widths, column, n);
+                                self.cell_blocks(&cell.content, Some(width))
+                            }
+                            None => self.cell_blocks(&cell.content, None),
+                        };
+                        cells.push((r, column, n, content));
+                    }
+                    column += n;
+                }
+            }
+            if fixed.is_none() {
+                // Size single columns first, then widen the last column of
+                // spanning cells that don't fit.
+                let mut by_span: Vec<_> = cells.iter().collect();
+                by_span.sort_by_key(|(_, _, n, _)| *n);
+                for (_, column, n, content) in by_span {
+                    let needed = content.iter().map(|l| l.chars().count()).max().unwrap_or(0);
+                    let available = span_width(&widths, *column, *n);
+                    if needed > available {
+                        widths[column + n - 1] += needed - available;
+                    }
+                }
+            }
+
+            let multiline = fixed.is_some() || cells.iter().any(|(_, _, _, c)| c.len() > 1);
+            let rule = widths
+                .iter()
+                .map(|w| "-".repeat(*w))
+                .collect::<Vec<_>>()
+                .join(" ");
+            let border = "-".repeat(span_width(&widths, 0, grid.width));
+            let head_rows = grid
+                .rows
+                .iter()
+                .take_while(|row| row.part == TablePart::Head)
+                .count();
+
+            let mut cells = cells.into_iter().peekable();
+            if multiline {
+                lines.push(border.clone());
+            } else if head_rows == 0 {
+                lines.push(rule.clone());
+            }
+            for r in 0..grid.rows.len() {
+                if r == head_rows && head_rows > 0 {
+                    lines.push(rule.clone());
+                } else if multiline && r > head_rows {
+                    lines.push(String::new());
+                }
+                let mut row_cells = Vec::new();
+                while let Some(cell) = cells.next_if(|(row, ..)| *row == r) {
+                    row_cells.push(cell);
+                }
+                let height = row_cells
+                    .iter()
+                    .map(|c| c.3.len())
+                    .max()
+                    .unwrap_or(0)
+                    .max(1);
+                for i in 0..height {
+                    let mut line = String::new();
+                    let mut column = 0;
+                    while column < grid.width {
+                        let n = span(r, column);
+                        let width = span_width(&widths, column, n);
+                        let text = match grid.get(r, column) {
+                            Some(GridSlot::Origin(cell)) => {
+                                let content = &row_cells
+                                    .iter()
+                                    .find(|(_, c, ..)| *c == column)
+                                    .expect("cell was rendered")
+                                    .3;
+                                let align = match cell.align {
+                                    Alignment::AlignDefault => &table.colspecs[column].0,
+                                    ref align => align,
+                                };
+                                let text = content.get(i).map(String::as_str).unwrap_or("");
+                                pad(text, width, align)
+                            }
+                            _ => " ".repeat(width),
+                        };
+                        if column > 0 {
+                            line.push(' ');
+                        }
+                        line.push_str(&text);
+                        column += n;
+                    }
+                    lines.push(line.trim_end().to_owned());
+                }
+            }
+            if multiline {
+                lines.push(border);
+            } else if head_rows == 0 {
+                lines.push(rule);
+            }
+        }
+        self.caption(&table.caption, indent, &mut lines);
+        lines
+    }
+
+    fn cell_blocks(&mut self, blocks: &'a [Block], width: Option<usize>) -> Vec<String> {
+        // Cells are rendered as if they were indented by the rest of the
+        // line, which leaves them `width` columns.
+        let indent = match width {
+            Some(width) => self.options.columns.saturating_sub(width),
+            None => return self.unwrapped(|writer| writer.blocks(blocks, 0)),
+        };
+        self.blocks(blocks, indent)
+    }
+
+    fn unwrapped(&mut self, f: impl FnOnce(&mut Self) -> Vec<String>) -> Vec<String> {
+        let unwrapped = std::mem::replace(&mut self.unwrapped, true);
+        let lines = f(self);
+        self.unwrapped = unwrapped;
+        lines
+    }
+
+    fn wrap(&mut self, inlines: &'a [Inline], indent: usize) -> Vec<String> {
+        let mut chunks = Vec::new();
+        self.inlines(inlines, &mut chunks);
+        let width = match self.unwrapped {
+            true => None,
+            false => self.options.width(indent),
+        };
+        wrap(&chunks, width, self.options.wrap)
+    }
+
+    fn inlines(&mut self, inlines: &'a [Inline], chunks: &mut Vec<Chunk>) {
+        for inline in inlines {
+            self.inline(inline, chunks);
+        }
+    }
+
+    fn inline(&mut self, inline: &'a Inline, chunks: &mut Vec<Chunk>) {
+        match inline {
+            Inline::Str(s) => chunks.push(Chunk::Text(s.clone())),
+            Inline::Emph(inlines)
+            | Inline::Underline(inlines)
+            | Inline::Strong(inlines)
+            | Inline::Strikeout(inlines)
+            | Inline::Cite(_, inlines)
+            | Inline::Link(_, inlines, _)
+            | Inline::Span(_, inlines) => self.inlines(inlines, chunks),
+            Inline::Superscript(inlines) => self.script(inlines, '^', SUPERSCRIPTS, chunks),
+            Inline::Subscript(inlines) => self.script(inlines, '_', SUBSCRIPTS, chunks),
+            Inline::SmallCaps(inlines) => {
+                let start = chunks.len();
+                self.inlines(inlines, chunks);
+                for chunk in &mut chunks[start..] {
+                    if let Chunk::Text(s) = chunk {
+                        *s = s.to_uppercase();
+                    }
+                }
+            }
+            Inline::Quoted(quote, inlines) => {
+                let (open, close) = match quote {
+                    QuoteType::SingleQuote => ("‘", "’"),
+                    QuoteType::DoubleQuote => ("“", "”"),
+                };
+                chunks.push(Chunk::Text(open.into()));
+                self.inlines(inlines, chunks);
+                chunks.push(Chunk::Text(close.into()));
+            }
+            Inline::Code(_, code) => chunks.push(Chunk::Text(code.clone())),
+            Inline::Space => chunks.push(Chunk::Space),
+            Inline::SoftBreak => chunks.push(Chunk::SoftBreak),
+            Inline::LineBreak => chunks.push(Chunk::Newline),
+            Inline::Math(MathType::InlineMath, tex) => chunks.push(Chunk::Text(tex.clone())),
+            Inline::Math(MathType::DisplayMath, tex) => {
+                chunks.push(Chunk::Newline);
+                chunks.push(Chunk::Text(tex.clone()));
+                chunks.push(Chunk::Newline);
+            }
+            Inline::RawInline(format, raw) if format.0 == "plain" => {
+                chunks.push(Chunk::Text(raw.clone()))
+            }
+            Inline::RawInline(..) => {}
+            Inline::Image(_, inlines, _) => {
+                chunks.push(Chunk::Text("[".into()));
+                self.inlines(inlines, chunks);
+                chunks.push(Chunk::Text("]".into()));
+            }
+            Inline::Note(blocks) => {
+                self.notes.push(blocks);
+                chunks.push(Chunk::Text(format!("[{}]", self.notes.len())));
+            }
+        }
+    }
+
+    /// Renders a superscript or subscript with Unicode characters where
+    /// they exist, and as `^(text)` or `_(text)` otherwise.
+    fn script(
+        &mut self,
+        inlines: &'a [Inline],
+        prefix: char,
+        table: &[(char, char)],
+        chunks: &mut Vec<Chunk>,
+    ) {
+        let mut inner = Vec::new();
+        self.inlines(inlines, &mut inner);
+        let mapped: Option<String> = match inner.as_slice() {
+            [Chunk::Text(s)] => s
+                .chars()
+                .map(|c| table.iter().find(|(from, _)| *from == c).map(|(_, to)| *to))
+                .collect(),
+            _ => None,
+        };
+        match mapped {
+            Some(s) => chunks.push(Chunk::Text(s)),
+            None => {
+                chunks.push(Chunk::Text(format!("{}(", prefix)));
+                chunks.extend(inner);
+                chunks.push(Chunk::Text(")".into()));
+            }
+        }
+    }
+}
+
+const SUPERSCRIPTS: &[(char, char)] = &[
+    ('0', '⁰'),
+    ('1', '¹'),
+    ('2', '²'),
+    ('3', '³'),
+    ('4', '⁴'),
+    ('5', '⁵'),
+    ('6', '⁶'),
+    ('7', '⁷'),
+    ('8', '⁸'),
+    ('9', '⁹'),
+    ('+', '⁺'),
+    ('-', '⁻'),
+    ('=', '⁼'),
+    ('(', '⁽'),
+    (')', '⁾'),
+];
+
+const SUBSCRIPTS: &[(char, char)] = &[
+    ('0', '₀'),
+    ('1', '₁'),
+    ('2', '₂'),
+    ('3', '₃'),
+    ('4', '₄'),
+    ('5', '₅'),
+    ('6', '₆'),
+    ('7', '₇'),
+    ('8', '₈'),
+    ('9', '₉'),
+    ('+', '₊'),
+    ('-', '₋'),
+    ('=', '₌'),
+    ('(', '₍'),
+    (')', '₎'),
+];
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::builder::*;
+    use crate::definition::{Alignment::*, ListNumberDelim, ListNumberStyle};
+
+    fn plain_text(blocks: Blocks, columns: usize) -> String {
+        let options = WriterOptions {
+            columns,
+            ..Default::default()
+        };
+        blocks_to_plain(&blocks, &options)
+    }
+
+    #[test]
+    fn paragraphs() {
+        let blocks = header(1, text("A title"))
+            + para(text("The quick brown fox ") + strong("jumps") + text(" over the lazy dog."))
+            + para(text("x") + superscript("2") + text(" and H") + subscript("2x"));
+        assert_eq!(
+            plain_text(blocks, 20),
+            "A title\n\nThe quick brown fox\njumps over the lazy\ndog.\n\nx² and H_(2x)\n"
+        );
+    }
+
+    #[test]
+    fn lists() {
+        let attrs = ListAttributes {
+            start_number: 9,
+            style: ListNumberStyle::UpperAlpha,
+            delim: ListNumberDelim::TwoParens,
+        };
+        let blocks = ordered_list_with(attrs, [para("one two three"), para("four")])
+            + bullet_list([plain("a") + bullet_list([plain("b")]), plain("c")]);
+        assert_eq!(
+            plain_text(blocks, 12),
+            "(I) one two\n    three\n\n(J) four\n\n-   a\n    -   b\n-   c\n"
+        );
+
+        let blocks = ordered_list((1..=10).map(|i| plain(text(&i.to_string()))));
+        let output = plain_text(blocks, 72);
+        assert!(output.starts_with("1.  1\n2.  2\n"));
+        assert!(output.ends_with("9.  9\n10. 10\n"));
+    }
+
+    #[test]
+    fn block_quotes_and_code() {
+        let blocks = block_quote(para(text("quoted text here")) + block_quote(para("nested")))
+            + code_block("let x = 1;\n\nx");
+        assert_eq!(
+            plain_text(blocks, 10),
+            "  quoted\n  text\n  here\n\n    nested\n\n    let x = 1;\n\n    x\n"
+        );
+    }
+
+    #[test]
+    fn notes() {
+        let blocks = para(text("a") + note(para(text("first") + note(para("inner")))))
+            + para(text("b") + note(para("second")));
+        assert_eq!(
+            plain_text(blocks, 72),
+            "a[1]\n\nb[2]\n\n[1] first[3]\n\n[2] second\n\n[3] inner\n"
+        );
+    }
+
+    #[test]
+    fn tables() {
+        let table = TableBuilder::new()
+            .alignments([AlignRight, AlignLeft, AlignCenter])
+            .header([plain("Right"), plain("Left"), plain("Center")])
+            .body([
+                vec![
+                    cell(AlignDefault, 1, 1, plain("1")),
+                    cell(AlignDefault, 1, 2, plain("spanning both")),
+                ],
+                vec![
+                    cell(AlignDefault, 1, 1, plain("12")),
+                    cell(AlignDefault, 1, 1, plain("ab")),
+                    cell(AlignDefault, 1, 1, plain("c")),
+                ],
+            ])
+            .caption(simple_caption(plain("The caption")))
+            .build();
+        assert_eq!(
+            plain_text(Blocks::from(table), 72),
+            "Right Left  Center\n\
+             ----- ---- --------\n    \
+             1 spanning both\n   \
+             12 ab      c\n\n\
+             The caption\n"
+        );
+    }
+
+    #[test]
+    fn tables_with_widths() {
+        let table = TableBuilder::new()
+            .alignments([AlignLeft, AlignRight])
+            .widths([0.5, 0.5])
+            .body([[plain("some long text"), plain("1")]])
+            .build();
+        assert_eq!(
+            plain_text(Blocks::from(table), 20),
+            "-------------------\n\
+             some long         1\n\
+             text\n\
+             -------------------\n"
+        );
+    }
+}
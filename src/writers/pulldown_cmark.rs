@@ -0,0 +1,607 @@
+//! A writer for the events of [pulldown-cmark], a CommonMark parser widely
+//! used in the Rust ecosystem. It is only available with the
+//! `pulldown-cmark` feature.
+//!
+//! The events can be rendered by any consumer of pulldown-cmark events,
+//! such as `pulldown_cmark::html::push_html` or `pulldown-cmark-to-cmark`.
+//! The writer follows the mapping of
+//! [`readers::pulldown_cmark`](crate::readers::pulldown_cmark) in reverse,
+//! so that documents read from events are written back the same way: bullet
+//! list items starting with `☐` or `☒` become task list items, divs with the
+//! class of a GitHub alert become alerts, and links with the class `uri` or
+//! `email` become autolinks. Footnotes are defined after the blocks.
+//!
+//! Elements that have no events are written as raw HTML: spans and divs
+//! with attributes as `<span>` and `<div>` tags around their content,
+//! underlines and small caps as `<u>` and `<span>` tags, and figures and
+//! tables that don't fit a pipe table as a whole. Raw blocks in `yaml` or
+//! `toml` become metadata blocks, other raw blocks and inlines are only
+//! kept if their format is `html`, and the attributes of code, links and
+//! images are dropped.
+//!
+//! ```
+//! use pandoc_types::builder::*;
+//! use pandoc_types::writers::pulldown_cmark::write_events;
+//! use pulldown_cmark::{Event, Tag, TagEnd};
+//!
+//! let doc = doc(para(text("Hello ") + strong("world")));
+//! assert_eq!(
+//!     write_events(&doc),
+//!     [
+//!         Event::Start(Tag::Paragraph),
+//!         Event::Text("Hello ".into()),
+//!         Event::Start(Tag::Strong),
+//!         Event::Text("world".into()),
+//!         Event::End(TagEnd::Strong),
+//!         Event::End(TagEnd::Paragraph),
+//!     ]
+//! );
+//! ```
+//!
+//! [pulldown-cmark]: https://docs.rs/pulldown-cmark
+use std::slice;
+
+use pulldown_cmark::{
+    BlockQuoteKind, CodeBlockKind, CowStr, Event, HeadingLevel, LinkType, MetadataBlockKind, Tag,
+};
+
+use super::{html, task, WriterOptions};
+use crate::definition::{
+    Alignment, Attr, Block, Format, GridSlot, Inline, MathType, Pandoc, QuoteType, Table, Target,
+};
+
+/// Renders a document as pulldown-cmark events.
+pub fn write_events(doc: &Pandoc) -> Vec<Event<'_>> {
+    blocks_to_events(&doc.blocks)
+}
+
+/// Renders a list of blocks as pulldown-cmark events, followed by the
+/// definitions of their footnotes.
+pub fn blocks_to_events(blocks: &[Block]) -> Vec<Event<'_>> {
+    let mut writer = Writer {
+        events: Vec::new(),
+        notes: Vec::new(),
+    };
+    writer.blocks(blocks);
+    // Footnotes may contain more footnotes, which are numbered after them.
+    let mut i = 0;
+    while i < writer.notes.len() {
+        let label = CowStr::from((i + 1).to_string());
+        let blocks = writer.notes[i];
+        writer.tagged(Tag::FootnoteDefinition(label), |writer| {
+            writer.blocks(blocks)
+        });
+        i += 1;
+    }
+    writer.events
+}
+
+struct Writer<'a> {
+    events: Vec<Event<'a>>,
+    notes: Vec<&'a [Block]>,
+}
+
+impl<'a> Writer<'a> {
+    /// Writes the start and end of an element around the events written by
+    /// `content`.
+    fn tagged(&mut self, tag: Tag<'a>, content: impl FnOnce(&mut Self)) {
+        let end = tag.to_end();
+        self.events.push(Event::Start(tag));
+        content(self);
+        self.events.push(Event::End(end));
+    }
+
+    /// Writes text, joining it to the text right before it.
+    fn text(&mut self, text: &'a str) {
+        match self.events.last_mut() {
+            Some(Event::Text(last)) => *last = format!("{}{}", last, text).into(),
+            _ => self.events.push(Event::Text(text.into())),
+        }
+    }
+
+    fn html_block(&mut self, html: String) {
+        self.tagged(Tag::HtmlBlock, |writer| {
+            writer.events.push(Event::Html(html.into()))
+        });
+    }
+
+    /// Writes a block as raw HTML.
+    fn html(&mut self, block: &Block) {
+        let html = html::blocks_to_html(slice::from_ref(block), &WriterOptions::default());
+        self.html_block(html);
+    }
+
+    fn blocks(&mut self, blocks: &'a [Block]) {
+        for block in blocks {
+            self.block(block);
+        }
+    }
+
+    fn block(&mut self, block: &'a Block) {
+        match block {
+            Block::Plain(inlines) => self.inlines(inlines),
+            Block::Para(inlines) => self.tagged(Tag::Paragraph, |writer| writer.inlines(inlines)),
+            Block::LineBlock(lines) => self.tagged(Tag::Paragraph, |writer| {
+                for (i, line) in lines.iter().enumerate() {
+                    if i > 0 {
+                        writer.events.push(Event::HardBreak);
+                    }
+                    writer.inlines(line);
+                }
+            }),
+            Block::CodeBlock(attr, code) => {
+                let info = attr.classes.first().map_or("", String::as_str);
+                let kind = CodeBlockKind::Fenced(info.into());
+                self.tagged(Tag::CodeBlock(kind), |writer| {
+                    if !code.is_empty() {
+                        writer
+                            .events
+                            .push(Event::Text(format!("{}\n", code).into()));
+                    }
+                });
+            }
+            Block::RawBlock(Format(format), raw) if format == "html" => {
+                self.html_block(format!("{}\n", raw));
+            }
+            Block::RawBlock(Format(format), raw) if format == "yaml" || format == "toml" => {
+                let kind = match format.as_str() {
+                    "yaml" => MetadataBlockKind::YamlStyle,
+                    _ => MetadataBlockKind::PlusesStyle,
+                };
+                self.tagged(Tag::MetadataBlock(kind), |writer| {
+                    if !raw.is_empty() {
+                        writer.events.push(Event::Text(format!("{}\n", raw).into()));
+                    }
+                });
+            }
+            Block::RawBlock(..) | Block::Null => {}
+            Block::BlockQuote(blocks) => {
+                self.tagged(Tag::BlockQuote(None), |writer| writer.blocks(blocks));
+            }
+            Block::OrderedList(attrs, items) => {
+                let start = attrs.start_number.max(0) as u64;
+                self.tagged(Tag::List(Some(start)), |writer| writer.items(items));
+            }
+            Block::BulletList(items) => self.tagged(Tag::List(None), |writer| writer.items(items)),
+            Block::DefinitionList(items) => self.tagged(Tag::DefinitionList, |writer| {
+                for (term, definitions) in items {
+                    writer.tagged(Tag::DefinitionListTitle, |writer| writer.inlines(term));
+                    for blocks in definitions {
+                        writer.tagged(Tag::DefinitionListDefinition, |writer| {
+                            writer.blocks(blocks)
+                        });
+                    }
+                }
+            }),
+            Block::Header(level, attr, inlines) => {
+                let level = HeadingLevel::try_from((*level).clamp(1, 6) as usize);
+                let tag = Tag::Heading {
+                    level: level.unwrap_or(HeadingLevel::H1),
+                    id: (!attr.identifier.is_empty()).then(|| attr.identifier.as_str().into()),
+                    classes: attr
+                        .classes
+                        .iter()
+                        .map(|class| class.as_str().into())
+                        .collect(),
+                    attrs: attr
+                        .attributes
+                        .iter()
+                        .map(|(key, value)| (key.as_str().into(), Some(value.as_str().into())))
+                        .collect(),
+                };
+                self.tagged(tag, |writer| writer.inlines(inlines));
+            }
+            Block::HorizontalRule => self.events.push(Event::Rule),
+            Block::Table(table) => {
+                if !self.table(table) {
+                    self.html(block);
+                }
+            }
+            Block::Figure(..) => self.html(block),
+            Block::Div(attr, blocks) => match alert(attr) {
+                Some(kind) => {
+                    // The title is implied by the kind of alert.
+                    let blocks = match blocks.split_first() {
+                        Some((Block::Div(attr, _), rest)) if attr.classes == ["title"] => rest,
+                        _ => blocks,
+                    };
+                    self.tagged(Tag::BlockQuote(Some(kind)), |writer| writer.blocks(blocks));
+                }
+                None if attr == &Attr::default() => self.blocks(blocks),
+                None => {
+                    let mut open = "<div".to_owned();
+                    html::attr_to(&mut open, attr);
+                    open.push_str(">\n");
+                    self.html_block(open);
+                    self.blocks(blocks);
+                    self.html_block("</div>\n".to_owned());
+                }
+            },
+        }
+    }
+
+    fn items(&mut self, items: &'a [Vec<Block>]) {
+        for item in items {
+            self.tagged(Tag::Item, |writer| match task(item) {
+                Some((checked, rest)) => {
+                    let marker = |writer: &mut Self| {
+                        writer.events.push(Event::TaskListMarker(checked));
+                        writer.inlines(rest);
+                    };
+                    match &item[0] {
+                        Block::Para(_) => writer.tagged(Tag::Paragraph, marker),
+                        _ => marker(writer),
+                    }
+                    writer.blocks(&item[1..]);
+                }
+                None => writer.blocks(item),
+            });
+        }
+    }
+
+    /// Writes a table with at most one head row and cells holding a single
+    /// paragraph, or returns false if the table has anything else.
+    fn table(&mut self, table: &'a Table) -> bool {
+        let grid = table.grid();
+        if grid.width == 0
+            || !grid.is_valid()
+            || table.head.rows.len() > 1
+            || table.bodies.iter().any(|body| !body.head.is_empty())
+            || !table.foot.rows.is_empty()
+            || !table.caption.long.is_empty()
+        {
+            return false;
+        }
+        let mut rows = Vec::new();
+        for row in &grid.rows {
+            let mut cells = Vec::new();
+            for slot in &row.slots {
+                let inlines: &[Inline] = match slot {
+                    GridSlot::Origin(cell) if cell.row_span <= 1 && cell.col_span <= 1 => {
+                        match cell.content.as_slice() {
+                            [] => &[],
+                            [Block::Plain(inlines) | Block::Para(inlines)] => inlines,
+                            _ => return false,
+                        }
+                    }
+                    _ => return false,
+                };
+                cells.push(inlines);
+            }
+            rows.push(cells);
+        }
+        if table.head.rows.is_empty() {
+            rows.insert(0, vec![&[]; grid.width]);
+        }
+
+        let alignments = table
+            .colspecs
+            .iter()
+            .map(|spec| match spec.0 {
+                Alignment::AlignLeft => pulldown_cmark::Alignment::Left,
+                Alignment::AlignRight => pulldown_cmark::Alignment::Right,
+                Alignment::AlignCenter => pulldown_cmark::Alignment::Center,
+                Alignment::AlignDefault => pulldown_cmark::Alignment::None,
+            })
+            .collect();
+        self.tagged(Tag::Table(alignments), |writer| {
+            for (i, cells) in rows.into_iter().enumerate() {
+                let tag = if i == 0 {
+                    Tag::TableHead
+                } else {
+                    Tag::TableRow
+                };
+                writer.tagged(tag, |writer| {
+                    for inlines in cells {
+                        writer.tagged(Tag::TableCell, |writer| writer.inlines(inlines));
+                    }
+                });
+            }
+        });
+        true
+    }
+
+    fn inlines(&mut self, inlines: &'a [Inline]) {
+        for inline in inlines {
+            self.inline(inline);
+        }
+    }
+
+    fn inline(&mut self, inline: &'a Inline) {
+        match inline {
+            Inline::Str(s) => self.text(s),
+            Inline::Space => self.text(" "),
+            Inline::SoftBreak => self.events.push(Event::SoftBreak),
+            Inline::LineBreak => self.events.push(Event::HardBreak),
+            Inline::Emph(inlines) => self.tagged(Tag::Emphasis, |writer| writer.inlines(inlines)),
+            Inline::Strong(inlines) => self.tagged(Tag::Strong, |writer| writer.inlines(inlines)),
+            Inline::Strikeout(inlines) => {
+                self.tagged(Tag::Strikethrough, |writer| writer.inlines(inlines));
+            }
+            Inline::Superscript(inlines) => {
+                self.tagged(Tag::Superscript, |writer| writer.inlines(inlines));
+            }
+            Inline::Subscript(inlines) => {
+                self.tagged(Tag::Subscript, |writer| writer.inlines(inlines));
+            }
+            Inline::Underline(inlines) => self.html_inline("<u>", inlines, "</u>"),
+            Inline::SmallCaps(inlines) => {
+                self.html_inline("<span class=\"smallcaps\">", inlines, "</span>");
+            }
+            Inline::Quoted(QuoteType::SingleQuote, inlines) => {
+                self.text("‘");
+                self.inlines(inlines);
+                self.text("’");
+            }
+            Inline::Quoted(QuoteType::DoubleQuote, inlines) => {
+                self.text("“");
+                self.inlines(inlines);
+                self.text("”");
+            }
+            Inline::Cite(_, inlines) => self.inlines(inlines),
+            Inline::Code(_, code) => self.events.push(Event::Code(code.as_str().into())),
+            Inline::Math(MathType::InlineMath, tex) => {
+                self.events.push(Event::InlineMath(tex.as_str().into()));
+            }
+            Inline::Math(MathType::DisplayMath, tex) => {
+                self.events.push(Event::DisplayMath(tex.as_str().into()));
+            }
+            Inline::RawInline(Format(format), raw) if format == "html" => {
+                self.events.push(Event::InlineHtml(raw.as_str().into()));
+            }
+            Inline::RawInline(..) => {}
+            Inline::Link(attr, inlines, Target { url, title }) => {
+                let has_class = |class: &str| attr.classes.iter().any(|c| c == class);
+                let (link_type, url) = match url.strip_prefix("mailto:") {
+                    Some(email) if has_class("email") => (LinkType::Email, email),
+                    _ if has_class("uri") => (LinkType::Autolink, url.as_str()),
+                    _ => (LinkType::Inline, url.as_str()),
+                };
+                let tag = Tag::Link {
+                    link_type,
+                    dest_url: url.into(),
+                    title: title.as_str().into(),
+                    id: "".into(),
+                };
+                self.tagged(tag, |writer| writer.inlines(inlines));
+            }
+            Inline::Image(_, inlines, Target { url, title }) => {
+                let tag = Tag::Image {
+                    link_type: LinkType::Inline,
+                    dest_url: url.as_str().into(),
+                    title: title.as_str().into(),
+                    id: "".into(),
+                };
+                self.tagged(tag, |writer| writer.inlines(inlines));
+            }
+            Inline::Note(blocks) => {
+                self.notes.push(blocks);
+                let label = self.notes.len().to_string();
+                self.events.push(Event::FootnoteReference(label.into()));
+            }
+            Inline::Span(attr, inlines) if attr == &Attr::default() => self.inlines(inlines),
+            Inline::Span(attr, inlines) => {
+                let mut open = "<span".to_owned();
+                html::attr_to(&mut open, attr);
+                open.push('>');
+                self.events.push(Event::InlineHtml(open.into()));
+                self.inlines(inlines);
+                self.events.push(Event::InlineHtml("</span>".into()));
+            }
+        }
+    }
+
+    fn html_inline(&mut self, open: &'a str, inlines: &'a [Inline], close: &'a str) {
+        self.events.push(Event::InlineHtml(open.into()));
+        self.inlines(inlines);
+        self.events.push(Event::InlineHtml(close.into()));
+    }
+}
+
+/// Returns the kind of GitHub alert that a div with the given attributes
+/// stands for, if any.
+fn alert(attr: &Attr) -> Option<BlockQuoteKind> {
+    if !attr.identifier.is_empty() || !attr.attributes.is_empty() {
+        return None;
+    }
+    match attr.classes.as_slice() {
+        [class] => match class.as_str() {
+            "note" => Some(BlockQuoteKind::Note),
+            "tip" => Some(BlockQuoteKind::Tip),
+            "important" => Some(BlockQuoteKind::Important),
+            "warning" => Some(BlockQuoteKind::Warning),
+            "caution" => Some(BlockQuoteKind::Caution),
+            _ => None,
+        },
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::builder::*;
+    use crate::readers::pulldown_cmark::read_events;
+    use pulldown_cmark::{Options, Parser, TagEnd};
+
+    fn attr(id: &str, classes: &[&str], attributes: &[(&str, &str)]) -> Attr {
+        Attr {
+            identifier: id.into(),
+            classes: classes.iter().map(|&class| class.into()).collect(),
+            attributes: attributes
+                .iter()
+                .map(|&(key, value)| (key.into(), value.into()))
+                .collect(),
+        }
+    }
+
+    #[test]
+    fn blocks() {
+        let blocks = (header_with(attr("x", &["y"], &[("k", "v")]), 2, "Hi")
+            + code_block_with(attr("", &["rust"], &[]), "let x;")
+            + bullet_list([plain("☒ done"), para("loose")]))
+        .into_vec();
+        assert_eq!(
+            blocks_to_events(&blocks),
+            [
+                Event::Start(Tag::Heading {
+                    level: HeadingLevel::H2,
+                    id: Some("x".into()),
+                    classes: vec!["y".into()],
+                    attrs: vec![("k".into(), Some("v".into()))],
+                }),
+                Event::Text("Hi".into()),
+                Event::End(TagEnd::Heading(HeadingLevel::H2)),
+                Event::Start(Tag::CodeBlock(CodeBlockKind::Fenced("rust".into()))),
+                Event::Text("let x;\n".into()),
+                Event::End(TagEnd::CodeBlock),
+                Event::Start(Tag::List(None)),
+                Event::Start(Tag::Item),
+                Event::TaskListMarker(true),
+                Event::Text("done".into()),
+                Event::End(TagEnd::Item),
+                Event::Start(Tag::Item),
+                Event::Start(Tag::Paragraph),
+                Event::Text("loose".into()),
+                Event::End(TagEnd::Paragraph),
+                Event::End(TagEnd::Item),
+                Event::End(TagEnd::List(false)),
+            ]
+        );
+    }
+
+    #[test]
+    fn html() {
+        let blocks = (div_with(
+            attr("d", &[], &[]),
+            para(span_with(attr("", &["c"], &[]), "a")),
+        ) + para(underline("u"))
+            + raw_block("html", "<hr>")
+            + raw_block("latex", "\\relax"))
+        .into_vec();
+        assert_eq!(
+            blocks_to_events(&blocks),
+            [
+                Event::Start(Tag::HtmlBlock),
+                Event::Html("<div id=\"d\">\n".into()),
+                Event::End(TagEnd::HtmlBlock),
+                Event::Start(Tag::Paragraph),
+                Event::InlineHtml("<span class=\"c\">".into()),
+                Event::Text("a".into()),
+                Event::InlineHtml("</span>".into()),
+                Event::End(TagEnd::Paragraph),
+                Event::Start(Tag::HtmlBlock),
+                Event::Html("</div>\n".into()),
+                Event::End(TagEnd::HtmlBlock),
+                Event::Start(Tag::Paragraph),
+                Event::InlineHtml("<u>".into()),
+                Event::Text("u".into()),
+                Event::InlineHtml("</u>".into()),
+                Event::End(TagEnd::Paragraph),
+                Event::Start(Tag::HtmlBlock),
+                Event::Html("<hr>\n".into()),
+                Event::End(TagEnd::HtmlBlock),
+            ]
+        );
+
+        // Tables with captions are written as HTML.
+        let table = TableBuilder::new()
+            .caption(simple_caption(plain("cap")))
+            .header([plain("a")])
+            .build();
+        let blocks = [Block::Table(table)];
+        let events = blocks_to_events(&blocks);
+        assert!(matches!(
+            events.as_slice(),
+            [Event::Start(Tag::HtmlBlock), Event::Html(html), Event::End(TagEnd::HtmlBlock)]
+                if html.starts_with("<table>")
+        ));
+    }
+
+    #[test]
+    fn notes() {
+        let blocks =
+            para(str("a") + note(para(str("b") + note(plain("c")))) + note(plain("d"))).into_vec();
+        assert_eq!(
+            blocks_to_events(&blocks),
+            [
+                Event::Start(Tag::Paragraph),
+                Event::Text("a".into()),
+                Event::FootnoteReference("1".into()),
+                Event::FootnoteReference("2".into()),
+                Event::End(TagEnd::Paragraph),
+                Event::Start(Tag::FootnoteDefinition("1".into())),
+                Event::Start(Tag::Paragraph),
+                Event::Text("b".into()),
+                Event::FootnoteReference("3".into()),
+                Event::End(TagEnd::Paragraph),
+                Event::End(TagEnd::FootnoteDefinition),
+                Event::Start(Tag::FootnoteDefinition("2".into())),
+                Event::Text("d".into()),
+                Event::End(TagEnd::FootnoteDefinition),
+                Event::Start(Tag::FootnoteDefinition("3".into())),
+                Event::Text("c".into()),
+                Event::End(TagEnd::FootnoteDefinition),
+            ]
+        );
+    }
+
+    #[test]
+    fn round_trip() {
+        let input = "\
+# Title {#top .big}
+
+Some *emphasis*, **strong**, ~~struck~~, `code`, $x^2$ and <b>html</b>.\\
+A [link](/url \"title\"), an ![image](img.png), <http://x.org> and <me@x.org>.[^1]
+
+> [!TIP]
+> A tip.
+
+> Quoted.
+
+1. one
+2. two
+
+- [ ] to do
+- [x] done
+
+term
+: definition
+
+| a | b |
+|:--|--:|
+| c | *d* |
+
+```rust
+let x = 1;
+```
+
+<div>
+raw
+</div>
+
+---
+
+[^1]: A note.
+";
+        let options = (Options::all() - Options::ENABLE_OLD_FOOTNOTES) | Options::ENABLE_FOOTNOTES;
+        let doc = read_events(Parser::new_ext(input, options));
+        assert_eq!(read_events(write_events(&doc)), doc);
+    }
+
+    #[test]
+    fn front_matter() {
+        let options = Options::ENABLE_YAML_STYLE_METADATA_BLOCKS
+            | Options::ENABLE_PLUSES_DELIMITED_METADATA_BLOCKS;
+        for (input, format) in [
+            ("---\ntitle: x\nlist: [1, 2]\n---\n\nText\n", "yaml"),
+            ("+++\ntitle = 'x'\n+++\n\nText\n", "toml"),
+        ] {
+            let events: Vec<_> = Parser::new_ext(input, options).collect();
+            let doc = read_events(events.clone());
+            assert!(matches!(&doc.blocks[0], Block::RawBlock(Format(f), _) if f == format));
+            assert_eq!(write_events(&doc), events);
+            assert_eq!(read_events(write_events(&doc)), doc);
+        }
+    }
+}
@@ -0,0 +1,165 @@
+//! This test checks that documents in pandoc's JSON format survive a
+//! roundtrip through our types unchanged. Unlike the tests in
+//! `pandoc_compatibility.rs`, it doesn't require Pandoc to be installed.
+//!
+//! Each file in `tests/golden` holds the JSON for the corresponding `.txt`
+//! file in `tests`, with numbers formatted the way Pandoc formats them (for
+//! example `5.555555555555555e-2` for a column width). serde_json formats
+//! them differently (`0.05555555555555555`), so numbers are compared by
+//! value rather than by their text.
+//!
+//! The files were written by hand from the behaviour of Pandoc 3's Markdown
+//! reader, not produced by Pandoc, so they are only as good as that
+//! transcription and still have to be regenerated; `golden_fixtures` in
+//! `pandoc_compatibility.rs` compares them with Pandoc's output. To
+//! regenerate them, run this from the root of the repository with the
+//! Pandoc whose output the files should hold, and record the output of
+//! `pandoc --version` here:
+//!
+//! ```sh
+//! for f in inlines markdown-reader-more tables testsuite; do
+//!     pandoc -s -f markdown -t json tests/$f.txt -o tests/golden/$f.json
+//! done
+//! ```
+
+use pandoc_types::definition::{Block, IterBlocks, Pandoc, Table, VersionPolicy};
+use pandoc_types::readers::native::read_native;
+use pandoc_types::writers::native::write_native;
+use pandoc_types::writers::WriterOptions;
+use serde::de::DeserializeSeed;
+use serde_json::Value;
+
+/// Checks that two JSON values are the same, comparing numbers by value so
+/// that differences in how floats are formatted don't matter.
+fn assert_same_json(expected: &Value, actual: &Value, path: &str) {
+    match (expected, actual) {
+        (Value::Number(x), Value::Number(y)) => {
+            assert_eq!(x.as_f64(), y.as_f64(), "numbers differ at {}", path);
+            assert_eq!(x.is_f64(), y.is_f64(), "number types differ at {}", path);
+        }
+        (Value::Array(xs), Value::Array(ys)) => {
+            assert_eq!(xs.len(), ys.len(), "array lengths differ at {}", path);
+            for (i, (x, y)) in xs.iter().zip(ys).enumerate() {
+                assert_same_json(x, y, &format!("{}[{}]", path, i));
+            }
+        }
+        (Value::Object(xs), Value::Object(ys)) => {
+            let keys = |map: &serde_json::Map<String, Value>| {
+                let mut keys: Vec<_> = map.keys().cloned().collect();
+                keys.sort();
+                keys
+            };
+            assert_eq!(keys(xs), keys(ys), "object keys differ at {}", path);
+            for (key, x) in xs {
+                assert_same_json(x, &ys[key], &format!("{}.{}", path, key));
+            }
+        }
+        _ => assert_eq!(expected, actual, "values differ at {}", path),
+    }
+}
+
+fn check_golden(json: &str) -> Pandoc {
+    // Keep the version of the file, which may have a patch component that
+    // `PANDOC_API_VERSION` doesn't.
+    let mut deserializer = serde_json::Deserializer::from_str(json);
+    let versioned = VersionPolicy::Supported
+        .deserialize(&mut deserializer)
+        .unwrap();
+    let expected: Value = serde_json::from_str(json).unwrap();
+    let actual = serde_json::to_value(&versioned).unwrap();
+    assert_same_json(&expected, &actual, "$");
+    let doc = versioned.into_inner();
+
+    // Writing the document out as text and reading it back must give the
+    // same document, which checks that column widths are written precisely.
+    let text = serde_json::to_string(&doc).unwrap();
+    assert_eq!(serde_json::from_str::<Pandoc>(&text).unwrap(), doc);
+
+    // The same goes for the native format.
+    assert_eq!(
+        read_native(&write_native(&doc, &WriterOptions::default())).unwrap(),
+        doc
+    );
+    doc
+}
+
+fn collect_tables<'a>(blocks: impl Iterator<Item = &'a Block>, out: &mut Vec<Table>) {
+    for block in blocks {
+        if let Block::Table(table) = block {
+            out.push(table.clone());
+        }
+        collect_tables(block.iter_blocks(), out);
+    }
+}
+
+#[test]
+fn inlines() {
+    check_golden(include_str!("golden/inlines.json"));
+}
+
+#[test]
+fn markdown_reader_more() {
+    let doc = check_golden(include_str!("golden/markdown-reader-more.json"));
+    assert_eq!(doc.meta.doc_authors().len(), 4);
+}
+
+#[test]
+fn tables() {
+    check_golden(include_str!("golden/tables.json"));
+}
+
+#[test]
+fn testsuite() {
+    let doc = check_golden(include_str!("golden/testsuite.json"));
+    assert_eq!(doc.meta.doc_authors().len(), 2);
+}
+
+#[test]
+fn golden_tables_are_normalized() {
+    let mut found = Vec::new();
+    for json in [
+        include_str!("golden/markdown-reader-more.json"),
+        include_str!("golden/tables.json"),
+    ] {
+        let doc: Pandoc = serde_json::from_str(json).unwrap();
+        collect_tables(doc.blocks.iter(), &mut found);
+    }
+    assert_eq!(found.len(), 14);
+    for table in found {
+        assert!(table.grid().is_valid());
+        let mut normalized = table.clone();
+        normalized.normalize();
+        assert_eq!(normalized, table);
+    }
+}
+
+#[test]
+fn column_widths() {
+    // Pandoc writes column widths like Haskell's `show`, while serde_json
+    // writes the shortest form that reads back the same, so the widths are
+    // compared by value.
+    let json = include_str!("golden/markdown-reader-more.json");
+    assert!(json.contains(r#"{"t":"ColWidth","c":5.555555555555555e-2}"#));
+    let doc = check_golden(json);
+    let widths = |value: &Value| {
+        let mut widths = Vec::new();
+        collect_widths(value, &mut widths);
+        widths
+    };
+    let expected = widths(&serde_json::from_str(json).unwrap());
+    let actual = widths(&serde_json::to_value(&doc).unwrap());
+    assert!(expected.contains(&0.05555555555555555));
+    assert!(expected.contains(&0.2638888888888889));
+    assert_eq!(actual, expected);
+}
+
+fn collect_widths(value: &Value, out: &mut Vec<f64>) {
+    match value {
+        Value::Object(map) if map.get("t") == Some(&Value::from("ColWidth")) => {
+            out.push(map["c"].as_f64().unwrap());
+        }
+        Value::Object(map) => map.values().for_each(|value| collect_widths(value, out)),
+        Value::Array(values) => values.iter().for_each(|value| collect_widths(value, out)),
+        _ => {}
+    }
+}
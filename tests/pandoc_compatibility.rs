@@ -5,18 +5,20 @@
 //!
 //! This requires that Pandoc be installed and on PATH.
 
-use pandoc_types::definition::{Block, Inline, IterBlocks, IterInlines, Pandoc, Stringify};
+use pandoc_types::definition::{Block, Cell, Inline, IterBlocks, IterInlines, Pandoc, Stringify};
+use pandoc_types::writers::native::{blocks_to_native, write_native};
+use pandoc_types::writers::WriterOptions;
 
 use std::io::{self, Read, Write};
 use std::process::{Command, Stdio};
 
 fn pandoc_convert(input: &str, from: &str, to: &str) -> io::Result<String> {
+    pandoc(input, &["-s", "-f", from, "-t", to])
+}
+
+fn pandoc(input: &str, args: &[&str]) -> io::Result<String> {
     let process = Command::new("pandoc")
-        .arg("-s")
-        .arg("-f")
-        .arg(from)
-        .arg("-t")
-        .arg(to)
+        .args(args)
         .stdin(Stdio::piped())
         .stdout(Stdio::piped())
         .spawn()?;
@@ -244,6 +246,71 @@ fn testsuite() {
     check_roundtrip_stability(include_str!("testsuite.txt"));
 }
 
+/// Checks that the native writer lays out a document exactly like Pandoc,
+/// both on its own and with `-s`.
+fn check_native(markdown: &str) {
+    let json = pandoc_convert(markdown, "markdown", "json").unwrap();
+    let doc: Pandoc = serde_json::from_str(&json).unwrap();
+    let options = WriterOptions::default();
+    let args = ["-f", "json", "-t", "native", "--columns=72"];
+    let expected = pandoc(&json, &args).unwrap();
+    assert_eq!(blocks_to_native(&doc.blocks, &options), expected);
+    let expected = pandoc(&json, &[&args[..], &["-s"]].concat()).unwrap();
+    assert_eq!(write_native(&doc, &options), expected);
+}
+
+#[test]
+fn native_inlines() {
+    check_native(include_str!("inlines.txt"));
+}
+
+#[test]
+fn native_markdown_reader_more() {
+    check_native(include_str!("markdown-reader-more.txt"));
+}
+
+#[test]
+fn native_tables() {
+    check_native(include_str!("tables.txt"));
+}
+
+#[test]
+fn native_testsuite() {
+    check_native(include_str!("testsuite.txt"));
+}
+
+/// Checks that a fixture in `tests/golden` is what Pandoc makes of its
+/// source, see `tests/golden.rs` for how to regenerate it.
+fn check_golden_fixture(markdown: &str, fixture: &str) {
+    let json = pandoc_convert(markdown, "markdown", "json").unwrap();
+    let expected: serde_json::Value = serde_json::from_str(&json).unwrap();
+    let actual: serde_json::Value = serde_json::from_str(fixture).unwrap();
+    assert!(
+        expected == actual,
+        "the fixture differs from Pandoc's output"
+    );
+}
+
+#[test]
+fn golden_fixtures() {
+    check_golden_fixture(
+        include_str!("inlines.txt"),
+        include_str!("golden/inlines.json"),
+    );
+    check_golden_fixture(
+        include_str!("markdown-reader-more.txt"),
+        include_str!("golden/markdown-reader-more.json"),
+    );
+    check_golden_fixture(
+        include_str!("tables.txt"),
+        include_str!("golden/tables.json"),
+    );
+    check_golden_fixture(
+        include_str!("testsuite.txt"),
+        include_str!("golden/testsuite.json"),
+    );
+}
+
 #[test]
 fn stringify() {
     let json = pandoc_convert(include_str!("inlines.txt"), "markdown", "json").unwrap();
@@ -292,3 +359,40 @@ fn iter_mut_tables() {
     let markdown = pandoc_convert(&json, "json", "markdown").unwrap();
     assert_eq!(markdown, include_str!("tables_uppercase.txt"));
 }
+
+#[test]
+fn normalize_tables() {
+    let json = pandoc_convert(include_str!("tables.txt"), "markdown", "json").unwrap();
+    let doc: Pandoc = serde_json::from_str(&json).unwrap();
+    let mut tables = 0;
+    for block in &doc.blocks {
+        if let Block::Table(table) = block {
+            tables += 1;
+
+            // Tables produced by pandoc are already normalized.
+            let mut normalized = table.clone();
+            normalized.normalize();
+            assert_eq!(&normalized, table);
+
+            // Dropping the last cell of every row pads it with an empty cell.
+            let mut truncated = table.clone();
+            for row in truncated.bodies.iter_mut().flat_map(|b| b.body.iter_mut()) {
+                row.cells.pop();
+            }
+            truncated.normalize();
+            for (row, original) in truncated.bodies[0].body.iter().zip(&table.bodies[0].body) {
+                assert_eq!(row.cells.len(), original.cells.len());
+                assert_eq!(row.cells.last(), Some(&Cell::default()));
+            }
+
+            // Overlong rows are truncated to the width of the table.
+            let mut widened = table.clone();
+            for row in widened.bodies.iter_mut().flat_map(|b| b.body.iter_mut()) {
+                row.cells.push(Cell::default());
+            }
+            widened.normalize();
+            assert_eq!(&widened, table);
+        }
+    }
+    assert_eq!(tables, 7);
+}